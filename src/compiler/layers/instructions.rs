@@ -1,6 +1,7 @@
 use crate::compiler::layers::program_builder::ProgramBuilderLayer;
 use crate::computer::components::cpu::registers::reg::CPUReg;
 use crate::computer::instructions::Instruction;
+use crate::computer::semihosting::{SEMIHOSTING_ENTRY, SEMIHOSTING_EXIT};
 
 pub trait InstructionLayer: ProgramBuilderLayer {
     fn add(mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> Self {
@@ -47,4 +48,182 @@ pub trait InstructionLayer: ProgramBuilderLayer {
         self.add_instruction(Instruction::Lb(rd, rs1, imm));
         self
     }
+
+    fn lh(mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Lh(rd, rs1, imm));
+        self
+    }
+
+    fn lw(mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Lw(rd, rs1, imm));
+        self
+    }
+
+    fn ld(mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Ld(rd, rs1, imm));
+        self
+    }
+
+    fn sb(mut self, rs1: CPUReg, rs2: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Sb(rs1, rs2, imm));
+        self
+    }
+
+    fn sh(mut self, rs1: CPUReg, rs2: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Sh(rs1, rs2, imm));
+        self
+    }
+
+    fn sw(mut self, rs1: CPUReg, rs2: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Sw(rs1, rs2, imm));
+        self
+    }
+
+    fn sd(mut self, rs1: CPUReg, rs2: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Sd(rs1, rs2, imm));
+        self
+    }
+
+    fn addi(mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Addi(rd, rs1, imm));
+        self
+    }
+
+    fn slli(mut self, rd: CPUReg, rs1: CPUReg, shamt: u64) -> Self {
+        self.add_instruction(Instruction::Slli(rd, rs1, shamt));
+        self
+    }
+
+    fn srli(mut self, rd: CPUReg, rs1: CPUReg, shamt: u64) -> Self {
+        self.add_instruction(Instruction::Srli(rd, rs1, shamt));
+        self
+    }
+
+    fn srai(mut self, rd: CPUReg, rs1: CPUReg, shamt: u64) -> Self {
+        self.add_instruction(Instruction::Srai(rd, rs1, shamt));
+        self
+    }
+
+    fn ebreak(mut self) -> Self {
+        self.add_instruction(Instruction::EBreak);
+        self
+    }
+
+    /// Semihosting call with the operation in a0 and its parameter in a1, see `Semihosting`
+    fn semihosting_call(mut self) -> Self {
+        self.add_instruction(SEMIHOSTING_ENTRY);
+        self.add_instruction(Instruction::EBreak);
+        self.add_instruction(SEMIHOSTING_EXIT);
+        self
+    }
+
+    fn jalr(mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Jalr(rd, rs1, imm));
+        self
+    }
+
+    fn beq(mut self, rs1: CPUReg, rs2: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Beq(rs1, rs2, offset));
+        self
+    }
+
+    fn bne(mut self, rs1: CPUReg, rs2: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Bne(rs1, rs2, offset));
+        self
+    }
+
+    fn blt(mut self, rs1: CPUReg, rs2: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Blt(rs1, rs2, offset));
+        self
+    }
+
+    fn bge(mut self, rs1: CPUReg, rs2: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Bge(rs1, rs2, offset));
+        self
+    }
+
+    fn bltu(mut self, rs1: CPUReg, rs2: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Bltu(rs1, rs2, offset));
+        self
+    }
+
+    fn bgeu(mut self, rs1: CPUReg, rs2: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Bgeu(rs1, rs2, offset));
+        self
+    }
+
+    fn jal(mut self, rd: CPUReg, offset: u64) -> Self {
+        self.add_instruction(Instruction::Jal(rd, offset));
+        self
+    }
+
+    fn lui(mut self, rd: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Lui(rd, imm));
+        self
+    }
+
+    fn auipc(mut self, rd: CPUReg, imm: u64) -> Self {
+        self.add_instruction(Instruction::Auipc(rd, imm));
+        self
+    }
+
+    fn lr_w(mut self, rd: CPUReg, rs1: CPUReg) -> Self {
+        self.add_instruction(Instruction::LrW(rd, rs1));
+        self
+    }
+
+    fn lr_d(mut self, rd: CPUReg, rs1: CPUReg) -> Self {
+        self.add_instruction(Instruction::LrD(rd, rs1));
+        self
+    }
+
+    fn sc_w(mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> Self {
+        self.add_instruction(Instruction::ScW(rd, rs1, rs2));
+        self
+    }
+
+    fn sc_d(mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> Self {
+        self.add_instruction(Instruction::ScD(rd, rs1, rs2));
+        self
+    }
+
+    fn csrr(mut self, rd: CPUReg, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrr(rd, csr));
+        self
+    }
+
+    fn csrrw(mut self, rd: CPUReg, rs1: CPUReg, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrrw(rd, rs1, csr));
+        self
+    }
+
+    fn csrrs(mut self, rd: CPUReg, rs1: CPUReg, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrrs(rd, rs1, csr));
+        self
+    }
+
+    fn csrrc(mut self, rd: CPUReg, rs1: CPUReg, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrrc(rd, rs1, csr));
+        self
+    }
+
+    fn csrrwi(mut self, rd: CPUReg, uimm: u64, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrrwi(rd, uimm, csr));
+        self
+    }
+
+    fn csrrsi(mut self, rd: CPUReg, uimm: u64, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrrsi(rd, uimm, csr));
+        self
+    }
+
+    fn csrrci(mut self, rd: CPUReg, uimm: u64, csr: u16) -> Self {
+        self.add_instruction(Instruction::Csrrci(rd, uimm, csr));
+        self
+    }
+
+    fn mret(mut self) -> Self {
+        self.add_instruction(Instruction::Mret);
+        self
+    }
 }
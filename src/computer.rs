@@ -1,41 +1,423 @@
-use crate::computer::address::BOOT_ROM_START;
+use crate::computer::address::{Address, BOOT_ROM_START};
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::BusError;
+use crate::computer::components::bus::status::BusStatus;
 use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::execute::FunctionalMemory;
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
 use crate::computer::components::cpu::CPU;
-use crate::computer::components::ram::RAM;
+use crate::computer::components::device::registry::{DeviceError, DeviceRegistry};
+use crate::computer::components::device::{BusDevice, MachineRequest};
 use crate::computer::components::rom::ROM;
-use crate::computer::components::MMC;
+use crate::computer::memory_map::{MemoryMap, RegionKind};
+use crate::computer::semihosting::{Semihosting, SemihostingReturn};
+use log::debug;
 
 pub mod address;
 pub mod components;
+pub mod fdt;
 pub mod instructions;
+pub mod memory_map;
+pub mod semihosting;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug)]
 pub struct Computer {
     pub bus: Bus,
-    pub cpu: CPU,
-    pub ram: RAM,
-    pub rom: ROM,
+    /// Hart ids are the indices, hart 0 boots first in a single hart computer
+    pub harts: Vec<CPU>,
+    pub devices: DeviceRegistry,
+    /// Set once a device stopped the machine
+    exit_code: Option<u64>,
+    /// Cycle-accurate ticks since the start or the last reset
+    ticks: u64,
+    /// Whether the harts boot with a device tree, placed again on reset
+    boot_device_tree: bool,
+    /// Non-maskable interrupts as hart index, vector and cause, waiting for the hart to take them
+    pending_nmis: Vec<(usize, u64, u64)>,
+    /// Serves the semihosting calls of the harts, without it they halt on them
+    semihosting: Option<Semihosting>,
+}
+
+impl Default for Computer {
+    fn default() -> Self {
+        Self::with_memory_map(MemoryMap::default())
+    }
 }
 
 impl Computer {
+    #[cfg(test)]
     pub fn new() -> Computer {
         Computer::default()
     }
 
+    /// RAM and ROM regions of the memory map are backed by memory, device regions are left for `attach_device`
+    pub fn with_memory_map(memory_map: MemoryMap) -> Computer {
+        Computer {
+            bus: Bus::default(),
+            harts: vec![CPU::default()],
+            devices: DeviceRegistry::new(memory_map),
+            exit_code: None,
+            ticks: 0,
+            boot_device_tree: false,
+            pending_nmis: Vec::new(),
+            semihosting: None,
+        }
+    }
+
+    /// Computer with the given harts sharing the bus, their hart ids are set to their indices
+    pub fn with_harts(memory_map: MemoryMap, harts: Vec<CPU>) -> Computer {
+        let mut computer = Computer::with_memory_map(memory_map);
+        computer.harts.clear();
+        for hart in harts {
+            computer.add_hart(hart);
+        }
+        computer
+    }
+
+    /// Adds a hart sharing the bus and returns its hart id
+    pub fn add_hart(&mut self, mut hart: CPU) -> usize {
+        let hart_id = self.harts.len();
+        hart.set_hart_id(hart_id);
+        self.harts.push(hart);
+        hart_id
+    }
+
+    /// Ticks the harts in the order of their ids, then the devices.
+    /// Halted harts wait for the others; once all of them halted, the next tick resumes them.
+    /// Returns whether any hart is still running and no device stopped the machine.
     pub fn tick(&mut self) -> bool {
-        let do_continue = self.cpu.tick(&mut self.bus);
+        if self.exit_code.is_some() {
+            return false;
+        }
+        self.deliver_nmis();
+        self.deliver_interrupts();
+        let all_halted = self.harts.iter().all(|hart| hart.is_halted());
+        let mut do_continue = false;
+        for index in 0..self.harts.len() {
+            let hart = &mut self.harts[index];
+            if all_halted || !hart.is_halted() {
+                do_continue |= hart.tick(&mut self.bus) || self.semihost(index);
+            }
+        }
+        self.tick_devices();
+        self.update_hart_interrupts();
+        self.ticks += 1;
+
+        do_continue && !self.handle_machine_requests()
+    }
+
+    /// Lets the devices master, serve and count one tick of the bus
+    fn tick_devices(&mut self) {
+        self.devices.master_tick(&mut self.bus);
+        self.snoop();
+        self.devices.process_bus(&mut self.bus);
+        self.devices.tick();
+    }
+
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Exit code of the guest once a device stopped the machine
+    pub fn get_exit_code(&self) -> Option<u64> {
+        self.exit_code
+    }
+
+    /// Reinitialises the harts, the bus and the devices while keeping their configuration.
+    /// RAM is cleared, ROM keeps its contents so the harts boot the same program again.
+    pub fn reset(&mut self) {
+        self.harts.iter_mut().for_each(CPU::reset);
+        self.bus = Bus::with_arbitration(self.bus.get_arbitration().clone());
+        self.devices.reset();
+        self.exit_code = None;
+        self.ticks = 0;
+        self.pending_nmis.clear();
+        if let Some(semihosting) = self.semihosting.as_mut() {
+            semihosting.reset();
+        }
+        if self.boot_device_tree {
+            self.place_device_tree();
+        }
+    }
 
-        if let Some(mmc) = self.bus.get_active_mmc() {
-            match mmc {
-                MMC::RAM => self.ram.process_bus(&mut self.bus),
-                MMC::ROM => self.rom.process_bus(&mut self.bus),
+    /// Applies the pending requests of the devices, returns whether the machine stopped
+    fn handle_machine_requests(&mut self) -> bool {
+        while let Some(request) = self.devices.take_machine_request() {
+            debug!(target: "computer", "{request:?}");
+            match request {
+                MachineRequest::Exit(code) => self.exit_code = Some(code),
+                MachineRequest::Reset => self.reset(),
+                MachineRequest::Nmi { vector, cause } => {
+                    let harts = 0..self.harts.len();
+                    self.pending_nmis
+                        .extend(harts.map(|hart| (hart, vector, cause)));
+                }
             }
+        }
+        self.exit_code.is_some()
+    }
+
+    /// Serves the call of a hart which halted on a semihosting `ebreak`, returns whether the hart continues.
+    /// The hart's caches are written back first, the call accesses memory directly.
+    fn semihost(&mut self, index: usize) -> bool {
+        let Some(semihosting) = self.semihosting.as_mut() else {
+            return false;
+        };
+        let hart = &mut self.harts[index];
+        let mut memory = ComputerMemory {
+            devices: &mut self.devices,
+            bus: &mut self.bus,
+            owner: BusOwner::CPU(hart.get_hart_id()),
         };
+        // PC has already advanced past the ebreak
+        let ebreak = hart.get_register(CPUReg::PC).wrapping_sub(4);
+        if hart.get_exception().is_some() || !Semihosting::is_call(&mut memory, ebreak) {
+            return false;
+        }
+        hart.write_back_caches(&mut memory);
+        let (operation, parameter) = (
+            hart.get_register(CPUReg::X10),
+            hart.get_register(CPUReg::X11),
+        );
+        match semihosting.call(operation, parameter, &mut memory) {
+            SemihostingReturn::Value(value) => {
+                hart.set_register(CPUReg::X10, value);
+                hart.resume();
+                true
+            }
+            SemihostingReturn::Exit(code) => {
+                self.exit_code = Some(code);
+                false
+            }
+        }
+    }
+
+    /// Hands pending non-maskable interrupts to the harts, which take them at their next instruction boundary
+    fn deliver_nmis(&mut self) {
+        let (harts, bus) = (&mut self.harts, &mut self.bus);
+        self.pending_nmis
+            .retain(|(hart, vector, cause)| !harts[*hart].take_nmi(*vector, *cause, bus));
+    }
+
+    /// Lets the harts take their pending interrupts, the in-order ones wait for an instruction boundary
+    fn deliver_interrupts(&mut self) {
+        for hart in self.harts.iter_mut() {
+            hart.take_interrupt(&mut self.bus);
+        }
+    }
+
+    /// Lets the caches of the harts not owning the bus observe a new transaction before it is served.
+    /// Modified lines they hold are written to memory first.
+    fn snoop(&mut self) {
+        let Some(snoop) = self.bus.get_snoop() else {
+            return;
+        };
+        let mut shared = false;
+        for hart in self.harts.iter_mut() {
+            if BusOwner::CPU(hart.get_hart_id()) == snoop.master {
+                continue;
+            }
+            let response = hart.snoop(&snoop);
+            shared |= response.shared;
+            for (line, data) in response.flushed {
+                for (offset, beat) in data.chunks(8).enumerate() {
+                    let value = u64::from_le_bytes(beat.try_into().unwrap());
+                    let address = line + offset as u64 * 8;
+                    let _ = self
+                        .devices
+                        .write(address, value, BusStatus::WriteDoubleWord);
+                }
+            }
+        }
+        self.bus.put_snoop_result(shared);
+    }
+
+    fn update_hart_interrupts(&mut self) {
+        self.devices.route_interrupts();
+        for hart in self.harts.iter_mut() {
+            let mip = self.devices.hart_interrupts(hart.get_hart_id());
+            hart.set_pending_interrupts(mip);
+        }
+    }
+
+    /// Indices of the harts a tick or step advances
+    fn active_harts(&self) -> Vec<usize> {
+        let all_halted = self.harts.iter().all(|hart| hart.is_halted());
+        (0..self.harts.len())
+            .filter(|index| all_halted || !self.harts[*index].is_halted())
+            .collect()
+    }
 
-        do_continue
+    /// Maps an additional device into the address space, rejecting overlapping ranges.
+    /// Devices either fill a device region of the memory map or get a new region of their own.
+    pub fn attach_device(&mut self, device: impl BusDevice) -> Result<(), DeviceError> {
+        self.devices.attach(device)
+    }
+
+    /// Names of the devices raising an interrupt
+    #[cfg(test)]
+    pub fn pending_interrupts(&self) -> Vec<&str> {
+        self.devices.pending_interrupts()
+    }
+
+    /// Ticks until the harts reach an instruction boundary, finishing partially executed instructions.
+    pub fn finish_instruction(&mut self) -> bool {
+        while self
+            .active_harts()
+            .iter()
+            .any(|index| !self.harts[*index].is_at_instruction_boundary())
+        {
+            if !self.tick() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Executes a single whole instruction on each running hart, bypassing micro operations and the bus.
+    /// The devices are ticked once per step, time advances by one tick per instruction.
+    pub fn step_instruction(&mut self) -> bool {
+        if self.exit_code.is_some() || !self.finish_instruction() {
+            return false;
+        }
+
+        self.deliver_nmis();
+        self.deliver_interrupts();
+        let mut do_continue = false;
+        for index in self.active_harts() {
+            let hart = &mut self.harts[index];
+            let mut memory = ComputerMemory {
+                devices: &mut self.devices,
+                bus: &mut self.bus,
+                owner: BusOwner::CPU(hart.get_hart_id()),
+            };
+            do_continue |= hart.execute_next_instruction(&mut memory) || self.semihost(index);
+        }
+        self.tick_devices();
+        self.update_hart_interrupts();
+        self.ticks += 1;
+        do_continue && !self.handle_machine_requests()
+    }
+
+    /// Executes the given amount of instructions in fast mode.
+    /// Afterward the computer can continue to be ticked cycle-accurately.
+    pub fn fast_forward(&mut self, instructions: u64) -> bool {
+        for _ in 0..instructions {
+            if !self.step_instruction() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Writes the dirty cache lines of all harts to memory and invalidates the caches, e.g. before inspecting memory.
+    /// Only valid at instruction boundaries, like the fast mode.
+    pub fn write_back_caches(&mut self) {
+        for hart in self.harts.iter_mut() {
+            let mut memory = ComputerMemory {
+                devices: &mut self.devices,
+                bus: &mut self.bus,
+                owner: BusOwner::CPU(hart.get_hart_id()),
+            };
+            hart.write_back_caches(&mut memory);
+        }
+    }
+
+    /// Flattened device tree describing the harts, the RAM and the devices
+    pub fn device_tree(&self) -> Vec<u8> {
+        fdt::device_tree(self)
+    }
+
+    /// Places the device tree at the end of the first RAM region and passes it to the harts like RISC-V firmware
+    /// expects, with the hart id in a0 and the address of the blob in a1. Done again on every reset.
+    /// Returns the address, none without RAM large enough to hold it.
+    pub fn boot_with_device_tree(&mut self) -> Option<u64> {
+        self.boot_device_tree = true;
+        self.place_device_tree()
+    }
+
+    fn place_device_tree(&mut self) -> Option<u64> {
+        let mut blob = self.device_tree();
+        blob.resize(blob.len().next_multiple_of(8), 0);
+        let memory_map = self.devices.get_memory_map();
+        let ram = memory_map
+            .regions()
+            .iter()
+            .find(|region| region.kind == RegionKind::RAM)?;
+        if (blob.len() as u64) > ram.size {
+            return None;
+        }
+        let address = (ram.base + ram.size - blob.len() as u64) & !0b111;
+        for (offset, chunk) in blob.chunks(8).enumerate() {
+            let value = u64::from_le_bytes(chunk.try_into().unwrap());
+            self.devices
+                .write(
+                    address + offset as u64 * 8,
+                    value,
+                    BusStatus::WriteDoubleWord,
+                )
+                .ok()?;
+        }
+        debug!(target: "computer", "Device tree of {} bytes at {address:#x}", blob.len());
+        for hart in self.harts.iter_mut() {
+            hart.set_register(CPUReg::X10, hart.get_hart_id() as u64);
+            hart.set_register(CPUReg::X11, address);
+        }
+        Some(address)
+    }
+
+    /// Lets the harts request host services with semihosting calls, or makes the calls halt with None
+    pub fn set_semihosting(&mut self, semihosting: Option<Semihosting>) {
+        self.semihosting = semihosting;
+    }
+
+    #[cfg(test)]
+    pub fn get_semihosting(&self) -> Option<&Semihosting> {
+        self.semihosting.as_ref()
+    }
+
+    #[cfg(test)]
+    pub fn get_semihosting_mut(&mut self) -> Option<&mut Semihosting> {
+        self.semihosting.as_mut()
     }
 
     pub fn set_boot_rom(&mut self, data: Vec<u8>) {
-        self.rom.force_write(data, BOOT_ROM_START)
+        self.devices
+            .get_at_mut::<ROM>(BOOT_ROM_START)
+            .expect("Memory map without a boot rom")
+            .force_write(data, BOOT_ROM_START)
+    }
+}
+
+struct ComputerMemory<'a> {
+    devices: &'a mut DeviceRegistry,
+    /// Holds the LR/SC reservations shared with the cycle-accurate mode
+    bus: &'a mut Bus,
+    owner: BusOwner,
+}
+
+impl FunctionalMemory for ComputerMemory<'_> {
+    fn read_dw(&mut self, address: Address) -> Result<u64, BusError> {
+        self.devices.read(address.value())
+    }
+
+    fn fetch_dw(&mut self, address: Address) -> Result<u64, BusError> {
+        self.devices.fetch_dw(address.value())
+    }
+
+    fn write(&mut self, address: Address, data: u64, status: BusStatus) -> Result<(), BusError> {
+        let size = status.write_size().unwrap_or(1);
+        self.bus
+            .invalidate_reservations(address.value(), size, self.owner);
+        self.devices.write(address.value(), data, status)
+    }
+
+    fn reserve(&mut self, address: Address) {
+        self.bus.reserve(address, self.owner);
+    }
+
+    fn take_reservation(&mut self, address: Address) -> bool {
+        self.bus.take_reservation(address, self.owner)
     }
 }
@@ -5,6 +5,10 @@ pub const BOOT_ROM_START: u64 = 0x0000_0000_0000_0000;
 pub const BOOT_ROM_END: u64 = 0x0000_0000_0000_1000;
 pub const BOOT_ROM_SIZE: u64 = BOOT_ROM_END - BOOT_ROM_START;
 
+// Addresses between the boot rom and RAM are free for peripherals
+pub const RAM_START: u64 = 0x0000_0000_8000_0000;
+pub const RAM_SIZE: u64 = 0x0000_0000_0800_0000;
+
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 pub struct Address(u64);
 
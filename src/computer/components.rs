@@ -1,12 +1,18 @@
 pub mod bus;
+pub mod clint;
 pub mod cpu;
+pub mod device;
+pub mod dma;
+pub mod framebuffer;
+pub mod gpio;
+pub mod htif;
+pub mod input;
+pub mod plic;
 pub mod ram;
+pub mod rng;
 pub mod rom;
-
-#[derive(Debug, Default, Clone, Copy, PartialEq)]
-/// Memory Mapped Component
-pub enum MMC {
-    #[default]
-    RAM,
-    ROM,
-}
+pub mod rtc;
+pub mod test_finisher;
+pub mod uart;
+pub mod virtio;
+pub mod watchdog;
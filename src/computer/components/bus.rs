@@ -1,17 +1,43 @@
-use crate::computer::address::{Address, BOOT_ROM_END, BOOT_ROM_START};
+use crate::computer::address::Address;
+use crate::computer::components::bus::arbitration::Arbitration;
 use crate::computer::components::bus::owner::BusOwner;
-use crate::computer::components::bus::status::BusStatus;
-use crate::computer::components::MMC;
+use crate::computer::components::bus::response::BusResponse;
+use crate::computer::components::bus::snoop::{Snoop, SnoopKind};
+use crate::computer::components::bus::status::{BusStatus, BURST_BEAT_SIZE};
 
+pub mod arbitration;
 pub mod owner;
+pub mod response;
+pub mod snoop;
 pub mod status;
 
+/// Bytes covered by a reservation of LR/SC, reservations are naturally aligned
+pub const RESERVATION_GRANULE: u64 = 8;
+
 #[derive(Debug, Default, PartialEq)]
 pub struct Bus {
     address: Address,
     data: u64,
     owner: BusOwner,
     status: BusStatus,
+    /// Index of the current beat of a burst, 0 for single transfers
+    beat: u64,
+    /// Answer of the addressed device to the current transaction
+    response: BusResponse,
+    arbitration: Arbitration,
+    /// Masters which failed to take the bus and keep asking for it
+    requests: Vec<BusOwner>,
+    last_owner: BusOwner,
+    /// Granule addresses reserved by the masters' LR, one per master
+    reservations: Vec<(BusOwner, u64)>,
+    /// The current read fetches a line for writing, BusRdX
+    exclusive: bool,
+    /// The current read fetches instructions, checked against the execute permission
+    fetch: bool,
+    /// The caches have observed the current transaction
+    snooped: bool,
+    /// Another cache kept a copy of the line read by the current transaction
+    shared: bool,
 }
 
 impl Bus {
@@ -23,16 +49,48 @@ impl Bus {
         self.owner == BusOwner::None && self.status == BusStatus::Idle
     }
 
+    pub fn with_arbitration(arbitration: Arbitration) -> Self {
+        Self {
+            arbitration,
+            ..Self::default()
+        }
+    }
+
+    pub fn get_arbitration(&self) -> &Arbitration {
+        &self.arbitration
+    }
+
+    pub fn set_arbitration(&mut self, arbitration: Arbitration) {
+        self.arbitration = arbitration;
+    }
+
+    /// Grants a free bus unless a waiting master takes precedence.
+    /// Failed attempts are remembered as requests until the master gets the bus.
     pub fn take_ownership(&mut self, source: BusOwner) -> bool {
-        if !self.is_available() {
+        let outranked = self.requests.iter().any(|waiting| {
+            *waiting != source && self.arbitration.precedes(*waiting, source, self.last_owner)
+        });
+        if !self.is_available() || outranked {
+            if !self.requests.contains(&source) {
+                self.requests.push(source);
+            }
             return false;
         }
 
+        self.requests.retain(|waiting| *waiting != source);
+        self.last_owner = source;
         self.owner = source;
         self.status = BusStatus::Idle;
+        self.beat = 0;
+        self.response = BusResponse::Ok;
         true
     }
 
+    /// Forgets the request of a master which no longer wants the bus
+    pub fn withdraw_request(&mut self, source: BusOwner) {
+        self.requests.retain(|waiting| *waiting != source);
+    }
+
     pub fn release_ownership(&mut self, source: BusOwner) -> bool {
         if source != self.owner {
             return false;
@@ -40,6 +98,8 @@ impl Bus {
 
         self.owner = BusOwner::None;
         self.status = BusStatus::Idle;
+        self.beat = 0;
+        self.response = BusResponse::Ok;
         true
     }
 
@@ -63,7 +123,106 @@ impl Bus {
         if source != self.owner {
             return false;
         }
+        if let Some(size) = status.write_size() {
+            self.invalidate_reservations(self.address.value(), size * status.beats(), source);
+        }
         self.status = status;
+        self.beat = 0;
+        self.response = BusResponse::Ok;
+        self.exclusive = false;
+        self.fetch = false;
+        self.snooped = false;
+        self.shared = false;
+        true
+    }
+
+    /// Marks the current read as fetching the bytes for writing, other caches invalidate their copies.
+    /// The reservations of other masters are lost like on a write.
+    pub fn request_exclusive(&mut self, source: BusOwner) -> bool {
+        if source != self.owner || !self.status.is_read() {
+            return false;
+        }
+        let size = BURST_BEAT_SIZE * self.status.beats();
+        self.invalidate_reservations(self.address.value(), size, source);
+        self.exclusive = true;
+        true
+    }
+
+    /// Marks the current read as an instruction fetch
+    pub fn request_fetch(&mut self, source: BusOwner) -> bool {
+        if source != self.owner || !self.status.is_read() {
+            return false;
+        }
+        self.fetch = true;
+        true
+    }
+
+    pub fn is_fetch(&self) -> bool {
+        self.fetch
+    }
+
+    /// Current transaction if the caches have not observed it yet
+    pub fn get_snoop(&self) -> Option<Snoop> {
+        if !self.is_active() || self.snooped {
+            return None;
+        }
+        let kind = match (self.status.is_read(), self.exclusive) {
+            (true, false) => SnoopKind::Read,
+            (true, true) => SnoopKind::ReadExclusive,
+            (false, _) => SnoopKind::Write,
+        };
+        let size = self.status.write_size().unwrap_or(BURST_BEAT_SIZE);
+        Some(Snoop {
+            kind,
+            address: self.address.value(),
+            size: size * self.status.beats(),
+            master: self.owner,
+        })
+    }
+
+    /// Records that the caches observed the current transaction and whether one of them keeps a copy
+    pub fn put_snoop_result(&mut self, shared: bool) {
+        self.snooped = true;
+        self.shared = shared;
+    }
+
+    /// Another cache keeps a copy of the bytes read by the current transaction
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Reserves the granule holding the address for the master's LR, replacing its previous reservation.
+    /// Also used by the fast execution mode, which bypasses the bus protocol.
+    pub fn reserve(&mut self, address: Address, source: BusOwner) {
+        let granule = address.value() & !(RESERVATION_GRANULE - 1);
+        self.reservations.retain(|(owner, _)| *owner != source);
+        self.reservations.push((source, granule));
+    }
+
+    /// Consumes the master's reservation for SC, true if it still covers the address
+    pub fn take_reservation(&mut self, address: Address, source: BusOwner) -> bool {
+        let granule = address.value() & !(RESERVATION_GRANULE - 1);
+        let reserved = self.reservations.contains(&(source, granule));
+        self.reservations.retain(|(owner, _)| *owner != source);
+        reserved
+    }
+
+    /// Drops the reservations other masters hold on the written bytes
+    pub fn invalidate_reservations(&mut self, address: u64, size: u64, source: BusOwner) {
+        let end = address.wrapping_add(size);
+        self.reservations.retain(|(owner, granule)| {
+            *owner == source || end <= *granule || granule + RESERVATION_GRANULE <= address
+        });
+    }
+
+    /// Moves a burst on to its next beat once the device has answered the current one.
+    /// Fails after the last beat.
+    pub fn next_beat(&mut self, source: BusOwner) -> bool {
+        if source != self.owner || self.beat + 1 >= self.status.beats() {
+            return false;
+        }
+        self.beat += 1;
+        self.response = BusResponse::Ok;
         true
     }
 
@@ -71,23 +230,53 @@ impl Bus {
         self.address
     }
 
+    pub fn get_beat(&self) -> u64 {
+        self.beat
+    }
+
+    /// Address of the current beat, the transaction's address for single transfers
+    pub fn get_beat_address(&self) -> Address {
+        Address::new(
+            self.address
+                .value()
+                .wrapping_add(self.beat * BURST_BEAT_SIZE),
+        )
+    }
+
     pub fn get_data(&self) -> u64 {
         self.data
     }
 
+    pub fn get_owner(&self) -> BusOwner {
+        self.owner
+    }
+
     pub fn get_status(&self) -> BusStatus {
         self.status
     }
 
-    pub fn get_active_mmc(&self) -> Option<MMC> {
-        if self.status == BusStatus::Idle {
-            return None;
-        }
+    /// A transaction is in progress which the addressed device has to serve
+    pub fn is_active(&self) -> bool {
+        self.status != BusStatus::Idle
+    }
 
-        match self.get_address().value() {
-            BOOT_ROM_START..=BOOT_ROM_END => Some(MMC::ROM),
-            _ => Some(MMC::RAM),
-        }
+    /// The addressed device needs more time, the owner has to keep the transaction on the bus
+    pub fn put_busy(&mut self) {
+        self.response = BusResponse::Retry;
+    }
+
+    /// Handshake for the owner, the device's answer is available
+    pub fn is_ready(&self) -> bool {
+        self.response != BusResponse::Retry
+    }
+
+    /// Devices are not bus owners, they answer the owner's transaction
+    pub fn put_response(&mut self, response: BusResponse) {
+        self.response = response;
+    }
+
+    pub fn get_response(&self) -> BusResponse {
+        self.response
     }
 
     pub fn force_put_data(&mut self, data: u64) {
@@ -0,0 +1,45 @@
+use crate::computer::components::bus::owner::BusOwner;
+
+/// Decides which master gets a free bus when several are waiting for it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arbitration {
+    /// Masters earlier in the list always win, masters missing from the list come last.
+    /// Masters of equal priority take turns.
+    FixedPriority(Vec<BusOwner>),
+    /// The master that owned the bus last gets the lowest priority
+    RoundRobin,
+}
+
+impl Default for Arbitration {
+    /// The DMA first, the harts take turns
+    fn default() -> Self {
+        Self::FixedPriority(vec![BusOwner::DMA])
+    }
+}
+
+impl Arbitration {
+    /// Lower ranks win the bus
+    fn rank(&self, master: BusOwner, last_owner: BusOwner) -> (usize, usize) {
+        let priority = match self {
+            Arbitration::FixedPriority(order) => order
+                .iter()
+                .position(|owner| *owner == master)
+                .unwrap_or(order.len()),
+            Arbitration::RoundRobin => 0,
+        };
+        // Distance from the last owner in the round-robin cycle, the last owner itself comes last
+        let turn = match last_owner {
+            BusOwner::None => master.master_index(),
+            last => master
+                .master_index()
+                .wrapping_sub(last.master_index())
+                .wrapping_sub(1),
+        };
+        (priority, turn)
+    }
+
+    /// Whether the waiting master has to be served before the one asking for the bus
+    pub fn precedes(&self, waiting: BusOwner, master: BusOwner, last_owner: BusOwner) -> bool {
+        self.rank(waiting, last_owner) < self.rank(master, last_owner)
+    }
+}
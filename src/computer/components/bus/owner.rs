@@ -2,5 +2,21 @@
 pub enum BusOwner {
     #[default]
     None,
-    CPU,
+    /// Hart with the given id
+    CPU(usize),
+    DMA,
+    /// Bus mastering device other than the DMA, told apart by its base address
+    Device(u64),
+}
+
+impl BusOwner {
+    /// Position in the round-robin cycle: harts in order of their ids, followed by the other devices and the DMA
+    pub fn master_index(&self) -> usize {
+        match self {
+            BusOwner::CPU(hart) => *hart,
+            BusOwner::Device(_) => usize::MAX - 1,
+            BusOwner::DMA => usize::MAX,
+            BusOwner::None => panic!("BusOwner::None is not a master"),
+        }
+    }
 }
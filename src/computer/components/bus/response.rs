@@ -0,0 +1,19 @@
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum BusResponse {
+    #[default]
+    Ok,
+    /// The transaction can not be served, the master has to abort it
+    Error(BusError),
+    /// The device is busy, the master has to keep the transaction on the bus
+    Retry,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BusError {
+    /// No device answers at the address
+    Unmapped,
+    /// The region or device does not allow this kind of access, e.g. writes to ROM
+    AccessDenied,
+    /// The address is not aligned to the access size
+    Misaligned,
+}
@@ -0,0 +1,21 @@
+use crate::computer::components::bus::owner::BusOwner;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnoopKind {
+    /// Read which lets other caches keep their copies, BusRd
+    Read,
+    /// Read of a line to be written, other copies are invalidated, BusRdX
+    ReadExclusive,
+    /// Write to memory, other copies are invalidated
+    Write,
+}
+
+/// Transaction on the bus as observed by the caches of the other masters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Snoop {
+    pub kind: SnoopKind,
+    pub address: u64,
+    /// Bytes accessed, all beats of a burst
+    pub size: u64,
+    pub master: BusOwner,
+}
@@ -1,3 +1,6 @@
+/// Bytes transferred per beat of a burst, beats are double words at incrementing addresses
+pub const BURST_BEAT_SIZE: u64 = 8;
+
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum BusStatus {
     #[default]
@@ -7,4 +10,62 @@ pub enum BusStatus {
     WriteHalfWord,
     WriteWord,
     WriteDoubleWord,
+    /// Reads the given number of double word beats
+    ReadBurst(u8),
+    /// Writes the given number of double word beats
+    WriteBurst(u8),
+}
+
+impl BusStatus {
+    /// Number of bytes written, per beat for bursts, None for reads and an idle bus
+    pub fn write_size(&self) -> Option<u64> {
+        match self {
+            BusStatus::WriteByte => Some(1),
+            BusStatus::WriteHalfWord => Some(2),
+            BusStatus::WriteWord => Some(4),
+            BusStatus::WriteDoubleWord | BusStatus::WriteBurst(_) => Some(BURST_BEAT_SIZE),
+            BusStatus::Idle | BusStatus::Read | BusStatus::ReadBurst(_) => None,
+        }
+    }
+
+    /// Write of a single transfer of 1, 2, 4 or 8 bytes
+    pub fn write_of_size(size: u64) -> BusStatus {
+        match size {
+            1 => BusStatus::WriteByte,
+            2 => BusStatus::WriteHalfWord,
+            4 => BusStatus::WriteWord,
+            _ => BusStatus::WriteDoubleWord,
+        }
+    }
+
+    /// Alignment the bus requires, bursts move aligned double words.
+    /// Single transfers may be unaligned, masters decide how to handle them.
+    pub fn alignment(&self) -> Option<u64> {
+        self.is_burst().then_some(BURST_BEAT_SIZE)
+    }
+
+    pub fn is_read(&self) -> bool {
+        matches!(self, BusStatus::Read | BusStatus::ReadBurst(_))
+    }
+
+    pub fn is_burst(&self) -> bool {
+        matches!(self, BusStatus::ReadBurst(_) | BusStatus::WriteBurst(_))
+    }
+
+    /// Number of beats of the transaction, single transfers have one
+    pub fn beats(&self) -> u64 {
+        match self {
+            BusStatus::ReadBurst(beats) | BusStatus::WriteBurst(beats) => *beats as u64,
+            _ => 1,
+        }
+    }
+
+    /// A single transfer of one burst beat, for devices without native burst support
+    pub fn single_beat(&self) -> BusStatus {
+        match self {
+            BusStatus::ReadBurst(_) => BusStatus::Read,
+            BusStatus::WriteBurst(_) => BusStatus::WriteDoubleWord,
+            status => *status,
+        }
+    }
 }
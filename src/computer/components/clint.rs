@@ -0,0 +1,156 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::csr::{MIP_MSIP, MIP_MTIP};
+use crate::computer::components::device::BusDevice;
+use crate::computer::fdt::{cpu_interrupt_controller_phandle, DeviceTreeNode, PropertyValue};
+use log::debug;
+use std::ops::RangeInclusive;
+
+// Register offsets, laid out like the SiFive CLINT
+/// Software interrupt pending bit of each hart, a word per hart
+pub const CLINT_MSIP: u64 = 0x0000;
+/// Timer compare value of each hart, a double word per hart
+pub const CLINT_MTIMECMP: u64 = 0x4000;
+/// Timer shared by all harts, counts computer ticks
+pub const CLINT_MTIME: u64 = 0xBFF8;
+pub const CLINT_SIZE: u64 = 0x1_0000;
+
+/// Core-local interruptor, raises the software and timer interrupts of each hart.
+/// A hart's timer interrupt is pending while `mtime >= mtimecmp`.
+#[derive(Debug)]
+pub struct Clint {
+    name: String,
+    base: u64,
+    msip: Vec<u32>,
+    mtimecmp: Vec<u64>,
+    mtime: u64,
+}
+
+impl Clint {
+    pub fn new(name: &str, base: u64, harts: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            msip: vec![0; harts],
+            // No timer interrupt until software programs a compare value
+            mtimecmp: vec![u64::MAX; harts],
+            mtime: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_mtime(&self) -> u64 {
+        self.mtime
+    }
+
+    /// Registers are accessed bytewise so any access size works
+    fn read_byte(&self, offset: u64) -> u8 {
+        let register = match offset {
+            CLINT_MTIME..=0xBFFF => Some((self.mtime, offset - CLINT_MTIME)),
+            CLINT_MTIMECMP.. => {
+                let hart = ((offset - CLINT_MTIMECMP) / 8) as usize;
+                self.mtimecmp.get(hart).map(|value| (*value, offset % 8))
+            }
+            _ => {
+                let hart = ((offset - CLINT_MSIP) / 4) as usize;
+                self.msip.get(hart).map(|value| (*value as u64, offset % 4))
+            }
+        };
+        register.map_or(0, |(value, byte)| (value >> (8 * byte)) as u8)
+    }
+
+    fn write_byte(&mut self, offset: u64, value: u8) {
+        let merge = |register: u64, byte: u64| {
+            register & !(0xFF << (8 * byte)) | (value as u64) << (8 * byte)
+        };
+        match offset {
+            CLINT_MTIME..=0xBFFF => self.mtime = merge(self.mtime, offset - CLINT_MTIME),
+            CLINT_MTIMECMP.. => {
+                let hart = ((offset - CLINT_MTIMECMP) / 8) as usize;
+                if let Some(mtimecmp) = self.mtimecmp.get_mut(hart) {
+                    *mtimecmp = merge(*mtimecmp, offset % 8);
+                }
+            }
+            _ => {
+                let hart = ((offset - CLINT_MSIP) / 4) as usize;
+                if let Some(msip) = self.msip.get_mut(hart) {
+                    // Only the lowest bit is writable
+                    *msip = merge(*msip as u64, offset % 4) as u32 & 1;
+                }
+            }
+        }
+    }
+}
+
+impl BusDevice for Clint {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (CLINT_SIZE - 1)
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_dw(bus.get_address().value())),
+            status => {
+                let data = bus.get_data();
+                let size = status.write_size().unwrap_or(0);
+                debug!(target: "clint", "Write {data:#x} to {offset:#x}");
+                for byte in 0..size {
+                    self.write_byte(offset + byte, (data >> (8 * byte)) as u8);
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        self.mtime = self.mtime.wrapping_add(1);
+    }
+
+    fn reset(&mut self) {
+        self.msip.fill(0);
+        self.mtimecmp.fill(u64::MAX);
+        self.mtime = 0;
+    }
+
+    fn hart_interrupts(&self, hart: usize) -> u64 {
+        let software = self.msip.get(hart).is_some_and(|msip| *msip & 1 != 0);
+        let timer = self
+            .mtimecmp
+            .get(hart)
+            .is_some_and(|mtimecmp| self.mtime >= *mtimecmp);
+        (software as u64 * MIP_MSIP) | (timer as u64 * MIP_MTIP)
+    }
+
+    /// Wired to the machine software and timer interrupts of each hart
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let interrupts = (0..self.msip.len())
+            .map(cpu_interrupt_controller_phandle)
+            .flat_map(|phandle| {
+                [
+                    phandle,
+                    MIP_MSIP.trailing_zeros(),
+                    phandle,
+                    MIP_MTIP.trailing_zeros(),
+                ]
+            })
+            .collect();
+        let node = DeviceTreeNode::new(
+            "clint",
+            &["sifive,clint0", "riscv,clint0"],
+            self.address_range(),
+        )
+        .with_property("interrupts-extended", PropertyValue::Cells(interrupts));
+        Some(node)
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = address - self.base;
+        (0..8).fold(0, |value, byte| {
+            value | (self.read_byte(offset + byte) as u64) << (8 * byte)
+        })
+    }
+}
@@ -1,29 +1,67 @@
 use crate::computer::address::Address;
 use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::{BusError, BusResponse};
+use crate::computer::components::bus::snoop::Snoop;
 use crate::computer::components::bus::status::BusStatus;
 use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::alu::{ALUOp, ALUResult, BranchCondition};
+use crate::computer::components::cpu::branch_prediction::BranchPredictor;
 use crate::computer::components::cpu::builder::CPUBuilder;
+use crate::computer::components::cpu::cache::{
+    Cache, CacheConfig, CacheRequest, CacheStats, Caches, SnoopResponse,
+};
+use crate::computer::components::cpu::csr::{CsrOp, Csrs};
 use crate::computer::components::cpu::decompose::decompose_instruction;
+use crate::computer::components::cpu::exception::{Exception, MemoryAccess};
+use crate::computer::components::cpu::fusion::{
+    fuse_instructions, fuse_micro_ops, is_macro_fusion_head, FusionConfig, FusionStats,
+};
 use crate::computer::components::cpu::micro_op::{MicroOp, MicroOpResponse};
+use crate::computer::components::cpu::misaligned::{
+    is_aligned, merge_part, sign_extend, split_access, MisalignedPolicy,
+};
+use crate::computer::components::cpu::ooo::{OoOConfig, OoOCore};
 use crate::computer::components::cpu::registers::flags::CPUFlagsAccessTrait;
 use crate::computer::components::cpu::registers::{CPURegisters, CPURegistersAccessTrait};
+use crate::computer::instructions::Instruction;
 use crate::log_microop_debug;
 use log::{debug, trace};
 use registers::reg::CPUReg;
-use registers::reg::CPUReg::IR;
+use registers::reg::CPUReg::{IR, PC, TMP2, TMP3};
 use std::collections::VecDeque;
 
-mod builder;
+pub mod alu;
+pub mod branch_prediction;
+pub mod builder;
+pub mod cache;
+pub mod csr;
 mod decompose;
-mod micro_op;
+pub mod exception;
+pub mod execute;
+pub mod fusion;
+pub mod micro_op;
+pub mod misaligned;
+pub mod ooo;
 pub mod registers;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default)]
 pub struct CPU {
     registers: CPURegisters,
     micro_op_queue: VecDeque<MicroOp>,
     ticks: u64,
     decode_counter: u64,
+    fusion: FusionConfig,
+    fusion_stats: FusionStats,
+    /// Decoded instruction waiting for the prefetch of its potential fusion partner
+    pending_fusion: Option<Instruction>,
+    /// Out-of-order backend, replaces the micro operation queue when present
+    ooo: Option<OoOCore>,
+    exception: Option<Exception>,
+    misaligned: MisalignedPolicy,
+    csrs: Csrs,
+    /// The last tick or instruction halted the hart
+    halted: bool,
+    caches: Caches,
 }
 
 impl CPU {
@@ -35,11 +73,198 @@ impl CPU {
         CPUBuilder::new()
     }
 
+    pub fn get_ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn get_fusion_stats(&self) -> FusionStats {
+        self.fusion_stats
+    }
+
+    pub fn set_fusion(&mut self, fusion: FusionConfig) {
+        self.fusion = fusion;
+    }
+
+    /// Enables the out-of-order backend, or the in-order micro operation queue with None
+    pub fn set_out_of_order(&mut self, config: Option<OoOConfig>) {
+        self.ooo = config.map(OoOCore::new);
+        self.set_misaligned_policy(self.misaligned);
+    }
+
+    #[cfg(test)]
+    pub fn get_misaligned_policy(&self) -> MisalignedPolicy {
+        self.misaligned
+    }
+
+    /// Applies to every execution mode
+    pub fn set_misaligned_policy(&mut self, policy: MisalignedPolicy) {
+        self.misaligned = policy;
+        if let Some(ooo) = self.ooo.as_mut() {
+            ooo.set_misaligned_policy(policy);
+        }
+    }
+
+    /// Only the out-of-order backend predicts branches, the in-order core hands the predictor back.
+    /// Enabling or replacing the out-of-order backend afterward resets it to static not-taken.
+    pub fn set_branch_predictor(
+        &mut self,
+        predictor: Box<dyn BranchPredictor>,
+    ) -> Result<(), Box<dyn BranchPredictor>> {
+        match self.ooo.as_mut() {
+            Some(ooo) => {
+                ooo.set_branch_predictor(predictor);
+                Ok(())
+            }
+            None => Err(predictor),
+        }
+    }
+
+    /// Executes the given micro operations ahead of the next fetch, in-order mode only
+    #[cfg(test)]
+    pub fn queue_micro_ops(&mut self, micro_ops: Vec<MicroOp>) {
+        self.micro_op_queue.extend(micro_ops);
+    }
+
+    pub fn get_exception(&self) -> Option<Exception> {
+        self.exception
+    }
+
+    pub fn get_ooo(&self) -> Option<&OoOCore> {
+        self.ooo.as_ref()
+    }
+
+    pub fn get_hart_id(&self) -> usize {
+        self.csrs.hart_id as usize
+    }
+
+    /// Harts are told apart on the bus by their id, it has to be unique within a computer
+    pub fn set_hart_id(&mut self, hart_id: usize) {
+        self.csrs.hart_id = hart_id as u64;
+    }
+
+    #[cfg(test)]
+    pub fn get_csrs(&self) -> Csrs {
+        self.csrs
+    }
+
+    /// Updates the mip CSR from the interrupt lines of the devices
+    pub fn set_pending_interrupts(&mut self, mip: u64) {
+        self.csrs.mip = mip;
+    }
+
+    /// Takes a non-maskable interrupt, which can not be returned from: mepc and mcause record the interrupted
+    /// instruction and the cause, execution continues at the vector, also on a halted hart.
+    /// The in-order pipeline takes it at an instruction boundary and returns false while mid-instruction,
+    /// the out-of-order backend squashes its uncommitted instructions instead.
+    pub fn take_nmi(&mut self, vector: u64, cause: u64, bus: &mut Bus) -> bool {
+        if let Some(ooo) = self.ooo.as_mut() {
+            ooo.squash(bus, &mut self.caches);
+        } else if !self.micro_op_queue.is_empty() {
+            return false;
+        }
+        debug!(target: "cpu", "NMI {cause:#x} to {vector:#x}");
+        self.csrs.mepc = self.get_register(PC);
+        self.csrs.mcause = cause;
+        self.set_register(PC, vector);
+        self.exception = None;
+        self.halted = false;
+        true
+    }
+
+    /// Takes the highest priority pending and enabled interrupt, see `Csrs::pending_interrupt`.
+    /// Like non-maskable interrupts they are taken at instruction boundaries, mepc is the next
+    /// instruction to execute. Halted harts don't take interrupts. Returns whether one was taken.
+    pub fn take_interrupt(&mut self, bus: &mut Bus) -> bool {
+        let Some(cause) = self.csrs.pending_interrupt() else {
+            return false;
+        };
+        if self.halted {
+            return false;
+        }
+        if let Some(ooo) = self.ooo.as_mut() {
+            ooo.squash(bus, &mut self.caches);
+        } else if !self.micro_op_queue.is_empty() {
+            return false;
+        }
+        let handler = self.csrs.enter_interrupt(cause, self.get_register(PC));
+        debug!(target: "cpu", "Interrupt {cause} to {handler:#x}");
+        self.set_register(PC, handler);
+        true
+    }
+
+    /// Continues a halted hart at PC, e.g. once the host served the request it halted for
+    pub fn resume(&mut self) {
+        self.halted = false;
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Adds an instruction cache, or removes it with None
+    pub fn set_icache(&mut self, config: Option<CacheConfig>) {
+        self.caches.instruction = config.map(Cache::new_instruction);
+    }
+
+    /// Adds a data cache, or removes it with None.
+    /// Dirty lines of a replaced data cache are lost, see `write_back_caches`.
+    pub fn set_dcache(&mut self, config: Option<CacheConfig>) {
+        self.caches.data = config.map(Cache::new);
+    }
+
+    #[cfg(test)]
+    pub fn get_caches(&self) -> &Caches {
+        &self.caches
+    }
+
+    pub fn get_icache_stats(&self) -> Option<CacheStats> {
+        self.caches.instruction.as_ref().map(Cache::get_stats)
+    }
+
+    pub fn get_dcache_stats(&self) -> Option<CacheStats> {
+        self.caches.data.as_ref().map(Cache::get_stats)
+    }
+
+    /// Returns to the boot state, keeping the hart id and the configuration of the backends and caches.
+    /// Cached lines are dropped without being written back.
+    pub fn reset(&mut self) {
+        self.registers = CPURegisters::default();
+        self.micro_op_queue.clear();
+        self.pending_fusion = None;
+        self.exception = None;
+        self.halted = false;
+        self.csrs.reset();
+        if let Some(ooo) = self.ooo.as_mut() {
+            ooo.reset();
+        }
+        self.caches.reset();
+    }
+
+    /// Lets the caches observe a transaction of another bus master
+    pub fn snoop(&mut self, snoop: &Snoop) -> SnoopResponse {
+        let owner = self.bus_owner();
+        self.caches.snoop(snoop, owner)
+    }
+
+    fn bus_owner(&self) -> BusOwner {
+        BusOwner::CPU(self.get_hart_id())
+    }
+
     pub fn tick(&mut self, bus: &mut Bus) -> bool {
         trace!(target: "cpu", "Tick {}", self.ticks);
 
+        if let Some(ooo) = self.ooo.as_mut() {
+            let result = ooo.tick(&mut self.registers, &mut self.csrs, bus, &mut self.caches);
+            if let Some(exception) = result.exception {
+                self.raise(exception);
+            }
+            self.ticks = self.ticks.wrapping_add(1);
+            self.halted = result.halt;
+            return !result.halt;
+        }
+
         if self.micro_op_queue.is_empty() {
-            self.micro_op_queue = MicroOp::default_queue();
+            self.micro_op_queue = self.with_caches(MicroOp::default_queue());
             debug!(target: "cpu", "New Fetch/Decode Cycle")
         }
 
@@ -56,12 +281,43 @@ impl CPU {
             MicroOp::BusWriteAddress(register) => self.mo_bus_write_address(bus, register),
             MicroOp::BusWriteData(register) => self.mo_bus_write_data(bus, register),
             MicroOp::BusSetRead => self.mo_bus_set_read(bus),
+            MicroOp::BusSetIdle => self.mo_bus_set_idle(bus),
             MicroOp::BusSetWriteByte => self.mo_bus_set_write_byte(bus),
             MicroOp::BusSetWriteHalfWord => self.mo_bus_set_write_half_word(bus),
             MicroOp::BusSetWriteWord => self.mo_bus_set_write_word(bus),
             MicroOp::BusSetWriteDoubleWord => self.mo_bus_set_write_double_word(bus),
+            MicroOp::BusAwaitWrite => self.mo_bus_await_write(bus),
+            #[cfg(test)]
+            MicroOp::BusSetReadBurst(beats) => self.mo_bus_set_read_burst(bus, beats),
+            #[cfg(test)]
+            MicroOp::BusSetWriteBurst(beats) => self.mo_bus_set_write_burst(bus, beats),
+            #[cfg(test)]
+            MicroOp::BusReadBeat(register) => self.mo_bus_read_beat(bus, register),
+            #[cfg(test)]
+            MicroOp::BusWriteBeat(register) => self.mo_bus_write_beat(bus, register),
+            MicroOp::BusReadPart(register, offset, size) => {
+                self.mo_bus_read_part(bus, register, offset, size)
+            }
+            MicroOp::RegisterSignExtend(register, size) => {
+                self.mo_register_sign_extend(register, size)
+            }
+            MicroOp::BusReserve(size) => self.mo_bus_reserve(bus, size),
+            MicroOp::BusStoreConditional(rd, size) => self.mo_bus_store_conditional(bus, rd, size),
+            MicroOp::CsrRead(rd, csr) => self.mo_csr_read(rd, csr),
+            MicroOp::CsrAccess(op, rd, csr, rs) => self.mo_csr_access(op, rd, csr, rs),
+            MicroOp::Mret => self.mo_mret(),
+            MicroOp::ICacheRead(address, rd) => self.mo_cache_read(bus, address, rd, 4),
+            MicroOp::DCacheRead(address, rd, size) => {
+                self.mo_cache_read(bus, address, rd, size as u64)
+            }
+            MicroOp::DCacheWrite(address, data, size) => {
+                self.mo_dcache_write(bus, address, data, size as u64)
+            }
+            MicroOp::DCacheFlush(address, size) => self.mo_dcache_flush(bus, address, size as u64),
             MicroOp::Decode => self.mo_decode(),
+            MicroOp::DecodeFused => self.mo_decode_fused(),
             MicroOp::ALUAdd(rd, rs1, rs2) => self.mo_alu_add(rd, rs1, rs2),
+            MicroOp::ALUAddi(rd, rs1, rs2) => self.mo_alu_addi(rd, rs1, rs2),
             MicroOp::ALUAnd(rd, rs1, rs2) => self.mo_alu_and(rd, rs1, rs2),
             MicroOp::ALUOr(rd, rs1, rs2) => self.mo_alu_or(rd, rs1, rs2),
             MicroOp::ALUSub(rd, rs1, rs2) => self.mo_alu_sub(rd, rs1, rs2),
@@ -69,7 +325,19 @@ impl CPU {
             MicroOp::ALUSll(rd, rs1, rs2) => self.mo_alu_shift_left_logical(rd, rs1, rs2),
             MicroOp::ALUSrl(rd, rs1, rs2) => self.mo_alu_shift_right_logical(rd, rs1, rs2),
             MicroOp::ALUSra(rd, rs1, rs2) => self.mo_alu_shift_right_arithmetic(rd, rs1, rs2),
+            MicroOp::AGUAdd(rd, rs1, rs2) => self.mo_agu_add(rd, rs1, rs2),
+            MicroOp::AGUAddAligned(rd, rs1, rs2) => self.mo_agu_add_aligned(rd, rs1, rs2),
+            MicroOp::Branch(condition, rs1, rs2, offset) => {
+                self.mo_branch(condition, rs1, rs2, offset)
+            }
             MicroOp::RegisterLoadImm(register, imm) => self.mo_register_load_imm(register, imm),
+            MicroOp::RegisterMove(rd, rs) => self.mo_register_move(rd, rs),
+            MicroOp::ALUAddImm(rd, rs1, imm) => self.mo_alu_add_imm(rd, rs1, imm),
+            MicroOp::AGUAddImm(rd, rs1, imm) => self.mo_agu_add_imm(rd, rs1, imm),
+            MicroOp::FusedLuiAddi(rd, upper, imm) => self.mo_fused_lui_addi(rd, upper, imm),
+            MicroOp::FusedAuipcJalr(rd1, rd2, upper, imm) => {
+                self.mo_fused_auipc_jalr(rd1, rd2, upper, imm)
+            }
         };
 
         if response.repeat {
@@ -77,9 +345,157 @@ impl CPU {
         };
 
         self.ticks = self.ticks.wrapping_add(1);
+        self.halted = response.halt;
 
         !response.halt
     }
+
+    fn raise(&mut self, exception: Exception) {
+        debug!(target: "cpu", "Exception: {exception}");
+        self.exception = Some(exception);
+    }
+
+    /// Returns the response to the current micro operation unless the device answered Ok.
+    /// Busy devices repeat the micro operation, on a bus error the CPU halts after releasing the bus.
+    fn check_bus_response(&mut self, bus: &Bus, access: MemoryAccess) -> Option<MicroOpResponse> {
+        match bus.get_response() {
+            BusResponse::Ok => None,
+            BusResponse::Retry => Some(MicroOpResponse::new_repeat()),
+            BusResponse::Error(error) => {
+                let address = bus.get_beat_address().value();
+                self.raise(Exception::from_bus_error(access, error, address));
+                self.micro_op_queue = VecDeque::from(vec![MicroOp::BusRelease, MicroOp::Halt]);
+                Some(MicroOpResponse::default())
+            }
+        }
+    }
+
+    /// Applies the misaligned policy before the status is put on the bus.
+    /// Trapped accesses halt the CPU, split accesses replace the pending read with aligned part reads.
+    fn check_alignment(
+        &mut self,
+        address: u64,
+        size: u64,
+        access: MemoryAccess,
+    ) -> Option<MicroOpResponse> {
+        if is_aligned(address, size) {
+            return None;
+        }
+        match self.misaligned {
+            MisalignedPolicy::Allow => None,
+            MisalignedPolicy::Trap => {
+                let error = BusError::Misaligned;
+                self.raise(Exception::from_bus_error(access, error, address));
+                self.micro_op_queue = VecDeque::from(vec![MicroOp::BusRelease, MicroOp::Halt]);
+                Some(MicroOpResponse::default())
+            }
+            MisalignedPolicy::Split if access == MemoryAccess::Store => None,
+            MisalignedPolicy::Split => {
+                let read = self.micro_op_queue.pop_front();
+                let register = read.expect("Split without a read").destinations()[0];
+                let mut parts = Vec::new();
+                for (part, part_size) in split_access(address, size) {
+                    parts.extend([
+                        MicroOp::BusSetIdle,
+                        MicroOp::RegisterLoadImm(TMP2, part),
+                        MicroOp::BusWriteAddress(TMP2),
+                        MicroOp::BusSetRead,
+                        MicroOp::BusReadPart(
+                            TMP3,
+                            part.wrapping_sub(address) as u8,
+                            part_size as u8,
+                        ),
+                    ]);
+                }
+                // Parts are merged in a scratch register, as every write to IR advances the PC
+                parts.push(MicroOp::RegisterSignExtend(TMP3, size as u8));
+                parts.push(MicroOp::RegisterMove(register, TMP3));
+                self.push_front_micro_ops(parts);
+                Some(MicroOpResponse::default())
+            }
+        }
+    }
+
+    /// Split stores replace the pending write acknowledgement with aligned part writes
+    fn check_store_alignment(&mut self, bus: &Bus, status: BusStatus) -> Option<MicroOpResponse> {
+        let address = bus.get_address().value();
+        let size = status.write_size()?;
+        let response = self.check_alignment(address, size, MemoryAccess::Store);
+        if response.is_some()
+            || is_aligned(address, size)
+            || self.misaligned != MisalignedPolicy::Split
+        {
+            return response;
+        }
+
+        let await_write = self.micro_op_queue.pop_front();
+        debug_assert_eq!(await_write, Some(MicroOp::BusAwaitWrite));
+        let data = bus.get_data();
+        let mut parts = Vec::new();
+        for (part, part_size) in split_access(address, size) {
+            parts.extend([
+                MicroOp::BusSetIdle,
+                MicroOp::RegisterLoadImm(TMP2, part),
+                MicroOp::BusWriteAddress(TMP2),
+                MicroOp::RegisterLoadImm(TMP3, data >> (8 * part.wrapping_sub(address))),
+                MicroOp::BusWriteData(TMP3),
+                MicroOp::set_write(part_size),
+                MicroOp::BusAwaitWrite,
+            ]);
+        }
+        self.push_front_micro_ops(parts);
+        Some(MicroOpResponse::default())
+    }
+
+    /// Atomic accesses always trap when misaligned, regardless of the policy
+    fn check_atomic_alignment(
+        &mut self,
+        address: u64,
+        size: u8,
+        access: MemoryAccess,
+    ) -> Option<MicroOpResponse> {
+        if is_aligned(address, size as u64) {
+            return None;
+        }
+        self.raise(Exception::from_bus_error(
+            access,
+            BusError::Misaligned,
+            address,
+        ));
+        self.micro_op_queue = VecDeque::from(vec![MicroOp::BusRelease, MicroOp::Halt]);
+        Some(MicroOpResponse::default())
+    }
+
+    /// Replaces the bus accesses of the queue with cache accesses
+    fn with_caches(&self, queue: VecDeque<MicroOp>) -> VecDeque<MicroOp> {
+        VecDeque::from(self.caches.cache_micro_ops(Vec::from(queue)))
+    }
+
+    /// Raises the exception of a failed cache access, the cache already released the bus
+    fn cache_fault(
+        &mut self,
+        access: MemoryAccess,
+        error: BusError,
+        address: u64,
+    ) -> MicroOpResponse {
+        self.raise(Exception::from_bus_error(access, error, address));
+        self.micro_op_queue = VecDeque::from(vec![MicroOp::Halt]);
+        MicroOpResponse::default()
+    }
+
+    fn push_front_micro_ops(&mut self, micro_ops: Vec<MicroOp>) {
+        for micro_op in micro_ops.into_iter().rev() {
+            self.micro_op_queue.push_front(micro_op);
+        }
+    }
+
+    fn read_access(register: CPUReg) -> MemoryAccess {
+        if register == IR {
+            MemoryAccess::Fetch
+        } else {
+            MemoryAccess::Load
+        }
+    }
 }
 
 /// Micro operations
@@ -95,13 +511,13 @@ impl CPU {
     }
 
     fn mo_bus_release(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.release_ownership(BusOwner::CPU);
+        let success = bus.release_ownership(self.bus_owner());
         log_microop_debug!("bus_release", "{}", if success { "✔" } else { "✘" });
         MicroOpResponse::default()
     }
 
     fn mo_bus_take(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.take_ownership(BusOwner::CPU);
+        let success = bus.take_ownership(self.bus_owner());
         if success {
             log_microop_debug!("bus_take", "✔");
             MicroOpResponse::default()
@@ -114,7 +530,7 @@ impl CPU {
     fn mo_bus_write_address(&mut self, bus: &mut Bus, register: CPUReg) -> MicroOpResponse {
         // Failed write operations will be ignored
         let address = Address::new(self.get_register(register));
-        bus.put_address(address, BusOwner::CPU);
+        bus.put_address(address, self.bus_owner());
         log_microop_debug!("bus_write_address", "{register} → {address}");
         MicroOpResponse::default()
     }
@@ -122,12 +538,15 @@ impl CPU {
     fn mo_bus_write_data(&mut self, bus: &mut Bus, register: CPUReg) -> MicroOpResponse {
         // Failed write operations will be ignored
         let value = self.get_register(register);
-        let success = bus.put_data(value, BusOwner::CPU);
+        let success = bus.put_data(value, self.bus_owner());
         log_microop_debug!("bus_write_data", "{register} ← {value}; Success: {success}");
         MicroOpResponse::default()
     }
 
     fn mo_bus_read_byte(&mut self, bus: &Bus, register: CPUReg) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, Self::read_access(register)) {
+            return response;
+        }
         let data = (bus.get_data() & 0xFF) as i8 as i64 as u64; // Sign extension
         self.set_register(register, data);
         log_microop_debug!("bus_read_byte", "{register} ← {data}");
@@ -135,6 +554,9 @@ impl CPU {
     }
 
     fn mo_bus_read_half_word(&mut self, bus: &Bus, register: CPUReg) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, Self::read_access(register)) {
+            return response;
+        }
         let data = (bus.get_data() & 0xFFFF) as i16 as i64 as u64;
         self.set_register(register, data);
         log_microop_debug!("bus_read_half_word", "{register} ← {data}");
@@ -142,6 +564,9 @@ impl CPU {
     }
 
     fn mo_bus_read_word(&mut self, bus: &Bus, register: CPUReg) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, Self::read_access(register)) {
+            return response;
+        }
         let data = (bus.get_data() & 0xFFFF_FFFF) as i32 as i64 as u64;
         self.set_register(register, data);
         log_microop_debug!("bus_read_word", "{register} ← {data}");
@@ -149,6 +574,9 @@ impl CPU {
     }
 
     fn mo_bus_read_double_word(&mut self, bus: &Bus, register: CPUReg) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, Self::read_access(register)) {
+            return response;
+        }
         let data = bus.get_data();
         self.set_register(register, data);
         log_microop_debug!("bus_read_word", "{register} ← {data}");
@@ -156,39 +584,328 @@ impl CPU {
     }
 
     fn mo_bus_set_read(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.put_status(BusStatus::Read, BusOwner::CPU);
+        // The read micro operation following the status determines the access size and kind
+        let read = self.micro_op_queue.front().copied();
+        let read = read.and_then(|read| Some((read.read_size()?, read.destinations()[0])));
+        let access = read.map(|(_, rd)| Self::read_access(rd));
+        if let (Some((size, _)), Some(access)) = (read, access) {
+            let address = bus.get_address().value();
+            if let Some(response) = self.check_alignment(address, size, access) {
+                return response;
+            }
+        }
+        let mut success = bus.put_status(BusStatus::Read, self.bus_owner());
+        if access == Some(MemoryAccess::Fetch) {
+            success &= bus.request_fetch(self.bus_owner());
+        }
         log_microop_debug!("bus_set_read", "{}", if success { "✔" } else { "✘" });
         MicroOpResponse::default()
     }
 
     fn mo_bus_set_write_byte(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.put_status(BusStatus::WriteByte, BusOwner::CPU);
+        if let Some(response) = self.check_store_alignment(bus, BusStatus::WriteByte) {
+            return response;
+        }
+        let success = bus.put_status(BusStatus::WriteByte, self.bus_owner());
         log_microop_debug!("bus_set_write_byte", "{}", if success { "✔" } else { "✘" });
         MicroOpResponse::default()
     }
 
     fn mo_bus_set_write_half_word(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.put_status(BusStatus::WriteHalfWord, BusOwner::CPU);
+        if let Some(response) = self.check_store_alignment(bus, BusStatus::WriteHalfWord) {
+            return response;
+        }
+        let success = bus.put_status(BusStatus::WriteHalfWord, self.bus_owner());
         log_microop_debug!("bus_set_write_hw", "{}", if success { "✔" } else { "✘" });
         MicroOpResponse::default()
     }
 
     fn mo_bus_set_write_word(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.put_status(BusStatus::WriteWord, BusOwner::CPU);
+        if let Some(response) = self.check_store_alignment(bus, BusStatus::WriteWord) {
+            return response;
+        }
+        let success = bus.put_status(BusStatus::WriteWord, self.bus_owner());
         log_microop_debug!("bus_set_write_w", "{}", if success { "✔" } else { "✘" });
         MicroOpResponse::default()
     }
 
     fn mo_bus_set_write_double_word(&mut self, bus: &mut Bus) -> MicroOpResponse {
-        let success = bus.put_status(BusStatus::WriteDoubleWord, BusOwner::CPU);
+        if let Some(response) = self.check_store_alignment(bus, BusStatus::WriteDoubleWord) {
+            return response;
+        }
+        let success = bus.put_status(BusStatus::WriteDoubleWord, self.bus_owner());
         log_microop_debug!("bus_set_write_dw", "{}", if success { "✔" } else { "✘" });
         MicroOpResponse::default()
     }
 
+    fn mo_bus_await_write(&mut self, bus: &Bus) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, MemoryAccess::Store) {
+            log_microop_debug!("bus_await_write", "{:?}", bus.get_response());
+            return response;
+        }
+        log_microop_debug!("bus_await_write", "✔");
+        MicroOpResponse::default()
+    }
+
+    fn mo_bus_set_idle(&mut self, bus: &mut Bus) -> MicroOpResponse {
+        let success = bus.put_status(BusStatus::Idle, self.bus_owner());
+        log_microop_debug!("bus_set_idle", "{}", if success { "✔" } else { "✘" });
+        MicroOpResponse::default()
+    }
+
+    #[cfg(test)]
+    fn mo_bus_set_read_burst(&mut self, bus: &mut Bus, beats: u8) -> MicroOpResponse {
+        let success = bus.put_status(BusStatus::ReadBurst(beats), self.bus_owner());
+        log_microop_debug!(
+            "bus_set_read_burst",
+            "{beats} beats {}",
+            if success { "✔" } else { "✘" }
+        );
+        MicroOpResponse::default()
+    }
+
+    #[cfg(test)]
+    fn mo_bus_set_write_burst(&mut self, bus: &mut Bus, beats: u8) -> MicroOpResponse {
+        let success = bus.put_status(BusStatus::WriteBurst(beats), self.bus_owner());
+        log_microop_debug!(
+            "bus_set_write_burst",
+            "{beats} beats {}",
+            if success { "✔" } else { "✘" }
+        );
+        MicroOpResponse::default()
+    }
+
+    #[cfg(test)]
+    fn mo_bus_read_beat(&mut self, bus: &mut Bus, register: CPUReg) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, MemoryAccess::Load) {
+            return response;
+        }
+        let data = bus.get_data();
+        self.set_register(register, data);
+        bus.next_beat(self.bus_owner());
+        log_microop_debug!("bus_read_beat", "#{} {register} ← {data}", bus.get_beat());
+        MicroOpResponse::default()
+    }
+
+    #[cfg(test)]
+    fn mo_bus_write_beat(&mut self, bus: &mut Bus, register: CPUReg) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, MemoryAccess::Store) {
+            return response;
+        }
+        let data = self.get_register(register);
+        bus.put_data(data, self.bus_owner());
+        let success = bus.next_beat(self.bus_owner());
+        log_microop_debug!(
+            "bus_write_beat",
+            "#{} ← {data} {}",
+            bus.get_beat(),
+            if success { "✔" } else { "✘" }
+        );
+        MicroOpResponse::default()
+    }
+
+    fn mo_bus_read_part(
+        &mut self,
+        bus: &Bus,
+        register: CPUReg,
+        offset: u8,
+        size: u8,
+    ) -> MicroOpResponse {
+        if let Some(response) = self.check_bus_response(bus, Self::read_access(register)) {
+            return response;
+        }
+        // The first part starts with a cleared register
+        let value = match offset {
+            0 => 0,
+            _ => self.get_register(register),
+        };
+        let value = merge_part(value, bus.get_data(), offset as u64, size as u64);
+        self.set_register(register, value);
+        log_microop_debug!("bus_read_part", "{register}[{offset}..+{size}] ← {value}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_register_sign_extend(&mut self, register: CPUReg, size: u8) -> MicroOpResponse {
+        let value = sign_extend(self.get_register(register), size as u64);
+        self.set_register(register, value);
+        log_microop_debug!("register_sign_extend", "{register} ← {value}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_bus_reserve(&mut self, bus: &mut Bus, size: u8) -> MicroOpResponse {
+        let address = bus.get_address();
+        let access = MemoryAccess::Load;
+        if let Some(response) = self.check_atomic_alignment(address.value(), size, access) {
+            return response;
+        }
+        bus.reserve(address, self.bus_owner());
+        log_microop_debug!("bus_reserve", "{address}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_bus_store_conditional(&mut self, bus: &mut Bus, rd: CPUReg, size: u8) -> MicroOpResponse {
+        let address = bus.get_address();
+        let access = MemoryAccess::Store;
+        if let Some(response) = self.check_atomic_alignment(address.value(), size, access) {
+            return response;
+        }
+        let reserved = bus.take_reservation(address, self.bus_owner());
+        self.set_register(rd, !reserved as u64);
+        if !reserved {
+            // Drops the write, the bus is released without a transaction
+            while self
+                .micro_op_queue
+                .front()
+                .is_some_and(|micro_op| *micro_op != MicroOp::BusRelease)
+            {
+                self.micro_op_queue.pop_front();
+            }
+        }
+        log_microop_debug!(
+            "bus_store_cond",
+            "{address} {}",
+            if reserved {
+                "✔"
+            } else {
+                "✘ (reservation lost)"
+            }
+        );
+        MicroOpResponse::default()
+    }
+
+    fn mo_csr_read(&mut self, rd: CPUReg, csr: u16) -> MicroOpResponse {
+        let value = self.csrs.read(csr);
+        self.set_register(rd, value);
+        log_microop_debug!("csr_read", "{rd} ← CSR[{csr:#05x}] = {value}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_csr_access(&mut self, op: CsrOp, rd: CPUReg, csr: u16, rs: CPUReg) -> MicroOpResponse {
+        let operand = self.get_register(rs);
+        let old = self.csrs.access(op, csr, operand);
+        self.set_register(rd, old);
+        log_microop_debug!(
+            "csr_access",
+            "{rd} ← CSR[{csr:#05x}] = {old}; {op:?} {operand}"
+        );
+        MicroOpResponse::default()
+    }
+
+    fn mo_mret(&mut self) -> MicroOpResponse {
+        let address = self.csrs.mret();
+        self.set_register(PC, address);
+        log_microop_debug!("mret", "PC ← {address:#x}");
+        MicroOpResponse::default()
+    }
+
+    /// Reads through the instruction cache if rd is IR, otherwise through the data cache
+    fn mo_cache_read(
+        &mut self,
+        bus: &mut Bus,
+        address_register: CPUReg,
+        rd: CPUReg,
+        size: u64,
+    ) -> MicroOpResponse {
+        let address = self.get_register(address_register);
+        let access = Self::read_access(rd);
+        let owner = self.bus_owner();
+        let Some(cache) = self.caches.serving(rd == IR, address, size) else {
+            let mut fallback = Vec::new();
+            if rd != IR && self.caches.needs_flush(address, size) {
+                fallback.push(MicroOp::DCacheFlush(address_register, size as u8));
+            }
+            fallback.extend([
+                MicroOp::BusTake,
+                MicroOp::BusWriteAddress(address_register),
+                MicroOp::BusSetRead,
+                MicroOp::read_of_size(size, rd),
+                MicroOp::BusRelease,
+            ]);
+            log_microop_debug!("cache_read", "{address:#x} bypasses the cache");
+            self.push_front_micro_ops(fallback);
+            return MicroOpResponse::default();
+        };
+        let request = CacheRequest::Read { address, size };
+        match cache.access(request, bus, owner) {
+            None => MicroOpResponse::new_repeat(),
+            Some(Ok(data)) => {
+                let data = sign_extend(data, size);
+                self.set_register(rd, data);
+                log_microop_debug!("cache_read", "{rd} ← {data}");
+                MicroOpResponse::default()
+            }
+            Some(Err(error)) => self.cache_fault(access, error, address),
+        }
+    }
+
+    fn mo_dcache_write(
+        &mut self,
+        bus: &mut Bus,
+        address_register: CPUReg,
+        data_register: CPUReg,
+        size: u64,
+    ) -> MicroOpResponse {
+        let address = self.get_register(address_register);
+        let data = self.get_register(data_register);
+        let owner = self.bus_owner();
+        let Some(cache) = self.caches.serving(false, address, size) else {
+            let mut fallback = Vec::new();
+            if self.caches.needs_flush(address, size) {
+                fallback.push(MicroOp::DCacheFlush(address_register, size as u8));
+            }
+            fallback.extend([
+                MicroOp::BusTake,
+                MicroOp::BusWriteAddress(address_register),
+                MicroOp::BusWriteData(data_register),
+                MicroOp::set_write(size),
+                MicroOp::BusAwaitWrite,
+                MicroOp::BusRelease,
+            ]);
+            log_microop_debug!("dcache_write", "{address:#x} bypasses the cache");
+            self.push_front_micro_ops(fallback);
+            return MicroOpResponse::default();
+        };
+        let request = CacheRequest::Write {
+            address,
+            data,
+            size,
+        };
+        match cache.access(request, bus, owner) {
+            None => MicroOpResponse::new_repeat(),
+            Some(Ok(_)) => {
+                log_microop_debug!("dcache_write", "{address:#x} ← {data}");
+                MicroOpResponse::default()
+            }
+            Some(Err(error)) => self.cache_fault(MemoryAccess::Store, error, address),
+        }
+    }
+
+    fn mo_dcache_flush(
+        &mut self,
+        bus: &mut Bus,
+        address_register: CPUReg,
+        size: u64,
+    ) -> MicroOpResponse {
+        let address = self.get_register(address_register);
+        let owner = self.bus_owner();
+        let Some(cache) = self.caches.data.as_mut() else {
+            return MicroOpResponse::default();
+        };
+        match cache.access(CacheRequest::Flush { address, size }, bus, owner) {
+            None => MicroOpResponse::new_repeat(),
+            Some(Ok(_)) => {
+                log_microop_debug!("dcache_flush", "{address:#x}");
+                MicroOpResponse::default()
+            }
+            Some(Err(error)) => self.cache_fault(MemoryAccess::Store, error, address),
+        }
+    }
+
     fn mo_decode(&mut self) -> MicroOpResponse {
         let instruction_bits = self.get_register(IR) as u32;
-        let (instruction, queue) = decompose_instruction(instruction_bits);
-        self.micro_op_queue = VecDeque::from(queue);
+        let Some(instruction) = Instruction::try_decode(instruction_bits) else {
+            return self.illegal_instruction(instruction_bits);
+        };
         self.decode_counter = self.decode_counter.wrapping_add(1);
         log_microop_debug!(
             "decode",
@@ -196,59 +913,206 @@ impl CPU {
             self.decode_counter,
             instruction_bits
         );
+
+        if self.fusion.macro_op && is_macro_fusion_head(instruction) {
+            self.pending_fusion = Some(instruction);
+            self.micro_op_queue = self.with_caches(MicroOp::prefetch_queue());
+        } else {
+            let queue = decompose_instruction(instruction, 4);
+            self.set_micro_op_queue(queue);
+        }
+        MicroOpResponse::default()
+    }
+
+    fn mo_decode_fused(&mut self) -> MicroOpResponse {
+        let first = self.pending_fusion.take().unwrap();
+        let instruction_bits = self.get_register(IR) as u32;
+        let Some(second) = Instruction::try_decode(instruction_bits) else {
+            // The head executes alone, the second instruction raises once it is fetched again
+            self.set_register(PC, self.get_register(PC).wrapping_sub(4));
+            self.set_micro_op_queue(decompose_instruction(first, 4));
+            return MicroOpResponse::default();
+        };
+        self.decode_counter = self.decode_counter.wrapping_add(1);
+        log_microop_debug!(
+            "decode_fused",
+            "#{}: {:032b} | {second}",
+            self.decode_counter,
+            instruction_bits
+        );
+
+        // PC already points past the prefetched instruction
+        let mut queue = decompose_instruction(first, 8);
+        queue.extend(decompose_instruction(second, 4));
+        match fuse_instructions(first, second, queue.len(), &mut self.fusion_stats) {
+            Some(fused) => self.micro_op_queue = self.with_caches(VecDeque::from(fused)),
+            None => self.set_micro_op_queue(queue),
+        }
+        MicroOpResponse::default()
+    }
+
+    /// Halts on the undecodable instruction in IR with PC moved back onto it
+    fn illegal_instruction(&mut self, instruction_bits: u32) -> MicroOpResponse {
+        let pc = self.get_register(PC).wrapping_sub(4);
+        self.set_register(PC, pc);
+        self.raise(Exception::IllegalInstruction(instruction_bits));
+        self.micro_op_queue = VecDeque::from(vec![MicroOp::Halt]);
         MicroOpResponse::default()
     }
 
+    fn set_micro_op_queue(&mut self, queue: Vec<MicroOp>) {
+        let queue = if self.fusion.micro_op {
+            fuse_micro_ops(queue, &mut self.fusion_stats)
+        } else {
+            queue
+        };
+        self.micro_op_queue = VecDeque::from(self.caches.cache_micro_ops(queue));
+    }
+
     fn mo_register_load_imm(&mut self, register: CPUReg, imm: u64) -> MicroOpResponse {
         self.set_register(register, imm);
         log_microop_debug!("register_load_imm", "{register} ← {imm}");
         MicroOpResponse::default()
     }
+
+    fn mo_register_move(&mut self, rd: CPUReg, rs: CPUReg) -> MicroOpResponse {
+        let value = self.get_register(rs);
+        self.set_register(rd, value);
+        log_microop_debug!("register_move", "{rd} ← {rs}({value})");
+        MicroOpResponse::default()
+    }
 }
 
-/// ALU OPERATIONS
+/// AGU OPERATIONS
 impl CPU {
-    fn mo_alu_add(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+    fn mo_agu_add(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
         let value1 = self.get_register(rs1);
         let value2 = self.get_register(rs2);
-        let (mut result, carry) = value1.overflowing_add(value2);
-        if carry {
-            result = u64::MAX;
-        }
+        let result = value1.wrapping_add(value2);
         self.set_register(rd, result);
-        self.set_carry(carry);
-        self.set_zero(result == 0);
-        self.set_subtract(false);
         log_microop_debug!(
-            "alu_add",
+            "agu_add",
             "{rd}({result}) = {rs1}({value1}) + {rs2}({value2})"
         );
         MicroOpResponse::default()
     }
 
-    fn mo_alu_and(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+    fn mo_agu_add_aligned(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
         let value1 = self.get_register(rs1);
         let value2 = self.get_register(rs2);
-        let result = value1 & value2;
+        let result = value1.wrapping_add(value2) & !1;
         self.set_register(rd, result);
-        self.set_carry(false);
-        self.set_zero(result == 0);
-        self.set_subtract(false);
         log_microop_debug!(
-            "alu_and",
-            "{rd}({result}) = {rs1}({value1}) & {rs2}({value2})"
+            "agu_add_aligned",
+            "{rd}({result}) = ({rs1}({value1}) + {rs2}({value2})) & !1"
         );
         MicroOpResponse::default()
     }
+}
 
-    fn mo_alu_or(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+/// BRANCH OPERATIONS
+impl CPU {
+    fn mo_branch(
+        &mut self,
+        condition: BranchCondition,
+        rs1: CPUReg,
+        rs2: CPUReg,
+        offset: CPUReg,
+    ) -> MicroOpResponse {
         let value1 = self.get_register(rs1);
         let value2 = self.get_register(rs2);
-        let result = value1 | value2;
+        let taken = condition.evaluate(value1, value2);
+        if taken {
+            let target = self
+                .get_register(PC)
+                .wrapping_add(self.get_register(offset));
+            self.set_register(PC, target);
+        }
+        log_microop_debug!(
+            "branch",
+            "{condition:?} {rs1}({value1}), {rs2}({value2}): {}",
+            if taken { "taken" } else { "not taken" }
+        );
+        MicroOpResponse::default()
+    }
+}
+
+/// FUSED OPERATIONS
+impl CPU {
+    fn mo_alu_add_imm(&mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> MicroOpResponse {
+        let value = self.get_register(rs1);
+        let result = self.alu_write(rd, ALUOp::Addi.compute(value, imm));
+        log_microop_debug!("alu_add_imm", "{rd}({result}) = {rs1}({value}) + {imm}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_agu_add_imm(&mut self, rd: CPUReg, rs1: CPUReg, imm: u64) -> MicroOpResponse {
+        let value = self.get_register(rs1);
+        let result = value.wrapping_add(imm);
         self.set_register(rd, result);
-        self.set_carry(false);
-        self.set_zero(result == 0);
-        self.set_subtract(false);
+        log_microop_debug!("agu_add_imm", "{rd}({result}) = {rs1}({value}) + {imm}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_fused_lui_addi(&mut self, rd: CPUReg, upper: u64, imm: u64) -> MicroOpResponse {
+        let result = self.alu_write(rd, ALUOp::Addi.compute(upper, imm));
+        log_microop_debug!("fused_lui_addi", "{rd}({result}) = {upper} + {imm}");
+        MicroOpResponse::default()
+    }
+
+    fn mo_fused_auipc_jalr(
+        &mut self,
+        rd_auipc: CPUReg,
+        rd_jalr: CPUReg,
+        upper: u64,
+        imm: u64,
+    ) -> MicroOpResponse {
+        // PC already points past both instructions
+        let return_address = self.get_register(PC);
+        let base = return_address.wrapping_sub(8).wrapping_add(upper);
+        let target = base.wrapping_add(imm) & !1;
+        self.set_register(rd_auipc, base);
+        self.set_register(rd_jalr, return_address);
+        self.set_register(PC, target);
+        log_microop_debug!(
+            "fused_auipc_jalr",
+            "{rd_auipc} ← {base}; {rd_jalr} ← {return_address}; PC ← {target}"
+        );
+        MicroOpResponse::default()
+    }
+}
+
+/// ALU OPERATIONS
+impl CPU {
+    fn mo_alu_add(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+        let (result, value1, value2) = self.alu_execute(ALUOp::Add, rd, rs1, rs2);
+        log_microop_debug!(
+            "alu_add",
+            "{rd}({result}) = {rs1}({value1}) + {rs2}({value2})"
+        );
+        MicroOpResponse::default()
+    }
+
+    fn mo_alu_addi(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+        let (result, value1, value2) = self.alu_execute(ALUOp::Addi, rd, rs1, rs2);
+        log_microop_debug!(
+            "alu_addi",
+            "{rd}({result}) = {rs1}({value1}) + {rs2}({value2})"
+        );
+        MicroOpResponse::default()
+    }
+
+    fn mo_alu_and(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+        let (result, value1, value2) = self.alu_execute(ALUOp::And, rd, rs1, rs2);
+        log_microop_debug!(
+            "alu_and",
+            "{rd}({result}) = {rs1}({value1}) & {rs2}({value2})"
+        );
+        MicroOpResponse::default()
+    }
+
+    fn mo_alu_or(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
+        let (result, value1, value2) = self.alu_execute(ALUOp::Or, rd, rs1, rs2);
         log_microop_debug!(
             "alu_or",
             "{rd}({result}) = {rs1}({value1}) | {rs2}({value2})"
@@ -257,16 +1121,7 @@ impl CPU {
     }
 
     fn mo_alu_sub(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
-        let value1 = self.get_register(rs1);
-        let value2 = self.get_register(rs2);
-        let (mut result, carry) = value1.overflowing_sub(value2);
-        if carry {
-            result = 0;
-        }
-        self.set_register(rd, result);
-        self.set_carry(carry);
-        self.set_zero(result == 0);
-        self.set_subtract(true);
+        let (result, value1, value2) = self.alu_execute(ALUOp::Sub, rd, rs1, rs2);
         log_microop_debug!(
             "alu_sub",
             "{rd}({result}) = {rs1}({value1}) - {rs2}({value2})"
@@ -275,13 +1130,7 @@ impl CPU {
     }
 
     fn mo_alu_xor(&mut self, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> MicroOpResponse {
-        let value1 = self.get_register(rs1);
-        let value2 = self.get_register(rs2);
-        let result = value1 ^ value2;
-        self.set_register(rd, result);
-        self.set_carry(false);
-        self.set_zero(result == 0);
-        self.set_subtract(false);
+        let (result, value1, value2) = self.alu_execute(ALUOp::Xor, rd, rs1, rs2);
         log_microop_debug!(
             "alu_xor",
             "{rd}({result}) = {rs1}({value1}) ^ {rs2}({value2})"
@@ -295,10 +1144,8 @@ impl CPU {
         rs1: CPUReg,
         rs2: CPUReg,
     ) -> MicroOpResponse {
-        let value = self.get_register(rs1);
-        let shift = self.get_register(rs2) & 0b11_1111;
-        let result = value << shift;
-        self.set_register(rd, result);
+        let (result, value, shift) = self.alu_execute(ALUOp::Sll, rd, rs1, rs2);
+        let shift = shift & 0b11_1111;
         log_microop_debug!(
             "alu_sll",
             "{rd}({result}) = {rs1}({value}) << {rs2}({shift})"
@@ -312,10 +1159,8 @@ impl CPU {
         rs1: CPUReg,
         rs2: CPUReg,
     ) -> MicroOpResponse {
-        let value = self.get_register(rs1);
-        let shift = self.get_register(rs2) & 0b11_1111;
-        let result = value >> shift;
-        self.set_register(rd, result);
+        let (result, value, shift) = self.alu_execute(ALUOp::Srl, rd, rs1, rs2);
+        let shift = shift & 0b11_1111;
         log_microop_debug!(
             "alu_srl",
             "{rd}({result}) = {rs1}({value}) >> {rs2}({shift})"
@@ -329,16 +1174,34 @@ impl CPU {
         rs1: CPUReg,
         rs2: CPUReg,
     ) -> MicroOpResponse {
-        let value = self.get_register(rs1);
-        let shift = self.get_register(rs2) & 0b11_1111;
-        let result = (value as i64 >> shift) as u64;
-        self.set_register(rd, result);
+        let (result, value, shift) = self.alu_execute(ALUOp::Sra, rd, rs1, rs2);
+        let shift = shift & 0b11_1111;
         log_microop_debug!(
             "alu_sra",
             "{rd}({result}) = {rs1}({value}) >>* {rs2}({shift})"
         );
         MicroOpResponse::default()
     }
+
+    /// Reads both operands, writes the result to rd and updates the flags.
+    /// Returns (result, value1, value2) for logging purposes.
+    fn alu_execute(&mut self, op: ALUOp, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> (u64, u64, u64) {
+        let value1 = self.get_register(rs1);
+        let value2 = self.get_register(rs2);
+        let result = self.alu_write(rd, op.compute(value1, value2));
+        (result, value1, value2)
+    }
+
+    /// Writes the result to rd and updates the flags, returns the written value
+    fn alu_write(&mut self, rd: CPUReg, result: ALUResult) -> u64 {
+        self.set_register(rd, result.value);
+        if let Some(flags) = result.flags {
+            self.set_zero(flags.zero);
+            self.set_carry(flags.carry);
+            self.set_subtract(flags.subtract);
+        }
+        result.value
+    }
 }
 
 impl CPURegistersAccessTrait for CPU {
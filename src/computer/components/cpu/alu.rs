@@ -0,0 +1,101 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ALUOp {
+    Add,
+    /// Wrapping addition of a sign-extended immediate, sets the carry flag on overflow
+    Addi,
+    And,
+    Or,
+    Sub,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+}
+
+/// Comparison evaluated by conditional branches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BranchCondition {
+    Equal,
+    NotEqual,
+    LessThan,
+    GreaterEqual,
+    LessThanUnsigned,
+    GreaterEqualUnsigned,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ALUFlags {
+    pub zero: bool,
+    pub carry: bool,
+    pub subtract: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ALUResult {
+    pub value: u64,
+    /// Shift operations leave the flags register untouched
+    pub flags: Option<ALUFlags>,
+}
+
+impl ALUOp {
+    pub fn compute(&self, value1: u64, value2: u64) -> ALUResult {
+        match self {
+            ALUOp::Add => {
+                let (mut result, carry) = value1.overflowing_add(value2);
+                if carry {
+                    result = u64::MAX;
+                }
+                ALUResult::with_flags(result, carry, false)
+            }
+            ALUOp::Addi => {
+                let (result, carry) = value1.overflowing_add(value2);
+                ALUResult::with_flags(result, carry, false)
+            }
+            ALUOp::And => ALUResult::with_flags(value1 & value2, false, false),
+            ALUOp::Or => ALUResult::with_flags(value1 | value2, false, false),
+            ALUOp::Sub => {
+                let (mut result, carry) = value1.overflowing_sub(value2);
+                if carry {
+                    result = 0;
+                }
+                ALUResult::with_flags(result, carry, true)
+            }
+            ALUOp::Xor => ALUResult::with_flags(value1 ^ value2, false, false),
+            ALUOp::Sll => ALUResult::without_flags(value1 << (value2 & 0b11_1111)),
+            ALUOp::Srl => ALUResult::without_flags(value1 >> (value2 & 0b11_1111)),
+            ALUOp::Sra => {
+                ALUResult::without_flags(((value1 as i64) >> (value2 & 0b11_1111)) as u64)
+            }
+        }
+    }
+}
+
+impl BranchCondition {
+    pub fn evaluate(&self, value1: u64, value2: u64) -> bool {
+        match self {
+            BranchCondition::Equal => value1 == value2,
+            BranchCondition::NotEqual => value1 != value2,
+            BranchCondition::LessThan => (value1 as i64) < (value2 as i64),
+            BranchCondition::GreaterEqual => (value1 as i64) >= (value2 as i64),
+            BranchCondition::LessThanUnsigned => value1 < value2,
+            BranchCondition::GreaterEqualUnsigned => value1 >= value2,
+        }
+    }
+}
+
+impl ALUResult {
+    fn with_flags(value: u64, carry: bool, subtract: bool) -> Self {
+        Self {
+            value,
+            flags: Some(ALUFlags {
+                zero: value == 0,
+                carry,
+                subtract,
+            }),
+        }
+    }
+
+    fn without_flags(value: u64) -> Self {
+        Self { value, flags: None }
+    }
+}
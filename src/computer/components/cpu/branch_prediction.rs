@@ -0,0 +1,172 @@
+use crate::computer::components::cpu::branch_prediction::btb::BranchTargetBuffer;
+use crate::computer::components::cpu::branch_prediction::predictors::StaticNotTaken;
+use crate::computer::components::cpu::branch_prediction::ras::ReturnAddressStack;
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::instructions::Instruction;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+pub mod btb;
+pub mod predictors;
+pub mod ras;
+
+/// Direction predictor for conditional branches
+pub trait BranchPredictor: Debug {
+    fn name(&self) -> &'static str;
+
+    /// Predicts whether the conditional branch at the given address is taken
+    fn predict(&self, address: u64) -> bool;
+
+    /// Trains the predictor with the outcome of a committed branch
+    fn update(&mut self, address: u64, taken: bool);
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BranchRecord {
+    pub executed: u64,
+    pub taken: u64,
+    pub mispredicted: u64,
+}
+
+impl BranchRecord {
+    pub fn accuracy(&self) -> f64 {
+        if self.executed == 0 {
+            return 1.0;
+        }
+        1.0 - self.mispredicted as f64 / self.executed as f64
+    }
+
+    fn record(&mut self, taken: bool, mispredicted: bool) {
+        self.executed = self.executed.wrapping_add(1);
+        self.taken = self.taken.wrapping_add(taken as u64);
+        self.mispredicted = self.mispredicted.wrapping_add(mispredicted as u64);
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BranchStats {
+    /// Committed control instructions by address
+    pub branches: BTreeMap<u64, BranchRecord>,
+    pub btb_hits: u64,
+    pub btb_misses: u64,
+    pub ras_predictions: u64,
+}
+
+impl BranchStats {
+    #[cfg(test)]
+    pub fn get(&self, address: u64) -> BranchRecord {
+        self.branches.get(&address).copied().unwrap_or_default()
+    }
+
+    pub fn total(&self) -> BranchRecord {
+        self.branches
+            .values()
+            .fold(BranchRecord::default(), |total, record| BranchRecord {
+                executed: total.executed + record.executed,
+                taken: total.taken + record.taken,
+                mispredicted: total.mispredicted + record.mispredicted,
+            })
+    }
+}
+
+/// Predicts the fetch address following control instructions.
+/// Conditional branches use the pluggable direction predictor, indirect jumps the BTB
+/// and returns the return-address stack. Direct targets are computed at fetch since
+/// instructions are decoded there.
+///
+/// The return-address stack is updated speculatively and not repaired after mispredictions.
+#[derive(Debug)]
+pub struct BranchPredictionUnit {
+    predictor: Box<dyn BranchPredictor>,
+    btb: BranchTargetBuffer,
+    ras: ReturnAddressStack,
+    stats: BranchStats,
+}
+
+impl BranchPredictionUnit {
+    pub fn new(btb_entries: usize, ras_depth: usize) -> Self {
+        Self {
+            predictor: Box::new(StaticNotTaken),
+            btb: BranchTargetBuffer::new(btb_entries),
+            ras: ReturnAddressStack::new(ras_depth),
+            stats: BranchStats::default(),
+        }
+    }
+
+    pub fn set_predictor(&mut self, predictor: Box<dyn BranchPredictor>) {
+        self.predictor = predictor;
+    }
+
+    pub fn get_predictor(&self) -> &dyn BranchPredictor {
+        self.predictor.as_ref()
+    }
+
+    pub fn get_stats(&self) -> &BranchStats {
+        &self.stats
+    }
+
+    /// Predicts the address following the control instruction, None if it cannot be predicted
+    pub fn predict(&mut self, address: u64, instruction: Instruction) -> Option<u64> {
+        let fallthrough = address.wrapping_add(4);
+        match instruction {
+            Instruction::Jal(rd, _) => {
+                if is_link(rd) {
+                    self.ras.push(fallthrough);
+                }
+                instruction.direct_target(address)
+            }
+            Instruction::Jalr(rd, rs1, _) => {
+                let target = if rd == CPUReg::X0 && is_link(rs1) {
+                    self.stats.ras_predictions = self.stats.ras_predictions.wrapping_add(1);
+                    self.ras.pop()
+                } else {
+                    let target = self.btb.lookup(address);
+                    match target {
+                        Some(_) => self.stats.btb_hits = self.stats.btb_hits.wrapping_add(1),
+                        None => self.stats.btb_misses = self.stats.btb_misses.wrapping_add(1),
+                    }
+                    target
+                };
+                if is_link(rd) {
+                    self.ras.push(fallthrough);
+                }
+                target
+            }
+            _ if instruction.is_branch() => {
+                if self.predictor.predict(address) {
+                    instruction.direct_target(address)
+                } else {
+                    Some(fallthrough)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Trains the predictors with a committed control instruction
+    pub fn resolve(
+        &mut self,
+        address: u64,
+        instruction: Instruction,
+        next_address: u64,
+        mispredicted: bool,
+    ) {
+        let taken = next_address != address.wrapping_add(4);
+        if instruction.is_branch() {
+            self.predictor.update(address, taken);
+        }
+        if let Instruction::Jalr(..) = instruction {
+            self.btb.update(address, next_address);
+        }
+        self.stats
+            .branches
+            .entry(address)
+            .or_default()
+            .record(taken, mispredicted);
+    }
+}
+
+/// Link registers by RISC-V calling convention
+fn is_link(register: CPUReg) -> bool {
+    matches!(register, CPUReg::X1 | CPUReg::X5)
+}
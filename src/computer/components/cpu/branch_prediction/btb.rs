@@ -0,0 +1,37 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BranchTargetBufferEntry {
+    address: u64,
+    target: u64,
+}
+
+/// Direct-mapped cache of the last target of indirect jumps
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BranchTargetBuffer {
+    entries: Vec<Option<BranchTargetBufferEntry>>,
+}
+
+impl BranchTargetBuffer {
+    pub fn new(size: usize) -> Self {
+        Self {
+            entries: vec![None; size],
+        }
+    }
+
+    pub fn lookup(&self, address: u64) -> Option<u64> {
+        let entry = (*self.entries.get(self.index(address)?)?)?;
+        (entry.address == address).then_some(entry.target)
+    }
+
+    pub fn update(&mut self, address: u64, target: u64) {
+        if let Some(index) = self.index(address) {
+            self.entries[index] = Some(BranchTargetBufferEntry { address, target });
+        }
+    }
+
+    fn index(&self, address: u64) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        Some(((address >> 2) % self.entries.len() as u64) as usize)
+    }
+}
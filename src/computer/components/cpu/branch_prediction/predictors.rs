@@ -0,0 +1,164 @@
+use crate::computer::components::cpu::branch_prediction::BranchPredictor;
+
+/// Two-bit saturating counter, predicts taken in the upper half
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SaturatingCounter(u8);
+
+impl Default for SaturatingCounter {
+    /// Weakly not taken
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+impl SaturatingCounter {
+    fn is_taken(&self) -> bool {
+        self.0 >= 2
+    }
+
+    fn update(&mut self, taken: bool) {
+        self.0 = if taken {
+            (self.0 + 1).min(3)
+        } else {
+            self.0.saturating_sub(1)
+        };
+    }
+}
+
+fn table_index(value: u64, size: usize) -> usize {
+    (value % size.max(1) as u64) as usize
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StaticNotTaken;
+
+impl BranchPredictor for StaticNotTaken {
+    fn name(&self) -> &'static str {
+        "static not-taken"
+    }
+
+    fn predict(&self, _address: u64) -> bool {
+        false
+    }
+
+    fn update(&mut self, _address: u64, _taken: bool) {}
+}
+
+/// Per-address two-bit counters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bimodal {
+    counters: Vec<SaturatingCounter>,
+}
+
+impl Bimodal {
+    pub fn new(entries: usize) -> Self {
+        Self {
+            counters: vec![SaturatingCounter::default(); entries.max(1)],
+        }
+    }
+
+    fn index(&self, address: u64) -> usize {
+        table_index(address >> 2, self.counters.len())
+    }
+}
+
+impl BranchPredictor for Bimodal {
+    fn name(&self) -> &'static str {
+        "bimodal"
+    }
+
+    fn predict(&self, address: u64) -> bool {
+        self.counters[self.index(address)].is_taken()
+    }
+
+    fn update(&mut self, address: u64, taken: bool) {
+        let index = self.index(address);
+        self.counters[index].update(taken);
+    }
+}
+
+/// Two-bit counters indexed by the address XOR the global history of committed branches
+#[derive(Debug, Clone, PartialEq)]
+pub struct GShare {
+    counters: Vec<SaturatingCounter>,
+    history: u64,
+    history_mask: u64,
+}
+
+impl GShare {
+    pub fn new(entries: usize, history_bits: u32) -> Self {
+        Self {
+            counters: vec![SaturatingCounter::default(); entries.max(1)],
+            history: 0,
+            history_mask: 1u64.checked_shl(history_bits).unwrap_or(0).wrapping_sub(1),
+        }
+    }
+
+    fn index(&self, address: u64) -> usize {
+        table_index((address >> 2) ^ self.history, self.counters.len())
+    }
+}
+
+impl BranchPredictor for GShare {
+    fn name(&self) -> &'static str {
+        "gshare"
+    }
+
+    fn predict(&self, address: u64) -> bool {
+        self.counters[self.index(address)].is_taken()
+    }
+
+    fn update(&mut self, address: u64, taken: bool) {
+        let index = self.index(address);
+        self.counters[index].update(taken);
+        self.history = ((self.history << 1) | taken as u64) & self.history_mask;
+    }
+}
+
+/// Chooses between bimodal and gshare per address, based on which was right more often
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tournament {
+    bimodal: Bimodal,
+    gshare: GShare,
+    /// Taken means prefer gshare
+    choosers: Vec<SaturatingCounter>,
+}
+
+impl Tournament {
+    pub fn new(entries: usize, history_bits: u32) -> Self {
+        Self {
+            bimodal: Bimodal::new(entries),
+            gshare: GShare::new(entries, history_bits),
+            choosers: vec![SaturatingCounter::default(); entries.max(1)],
+        }
+    }
+
+    fn index(&self, address: u64) -> usize {
+        table_index(address >> 2, self.choosers.len())
+    }
+}
+
+impl BranchPredictor for Tournament {
+    fn name(&self) -> &'static str {
+        "tournament"
+    }
+
+    fn predict(&self, address: u64) -> bool {
+        if self.choosers[self.index(address)].is_taken() {
+            self.gshare.predict(address)
+        } else {
+            self.bimodal.predict(address)
+        }
+    }
+
+    fn update(&mut self, address: u64, taken: bool) {
+        let bimodal_correct = self.bimodal.predict(address) == taken;
+        let gshare_correct = self.gshare.predict(address) == taken;
+        if bimodal_correct != gshare_correct {
+            let index = self.index(address);
+            self.choosers[index].update(gshare_correct);
+        }
+        self.bimodal.update(address, taken);
+        self.gshare.update(address, taken);
+    }
+}
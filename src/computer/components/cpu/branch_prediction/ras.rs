@@ -0,0 +1,29 @@
+/// Return-address stack, the oldest entry is dropped on overflow
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReturnAddressStack {
+    depth: usize,
+    entries: Vec<u64>,
+}
+
+impl ReturnAddressStack {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            entries: Vec::with_capacity(depth),
+        }
+    }
+
+    pub fn push(&mut self, address: u64) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.entries.len() == self.depth {
+            self.entries.remove(0);
+        }
+        self.entries.push(address);
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        self.entries.pop()
+    }
+}
@@ -1,11 +1,24 @@
+use crate::computer::components::cpu::branch_prediction::BranchPredictor;
+use crate::computer::components::cpu::cache::CacheConfig;
+use crate::computer::components::cpu::fusion::FusionConfig;
+use crate::computer::components::cpu::misaligned::MisalignedPolicy;
+use crate::computer::components::cpu::ooo::OoOConfig;
 use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
 use crate::computer::components::cpu::registers::flags::CPUFlagsAccessTrait;
 use crate::computer::components::cpu::registers::{CPURegisters, CPURegistersAccessTrait};
 use crate::computer::components::cpu::CPU;
+use std::fmt::Display;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default)]
 pub struct CPUBuilder {
     registers: CPURegisters,
+    fusion: FusionConfig,
+    out_of_order: Option<OoOConfig>,
+    issue_width: Option<usize>,
+    branch_predictor: Option<Box<dyn BranchPredictor>>,
+    misaligned: MisalignedPolicy,
+    icache: Option<CacheConfig>,
+    dcache: Option<CacheConfig>,
 }
 
 impl CPUBuilder {
@@ -13,10 +26,86 @@ impl CPUBuilder {
         Self::default()
     }
 
-    pub fn build(self) -> CPU {
+    pub fn micro_op_fusion(mut self, enabled: bool) -> Self {
+        self.fusion.micro_op = enabled;
+        self
+    }
+
+    pub fn macro_op_fusion(mut self, enabled: bool) -> Self {
+        self.fusion.macro_op = enabled;
+        self
+    }
+
+    pub fn out_of_order(mut self, config: OoOConfig) -> Self {
+        self.out_of_order = Some(config);
+        self
+    }
+
+    /// Enables the out-of-order backend with the default configuration if not already enabled.
+    /// Overrides the width of the out-of-order configuration regardless of the call order.
+    pub fn issue_width(mut self, width: usize) -> Self {
+        self.issue_width = Some(width);
+        self
+    }
+
+    /// Only the out-of-order backend predicts branches, building without it fails.
+    /// Defaults to static not-taken.
+    pub fn branch_predictor(mut self, predictor: impl BranchPredictor + 'static) -> Self {
+        self.branch_predictor = Some(Box::new(predictor));
+        self
+    }
+
+    pub fn misaligned_policy(mut self, policy: MisalignedPolicy) -> Self {
+        self.misaligned = policy;
+        self
+    }
+
+    pub fn icache(mut self, config: CacheConfig) -> Self {
+        self.icache = Some(config);
+        self
+    }
+
+    pub fn dcache(mut self, config: CacheConfig) -> Self {
+        self.dcache = Some(config);
+        self
+    }
+
+    pub fn build(self) -> Result<CPU, CPUBuildError> {
+        let out_of_order = match self.issue_width {
+            Some(issue_width) => Some(OoOConfig {
+                issue_width,
+                ..self.out_of_order.unwrap_or_default()
+            }),
+            None => self.out_of_order,
+        };
         let mut cpu = CPU::new();
         cpu.set_registers(self.registers);
-        cpu
+        cpu.set_fusion(self.fusion);
+        cpu.set_out_of_order(out_of_order);
+        cpu.set_misaligned_policy(self.misaligned);
+        cpu.set_icache(self.icache);
+        cpu.set_dcache(self.dcache);
+        if let Some(predictor) = self.branch_predictor {
+            cpu.set_branch_predictor(predictor)
+                .map_err(|_| CPUBuildError::PredictorWithoutOutOfOrder)?;
+        }
+        Ok(cpu)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CPUBuildError {
+    /// A branch predictor was given without enabling the out-of-order backend
+    PredictorWithoutOutOfOrder,
+}
+
+impl Display for CPUBuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CPUBuildError::PredictorWithoutOutOfOrder => {
+                write!(f, "Branch prediction needs the out-of-order backend")
+            }
+        }
     }
 }
 
@@ -0,0 +1,844 @@
+use crate::computer::address::{Address, RAM_SIZE, RAM_START};
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::{BusError, BusResponse};
+use crate::computer::components::bus::snoop::{Snoop, SnoopKind};
+use crate::computer::components::bus::status::{BusStatus, BURST_BEAT_SIZE};
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::micro_op::MicroOp;
+use crate::computer::components::cpu::misaligned::is_aligned;
+use crate::computer::components::cpu::registers::reg::CPUReg::IR;
+use log::debug;
+use std::collections::VecDeque;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Replacement {
+    /// Evicts the least recently used line of the set
+    #[default]
+    LRU,
+    /// Evicts the line filled first
+    FIFO,
+    /// Evicts a pseudo-random line, the sequence is the same on every run
+    Random,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum WritePolicy {
+    /// Writes only update the line, dirty lines are written to memory when evicted
+    #[default]
+    WriteBack,
+    /// Writes update the line and memory
+    WriteThrough,
+}
+
+/// Snooping protocol keeping the caches of several harts coherent
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Coherence {
+    /// Transactions of other bus masters are ignored
+    #[default]
+    None,
+    /// Modified, shared and invalid lines; writes to shared lines invalidate the other copies
+    MSI,
+    /// MSI with an exclusive state for lines no other cache holds, which are written without a bus transaction
+    MESI,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheConfig {
+    /// Capacity in bytes
+    pub size: u64,
+    pub associativity: u64,
+    /// Bytes per line, a multiple of the burst beat size; lines are filled and written back with bursts
+    pub line_size: u64,
+    pub replacement: Replacement,
+    pub write_policy: WritePolicy,
+    /// Write misses fill the line before writing it, otherwise they only go to memory
+    pub write_allocate: bool,
+    /// Ticks a hit takes
+    pub hit_latency: u64,
+    /// Address ranges which may be cached, they must not have read or write side effects.
+    /// Defaults to the RAM of the default memory map.
+    pub cacheable: Vec<RangeInclusive<u64>>,
+    pub coherence: Coherence,
+}
+
+impl Default for CacheConfig {
+    /// 4 KiB, 2-way set associative with 32 byte lines
+    fn default() -> Self {
+        Self {
+            size: 4096,
+            associativity: 2,
+            line_size: 32,
+            replacement: Replacement::default(),
+            write_policy: WritePolicy::default(),
+            write_allocate: true,
+            hit_latency: 1,
+            cacheable: vec![RAM_START..=RAM_START + (RAM_SIZE - 1)],
+            coherence: Coherence::default(),
+        }
+    }
+}
+
+impl CacheConfig {
+    fn sets(&self) -> u64 {
+        self.size / (self.associativity * self.line_size)
+    }
+
+    fn beats(&self) -> u8 {
+        (self.line_size / BURST_BEAT_SIZE) as u8
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Valid lines replaced by a fill
+    pub evictions: u64,
+    /// Dirty lines written to memory
+    pub write_backs: u64,
+    /// Write hits on shared lines which had to fetch the line exclusively
+    pub upgrades: u64,
+    /// Lines invalidated by transactions of other bus masters
+    pub invalidations: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            accesses => self.hits as f64 / accesses as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheRequest {
+    Read {
+        address: u64,
+        size: u64,
+    },
+    Write {
+        address: u64,
+        data: u64,
+        size: u64,
+    },
+    /// Writes back and invalidates the lines overlapping the bytes, before the CPU accesses them on the bus directly
+    Flush {
+        address: u64,
+        size: u64,
+    },
+}
+
+/// Coherence state of a line, caches without a protocol only use exclusive and modified
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum LineState {
+    #[default]
+    Invalid,
+    /// Clean, other caches may hold copies
+    Shared,
+    /// Clean, no other cache holds a copy
+    Exclusive,
+    /// Dirty, no other cache holds a copy
+    Modified,
+}
+
+impl Display for LineState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letter = match self {
+            LineState::Invalid => "I",
+            LineState::Shared => "S",
+            LineState::Exclusive => "E",
+            LineState::Modified => "M",
+        };
+        write!(f, "{letter}")
+    }
+}
+
+/// Answer of a cache to a snooped transaction
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SnoopResponse {
+    /// A copy of the line is kept
+    pub shared: bool,
+    /// Modified lines which have to be written to memory before the transaction is served: address, data
+    pub flushed: Vec<(u64, Vec<u8>)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CacheLine {
+    state: LineState,
+    tag: u64,
+    data: Vec<u8>,
+    last_used: u64,
+    filled: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CacheStep {
+    /// Remaining ticks of a hit
+    Wait(u64),
+    Take,
+    /// Burst write of the line at the address if it is still modified in the given way
+    WriteBack(u64, usize),
+    /// Burst read of a line into the given way of its set, exclusively to write it
+    Fill(u64, usize, bool),
+    /// Single write of the requested bytes to memory
+    WriteThrough,
+    Release,
+}
+
+/// Access in progress, one step per tick
+#[derive(Debug, Clone, PartialEq)]
+struct PendingAccess {
+    request: CacheRequest,
+    steps: VecDeque<CacheStep>,
+    /// The current step's transaction is on the bus
+    active: bool,
+    owned: bool,
+    /// Beats of the current fill or write-back
+    buffer: Vec<u64>,
+    error: Option<BusError>,
+    /// The request completes in a line, which may have been snooped away in the meantime
+    needs_line: bool,
+}
+
+/// Set associative cache sitting between a CPU and the bus.
+/// Misses fill whole lines with burst reads within a single bus tenure, after writing back a dirty victim.
+/// With a coherence protocol the cache snoops the transactions of other bus masters,
+/// modified lines are written to memory before the snooped transaction is served.
+///
+/// Limitations:
+/// - Without a coherence protocol, writes of other bus masters, e.g. DMA or other harts, are not observed.
+/// - The instruction cache is not kept coherent with stores of its own hart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    config: CacheConfig,
+    sets: Vec<Vec<CacheLine>>,
+    stats: CacheStats,
+    /// Incremented on each access, orders the lines for LRU and FIFO replacement
+    clock: u64,
+    random_state: u64,
+    pending: Option<PendingAccess>,
+    /// Fills fetch instructions, they need the execute permission
+    instruction: bool,
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        assert!(
+            config.line_size.is_power_of_two()
+                && config.line_size >= BURST_BEAT_SIZE
+                && config.line_size / BURST_BEAT_SIZE <= u8::MAX as u64,
+            "Cache lines must be a power of two of 8 to 2040 bytes, got {}",
+            config.line_size
+        );
+        assert!(
+            config.associativity > 0
+                && config.sets() > 0
+                && config.sets().is_power_of_two()
+                && config.size == config.sets() * config.associativity * config.line_size,
+            "Cache size {} does not divide into {}-way sets of {} byte lines",
+            config.size,
+            config.associativity,
+            config.line_size
+        );
+        let line = CacheLine {
+            state: LineState::Invalid,
+            tag: 0,
+            data: vec![0; config.line_size as usize],
+            last_used: 0,
+            filled: 0,
+        };
+        Self {
+            sets: vec![vec![line; config.associativity as usize]; config.sets() as usize],
+            config,
+            stats: CacheStats::default(),
+            clock: 0,
+            random_state: 0x9E37_79B9_7F4A_7C15,
+            pending: None,
+            instruction: false,
+        }
+    }
+
+    /// Cache of a hart's instruction fetches
+    pub fn new_instruction(config: CacheConfig) -> Self {
+        Self {
+            instruction: true,
+            ..Self::new(config)
+        }
+    }
+
+    pub fn get_config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    pub fn get_stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    pub fn is_cacheable(&self, address: u64) -> bool {
+        self.config
+            .cacheable
+            .iter()
+            .any(|range| range.contains(&address))
+    }
+
+    /// Whether the line holding the address is cached
+    pub fn contains(&self, address: u64) -> bool {
+        self.find(address).is_some()
+    }
+
+    /// Whether any line overlapping the bytes is cached
+    pub fn contains_any(&self, address: u64, size: u64) -> bool {
+        self.overlapping_lines(address, size)
+            .into_iter()
+            .any(|line| self.contains(line))
+    }
+
+    pub fn line_state(&self, address: u64) -> LineState {
+        self.find(address)
+            .map_or(LineState::Invalid, |(set, way)| self.sets[set][way].state)
+    }
+
+    /// Advances the access by one step, the same request has to be passed until it is answered.
+    /// Returns the read data, zero for writes and flushes, or the bus error.
+    pub fn access(
+        &mut self,
+        request: CacheRequest,
+        bus: &mut Bus,
+        owner: BusOwner,
+    ) -> Option<Result<u64, BusError>> {
+        if self.pending.is_none() {
+            self.start(request);
+        }
+        let mut pending = self.pending.take().unwrap();
+        debug_assert_eq!(pending.request, request);
+
+        match pending.steps.front().copied() {
+            None => {}
+            Some(CacheStep::Wait(ticks)) => {
+                if ticks <= 1 {
+                    pending.steps.pop_front();
+                } else {
+                    pending.steps[0] = CacheStep::Wait(ticks - 1);
+                }
+            }
+            Some(CacheStep::Take) if bus.take_ownership(owner) => {
+                pending.owned = true;
+                pending.steps.pop_front();
+            }
+            Some(CacheStep::Take) => {}
+            Some(CacheStep::WriteBack(line, way)) => {
+                let (set, tag) = (self.set_index(line), self.tag(line));
+                let cached = &self.sets[set][way];
+                if !pending.active {
+                    // A snooping master may have taken the line in the meantime
+                    if cached.state != LineState::Modified || cached.tag != tag {
+                        pending.steps.pop_front();
+                    } else {
+                        pending.buffer = cached
+                            .data
+                            .chunks(8)
+                            .map(|beat| u64::from_le_bytes(beat.try_into().unwrap()))
+                            .collect();
+                        bus.put_address(Address::new(line), owner);
+                        bus.put_data(pending.buffer[0], owner);
+                        bus.put_status(BusStatus::WriteBurst(self.config.beats()), owner);
+                        pending.active = true;
+                    }
+                } else if let Some(true) = Self::await_beat(&mut pending, bus) {
+                    let next = bus.get_beat() as usize + 1;
+                    if next < pending.buffer.len() {
+                        bus.put_data(pending.buffer[next], owner);
+                        bus.next_beat(owner);
+                    } else {
+                        debug!(target: "cache", "Wrote back line {line:#x}");
+                        self.stats.write_backs += 1;
+                        Self::end_transaction(&mut pending, bus, owner);
+                        let clean = self.clean_state(false);
+                        self.transition(set, way, clean, owner, "write-back");
+                    }
+                }
+            }
+            Some(CacheStep::Fill(line, way, exclusive)) => {
+                if !pending.active {
+                    bus.put_address(Address::new(line), owner);
+                    bus.put_status(BusStatus::ReadBurst(self.config.beats()), owner);
+                    if exclusive {
+                        bus.request_exclusive(owner);
+                    }
+                    if self.instruction {
+                        bus.request_fetch(owner);
+                    }
+                    pending.active = true;
+                } else if let Some(true) = Self::await_beat(&mut pending, bus) {
+                    pending.buffer.push(bus.get_data());
+                    if !bus.next_beat(owner) {
+                        let data = pending.buffer.drain(..).flat_map(u64::to_le_bytes);
+                        let data = data.collect();
+                        let state = match exclusive {
+                            true => LineState::Exclusive,
+                            false => self.clean_state(bus.is_shared()),
+                        };
+                        Self::end_transaction(&mut pending, bus, owner);
+                        self.fill(line, way, data, state, owner);
+                    }
+                }
+            }
+            Some(CacheStep::WriteThrough) => {
+                let CacheRequest::Write {
+                    address,
+                    data,
+                    size,
+                } = request
+                else {
+                    unreachable!("Only writes go through to memory")
+                };
+                if !pending.active {
+                    bus.put_address(Address::new(address), owner);
+                    bus.put_data(data, owner);
+                    bus.put_status(BusStatus::write_of_size(size), owner);
+                    pending.active = true;
+                } else if let Some(true) = Self::await_beat(&mut pending, bus) {
+                    Self::end_transaction(&mut pending, bus, owner);
+                }
+            }
+            Some(CacheStep::Release) => {
+                bus.release_ownership(owner);
+                pending.owned = false;
+                pending.steps.pop_front();
+            }
+        }
+
+        if !pending.steps.is_empty() {
+            self.pending = Some(pending);
+            return None;
+        }
+        let (error, needs_line) = (pending.error, pending.needs_line);
+        if let Some(error) = error {
+            return Some(Err(error));
+        }
+        if needs_line && !self.is_ready(request) {
+            debug!(target: "cache", "{request:?}: line was snooped away, retrying");
+            self.start(request);
+            return None;
+        }
+        Some(Ok(self.complete(request, owner)))
+    }
+
+    /// Aborts the access in progress, releasing the bus if it was already taken.
+    /// Lines are only replaced once their fill completed, nothing is lost.
+    pub fn cancel(&mut self, bus: &mut Bus, owner: BusOwner) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        if pending.owned {
+            bus.release_ownership(owner);
+        } else if pending.steps.front() == Some(&CacheStep::Take) {
+            bus.withdraw_request(owner);
+        }
+    }
+
+    /// Drops all lines, the access in progress and the statistics
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config.clone());
+    }
+
+    /// Observes a transaction of another bus master.
+    /// Reads downgrade the copies to shared, exclusive reads and writes invalidate them.
+    pub fn snoop(&mut self, snoop: &Snoop, owner: BusOwner) -> SnoopResponse {
+        let mut response = SnoopResponse::default();
+        if self.config.coherence == Coherence::None {
+            return response;
+        }
+        for line in self.overlapping_lines(snoop.address, snoop.size) {
+            let Some((set, way)) = self.find(line) else {
+                continue;
+            };
+            let cached = &self.sets[set][way];
+            if cached.state == LineState::Modified {
+                response.flushed.push((line, cached.data.clone()));
+            }
+            let state = match snoop.kind {
+                SnoopKind::Read => LineState::Shared,
+                SnoopKind::ReadExclusive | SnoopKind::Write => {
+                    self.stats.invalidations += 1;
+                    LineState::Invalid
+                }
+            };
+            let cause = format!("{:?} by {:?}", snoop.kind, snoop.master);
+            self.transition(set, way, state, owner, &cause);
+            response.shared |= state != LineState::Invalid;
+        }
+        response
+    }
+
+    /// Writes all dirty lines with the given function and invalidates the cache.
+    /// Used where the bus protocol is bypassed, statistics are left untouched.
+    pub fn write_back_all(&mut self, mut write: impl FnMut(u64, &[u8])) {
+        let line_bits = self.config.line_size.trailing_zeros();
+        let set_count = self.sets.len() as u64;
+        for (index, set) in self.sets.iter_mut().enumerate() {
+            for line in set.iter_mut() {
+                if line.state == LineState::Modified {
+                    let address = (line.tag * set_count + index as u64) << line_bits;
+                    write(address, &line.data);
+                }
+                line.state = LineState::Invalid;
+            }
+        }
+    }
+
+    /// Plans the steps of the request, hits and misses are counted here
+    fn start(&mut self, request: CacheRequest) {
+        self.clock = self.clock.wrapping_add(1);
+        let mut steps = VecDeque::new();
+        let mut needs_line = false;
+        let write_back = self.config.write_policy == WritePolicy::WriteBack;
+        match request {
+            CacheRequest::Read { address, .. } | CacheRequest::Write { address, .. } => {
+                let is_write = matches!(request, CacheRequest::Write { .. });
+                // Write-back caches keep written lines, they have to own them exclusively
+                let exclusive = is_write && write_back && self.config.coherence != Coherence::None;
+                needs_line = !is_write || write_back;
+                if let Some((set, way)) = self.find(address) {
+                    self.stats.hits += 1;
+                    self.sets[set][way].last_used = self.clock;
+                    if is_write && !write_back {
+                        steps.extend([
+                            CacheStep::Take,
+                            CacheStep::WriteThrough,
+                            CacheStep::Release,
+                        ]);
+                    } else if exclusive && self.sets[set][way].state == LineState::Shared {
+                        self.stats.upgrades += 1;
+                        let line = self.line_address(address);
+                        steps.extend([
+                            CacheStep::Take,
+                            CacheStep::Fill(line, way, true),
+                            CacheStep::Release,
+                        ]);
+                    } else {
+                        steps.push_back(CacheStep::Wait(self.config.hit_latency.max(1)));
+                    }
+                } else {
+                    self.stats.misses += 1;
+                    steps.push_back(CacheStep::Take);
+                    if !is_write || self.config.write_allocate {
+                        let line = self.line_address(address);
+                        let (set, way) = (self.set_index(address), self.victim(address));
+                        let victim = &self.sets[set][way];
+                        if victim.state == LineState::Modified {
+                            let victim_address = self.address_of(victim.tag, set);
+                            steps.push_back(CacheStep::WriteBack(victim_address, way));
+                        }
+                        steps.push_back(CacheStep::Fill(line, way, exclusive));
+                    } else {
+                        needs_line = false;
+                    }
+                    if is_write && !needs_line {
+                        steps.push_back(CacheStep::WriteThrough);
+                    }
+                    steps.push_back(CacheStep::Release);
+                }
+            }
+            CacheRequest::Flush { address, size } => {
+                let mut dirty = Vec::new();
+                for line in self.overlapping_lines(address, size) {
+                    if let Some((set, way)) = self.find(line) {
+                        if self.sets[set][way].state == LineState::Modified {
+                            dirty.push(CacheStep::WriteBack(line, way));
+                        } else {
+                            self.sets[set][way].state = LineState::Invalid;
+                        }
+                    }
+                }
+                if !dirty.is_empty() {
+                    steps.push_back(CacheStep::Take);
+                    steps.extend(dirty);
+                    steps.push_back(CacheStep::Release);
+                }
+            }
+        }
+        debug!(target: "cache", "{request:?}: {steps:?}");
+        self.pending = Some(PendingAccess {
+            request,
+            steps,
+            active: false,
+            owned: false,
+            buffer: Vec::new(),
+            error: None,
+            needs_line,
+        });
+    }
+
+    /// Whether the line the request completes in is still present, and writable for writes
+    fn is_ready(&self, request: CacheRequest) -> bool {
+        match request {
+            CacheRequest::Read { address, .. } => self.contains(address),
+            CacheRequest::Write { address, .. } => match self.line_state(address) {
+                LineState::Invalid => false,
+                LineState::Shared => self.config.coherence == Coherence::None,
+                LineState::Exclusive | LineState::Modified => true,
+            },
+            CacheRequest::Flush { .. } => true,
+        }
+    }
+
+    /// Applies the finished request to the cached lines and returns its result
+    fn complete(&mut self, request: CacheRequest, owner: BusOwner) -> u64 {
+        match request {
+            CacheRequest::Read { address, size } => {
+                let (set, way) = self.find(address).expect("Read line was filled");
+                let offset = (address % self.config.line_size) as usize;
+                let bytes = &self.sets[set][way].data[offset..offset + size as usize];
+                bytes
+                    .iter()
+                    .rev()
+                    .fold(0, |value, byte| (value << 8) | *byte as u64)
+            }
+            CacheRequest::Write {
+                address,
+                data,
+                size,
+            } => {
+                if let Some((set, way)) = self.find(address) {
+                    let offset = (address % self.config.line_size) as usize;
+                    let bytes = data.to_le_bytes();
+                    self.sets[set][way].data[offset..offset + size as usize]
+                        .copy_from_slice(&bytes[..size as usize]);
+                    if self.config.write_policy == WritePolicy::WriteBack {
+                        self.transition(set, way, LineState::Modified, owner, "write");
+                    }
+                }
+                0
+            }
+            CacheRequest::Flush { address, size } => {
+                for line in self.overlapping_lines(address, size) {
+                    if let Some((set, way)) = self.find(line) {
+                        self.transition(set, way, LineState::Invalid, owner, "flush");
+                    }
+                }
+                0
+            }
+        }
+    }
+
+    /// Whether the current transaction has been answered, None while the device is busy.
+    /// A bus error aborts the access after releasing the bus.
+    fn await_beat(pending: &mut PendingAccess, bus: &Bus) -> Option<bool> {
+        match bus.get_response() {
+            BusResponse::Retry => None,
+            BusResponse::Ok => Some(true),
+            BusResponse::Error(error) => {
+                debug!(target: "cache", "{:?} failed: {error:?}", pending.request);
+                pending.error = Some(error);
+                pending.active = false;
+                pending.steps = VecDeque::from(vec![CacheStep::Release]);
+                Some(false)
+            }
+        }
+    }
+
+    /// Ends the transaction of the current step, the next one must not be served with its status
+    fn end_transaction(pending: &mut PendingAccess, bus: &mut Bus, owner: BusOwner) {
+        bus.put_status(BusStatus::Idle, owner);
+        pending.active = false;
+        pending.steps.pop_front();
+    }
+
+    fn fill(&mut self, line: u64, way: usize, data: Vec<u8>, state: LineState, owner: BusOwner) {
+        let set = self.set_index(line);
+        let tag = self.tag(line);
+        let victim = &self.sets[set][way];
+        // An upgrade refills the line in place
+        if victim.state != LineState::Invalid && victim.tag != tag {
+            debug!(target: "cache", "Evicting line {:#x}", self.address_of(victim.tag, set));
+            self.stats.evictions += 1;
+            self.sets[set][way].state = LineState::Invalid;
+        }
+        debug!(target: "cache", "Filled line {line:#x} into way {way}");
+        self.sets[set][way] = CacheLine {
+            tag,
+            data,
+            last_used: self.clock,
+            filled: self.clock,
+            ..self.sets[set][way].clone()
+        };
+        self.transition(set, way, state, owner, "fill");
+    }
+
+    /// State of a line which is not dirty, shared if another cache holds a copy
+    fn clean_state(&self, shared: bool) -> LineState {
+        match self.config.coherence {
+            Coherence::MSI => LineState::Shared,
+            Coherence::MESI if shared => LineState::Shared,
+            Coherence::None | Coherence::MESI => LineState::Exclusive,
+        }
+    }
+
+    fn transition(
+        &mut self,
+        set: usize,
+        way: usize,
+        state: LineState,
+        owner: BusOwner,
+        cause: &str,
+    ) {
+        let address = self.address_of(self.sets[set][way].tag, set);
+        let previous = std::mem::replace(&mut self.sets[set][way].state, state);
+        if previous != state {
+            debug!(
+                target: "cache::coherence",
+                "{owner:?} {address:#x}: {previous} → {state} ({cause})"
+            );
+        }
+    }
+
+    /// Way replaced by a fill of the address, invalid ways first
+    fn victim(&mut self, address: u64) -> usize {
+        let set = &self.sets[self.set_index(address)];
+        if let Some(way) = set.iter().position(|line| line.state == LineState::Invalid) {
+            return way;
+        }
+        let oldest =
+            |key: fn(&CacheLine) -> u64| (0..set.len()).min_by_key(|way| key(&set[*way])).unwrap();
+        match self.config.replacement {
+            Replacement::LRU => oldest(|line| line.last_used),
+            Replacement::FIFO => oldest(|line| line.filled),
+            Replacement::Random => {
+                // xorshift64
+                self.random_state ^= self.random_state << 13;
+                self.random_state ^= self.random_state >> 7;
+                self.random_state ^= self.random_state << 17;
+                (self.random_state % self.config.associativity) as usize
+            }
+        }
+    }
+
+    fn find(&self, address: u64) -> Option<(usize, usize)> {
+        let set = self.set_index(address);
+        let tag = self.tag(address);
+        self.sets[set]
+            .iter()
+            .position(|line| line.state != LineState::Invalid && line.tag == tag)
+            .map(|way| (set, way))
+    }
+
+    fn overlapping_lines(&self, address: u64, size: u64) -> Vec<u64> {
+        let first = self.line_address(address);
+        let last = self.line_address(address.wrapping_add(size.max(1) - 1));
+        let count = (last.wrapping_sub(first) / self.config.line_size) as usize + 1;
+        (0..count as u64)
+            .map(|line| first.wrapping_add(line * self.config.line_size))
+            .collect()
+    }
+
+    fn line_address(&self, address: u64) -> u64 {
+        address & !(self.config.line_size - 1)
+    }
+
+    fn set_index(&self, address: u64) -> usize {
+        ((address / self.config.line_size) % self.sets.len() as u64) as usize
+    }
+
+    fn tag(&self, address: u64) -> u64 {
+        address / self.config.line_size / self.sets.len() as u64
+    }
+
+    fn address_of(&self, tag: u64, set: usize) -> u64 {
+        (tag * self.sets.len() as u64 + set as u64) * self.config.line_size
+    }
+}
+
+/// Level 1 caches of a CPU, either may be absent
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Caches {
+    pub instruction: Option<Cache>,
+    pub data: Option<Cache>,
+}
+
+impl Caches {
+    /// Cache serving an aligned access of the given size, None if it has to bypass the caches
+    pub fn serving(&mut self, fetch: bool, address: u64, size: u64) -> Option<&mut Cache> {
+        let cache = if fetch {
+            self.instruction.as_mut()
+        } else {
+            self.data.as_mut()
+        };
+        cache.filter(|cache| is_aligned(address, size) && cache.is_cacheable(address))
+    }
+
+    /// Whether a data access bypassing the cache has to flush cached lines first
+    pub fn needs_flush(&self, address: u64, size: u64) -> bool {
+        self.data
+            .as_ref()
+            .is_some_and(|cache| cache.contains_any(address, size))
+    }
+
+    pub fn reset(&mut self) {
+        let caches = [self.instruction.as_mut(), self.data.as_mut()];
+        caches.into_iter().flatten().for_each(Cache::reset);
+    }
+
+    /// Lets both caches observe a transaction of another bus master
+    pub fn snoop(&mut self, snoop: &Snoop, owner: BusOwner) -> SnoopResponse {
+        let mut response = SnoopResponse::default();
+        for cache in [self.instruction.as_mut(), self.data.as_mut()]
+            .into_iter()
+            .flatten()
+        {
+            let cache_response = cache.snoop(snoop, owner);
+            response.shared |= cache_response.shared;
+            response.flushed.extend(cache_response.flushed);
+        }
+        response
+    }
+
+    /// Replaces bus accesses of a decomposed instruction with cache accesses where caches are present.
+    /// Other bus accesses, e.g. of atomics, flush the data cache line they touch beforehand.
+    pub fn cache_micro_ops(&self, queue: Vec<MicroOp>) -> Vec<MicroOp> {
+        if self.instruction.is_none() && self.data.is_none() {
+            return queue;
+        }
+        let mut cached = Vec::with_capacity(queue.len());
+        let mut i = 0;
+        while i < queue.len() {
+            let window = &queue[i..];
+            match window {
+                [MicroOp::BusTake, MicroOp::BusWriteAddress(address), MicroOp::BusSetRead, read, MicroOp::BusRelease, ..]
+                    if read.read_size().is_some() =>
+                {
+                    let rd = read.destinations()[0];
+                    let size = read.read_size().unwrap() as u8;
+                    match (rd, &self.instruction, &self.data) {
+                        (IR, Some(_), _) => cached.push(MicroOp::ICacheRead(*address, rd)),
+                        (IR, None, _) => cached.extend_from_slice(&window[..5]),
+                        (_, _, Some(_)) => cached.push(MicroOp::DCacheRead(*address, rd, size)),
+                        (_, _, None) => cached.extend_from_slice(&window[..5]),
+                    }
+                    i += 5;
+                }
+                [MicroOp::BusTake, MicroOp::BusWriteAddress(address), MicroOp::BusWriteData(data), set_write, MicroOp::BusAwaitWrite, MicroOp::BusRelease, ..]
+                    if self.data.is_some() && set_write.write_size().is_some() =>
+                {
+                    let size = set_write.write_size().unwrap() as u8;
+                    cached.push(MicroOp::DCacheWrite(*address, *data, size));
+                    i += 6;
+                }
+                [MicroOp::BusTake, MicroOp::BusWriteAddress(address), ..]
+                    if self.data.is_some() =>
+                {
+                    cached.extend([MicroOp::DCacheFlush(*address, 8), MicroOp::BusTake]);
+                    i += 1;
+                }
+                _ => {
+                    cached.push(queue[i]);
+                    i += 1;
+                }
+            }
+        }
+        cached
+    }
+}
@@ -0,0 +1,155 @@
+/// Machine status, only the interrupt enable bits are implemented, the hart always runs in M-mode
+pub const CSR_MSTATUS: u16 = 0x300;
+/// Machine interrupt enable, one bit per mip bit
+pub const CSR_MIE: u16 = 0x304;
+/// Trap vector base address, the low two bits select direct (0) or vectored (1) interrupts
+pub const CSR_MTVEC: u16 = 0x305;
+pub const CSR_MSCRATCH: u16 = 0x340;
+/// Address of the instruction a trap interrupted
+pub const CSR_MEPC: u16 = 0x341;
+pub const CSR_MCAUSE: u16 = 0x342;
+/// Machine interrupt pending, read-only here: the bits follow the interrupt lines of the devices
+pub const CSR_MIP: u16 = 0x344;
+pub const CSR_MHARTID: u16 = 0xF14;
+
+// Bits of mstatus
+pub const MSTATUS_MIE: u64 = 1 << 3;
+pub const MSTATUS_MPIE: u64 = 1 << 7;
+/// Previous privilege mode, always M-mode
+const MSTATUS_MPP: u64 = 0b11 << 11;
+
+// Bits of mip and mie
+pub const MIP_MSIP: u64 = 1 << 3;
+pub const MIP_MTIP: u64 = 1 << 7;
+pub const MIP_MEIP: u64 = 1 << 11;
+
+/// Bit of mcause set for interrupts, the other bits hold the cause
+pub const MCAUSE_INTERRUPT: u64 = 1 << 63;
+
+// Interrupt causes in the order they are taken in when pending together
+const INTERRUPT_PRIORITY: [u64; 3] = [11, 3, 7];
+
+/// Modification of a CSR by the Zicsr instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsrOp {
+    /// CSRRW(I), replaces the value
+    Write,
+    /// CSRRS(I), sets the bits of the operand
+    Set,
+    /// CSRRC(I), clears the bits of the operand
+    Clear,
+}
+
+impl CsrOp {
+    /// The value of the CSR after applying the operand to the old value
+    pub fn apply(&self, old: u64, operand: u64) -> u64 {
+        match self {
+            CsrOp::Write => operand,
+            CsrOp::Set => old | operand,
+            CsrOp::Clear => old & !operand,
+        }
+    }
+}
+
+/// Control and status registers of a hart.
+/// Only machine mode interrupt handling is implemented, exceptions still halt the hart.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Csrs {
+    pub hart_id: u64,
+    pub mstatus: u64,
+    pub mie: u64,
+    pub mtvec: u64,
+    pub mscratch: u64,
+    pub mip: u64,
+    pub mepc: u64,
+    pub mcause: u64,
+}
+
+impl Csrs {
+    pub fn is_supported(csr: u16) -> bool {
+        Self::is_writable(csr) || matches!(csr, CSR_MIP | CSR_MHARTID)
+    }
+
+    /// Writes to mip are accepted and ignored, the read-only mhartid can't be written
+    pub fn is_writable(csr: u16) -> bool {
+        matches!(
+            csr,
+            CSR_MSTATUS | CSR_MIE | CSR_MTVEC | CSR_MSCRATCH | CSR_MEPC | CSR_MCAUSE | CSR_MIP
+        )
+    }
+
+    pub fn read(&self, csr: u16) -> u64 {
+        match csr {
+            CSR_MSTATUS => self.mstatus | MSTATUS_MPP,
+            CSR_MIE => self.mie,
+            CSR_MTVEC => self.mtvec,
+            CSR_MSCRATCH => self.mscratch,
+            CSR_MIP => self.mip,
+            CSR_MEPC => self.mepc,
+            CSR_MCAUSE => self.mcause,
+            CSR_MHARTID => self.hart_id,
+            _ => unreachable!("Unsupported CSR {csr:#x} is rejected by the decoder"),
+        }
+    }
+
+    pub fn write(&mut self, csr: u16, value: u64) {
+        match csr {
+            CSR_MSTATUS => self.mstatus = value & (MSTATUS_MIE | MSTATUS_MPIE),
+            CSR_MIE => self.mie = value & (MIP_MSIP | MIP_MTIP | MIP_MEIP),
+            // Only the direct and vectored modes exist
+            CSR_MTVEC => self.mtvec = value & !0b10,
+            CSR_MSCRATCH => self.mscratch = value,
+            CSR_MEPC => self.mepc = value & !0b11,
+            CSR_MCAUSE => self.mcause = value,
+            CSR_MIP => {}
+            _ => unreachable!("Read-only CSR {csr:#x} is rejected by the decoder"),
+        }
+    }
+
+    /// Applies a Zicsr instruction, returns the old value
+    pub fn access(&mut self, op: CsrOp, csr: u16, operand: u64) -> u64 {
+        let old = self.read(csr);
+        self.write(csr, op.apply(old, operand));
+        old
+    }
+
+    /// Cause of the interrupt the hart takes next, if any is pending, enabled and interrupts are enabled globally
+    pub fn pending_interrupt(&self) -> Option<u64> {
+        if self.mstatus & MSTATUS_MIE == 0 {
+            return None;
+        }
+        let pending = self.mip & self.mie;
+        INTERRUPT_PRIORITY
+            .into_iter()
+            .find(|cause| pending & (1 << cause) != 0)
+    }
+
+    /// Enters the handler of an interrupt taken at the address, returns the address of the handler
+    pub fn enter_interrupt(&mut self, cause: u64, address: u64) -> u64 {
+        self.mepc = address;
+        self.mcause = MCAUSE_INTERRUPT | cause;
+        let enabled = self.mstatus & MSTATUS_MIE != 0;
+        self.mstatus = if enabled { MSTATUS_MPIE } else { 0 };
+        let base = self.mtvec & !0b11;
+        if self.mtvec & 0b1 == 1 {
+            base.wrapping_add(4 * cause)
+        } else {
+            base
+        }
+    }
+
+    /// Returns from a trap handler, MRET: restores the interrupt enable and returns the address to continue at
+    pub fn mret(&mut self) -> u64 {
+        let enabled = self.mstatus & MSTATUS_MPIE != 0;
+        self.mstatus = if enabled { MSTATUS_MIE } else { 0 } | MSTATUS_MPIE;
+        self.mepc
+    }
+
+    /// Returns to the boot state, keeping the hart id
+    pub fn reset(&mut self) {
+        *self = Self {
+            hart_id: self.hart_id,
+            ..Self::default()
+        };
+    }
+}
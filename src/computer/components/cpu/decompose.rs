@@ -1,11 +1,14 @@
+use crate::computer::components::cpu::alu::BranchCondition;
+use crate::computer::components::cpu::csr::CsrOp;
 use crate::computer::components::cpu::micro_op::MicroOp;
 use crate::computer::components::cpu::registers::reg::CPUReg;
 use crate::computer::components::cpu::registers::reg::CPUReg::*;
 use crate::computer::instructions::Instruction;
 
-pub fn decompose_instruction(instruction_bits: u32) -> (Instruction, Vec<MicroOp>) {
-    let instruction = Instruction::decode(instruction_bits);
-    let queue = match instruction {
+/// pc_offset is the distance PC has already advanced past the instruction's address,
+/// which is 4 unless further instructions have been prefetched.
+pub fn decompose_instruction(instruction: Instruction, pc_offset: u64) -> Vec<MicroOp> {
+    match instruction {
         Instruction::Add(rd, rs1, rs2) => decompose_add(rd, rs1, rs2),
         Instruction::And(rd, rs1, rs2) => decompose_and(rd, rs1, rs2),
         Instruction::Or(rd, rs1, rs2) => decompose_or(rd, rs1, rs2),
@@ -14,11 +17,79 @@ pub fn decompose_instruction(instruction_bits: u32) -> (Instruction, Vec<MicroOp
         Instruction::Sll(rd, rs1, rs2) => decompose_sll(rd, rs1, rs2),
         Instruction::Srl(rd, rs1, rs2) => decompose_srl(rd, rs1, rs2),
         Instruction::Sra(rd, rs1, rs2) => decompose_sra(rd, rs1, rs2),
-        Instruction::Lb(rd, rs1, imm) => decompose_lb(rd, rs1, imm),
+        Instruction::Addi(rd, rs1, imm) => decompose_addi(rd, rs1, imm),
+        Instruction::Slli(rd, rs1, shamt) => {
+            decompose_shift_imm(shamt, MicroOp::ALUSll(rd, rs1, TMP0))
+        }
+        Instruction::Srli(rd, rs1, shamt) => {
+            decompose_shift_imm(shamt, MicroOp::ALUSrl(rd, rs1, TMP0))
+        }
+        Instruction::Srai(rd, rs1, shamt) => {
+            decompose_shift_imm(shamt, MicroOp::ALUSra(rd, rs1, TMP0))
+        }
+        Instruction::Jalr(rd, rs1, imm) => decompose_jalr(rd, rs1, imm),
+        Instruction::Lb(rd, rs1, imm) => decompose_load(rs1, imm, MicroOp::BusReadByte(rd)),
+        Instruction::Lh(rd, rs1, imm) => decompose_load(rs1, imm, MicroOp::BusReadHalfWord(rd)),
+        Instruction::Lw(rd, rs1, imm) => decompose_load(rs1, imm, MicroOp::BusReadWord(rd)),
+        Instruction::Ld(rd, rs1, imm) => decompose_load(rs1, imm, MicroOp::BusReadDoubleWord(rd)),
+        Instruction::Sb(rs1, rs2, imm) => decompose_store(rs1, rs2, imm, MicroOp::BusSetWriteByte),
+        Instruction::Sh(rs1, rs2, imm) => {
+            decompose_store(rs1, rs2, imm, MicroOp::BusSetWriteHalfWord)
+        }
+        Instruction::Sw(rs1, rs2, imm) => decompose_store(rs1, rs2, imm, MicroOp::BusSetWriteWord),
+        Instruction::Sd(rs1, rs2, imm) => {
+            decompose_store(rs1, rs2, imm, MicroOp::BusSetWriteDoubleWord)
+        }
+        Instruction::Beq(rs1, rs2, offset) => {
+            decompose_branch(BranchCondition::Equal, rs1, rs2, offset, pc_offset)
+        }
+        Instruction::Bne(rs1, rs2, offset) => {
+            decompose_branch(BranchCondition::NotEqual, rs1, rs2, offset, pc_offset)
+        }
+        Instruction::Blt(rs1, rs2, offset) => {
+            decompose_branch(BranchCondition::LessThan, rs1, rs2, offset, pc_offset)
+        }
+        Instruction::Bge(rs1, rs2, offset) => {
+            decompose_branch(BranchCondition::GreaterEqual, rs1, rs2, offset, pc_offset)
+        }
+        Instruction::Bltu(rs1, rs2, offset) => decompose_branch(
+            BranchCondition::LessThanUnsigned,
+            rs1,
+            rs2,
+            offset,
+            pc_offset,
+        ),
+        Instruction::Bgeu(rs1, rs2, offset) => decompose_branch(
+            BranchCondition::GreaterEqualUnsigned,
+            rs1,
+            rs2,
+            offset,
+            pc_offset,
+        ),
+        Instruction::Jal(rd, offset) => decompose_jal(rd, offset, pc_offset),
+        Instruction::Lui(rd, imm) => decompose_lui(rd, imm),
+        Instruction::Auipc(rd, imm) => decompose_auipc(rd, imm, pc_offset),
+        Instruction::LrW(rd, rs1) => decompose_load_reserved(rs1, 4, MicroOp::BusReadWord(rd)),
+        Instruction::LrD(rd, rs1) => {
+            decompose_load_reserved(rs1, 8, MicroOp::BusReadDoubleWord(rd))
+        }
+        Instruction::ScW(rd, rs1, rs2) => {
+            decompose_store_conditional(rd, rs1, rs2, 4, MicroOp::BusSetWriteWord)
+        }
+        Instruction::ScD(rd, rs1, rs2) => {
+            decompose_store_conditional(rd, rs1, rs2, 8, MicroOp::BusSetWriteDoubleWord)
+        }
+        Instruction::Csrr(rd, csr) => vec![MicroOp::CsrRead(rd, csr)],
+        Instruction::Csrrw(rd, rs1, csr) => vec![MicroOp::CsrAccess(CsrOp::Write, rd, csr, rs1)],
+        Instruction::Csrrs(rd, rs1, csr) => vec![MicroOp::CsrAccess(CsrOp::Set, rd, csr, rs1)],
+        Instruction::Csrrc(rd, rs1, csr) => vec![MicroOp::CsrAccess(CsrOp::Clear, rd, csr, rs1)],
+        Instruction::Csrrwi(rd, uimm, csr) => decompose_csr_imm(CsrOp::Write, rd, uimm, csr),
+        Instruction::Csrrsi(rd, uimm, csr) => decompose_csr_imm(CsrOp::Set, rd, uimm, csr),
+        Instruction::Csrrci(rd, uimm, csr) => decompose_csr_imm(CsrOp::Clear, rd, uimm, csr),
+        Instruction::Mret => vec![MicroOp::Mret],
         Instruction::ECall => vec![MicroOp::Halt],
         Instruction::EBreak => vec![MicroOp::Halt],
-    };
-    (instruction, queue)
+    }
 }
 
 // BASE INTEGER INSTRUCTIONS
@@ -54,15 +125,128 @@ fn decompose_sra(rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> Vec<MicroOp> {
     vec![MicroOp::ALUSra(rd, rs1, rs2)]
 }
 
+fn decompose_addi(rd: CPUReg, rs1: CPUReg, imm: u64) -> Vec<MicroOp> {
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, imm),
+        MicroOp::ALUAddi(rd, rs1, TMP0),
+    ]
+}
+
+/// The shift reads its amount from TMP0
+fn decompose_shift_imm(shamt: u64, shift: MicroOp) -> Vec<MicroOp> {
+    vec![MicroOp::RegisterLoadImm(TMP0, shamt), shift]
+}
+
+fn decompose_lui(rd: CPUReg, imm: u64) -> Vec<MicroOp> {
+    vec![MicroOp::RegisterLoadImm(
+        rd,
+        Instruction::upper_immediate_value(imm),
+    )]
+}
+
+fn decompose_auipc(rd: CPUReg, imm: u64, pc_offset: u64) -> Vec<MicroOp> {
+    let offset = Instruction::upper_immediate_value(imm).wrapping_sub(pc_offset);
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, offset),
+        MicroOp::AGUAdd(rd, PC, TMP0),
+    ]
+}
+
+// JUMP INSTRUCTIONS
+fn decompose_jal(rd: CPUReg, offset: u64, pc_offset: u64) -> Vec<MicroOp> {
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, offset.wrapping_sub(pc_offset)),
+        MicroOp::AGUAdd(TMP1, PC, TMP0),
+        MicroOp::RegisterMove(rd, PC),
+        MicroOp::RegisterMove(PC, TMP1),
+    ]
+}
+
+fn decompose_jalr(rd: CPUReg, rs1: CPUReg, imm: u64) -> Vec<MicroOp> {
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, imm),
+        MicroOp::AGUAddAligned(TMP1, rs1, TMP0),
+        MicroOp::RegisterMove(rd, PC),
+        MicroOp::RegisterMove(PC, TMP1),
+    ]
+}
+
+// BRANCH INSTRUCTIONS
+fn decompose_branch(
+    condition: BranchCondition,
+    rs1: CPUReg,
+    rs2: CPUReg,
+    offset: u64,
+    pc_offset: u64,
+) -> Vec<MicroOp> {
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, offset.wrapping_sub(pc_offset)),
+        MicroOp::Branch(condition, rs1, rs2, TMP0),
+    ]
+}
+
 // LOAD INSTRUCTIONS
-fn decompose_lb(rd: CPUReg, rs1: CPUReg, imm: u64) -> Vec<MicroOp> {
+fn decompose_load(rs1: CPUReg, imm: u64, read: MicroOp) -> Vec<MicroOp> {
     vec![
         MicroOp::RegisterLoadImm(TMP0, imm),
-        MicroOp::ALUAdd(TMP1, rs1, TMP0),
+        MicroOp::AGUAdd(TMP1, rs1, TMP0),
         MicroOp::BusTake,
         MicroOp::BusWriteAddress(TMP1),
         MicroOp::BusSetRead,
-        MicroOp::BusReadByte(rd),
+        read,
         MicroOp::BusRelease,
     ]
 }
+
+// STORE INSTRUCTIONS
+fn decompose_store(rs1: CPUReg, rs2: CPUReg, imm: u64, set_write: MicroOp) -> Vec<MicroOp> {
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, imm),
+        MicroOp::AGUAdd(TMP1, rs1, TMP0),
+        MicroOp::BusTake,
+        MicroOp::BusWriteAddress(TMP1),
+        MicroOp::BusWriteData(rs2),
+        set_write,
+        MicroOp::BusAwaitWrite,
+        MicroOp::BusRelease,
+    ]
+}
+
+// ATOMIC INSTRUCTIONS
+// The reservation is checked and the memory accessed within a single bus tenure
+fn decompose_load_reserved(rs1: CPUReg, size: u8, read: MicroOp) -> Vec<MicroOp> {
+    vec![
+        MicroOp::BusTake,
+        MicroOp::BusWriteAddress(rs1),
+        MicroOp::BusReserve(size),
+        MicroOp::BusSetRead,
+        read,
+        MicroOp::BusRelease,
+    ]
+}
+
+fn decompose_store_conditional(
+    rd: CPUReg,
+    rs1: CPUReg,
+    rs2: CPUReg,
+    size: u8,
+    set_write: MicroOp,
+) -> Vec<MicroOp> {
+    vec![
+        MicroOp::BusTake,
+        MicroOp::BusWriteAddress(rs1),
+        MicroOp::BusWriteData(rs2),
+        MicroOp::BusStoreConditional(rd, size),
+        set_write,
+        MicroOp::BusAwaitWrite,
+        MicroOp::BusRelease,
+    ]
+}
+
+// ZICSR INSTRUCTIONS
+fn decompose_csr_imm(op: CsrOp, rd: CPUReg, uimm: u64, csr: u16) -> Vec<MicroOp> {
+    vec![
+        MicroOp::RegisterLoadImm(TMP0, uimm),
+        MicroOp::CsrAccess(op, rd, csr, TMP0),
+    ]
+}
@@ -0,0 +1,64 @@
+use crate::computer::components::bus::response::BusError;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exception {
+    /// Raw instruction bits which could not be decoded
+    IllegalInstruction(u32),
+    /// Instruction fetch from an address the memory system could not serve
+    InstructionAccessFault(u64),
+    LoadAccessFault(u64),
+    StoreAccessFault(u64),
+    InstructionAddressMisaligned(u64),
+    LoadAddressMisaligned(u64),
+    StoreAddressMisaligned(u64),
+}
+
+/// Kind of memory access a bus error is reported for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MemoryAccess {
+    Fetch,
+    Load,
+    Store,
+}
+
+impl Exception {
+    pub fn from_bus_error(access: MemoryAccess, error: BusError, address: u64) -> Self {
+        match (access, error) {
+            (MemoryAccess::Fetch, BusError::Misaligned) => {
+                Exception::InstructionAddressMisaligned(address)
+            }
+            (MemoryAccess::Load, BusError::Misaligned) => Exception::LoadAddressMisaligned(address),
+            (MemoryAccess::Store, BusError::Misaligned) => {
+                Exception::StoreAddressMisaligned(address)
+            }
+            (MemoryAccess::Fetch, _) => Exception::InstructionAccessFault(address),
+            (MemoryAccess::Load, _) => Exception::LoadAccessFault(address),
+            (MemoryAccess::Store, _) => Exception::StoreAccessFault(address),
+        }
+    }
+}
+
+impl Display for Exception {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Exception::IllegalInstruction(bits) => write!(f, "Illegal instruction {bits:032b}"),
+            Exception::InstructionAccessFault(address) => {
+                write!(f, "Instruction access fault at {address:016x}")
+            }
+            Exception::LoadAccessFault(address) => write!(f, "Load access fault at {address:016x}"),
+            Exception::StoreAccessFault(address) => {
+                write!(f, "Store access fault at {address:016x}")
+            }
+            Exception::InstructionAddressMisaligned(address) => {
+                write!(f, "Misaligned instruction address {address:016x}")
+            }
+            Exception::LoadAddressMisaligned(address) => {
+                write!(f, "Misaligned load address {address:016x}")
+            }
+            Exception::StoreAddressMisaligned(address) => {
+                write!(f, "Misaligned store address {address:016x}")
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::response::BusError;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::alu::{ALUOp, BranchCondition};
+use crate::computer::components::cpu::csr::CsrOp;
+use crate::computer::components::cpu::exception::{Exception, MemoryAccess};
+use crate::computer::components::cpu::misaligned::{
+    is_aligned, merge_part, sign_extend, split_access, MisalignedPolicy,
+};
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::components::cpu::registers::reg::CPUReg::{IR, PC};
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::instructions::Instruction;
+use log::debug;
+
+/// Direct memory access for the fast execution mode, bypassing the bus protocol
+pub trait FunctionalMemory {
+    /// Reads like a bus read, devices apply their read side effects
+    fn read_dw(&mut self, address: Address) -> Result<u64, BusError>;
+    /// Reads instructions, which needs the execute permission
+    fn fetch_dw(&mut self, address: Address) -> Result<u64, BusError>;
+    /// Writes the low bytes of data according to the write status
+    fn write(&mut self, address: Address, data: u64, status: BusStatus) -> Result<(), BusError>;
+    /// Reserves the address for a following store-conditional, LR
+    fn reserve(&mut self, address: Address);
+    /// Consumes the reservation, true if it still covers the address
+    fn take_reservation(&mut self, address: Address) -> bool;
+}
+
+/// Fast execution mode
+/// Whole instructions are executed directly against the register file, no micro operations involved.
+/// Micro-architectural state (TMP registers, flags set by address calculations) is not reproduced.
+/// Memory is accessed without wait states, misaligned accesses follow the CPU's policy.
+/// Atomic accesses always trap when misaligned.
+/// Caches are bypassed, they are written back and invalidated before each instruction.
+impl CPU {
+    pub fn is_at_instruction_boundary(&self) -> bool {
+        match self.ooo.as_ref() {
+            Some(ooo) => ooo.is_drained(),
+            None => self.micro_op_queue.is_empty(),
+        }
+    }
+
+    /// Fetches, decodes and executes the instruction at PC.
+    /// Returns false if the instruction halts the CPU.
+    pub fn execute_next_instruction(&mut self, memory: &mut impl FunctionalMemory) -> bool {
+        self.write_back_caches(memory);
+        let running = self.fetch_and_execute(memory);
+        self.halted = !running;
+        running
+    }
+
+    /// Writes the dirty lines of both caches to memory and invalidates them
+    pub fn write_back_caches(&mut self, memory: &mut impl FunctionalMemory) {
+        let caches = [self.caches.instruction.as_mut(), self.caches.data.as_mut()];
+        for cache in caches.into_iter().flatten() {
+            cache.write_back_all(|address, data| {
+                for (offset, chunk) in (0..).step_by(8).zip(data.chunks(8)) {
+                    let value = u64::from_le_bytes(chunk.try_into().unwrap());
+                    let address = Address::new(address + offset);
+                    // Lines are only cached from memory which accepted their fill
+                    let _ = memory.write(address, value, BusStatus::WriteDoubleWord);
+                }
+            });
+        }
+    }
+
+    fn fetch_and_execute(&mut self, memory: &mut impl FunctionalMemory) -> bool {
+        let pc = self.get_register(PC);
+        let Some(data) = self.execute_read(MemoryAccess::Fetch, pc, 4, memory) else {
+            return false;
+        };
+        let instruction_bits = data as u32;
+        self.set_register(IR, instruction_bits as i32 as i64 as u64);
+
+        let Some(instruction) = Instruction::try_decode(instruction_bits) else {
+            self.set_register(PC, pc);
+            self.raise(Exception::IllegalInstruction(instruction_bits));
+            return false;
+        };
+        self.decode_counter = self.decode_counter.wrapping_add(1);
+        debug!(
+            target: "cpu",
+            "[fast] #{}: {:032b} | {instruction}",
+            self.decode_counter,
+            instruction_bits
+        );
+
+        self.execute_instruction(instruction, memory)
+    }
+
+    fn execute_instruction(
+        &mut self,
+        instruction: Instruction,
+        memory: &mut impl FunctionalMemory,
+    ) -> bool {
+        match instruction {
+            Instruction::Add(rd, rs1, rs2) => self.execute_alu(ALUOp::Add, rd, rs1, rs2),
+            Instruction::And(rd, rs1, rs2) => self.execute_alu(ALUOp::And, rd, rs1, rs2),
+            Instruction::Or(rd, rs1, rs2) => self.execute_alu(ALUOp::Or, rd, rs1, rs2),
+            Instruction::Sub(rd, rs1, rs2) => self.execute_alu(ALUOp::Sub, rd, rs1, rs2),
+            Instruction::Xor(rd, rs1, rs2) => self.execute_alu(ALUOp::Xor, rd, rs1, rs2),
+            Instruction::Sll(rd, rs1, rs2) => self.execute_alu(ALUOp::Sll, rd, rs1, rs2),
+            Instruction::Srl(rd, rs1, rs2) => self.execute_alu(ALUOp::Srl, rd, rs1, rs2),
+            Instruction::Sra(rd, rs1, rs2) => self.execute_alu(ALUOp::Sra, rd, rs1, rs2),
+            Instruction::Addi(rd, rs1, imm) => {
+                let value = self.get_register(rs1);
+                self.alu_write(rd, ALUOp::Addi.compute(value, imm));
+                true
+            }
+            Instruction::Slli(rd, rs1, shamt) => self.execute_shift_imm(ALUOp::Sll, rd, rs1, shamt),
+            Instruction::Srli(rd, rs1, shamt) => self.execute_shift_imm(ALUOp::Srl, rd, rs1, shamt),
+            Instruction::Srai(rd, rs1, shamt) => self.execute_shift_imm(ALUOp::Sra, rd, rs1, shamt),
+            Instruction::Jalr(rd, rs1, imm) => {
+                let target = self.get_register(rs1).wrapping_add(imm) & !1;
+                self.set_register(rd, self.get_register(PC));
+                self.set_register(PC, target);
+                true
+            }
+            Instruction::Jal(rd, offset) => {
+                let return_address = self.get_register(PC);
+                self.set_register(rd, return_address);
+                self.set_register(PC, return_address.wrapping_sub(4).wrapping_add(offset));
+                true
+            }
+            Instruction::Beq(rs1, rs2, offset) => {
+                self.execute_branch(BranchCondition::Equal, rs1, rs2, offset)
+            }
+            Instruction::Bne(rs1, rs2, offset) => {
+                self.execute_branch(BranchCondition::NotEqual, rs1, rs2, offset)
+            }
+            Instruction::Blt(rs1, rs2, offset) => {
+                self.execute_branch(BranchCondition::LessThan, rs1, rs2, offset)
+            }
+            Instruction::Bge(rs1, rs2, offset) => {
+                self.execute_branch(BranchCondition::GreaterEqual, rs1, rs2, offset)
+            }
+            Instruction::Bltu(rs1, rs2, offset) => {
+                self.execute_branch(BranchCondition::LessThanUnsigned, rs1, rs2, offset)
+            }
+            Instruction::Bgeu(rs1, rs2, offset) => {
+                self.execute_branch(BranchCondition::GreaterEqualUnsigned, rs1, rs2, offset)
+            }
+            Instruction::Lui(rd, imm) => {
+                self.set_register(rd, Instruction::upper_immediate_value(imm));
+                true
+            }
+            Instruction::Auipc(rd, imm) => {
+                let address = self.get_register(PC).wrapping_sub(4);
+                let value = address.wrapping_add(Instruction::upper_immediate_value(imm));
+                self.set_register(rd, value);
+                true
+            }
+            Instruction::Lb(rd, rs1, imm) => self.execute_load(1, rd, rs1, imm, memory),
+            Instruction::Lh(rd, rs1, imm) => self.execute_load(2, rd, rs1, imm, memory),
+            Instruction::Lw(rd, rs1, imm) => self.execute_load(4, rd, rs1, imm, memory),
+            Instruction::Ld(rd, rs1, imm) => self.execute_load(8, rd, rs1, imm, memory),
+            Instruction::Sb(rs1, rs2, imm) => {
+                self.execute_store(BusStatus::WriteByte, rs1, rs2, imm, memory)
+            }
+            Instruction::Sh(rs1, rs2, imm) => {
+                self.execute_store(BusStatus::WriteHalfWord, rs1, rs2, imm, memory)
+            }
+            Instruction::Sw(rs1, rs2, imm) => {
+                self.execute_store(BusStatus::WriteWord, rs1, rs2, imm, memory)
+            }
+            Instruction::Sd(rs1, rs2, imm) => {
+                self.execute_store(BusStatus::WriteDoubleWord, rs1, rs2, imm, memory)
+            }
+            Instruction::LrW(rd, rs1) => self.execute_load_reserved(4, rd, rs1, memory),
+            Instruction::LrD(rd, rs1) => self.execute_load_reserved(8, rd, rs1, memory),
+            Instruction::ScW(rd, rs1, rs2) => {
+                self.execute_store_conditional(BusStatus::WriteWord, rd, rs1, rs2, memory)
+            }
+            Instruction::ScD(rd, rs1, rs2) => {
+                self.execute_store_conditional(BusStatus::WriteDoubleWord, rd, rs1, rs2, memory)
+            }
+            Instruction::Csrr(rd, csr) => {
+                self.set_register(rd, self.csrs.read(csr));
+                true
+            }
+            Instruction::Csrrw(rd, rs1, csr) => self.execute_csr(CsrOp::Write, rd, csr, rs1),
+            Instruction::Csrrs(rd, rs1, csr) => self.execute_csr(CsrOp::Set, rd, csr, rs1),
+            Instruction::Csrrc(rd, rs1, csr) => self.execute_csr(CsrOp::Clear, rd, csr, rs1),
+            Instruction::Csrrwi(rd, uimm, csr) => self.execute_csr_imm(CsrOp::Write, rd, csr, uimm),
+            Instruction::Csrrsi(rd, uimm, csr) => self.execute_csr_imm(CsrOp::Set, rd, csr, uimm),
+            Instruction::Csrrci(rd, uimm, csr) => self.execute_csr_imm(CsrOp::Clear, rd, csr, uimm),
+            Instruction::Mret => {
+                let address = self.csrs.mret();
+                self.set_register(PC, address);
+                true
+            }
+            Instruction::ECall | Instruction::EBreak => false,
+        }
+    }
+
+    fn execute_alu(&mut self, op: ALUOp, rd: CPUReg, rs1: CPUReg, rs2: CPUReg) -> bool {
+        self.alu_execute(op, rd, rs1, rs2);
+        true
+    }
+
+    fn execute_csr(&mut self, op: CsrOp, rd: CPUReg, csr: u16, rs1: CPUReg) -> bool {
+        let operand = self.get_register(rs1);
+        self.execute_csr_imm(op, rd, csr, operand)
+    }
+
+    fn execute_csr_imm(&mut self, op: CsrOp, rd: CPUReg, csr: u16, operand: u64) -> bool {
+        let old = self.csrs.access(op, csr, operand);
+        self.set_register(rd, old);
+        true
+    }
+
+    fn execute_shift_imm(&mut self, op: ALUOp, rd: CPUReg, rs1: CPUReg, shamt: u64) -> bool {
+        let value = self.get_register(rs1);
+        self.alu_write(rd, op.compute(value, shamt));
+        true
+    }
+
+    /// Reads a value of the given size according to the misaligned policy, raising on failure.
+    /// Only the low `size` bytes of the result are meaningful.
+    fn execute_read(
+        &mut self,
+        access: MemoryAccess,
+        address: u64,
+        size: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> Option<u64> {
+        if is_aligned(address, size) {
+            return self.read_memory(access, address, memory);
+        }
+        match self.misaligned {
+            MisalignedPolicy::Trap => {
+                self.raise(Exception::from_bus_error(
+                    access,
+                    BusError::Misaligned,
+                    address,
+                ));
+                None
+            }
+            MisalignedPolicy::Split => {
+                let mut value = 0;
+                for (part, part_size) in split_access(address, size) {
+                    let data = self.read_memory(access, part, memory)?;
+                    value = merge_part(value, data, part.wrapping_sub(address), part_size);
+                }
+                Some(value)
+            }
+            MisalignedPolicy::Allow => self.read_memory(access, address, memory),
+        }
+    }
+
+    fn read_memory(
+        &mut self,
+        access: MemoryAccess,
+        address: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> Option<u64> {
+        let result = match access {
+            MemoryAccess::Fetch => memory.fetch_dw(Address::new(address)),
+            _ => memory.read_dw(Address::new(address)),
+        };
+        match result {
+            Ok(data) => Some(data),
+            Err(error) => {
+                self.raise(Exception::from_bus_error(access, error, address));
+                None
+            }
+        }
+    }
+
+    fn execute_load(
+        &mut self,
+        size: u64,
+        rd: CPUReg,
+        rs1: CPUReg,
+        imm: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> bool {
+        let base = self.get_register(rs1);
+        let address = base.wrapping_add(imm);
+        let Some(data) = self.execute_read(MemoryAccess::Load, address, size, memory) else {
+            return false;
+        };
+        self.set_register(rd, sign_extend(data, size));
+        true
+    }
+
+    fn execute_store(
+        &mut self,
+        status: BusStatus,
+        rs1: CPUReg,
+        rs2: CPUReg,
+        imm: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> bool {
+        let base = self.get_register(rs1);
+        let address = base.wrapping_add(imm);
+        let data = self.get_register(rs2);
+        let size = status.write_size().unwrap_or(1);
+        let parts = match self.misaligned {
+            _ if is_aligned(address, size) => vec![(address, size)],
+            MisalignedPolicy::Trap => {
+                self.raise(Exception::StoreAddressMisaligned(address));
+                return false;
+            }
+            MisalignedPolicy::Split => split_access(address, size),
+            MisalignedPolicy::Allow => vec![(address, size)],
+        };
+        for (part, part_size) in parts {
+            let part_data = data >> (8 * part.wrapping_sub(address));
+            let status = BusStatus::write_of_size(part_size);
+            if let Err(error) = memory.write(Address::new(part), part_data, status) {
+                self.raise(Exception::from_bus_error(MemoryAccess::Store, error, part));
+                return false;
+            }
+        }
+        true
+    }
+
+    fn execute_load_reserved(
+        &mut self,
+        size: u64,
+        rd: CPUReg,
+        rs1: CPUReg,
+        memory: &mut impl FunctionalMemory,
+    ) -> bool {
+        let address = self.get_register(rs1);
+        if !is_aligned(address, size) {
+            self.raise(Exception::LoadAddressMisaligned(address));
+            return false;
+        }
+        memory.reserve(Address::new(address));
+        let Some(data) = self.read_memory(MemoryAccess::Load, address, memory) else {
+            return false;
+        };
+        self.set_register(rd, sign_extend(data, size));
+        true
+    }
+
+    fn execute_store_conditional(
+        &mut self,
+        status: BusStatus,
+        rd: CPUReg,
+        rs1: CPUReg,
+        rs2: CPUReg,
+        memory: &mut impl FunctionalMemory,
+    ) -> bool {
+        let address = self.get_register(rs1);
+        let size = status.write_size().unwrap_or(1);
+        if !is_aligned(address, size) {
+            self.raise(Exception::StoreAddressMisaligned(address));
+            return false;
+        }
+        let data = self.get_register(rs2);
+        let reserved = memory.take_reservation(Address::new(address));
+        self.set_register(rd, !reserved as u64);
+        if !reserved {
+            return true;
+        }
+        if let Err(error) = memory.write(Address::new(address), data, status) {
+            self.raise(Exception::from_bus_error(
+                MemoryAccess::Store,
+                error,
+                address,
+            ));
+            return false;
+        }
+        true
+    }
+
+    fn execute_branch(
+        &mut self,
+        condition: BranchCondition,
+        rs1: CPUReg,
+        rs2: CPUReg,
+        offset: u64,
+    ) -> bool {
+        if condition.evaluate(self.get_register(rs1), self.get_register(rs2)) {
+            let address = self.get_register(PC).wrapping_sub(4);
+            self.set_register(PC, address.wrapping_add(offset));
+        }
+        true
+    }
+}
@@ -0,0 +1,121 @@
+use crate::computer::components::cpu::micro_op::MicroOp;
+use crate::computer::components::cpu::registers::reg::CPUReg::TMP0;
+use crate::computer::instructions::Instruction;
+use log::debug;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FusionConfig {
+    /// Fuses adjacent micro operations of a single instruction
+    pub micro_op: bool,
+    /// Fuses adjacent instructions, requires prefetching the following instruction
+    pub macro_op: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FusionCounter {
+    pub count: u64,
+    pub ticks_saved: u64,
+}
+
+impl FusionCounter {
+    fn record(&mut self, ticks_saved: u64) {
+        self.count = self.count.wrapping_add(1);
+        self.ticks_saved = self.ticks_saved.wrapping_add(ticks_saved);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FusionStats {
+    /// RegisterLoadImm(TMP0) + ALUAddi/AGUAdd consuming TMP0
+    pub add_immediate: FusionCounter,
+    /// LUI + ADDI
+    pub lui_addi: FusionCounter,
+    /// AUIPC + JALR
+    pub auipc_jalr: FusionCounter,
+}
+
+impl FusionStats {
+    pub fn total_ticks_saved(&self) -> u64 {
+        self.add_immediate.ticks_saved + self.lui_addi.ticks_saved + self.auipc_jalr.ticks_saved
+    }
+}
+
+/// Fuses RegisterLoadImm(TMP0, imm) with a directly following addition consuming TMP0.
+/// TMP0 is never written by the fused operation, so this is only done if no later micro operation reads it.
+pub fn fuse_micro_ops(queue: Vec<MicroOp>, stats: &mut FusionStats) -> Vec<MicroOp> {
+    let mut fused = Vec::with_capacity(queue.len());
+    let mut i = 0;
+    while i < queue.len() {
+        let next = queue.get(i + 1).copied();
+        let tmp0_read_later = queue
+            .iter()
+            .skip(i + 2)
+            .any(|micro_op| micro_op.sources().contains(&TMP0));
+
+        let fused_op = match (queue[i], next) {
+            (MicroOp::RegisterLoadImm(TMP0, imm), Some(MicroOp::ALUAddi(rd, rs1, TMP0)))
+                if rs1 != TMP0 && !tmp0_read_later =>
+            {
+                Some(MicroOp::ALUAddImm(rd, rs1, imm))
+            }
+            (MicroOp::RegisterLoadImm(TMP0, imm), Some(MicroOp::AGUAdd(rd, rs1, TMP0)))
+                if rs1 != TMP0 && !tmp0_read_later =>
+            {
+                Some(MicroOp::AGUAddImm(rd, rs1, imm))
+            }
+            _ => None,
+        };
+
+        match fused_op {
+            Some(micro_op) => {
+                debug!(target: "cpu", "Micro-op fusion: {:?}", micro_op);
+                stats.add_immediate.record(1);
+                fused.push(micro_op);
+                i += 2;
+            }
+            None => {
+                fused.push(queue[i]);
+                i += 1;
+            }
+        }
+    }
+    fused
+}
+
+/// Instructions which may start a macro-op fusion pair
+pub fn is_macro_fusion_head(instruction: Instruction) -> bool {
+    matches!(instruction, Instruction::Lui(..) | Instruction::Auipc(..))
+}
+
+/// Fuses two adjacent instructions into a single micro operation.
+/// The second instruction has to consume the destination of the first one.
+pub fn fuse_instructions(
+    first: Instruction,
+    second: Instruction,
+    unfused_micro_ops: usize,
+    stats: &mut FusionStats,
+) -> Option<Vec<MicroOp>> {
+    let (fused, counter) = match (first, second) {
+        (Instruction::Lui(rd, upper), Instruction::Addi(rd2, rs1, imm))
+            if rd == rd2 && rd == rs1 =>
+        {
+            let upper = Instruction::upper_immediate_value(upper);
+            (
+                vec![MicroOp::FusedLuiAddi(rd, upper, imm)],
+                &mut stats.lui_addi,
+            )
+        }
+        (Instruction::Auipc(rd, upper), Instruction::Jalr(rd2, rs1, imm)) if rd == rs1 => {
+            let upper = Instruction::upper_immediate_value(upper);
+            (
+                vec![MicroOp::FusedAuipcJalr(rd, rd2, upper, imm)],
+                &mut stats.auipc_jalr,
+            )
+        }
+        _ => return None,
+    };
+
+    counter.record(unfused_micro_ops.saturating_sub(fused.len()) as u64);
+    debug!(target: "cpu", "Macro-op fusion: {first} + {second}");
+    Some(fused)
+}
@@ -1,14 +1,18 @@
+use crate::computer::components::cpu::alu::BranchCondition;
+use crate::computer::components::cpu::csr::CsrOp;
 use crate::computer::components::cpu::registers::reg::CPUReg;
 use crate::computer::components::cpu::registers::reg::CPUReg::*;
 use std::collections::VecDeque;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub enum MicroOp {
     #[default]
     Stall,
     Halt,
     /// Decodes the instruction in the instruction register and decomposes it to micro operations
     Decode,
+    /// Decodes the prefetched instruction in the instruction register and fuses it with the pending one if possible
+    DecodeFused,
 
     // Bus operations
     BusRelease,
@@ -20,14 +24,50 @@ pub enum MicroOp {
     BusWriteAddress(CPUReg),
     BusWriteData(CPUReg),
     BusSetRead,
+    /// Ends the current transaction while keeping the bus, so the next one can be set up
+    BusSetIdle,
     BusSetWriteByte,
     BusSetWriteHalfWord,
     BusSetWriteWord,
     BusSetWriteDoubleWord,
+    /// Waits for the device to accept the write, raises a store exception on a bus error
+    BusAwaitWrite,
+    /// Starts a burst of the given number of double word beats at the address on the bus
+    #[cfg(test)]
+    BusSetReadBurst(u8),
+    /// Starts a burst write, the data on the bus is the first beat
+    #[cfg(test)]
+    BusSetWriteBurst(u8),
+    /// Waits for the current beat, reads it and moves on to the next one
+    #[cfg(test)]
+    BusReadBeat(CPUReg),
+    /// Waits for the current beat to be accepted and puts the register as the next beat's data
+    #[cfg(test)]
+    BusWriteBeat(CPUReg),
+    /// register, byte offset, size; merges a part of a split misaligned read into the register,
+    /// the part at offset 0 clears it
+    BusReadPart(CPUReg, u8, u8),
+    /// size; reserves the address on the bus for a following store-conditional, LR
+    BusReserve(u8),
+    /// rd, size; writes whether the reservation of the address on the bus was lost to rd,
+    /// the pending write is dropped if it was
+    BusStoreConditional(CPUReg, u8),
+
+    // Cache operations, each falls back to the bus sequence it replaces if the access bypasses the cache
+    /// address register, rd; fetches a word through the instruction cache
+    ICacheRead(CPUReg, CPUReg),
+    /// address register, rd, size; sign extended load through the data cache
+    DCacheRead(CPUReg, CPUReg, u8),
+    /// address register, data register, size; store through the data cache
+    DCacheWrite(CPUReg, CPUReg, u8),
+    /// address register, size; writes back and invalidates the data cache lines of a bus access
+    DCacheFlush(CPUReg, u8),
 
     // ALU operations
     /// rd, rs1, rs2
     ALUAdd(CPUReg, CPUReg, CPUReg),
+    /// rd, rs1, rs2 holding the immediate; wrapping, unlike ALUAdd
+    ALUAddi(CPUReg, CPUReg, CPUReg),
     ALUAnd(CPUReg, CPUReg, CPUReg),
     ALUOr(CPUReg, CPUReg, CPUReg),
     ALUSub(CPUReg, CPUReg, CPUReg),
@@ -36,8 +76,37 @@ pub enum MicroOp {
     ALUSrl(CPUReg, CPUReg, CPUReg),
     ALUSra(CPUReg, CPUReg, CPUReg),
 
+    // AGU operations
+    /// rd, rs1, rs2; wrapping address calculation which leaves the flags untouched
+    AGUAdd(CPUReg, CPUReg, CPUReg),
+    /// rd, rs1, rs2; AGUAdd clearing bit 0 of the result, the target of JALR
+    AGUAddAligned(CPUReg, CPUReg, CPUReg),
+
+    /// condition, rs1, rs2, offset register; adds the offset to PC if the condition holds
+    Branch(BranchCondition, CPUReg, CPUReg, CPUReg),
+
     // Register operations
     RegisterLoadImm(CPUReg, u64),
+    /// rd, rs
+    RegisterMove(CPUReg, CPUReg),
+    /// register, size in bytes
+    RegisterSignExtend(CPUReg, u8),
+    /// rd, csr
+    CsrRead(CPUReg, u16),
+    /// op, rd, csr, rs; reads the CSR to rd and modifies it with the value of rs
+    CsrAccess(CsrOp, CPUReg, u16, CPUReg),
+    /// Restores the interrupt enable of the interrupted code and returns to mepc
+    Mret,
+
+    // Fused operations
+    /// rd, rs1, imm; fused RegisterLoadImm + ALUAddi
+    ALUAddImm(CPUReg, CPUReg, u64),
+    /// rd, rs1, imm; fused RegisterLoadImm + AGUAdd
+    AGUAddImm(CPUReg, CPUReg, u64),
+    /// rd, upper immediate value, imm; fused LUI + ADDI
+    FusedLuiAddi(CPUReg, u64, u64),
+    /// AUIPC rd, JALR rd, upper immediate value, imm; fused AUIPC + JALR
+    FusedAuipcJalr(CPUReg, CPUReg, u64, u64),
 }
 
 impl MicroOp {
@@ -51,6 +120,133 @@ impl MicroOp {
             Self::Decode,
         ])
     }
+
+    /// Fetches the instruction following the currently decoded one for macro-op fusion
+    pub fn prefetch_queue() -> VecDeque<Self> {
+        VecDeque::from(vec![
+            Self::BusTake,
+            Self::BusWriteAddress(PC),
+            Self::BusSetRead,
+            Self::BusReadWord(IR),
+            Self::BusRelease,
+            Self::DecodeFused,
+        ])
+    }
+
+    /// Number of bytes a bus read micro operation uses
+    pub fn read_size(&self) -> Option<u64> {
+        match self {
+            Self::BusReadByte(_) => Some(1),
+            Self::BusReadHalfWord(_) => Some(2),
+            Self::BusReadWord(_) => Some(4),
+            Self::BusReadDoubleWord(_) => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Bus read micro operation of 1, 2, 4 or 8 bytes
+    pub fn read_of_size(size: u64, register: CPUReg) -> Self {
+        match size {
+            1 => Self::BusReadByte(register),
+            2 => Self::BusReadHalfWord(register),
+            4 => Self::BusReadWord(register),
+            _ => Self::BusReadDoubleWord(register),
+        }
+    }
+
+    /// Number of bytes a write status micro operation uses
+    pub fn write_size(&self) -> Option<u64> {
+        match self {
+            Self::BusSetWriteByte => Some(1),
+            Self::BusSetWriteHalfWord => Some(2),
+            Self::BusSetWriteWord => Some(4),
+            Self::BusSetWriteDoubleWord => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Sets the bus status for a single write of 1, 2, 4 or 8 bytes
+    pub fn set_write(size: u64) -> Self {
+        match size {
+            1 => Self::BusSetWriteByte,
+            2 => Self::BusSetWriteHalfWord,
+            4 => Self::BusSetWriteWord,
+            _ => Self::BusSetWriteDoubleWord,
+        }
+    }
+
+    /// Registers read by this micro operation
+    pub fn sources(&self) -> Vec<CPUReg> {
+        match *self {
+            Self::Decode | Self::DecodeFused => vec![IR],
+            Self::BusWriteAddress(rs)
+            | Self::BusWriteData(rs)
+            | Self::RegisterMove(_, rs)
+            | Self::CsrAccess(_, _, _, rs)
+            | Self::ICacheRead(rs, _)
+            | Self::DCacheRead(rs, _, _)
+            | Self::DCacheFlush(rs, _) => {
+                vec![rs]
+            }
+            #[cfg(test)]
+            Self::BusWriteBeat(rs) => vec![rs],
+            Self::DCacheWrite(address, data, _) => vec![address, data],
+            Self::ALUAdd(_, rs1, rs2)
+            | Self::ALUAddi(_, rs1, rs2)
+            | Self::ALUAnd(_, rs1, rs2)
+            | Self::ALUOr(_, rs1, rs2)
+            | Self::ALUSub(_, rs1, rs2)
+            | Self::ALUXor(_, rs1, rs2)
+            | Self::ALUSll(_, rs1, rs2)
+            | Self::ALUSrl(_, rs1, rs2)
+            | Self::ALUSra(_, rs1, rs2)
+            | Self::AGUAdd(_, rs1, rs2)
+            | Self::AGUAddAligned(_, rs1, rs2) => vec![rs1, rs2],
+            Self::ALUAddImm(_, rs1, _) | Self::AGUAddImm(_, rs1, _) => vec![rs1],
+            Self::BusReadPart(rd, _, _) | Self::RegisterSignExtend(rd, _) => vec![rd],
+            Self::Branch(_, rs1, rs2, offset) => vec![rs1, rs2, offset, PC],
+            Self::FusedAuipcJalr(..) => vec![PC],
+            _ => vec![],
+        }
+    }
+
+    /// Registers written by this micro operation, including the flags register
+    pub fn destinations(&self) -> Vec<CPUReg> {
+        match *self {
+            Self::BusReadByte(rd)
+            | Self::BusReadHalfWord(rd)
+            | Self::BusReadWord(rd)
+            | Self::BusReadDoubleWord(rd)
+            | Self::BusReadPart(rd, _, _)
+            | Self::ICacheRead(_, rd)
+            | Self::DCacheRead(_, rd, _)
+            | Self::RegisterSignExtend(rd, _)
+            | Self::BusStoreConditional(rd, _)
+            | Self::CsrRead(rd, _)
+            | Self::CsrAccess(_, rd, _, _)
+            | Self::ALUSll(rd, _, _)
+            | Self::ALUSrl(rd, _, _)
+            | Self::ALUSra(rd, _, _)
+            | Self::AGUAdd(rd, _, _)
+            | Self::AGUAddAligned(rd, _, _)
+            | Self::AGUAddImm(rd, _, _)
+            | Self::RegisterLoadImm(rd, _)
+            | Self::RegisterMove(rd, _) => vec![rd],
+            #[cfg(test)]
+            Self::BusReadBeat(rd) => vec![rd],
+            Self::ALUAdd(rd, _, _)
+            | Self::ALUAddi(rd, _, _)
+            | Self::ALUAnd(rd, _, _)
+            | Self::ALUOr(rd, _, _)
+            | Self::ALUSub(rd, _, _)
+            | Self::ALUXor(rd, _, _)
+            | Self::ALUAddImm(rd, _, _)
+            | Self::FusedLuiAddi(rd, _, _) => vec![rd, F],
+            Self::FusedAuipcJalr(rd1, rd2, _, _) => vec![rd1, rd2, PC],
+            Self::Branch(..) | Self::Mret => vec![PC],
+            _ => vec![],
+        }
+    }
 }
 
 #[derive(Debug, Default, PartialEq)]
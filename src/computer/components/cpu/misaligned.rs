@@ -0,0 +1,61 @@
+/// Instructions are four bytes and have to be aligned to them
+pub const INSTRUCTION_ALIGNMENT: u64 = 4;
+
+/// Handling of accesses whose address is not a multiple of their size.
+/// Applies alike to loads, stores and instruction fetch in every execution mode.
+/// Atomic memory operations always trap, a split access would not be atomic.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum MisalignedPolicy {
+    /// Raises an address-misaligned exception before the bus is accessed
+    #[default]
+    Trap,
+    /// Splits the access into naturally aligned transactions within a single bus tenure
+    Split,
+    /// Passes the access to the bus unchanged, memory handles it transparently
+    Allow,
+}
+
+pub fn is_aligned(address: u64, size: u64) -> bool {
+    address.is_multiple_of(size)
+}
+
+/// Size of the largest naturally aligned part starting at the address
+pub fn part_size(address: u64, remaining: u64) -> u64 {
+    [8, 4, 2, 1]
+        .into_iter()
+        .find(|size| *size <= remaining && is_aligned(address, *size))
+        .unwrap_or(1)
+}
+
+/// Naturally aligned (address, size) parts covering the access, in ascending address order
+pub fn split_access(address: u64, size: u64) -> Vec<(u64, u64)> {
+    let mut parts = Vec::new();
+    let end = address.wrapping_add(size);
+    let mut part_address = address;
+    while part_address != end {
+        let size = part_size(part_address, end.wrapping_sub(part_address));
+        parts.push((part_address, size));
+        part_address = part_address.wrapping_add(size);
+    }
+    parts
+}
+
+/// Places the low bytes of a part read at the byte offset within the access
+pub fn merge_part(value: u64, data: u64, offset: u64, size: u64) -> u64 {
+    let mask = if size >= 8 {
+        u64::MAX
+    } else {
+        (1 << (8 * size)) - 1
+    };
+    value | ((data & mask) << (8 * offset))
+}
+
+/// Sign extends the low bytes of a value of the given size
+pub fn sign_extend(value: u64, size: u64) -> u64 {
+    match size {
+        1 => value as i8 as i64 as u64,
+        2 => value as i16 as i64 as u64,
+        4 => value as i32 as i64 as u64,
+        _ => value,
+    }
+}
@@ -0,0 +1,1255 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::alu::{ALUOp, ALUResult};
+use crate::computer::components::cpu::branch_prediction::{
+    BranchPredictionUnit, BranchPredictor, BranchStats,
+};
+use crate::computer::components::cpu::cache::{CacheRequest, Caches};
+use crate::computer::components::cpu::csr::Csrs;
+use crate::computer::components::cpu::decompose::decompose_instruction;
+use crate::computer::components::cpu::exception::{Exception, MemoryAccess};
+use crate::computer::components::cpu::micro_op::MicroOp;
+use crate::computer::components::cpu::misaligned::{
+    is_aligned, MisalignedPolicy, INSTRUCTION_ALIGNMENT,
+};
+use crate::computer::components::cpu::ooo::bus_port::{BusRequester, BusTransaction};
+use crate::computer::components::cpu::ooo::rename_table::{PhysicalRegisterFile, RenameTable};
+use crate::computer::components::cpu::ooo::reorder_buffer::{
+    RenamedDestination, ReorderBuffer, ReorderBufferEntry,
+};
+use crate::computer::components::cpu::ooo::reservation_station::{
+    FunctionalUnit, Operand, ReservationStation, ReservationStationEntry,
+};
+use crate::computer::components::cpu::registers::flags::{CPUFlags, CPUFlagsAccessTrait};
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::components::cpu::registers::reg::CPUReg::{IR, PC};
+use crate::computer::components::cpu::registers::{CPURegisters, CPURegistersAccessTrait};
+use crate::computer::instructions::Instruction;
+use log::debug;
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+mod bus_port;
+pub mod rename_table;
+pub mod reorder_buffer;
+pub mod reservation_station;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OoOConfig {
+    /// Instructions renamed and committed per tick, micro operations issued per tick
+    pub issue_width: usize,
+    /// Micro operations the ALUs accept per tick
+    pub alu_units: usize,
+    /// Micro operations the AGUs accept per tick.
+    /// There is no equivalent for the load/store unit, it is bound to the single bus shared with instruction fetch.
+    pub agu_units: usize,
+    pub rob_size: usize,
+    pub alu_stations: usize,
+    pub agu_stations: usize,
+    pub lsu_stations: usize,
+    pub physical_registers: usize,
+    /// Ticks between issuing an ALU or AGU micro operation and its writeback
+    pub alu_latency: u64,
+    /// Capacity of the decoded micro operation queue between fetch and rename
+    pub frontend_queue_size: usize,
+    pub btb_entries: usize,
+    pub ras_depth: usize,
+}
+
+impl Default for OoOConfig {
+    fn default() -> Self {
+        Self {
+            issue_width: 1,
+            alu_units: 1,
+            agu_units: 1,
+            rob_size: 16,
+            alu_stations: 4,
+            agu_stations: 4,
+            lsu_stations: 4,
+            physical_registers: 32,
+            alu_latency: 1,
+            frontend_queue_size: 8,
+            btb_entries: 16,
+            ras_depth: 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OoOOp {
+    /// Register-only micro operation executed by the ALU
+    Compute(MicroOp),
+    /// Collapsed bus read sequence: address register, bus read micro operation
+    Load(CPUReg, MicroOp),
+    /// Collapsed bus write sequence: address register, data register, write status.
+    /// Stores are not speculative, they are only issued for the oldest instruction.
+    Store(CPUReg, CPUReg, BusStatus),
+    /// Load which reserves its address, LR
+    LoadReserved(CPUReg, MicroOp),
+    /// Store which only writes if the reservation still holds: address, data, write status, rd.
+    /// Not speculative, like stores.
+    StoreConditional(CPUReg, CPUReg, BusStatus, CPUReg),
+    Halt,
+    /// Undecodable instruction bits, raises an exception when committed
+    Illegal(u32),
+    /// Instruction fetch which failed or was not attempted, raises the exception when committed
+    FetchFault(Exception),
+}
+
+impl OoOOp {
+    /// Collapses the bus protocol micro operations of a decomposition into single loads
+    fn from_micro_ops(micro_ops: Vec<MicroOp>) -> Vec<OoOOp> {
+        let mut ops = Vec::new();
+        let mut address = None;
+        let mut data = None;
+        let mut reserve = false;
+        let mut conditional = None;
+        for micro_op in micro_ops {
+            match micro_op {
+                MicroOp::Stall
+                | MicroOp::BusTake
+                | MicroOp::BusSetRead
+                | MicroOp::BusAwaitWrite
+                | MicroOp::BusRelease => {}
+                MicroOp::BusWriteAddress(register) => address = Some(register),
+                MicroOp::BusWriteData(register) => data = Some(register),
+                MicroOp::BusReserve(_) => reserve = true,
+                MicroOp::BusStoreConditional(rd, _) => conditional = Some(rd),
+                MicroOp::BusReadByte(_)
+                | MicroOp::BusReadHalfWord(_)
+                | MicroOp::BusReadWord(_)
+                | MicroOp::BusReadDoubleWord(_) => {
+                    let address = address.expect("Bus read without address");
+                    ops.push(if reserve {
+                        OoOOp::LoadReserved(address, micro_op)
+                    } else {
+                        OoOOp::Load(address, micro_op)
+                    });
+                }
+                MicroOp::Halt => ops.push(OoOOp::Halt),
+                MicroOp::BusSetWriteByte
+                | MicroOp::BusSetWriteHalfWord
+                | MicroOp::BusSetWriteWord
+                | MicroOp::BusSetWriteDoubleWord => {
+                    let address = address.expect("Bus write without address");
+                    let data = data.expect("Bus write without data");
+                    let status = write_status(micro_op);
+                    ops.push(match conditional {
+                        Some(rd) => OoOOp::StoreConditional(address, data, status, rd),
+                        None => OoOOp::Store(address, data, status),
+                    });
+                }
+                _ => ops.push(OoOOp::Compute(micro_op)),
+            }
+        }
+        ops
+    }
+
+    fn unit(&self) -> Option<FunctionalUnit> {
+        match self {
+            OoOOp::Compute(
+                MicroOp::AGUAdd(..) | MicroOp::AGUAddAligned(..) | MicroOp::AGUAddImm(..),
+            ) => Some(FunctionalUnit::AGU),
+            OoOOp::Compute(_) => Some(FunctionalUnit::ALU),
+            OoOOp::Load(..)
+            | OoOOp::Store(..)
+            | OoOOp::LoadReserved(..)
+            | OoOOp::StoreConditional(..) => Some(FunctionalUnit::LSU),
+            OoOOp::Halt | OoOOp::Illegal(_) | OoOOp::FetchFault(_) => None,
+        }
+    }
+
+    fn sources(&self) -> Vec<CPUReg> {
+        match self {
+            OoOOp::Compute(micro_op) => micro_op.sources(),
+            OoOOp::Load(address, _) | OoOOp::LoadReserved(address, _) => vec![*address],
+            OoOOp::Store(address, data, _) | OoOOp::StoreConditional(address, data, _, _) => {
+                vec![*address, *data]
+            }
+            OoOOp::Halt | OoOOp::Illegal(_) | OoOOp::FetchFault(_) => vec![],
+        }
+    }
+
+    fn destinations(&self) -> Vec<CPUReg> {
+        match self {
+            OoOOp::Compute(micro_op)
+            | OoOOp::Load(_, micro_op)
+            | OoOOp::LoadReserved(_, micro_op) => micro_op.destinations(),
+            OoOOp::StoreConditional(_, _, _, rd) => vec![*rd],
+            OoOOp::Store(..) | OoOOp::Halt | OoOOp::Illegal(_) | OoOOp::FetchFault(_) => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OoOEvent {
+    Fetch {
+        address: u64,
+        bits: u32,
+    },
+    Rename {
+        id: u64,
+        op: OoOOp,
+        destinations: Vec<RenamedDestination>,
+    },
+    Issue {
+        id: u64,
+        unit: FunctionalUnit,
+    },
+    Writeback {
+        id: u64,
+    },
+    Commit {
+        address: u64,
+        instruction: Instruction,
+    },
+    Mispredict {
+        address: u64,
+        predicted: u64,
+        actual: u64,
+    },
+    Flush {
+        squashed: usize,
+    },
+    Exception {
+        address: u64,
+        exception: Exception,
+    },
+    Halt,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FunctionalUnitCounters {
+    pub alu: u64,
+    pub agu: u64,
+    pub lsu: u64,
+}
+
+impl FunctionalUnitCounters {
+    fn increment(&mut self, unit: FunctionalUnit) {
+        let counter = match unit {
+            FunctionalUnit::ALU => &mut self.alu,
+            FunctionalUnit::AGU => &mut self.agu,
+            FunctionalUnit::LSU => &mut self.lsu,
+        };
+        *counter = counter.wrapping_add(1);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OoOStats {
+    pub ticks: u64,
+    pub committed_instructions: u64,
+    pub issue_width: usize,
+    pub issued: FunctionalUnitCounters,
+    /// Ticks in which a ready micro operation could not issue because all units of its kind were in use
+    pub structural_stalls: FunctionalUnitCounters,
+    /// Ticks in which a ready micro operation could not issue because the issue width was exhausted
+    pub issue_width_stalls: u64,
+}
+
+impl OoOStats {
+    /// Achieved instructions per tick
+    pub fn ipc(&self) -> f64 {
+        if self.ticks == 0 {
+            return 0.0;
+        }
+        self.committed_instructions as f64 / self.ticks as f64
+    }
+
+    /// Theoretical maximum instructions per tick
+    pub fn max_ipc(&self) -> f64 {
+        self.issue_width as f64
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OoOTickResult {
+    pub halt: bool,
+    pub exception: Option<Exception>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct FrontendOp {
+    instruction_id: u64,
+    address: u64,
+    bits: u32,
+    instruction: Option<Instruction>,
+    /// Fetch address predicted to follow a control instruction
+    predicted_next: Option<u64>,
+    op: OoOOp,
+    last: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ExecutingOp {
+    id: u64,
+    remaining: u64,
+    results: Vec<u64>,
+}
+
+/// A bus read transfers a double word, i.e. up to two instructions per fetch
+const INSTRUCTIONS_PER_FETCH: usize = 2;
+
+/// Tomasulo-style out-of-order backend.
+/// Micro operations are renamed onto physical registers, wait in reservation stations until their
+/// operands are available, execute out of order and are committed in program order by the reorder buffer.
+/// Instructions are committed as a whole, exceptions are raised precisely at commit.
+/// Up to `issue_width` instructions are renamed and committed per tick.
+/// Fetch follows the branch prediction unit, mispredictions are detected at writeback
+/// and squash all younger micro operations.
+///
+/// Limitations:
+/// - Control flow instructions which cannot be predicted stall fetch until they are executed.
+/// - Flag-setting micro operations write the whole flags register.
+/// - Micro and macro-op fusion are not applied.
+#[derive(Debug)]
+pub struct OoOCore {
+    config: OoOConfig,
+    fetch_pc: u64,
+    /// Set after fetching a control instruction, cleared once the pipeline is drained
+    fetch_stalled: bool,
+    frontend: VecDeque<FrontendOp>,
+    rename_table: RenameTable,
+    physical_registers: PhysicalRegisterFile,
+    rob: ReorderBuffer,
+    alu_station: ReservationStation,
+    agu_station: ReservationStation,
+    lsu_station: ReservationStation,
+    executing: Vec<ExecutingOp>,
+    bus_transaction: Option<BusTransaction>,
+    misaligned: MisalignedPolicy,
+    /// Bus master of the hart the core belongs to
+    owner: BusOwner,
+    branch_unit: BranchPredictionUnit,
+    next_instruction_id: u64,
+    next_id: u64,
+    stats: OoOStats,
+    events: Vec<OoOEvent>,
+}
+
+impl OoOCore {
+    pub fn new(config: OoOConfig) -> Self {
+        Self {
+            config,
+            fetch_pc: 0,
+            fetch_stalled: false,
+            frontend: VecDeque::new(),
+            rename_table: RenameTable::default(),
+            physical_registers: PhysicalRegisterFile::new(config.physical_registers),
+            rob: ReorderBuffer::new(config.rob_size),
+            alu_station: ReservationStation::new(config.alu_stations),
+            agu_station: ReservationStation::new(config.agu_stations),
+            lsu_station: ReservationStation::new(config.lsu_stations),
+            executing: Vec::new(),
+            bus_transaction: None,
+            misaligned: MisalignedPolicy::default(),
+            owner: BusOwner::CPU(0),
+            branch_unit: BranchPredictionUnit::new(config.btb_entries, config.ras_depth),
+            next_instruction_id: 0,
+            next_id: 0,
+            stats: OoOStats {
+                issue_width: config.issue_width,
+                ..OoOStats::default()
+            },
+            events: Vec::new(),
+        }
+    }
+
+    pub fn set_misaligned_policy(&mut self, policy: MisalignedPolicy) {
+        self.misaligned = policy;
+    }
+
+    #[cfg(test)]
+    pub fn get_config(&self) -> OoOConfig {
+        self.config
+    }
+
+    #[cfg(test)]
+    pub fn get_rename_table(&self) -> &RenameTable {
+        &self.rename_table
+    }
+
+    #[cfg(test)]
+    pub fn get_physical_registers(&self) -> &PhysicalRegisterFile {
+        &self.physical_registers
+    }
+
+    #[cfg(test)]
+    pub fn get_reorder_buffer(&self) -> &ReorderBuffer {
+        &self.rob
+    }
+
+    pub fn get_reservation_station(&self, unit: FunctionalUnit) -> &ReservationStation {
+        match unit {
+            FunctionalUnit::ALU => &self.alu_station,
+            FunctionalUnit::AGU => &self.agu_station,
+            FunctionalUnit::LSU => &self.lsu_station,
+        }
+    }
+
+    fn get_reservation_station_mut(&mut self, unit: FunctionalUnit) -> &mut ReservationStation {
+        match unit {
+            FunctionalUnit::ALU => &mut self.alu_station,
+            FunctionalUnit::AGU => &mut self.agu_station,
+            FunctionalUnit::LSU => &mut self.lsu_station,
+        }
+    }
+
+    /// Events of the last tick
+    #[cfg(test)]
+    pub fn get_events(&self) -> &[OoOEvent] {
+        &self.events
+    }
+
+    pub fn get_stats(&self) -> OoOStats {
+        self.stats
+    }
+
+    pub fn get_branch_stats(&self) -> &BranchStats {
+        self.branch_unit.get_stats()
+    }
+
+    pub fn get_branch_predictor(&self) -> &dyn BranchPredictor {
+        self.branch_unit.get_predictor()
+    }
+
+    pub fn set_branch_predictor(&mut self, predictor: Box<dyn BranchPredictor>) {
+        self.branch_unit.set_predictor(predictor);
+    }
+
+    /// Discards all in-flight instructions and statistics.
+    /// The branch prediction unit keeps what it learned, like predictors which are not cleared on reset.
+    pub fn reset(&mut self) {
+        let previous = std::mem::replace(self, Self::new(self.config));
+        self.branch_unit = previous.branch_unit;
+        self.misaligned = previous.misaligned;
+    }
+
+    /// Discards all uncommitted instructions, fetching resumes at the committed PC
+    pub fn squash(&mut self, bus: &mut Bus, caches: &mut Caches) {
+        self.flush_from(0, bus, caches);
+    }
+
+    /// No instruction is in flight
+    pub fn is_drained(&self) -> bool {
+        self.rob.is_empty() && self.frontend.is_empty() && self.bus_transaction.is_none()
+    }
+
+    pub fn tick(
+        &mut self,
+        registers: &mut CPURegisters,
+        csrs: &mut Csrs,
+        bus: &mut Bus,
+        caches: &mut Caches,
+    ) -> OoOTickResult {
+        self.events.clear();
+        self.owner = BusOwner::CPU(csrs.hart_id as usize);
+        self.stats.ticks = self.stats.ticks.wrapping_add(1);
+
+        let result = self.commit(registers, csrs, bus, caches);
+        if result.halt {
+            return result;
+        }
+
+        self.complete_execution(bus, caches);
+        let load_issued = self.step_bus(registers, bus, caches);
+        self.issue(
+            self.config.issue_width.saturating_sub(load_issued as usize),
+            csrs,
+        );
+        self.dispatch(registers);
+        result
+    }
+
+    fn emit(&mut self, event: OoOEvent) {
+        debug!(target: "cpu::ooo", "{event}");
+        self.events.push(event);
+    }
+}
+
+/// Pipeline stages
+impl OoOCore {
+    fn commit(
+        &mut self,
+        registers: &mut CPURegisters,
+        csrs: &mut Csrs,
+        bus: &mut Bus,
+        caches: &mut Caches,
+    ) -> OoOTickResult {
+        for _ in 0..self.config.issue_width {
+            match self.commit_instruction(registers, csrs, bus, caches) {
+                Some(result) if result.halt => return result,
+                Some(_) => {}
+                None => break,
+            }
+        }
+        OoOTickResult::default()
+    }
+
+    /// Commits the oldest instruction if all of its micro operations are completed
+    fn commit_instruction(
+        &mut self,
+        registers: &mut CPURegisters,
+        csrs: &mut Csrs,
+        bus: &mut Bus,
+        caches: &mut Caches,
+    ) -> Option<OoOTickResult> {
+        let count = self.rob.head_instruction_completed()?;
+
+        let head = &self.rob.entries()[0];
+        let (head_id, address) = (head.id, head.address);
+        let exception = self
+            .rob
+            .entries()
+            .iter()
+            .take(count)
+            .find_map(|entry| entry.exception);
+        if let Some(exception) = exception {
+            // Everything older is already committed, PC still points to the faulting instruction
+            self.flush_from(head_id, bus, caches);
+            self.emit(OoOEvent::Exception { address, exception });
+            return Some(OoOTickResult {
+                halt: true,
+                exception: Some(exception),
+            });
+        }
+
+        let entries = self.rob.pop_front(count);
+        // Hardwired PC increment on IR writes, jumps overwrite PC afterward
+        registers.set_register(IR, entries[0].instruction_bits as i32 as i64 as u64);
+
+        let mut halt = false;
+        for entry in entries.iter() {
+            for destination in entry.destinations.iter() {
+                let value = self.physical_registers.get(destination.physical).value;
+                registers.set_register(destination.register, value);
+                if destination.register == PC {
+                    let instruction = entry.instruction.unwrap();
+                    self.branch_unit
+                        .resolve(address, instruction, value, entry.mispredicted);
+                }
+                if let Some(previous) = destination.previous {
+                    self.physical_registers.free(previous);
+                }
+                if self.rename_table.get(destination.register) == Some(destination.physical) {
+                    self.rename_table.set(destination.register, None);
+                    self.physical_registers.free(destination.physical);
+                }
+            }
+            if let Some((csr, value)) = entry.csr_write {
+                csrs.write(csr, value);
+            }
+            if entry.op == OoOOp::Compute(MicroOp::Mret) {
+                csrs.mret();
+            }
+            halt |= entry.op == OoOOp::Halt;
+        }
+
+        self.stats.committed_instructions = self.stats.committed_instructions.wrapping_add(1);
+        let instruction = entries[0]
+            .instruction
+            .expect("Committed instructions are always decodable");
+        self.emit(OoOEvent::Commit {
+            address,
+            instruction,
+        });
+
+        if halt {
+            // Drops the younger work, a halted hart must not keep asking for the bus
+            let last_id = entries.last().unwrap().id;
+            self.flush_from(last_id.wrapping_add(1), bus, caches);
+            self.emit(OoOEvent::Halt);
+        }
+        Some(OoOTickResult {
+            halt,
+            exception: None,
+        })
+    }
+
+    fn complete_execution(&mut self, bus: &mut Bus, caches: &mut Caches) {
+        for op in self.executing.iter_mut() {
+            op.remaining = op.remaining.saturating_sub(1);
+        }
+        let (finished, executing) = std::mem::take(&mut self.executing)
+            .into_iter()
+            .partition(|op| op.remaining == 0);
+        self.executing = executing;
+
+        for op in finished {
+            self.writeback(op.id, op.results, bus, caches);
+        }
+    }
+
+    /// Returns whether a load was issued
+    fn step_bus(&mut self, registers: &CPURegisters, bus: &mut Bus, caches: &mut Caches) -> bool {
+        if self.is_drained() {
+            self.resync(registers);
+        }
+
+        let mut load_issued = false;
+        if self.bus_transaction.is_none() {
+            self.bus_transaction = self.next_bus_transaction(caches);
+            load_issued = self
+                .bus_transaction
+                .is_some_and(|transaction| transaction.requester != BusRequester::Fetch);
+        } else if self.lsu_station.head_is_ready() {
+            self.stats.structural_stalls.increment(FunctionalUnit::LSU);
+        }
+        let Some(mut transaction) = self.bus_transaction else {
+            return load_issued;
+        };
+
+        match (transaction.step(bus, caches), transaction.requester) {
+            (None, _) => {}
+            (Some(Ok(data)), BusRequester::Fetch) => {
+                // A split fetch only reads the instruction at the misaligned address
+                let instructions = match transaction.cached_request() {
+                    Some(CacheRequest::Read { size, .. }) => {
+                        (size / INSTRUCTION_ALIGNMENT) as usize
+                    }
+                    _ if transaction.is_split() => 1,
+                    _ => INSTRUCTIONS_PER_FETCH,
+                };
+                self.deliver_fetch(transaction.address.value(), data, instructions)
+            }
+            (Some(Err(error)), BusRequester::Fetch) => {
+                let address = transaction.fault_address();
+                self.deliver_fetch_fault(
+                    transaction.address.value(),
+                    Exception::from_bus_error(MemoryAccess::Fetch, error, address),
+                )
+            }
+            (Some(Ok(data)), BusRequester::Load(id)) => {
+                let op = self.rob.get_mut(id).map(|entry| entry.op);
+                if let Some(OoOOp::Load(_, read) | OoOOp::LoadReserved(_, read)) = op {
+                    self.writeback(id, vec![extend_bus_data(read, data)], bus, caches);
+                }
+            }
+            (Some(Ok(data)), BusRequester::Store(id)) => {
+                // Only a store-conditional has a destination, for its success flag
+                self.writeback(id, vec![data], bus, caches)
+            }
+            (Some(Err(error)), BusRequester::Load(id)) => {
+                // Raised once the load commits, it might still be squashed by an older misprediction
+                let address = transaction.fault_address();
+                self.fault(
+                    id,
+                    Exception::from_bus_error(MemoryAccess::Load, error, address),
+                );
+            }
+            (Some(Err(error)), BusRequester::Store(id)) => {
+                let address = transaction.fault_address();
+                self.fault(
+                    id,
+                    Exception::from_bus_error(MemoryAccess::Store, error, address),
+                );
+            }
+        }
+
+        self.bus_transaction = if transaction.is_finished() {
+            None
+        } else {
+            Some(transaction)
+        };
+        load_issued
+    }
+
+    /// Loads and stores are issued in program order and take priority over instruction fetch.
+    /// A store waits until it belongs to the oldest instruction, younger loads wait behind it.
+    fn next_bus_transaction(&mut self, caches: &mut Caches) -> Option<BusTransaction> {
+        let store_waiting = self.lsu_station.entries().first().is_some_and(|entry| {
+            matches!(entry.op, OoOOp::Store(..) | OoOOp::StoreConditional(..))
+                && !self.is_oldest_instruction(entry.id)
+        });
+        let entry = if store_waiting {
+            None
+        } else {
+            self.lsu_station.take_ready_in_order()
+        };
+        if let Some(entry) = entry {
+            let values = entry.operand_values();
+            let address = Address::new(values[0]);
+            self.stats.issued.increment(FunctionalUnit::LSU);
+            self.emit(OoOEvent::Issue {
+                id: entry.id,
+                unit: FunctionalUnit::LSU,
+            });
+            let (transaction, size) = match entry.op {
+                OoOOp::Store(_, _, status) | OoOOp::StoreConditional(_, _, status, _) => (
+                    BusTransaction::write(
+                        BusRequester::Store(entry.id),
+                        address,
+                        values[1],
+                        status,
+                    ),
+                    status.write_size().unwrap_or(1),
+                ),
+                OoOOp::Load(_, read) | OoOOp::LoadReserved(_, read) => (
+                    BusTransaction::new(BusRequester::Load(entry.id), address),
+                    read.read_size().unwrap_or(1),
+                ),
+                _ => unreachable!("Only loads and stores wait in the load/store unit"),
+            };
+            let transaction = match entry.op {
+                OoOOp::LoadReserved(..) => transaction.reserved(),
+                OoOOp::StoreConditional(..) => transaction.conditional(),
+                _ => transaction,
+            };
+            let atomic = matches!(
+                entry.op,
+                OoOOp::LoadReserved(..) | OoOOp::StoreConditional(..)
+            );
+            let transaction =
+                self.apply_misaligned_policy(transaction.owned_by(self.owner), size, atomic)?;
+            let address = address.value();
+            let cached = !atomic && !transaction.is_split();
+            let request = match entry.op {
+                _ if cached && caches.serving(false, address, size).is_none() => None,
+                OoOOp::Store(..) if cached => Some(CacheRequest::Write {
+                    address,
+                    data: values[1],
+                    size,
+                }),
+                OoOOp::Load(..) if cached => Some(CacheRequest::Read { address, size }),
+                // Accesses bypassing the cache write back and invalidate the lines they touch
+                _ => None,
+            };
+            return Some(match request {
+                Some(request) => transaction.cached(request),
+                None if caches.needs_flush(address, size) => {
+                    transaction.cached(CacheRequest::Flush { address, size })
+                }
+                None => transaction,
+            });
+        }
+
+        if !self.fetch_stalled && self.frontend.len() < self.config.frontend_queue_size {
+            let transaction = BusTransaction::new(BusRequester::Fetch, Address::new(self.fetch_pc))
+                .owned_by(self.owner);
+            let transaction =
+                self.apply_misaligned_policy(transaction, INSTRUCTION_ALIGNMENT, false)?;
+            let address = self.fetch_pc;
+            if transaction.is_split()
+                || caches
+                    .serving(true, address, INSTRUCTION_ALIGNMENT)
+                    .is_none()
+            {
+                return Some(transaction);
+            }
+            // Cached fetches stop at the end of the line
+            let line_size = caches.instruction.as_ref().unwrap().get_config().line_size;
+            let size = (line_size - address % line_size).min(8);
+            return Some(transaction.cached(CacheRequest::Read { address, size }));
+        }
+        None
+    }
+
+    /// Trapped accesses never reach the bus, their exception is raised at commit.
+    /// Atomic accesses always trap.
+    fn apply_misaligned_policy(
+        &mut self,
+        transaction: BusTransaction,
+        size: u64,
+        atomic: bool,
+    ) -> Option<BusTransaction> {
+        let address = transaction.address.value();
+        if is_aligned(address, size) {
+            return Some(transaction);
+        }
+        let policy = if atomic {
+            MisalignedPolicy::Trap
+        } else {
+            self.misaligned
+        };
+        match policy {
+            MisalignedPolicy::Allow => Some(transaction),
+            MisalignedPolicy::Split => Some(transaction.split(size)),
+            MisalignedPolicy::Trap => {
+                match transaction.requester {
+                    BusRequester::Fetch => self.deliver_fetch_fault(
+                        address,
+                        Exception::InstructionAddressMisaligned(address),
+                    ),
+                    BusRequester::Load(id) => {
+                        self.fault(id, Exception::LoadAddressMisaligned(address))
+                    }
+                    BusRequester::Store(id) => {
+                        self.fault(id, Exception::StoreAddressMisaligned(address))
+                    }
+                }
+                None
+            }
+        }
+    }
+
+    fn deliver_fetch(&mut self, address: u64, data: u64, instructions: usize) {
+        let fetch_width = self.config.issue_width.clamp(1, instructions);
+        for i in 0..fetch_width {
+            let instruction_address = address.wrapping_add(4 * i as u64);
+            // Stop at predicted taken control instructions and unpredictable ones
+            if i > 0
+                && (self.fetch_stalled
+                    || self.fetch_pc != instruction_address
+                    || self.frontend.len() >= self.config.frontend_queue_size)
+            {
+                break;
+            }
+            let bits = (data >> (32 * i)) as u32;
+            self.deliver_instruction(instruction_address, bits);
+        }
+    }
+
+    /// Nothing is fetched past the faulting address until a redirect
+    fn deliver_fetch_fault(&mut self, address: u64, exception: Exception) {
+        let instruction_id = self.next_instruction_id;
+        self.next_instruction_id = self.next_instruction_id.wrapping_add(1);
+        self.frontend.push_back(FrontendOp {
+            instruction_id,
+            address,
+            bits: 0,
+            instruction: None,
+            predicted_next: None,
+            op: OoOOp::FetchFault(exception),
+            last: true,
+        });
+        self.fetch_stalled = true;
+    }
+
+    fn deliver_instruction(&mut self, address: u64, bits: u32) {
+        self.emit(OoOEvent::Fetch { address, bits });
+
+        let instruction = Instruction::try_decode(bits);
+        let predicted_next = instruction
+            .filter(Instruction::is_control)
+            .and_then(|instruction| self.branch_unit.predict(address, instruction));
+        let ops = match instruction {
+            Some(instruction) => OoOOp::from_micro_ops(decompose_instruction(instruction, 4)),
+            None => vec![OoOOp::Illegal(bits)],
+        };
+
+        let instruction_id = self.next_instruction_id;
+        self.next_instruction_id = self.next_instruction_id.wrapping_add(1);
+        let count = ops.len();
+        for (i, op) in ops.into_iter().enumerate() {
+            self.frontend.push_back(FrontendOp {
+                instruction_id,
+                address,
+                bits,
+                instruction,
+                predicted_next,
+                op,
+                last: i + 1 == count,
+            });
+        }
+
+        self.fetch_pc = predicted_next.unwrap_or(address.wrapping_add(4));
+        if predicted_next.is_none() && instruction.is_some_and(|i| i.is_control()) {
+            self.fetch_stalled = true;
+        }
+    }
+
+    /// Issues the oldest ready micro operations, bounded by the remaining issue width
+    /// and the number of units of each kind.
+    /// CSR micro operations are not speculative, they wait until their instruction is the oldest one
+    /// and modify the CSRs when it commits.
+    fn issue(&mut self, mut budget: usize, csrs: &Csrs) {
+        let mut candidates: Vec<(u64, FunctionalUnit)> = Vec::new();
+        for unit in [FunctionalUnit::ALU, FunctionalUnit::AGU] {
+            let station = self.get_reservation_station(unit);
+            candidates.extend(station.ready_ids().into_iter().map(|id| (id, unit)));
+        }
+        candidates.sort_by_key(|(id, _)| *id);
+
+        let mut available_alu = self.config.alu_units;
+        let mut available_agu = self.config.agu_units;
+        let mut stalled = Vec::new();
+        for (id, unit) in candidates {
+            if self.accesses_csrs(id) && !self.is_oldest_instruction(id) {
+                continue;
+            }
+            let available = match unit {
+                FunctionalUnit::ALU => &mut available_alu,
+                FunctionalUnit::AGU => &mut available_agu,
+                FunctionalUnit::LSU => unreachable!("Loads are issued by the bus port"),
+            };
+            if *available == 0 {
+                if !stalled.contains(&unit) {
+                    stalled.push(unit);
+                    self.stats.structural_stalls.increment(unit);
+                }
+                continue;
+            }
+            if budget == 0 {
+                self.stats.issue_width_stalls = self.stats.issue_width_stalls.wrapping_add(1);
+                break;
+            }
+            *available -= 1;
+            budget -= 1;
+
+            let entry = self.get_reservation_station_mut(unit).take(id).unwrap();
+            let OoOOp::Compute(micro_op) = entry.op else {
+                unreachable!("Only compute micro operations are issued to the ALU and AGU")
+            };
+            let results = match micro_op {
+                MicroOp::CsrRead(_, csr) => vec![csrs.read(csr)],
+                MicroOp::CsrAccess(op, _, csr, _) => {
+                    let old = csrs.read(csr);
+                    let value = op.apply(old, entry.operand_values()[0]);
+                    self.rob.get_mut(id).unwrap().csr_write = Some((csr, value));
+                    vec![old]
+                }
+                MicroOp::Mret => vec![csrs.mepc],
+                _ => evaluate(micro_op, &entry.operand_values()),
+            };
+            self.executing.push(ExecutingOp {
+                id,
+                remaining: self.config.alu_latency,
+                results,
+            });
+            self.stats.issued.increment(unit);
+            self.emit(OoOEvent::Issue { id, unit });
+        }
+    }
+
+    /// Renames the micro operations of up to `issue_width` instructions
+    fn dispatch(&mut self, registers: &CPURegisters) {
+        let mut instructions = 0;
+        while instructions < self.config.issue_width {
+            match self.dispatch_micro_op(registers) {
+                Some(true) => instructions += 1,
+                Some(false) => {}
+                None => break,
+            }
+        }
+    }
+
+    /// Returns whether the dispatched micro operation was the last one of its instruction,
+    /// None if nothing could be dispatched
+    fn dispatch_micro_op(&mut self, registers: &CPURegisters) -> Option<bool> {
+        let front = self.frontend.front()?;
+        let unit = front.op.unit();
+        let destinations = front.op.destinations();
+        let station_full = unit.is_some_and(|unit| self.get_reservation_station(unit).is_full());
+        if self.rob.is_full()
+            || station_full
+            || self.physical_registers.free_count() < destinations.len()
+        {
+            return None;
+        }
+
+        let front = self.frontend.pop_front().unwrap();
+        // Sources are read before renaming the destinations, a micro operation may read and write the same register
+        let operands: Vec<Operand> = front
+            .op
+            .sources()
+            .into_iter()
+            .map(|register| self.read_operand(register, front.address, registers))
+            .collect();
+        let destinations: Vec<RenamedDestination> = destinations
+            .into_iter()
+            .map(|register| {
+                let physical = self.physical_registers.allocate().unwrap();
+                let previous = self.rename_table.set(register, Some(physical));
+                RenamedDestination {
+                    register,
+                    physical,
+                    previous,
+                }
+            })
+            .collect();
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let exception = match front.op {
+            OoOOp::Illegal(bits) => Some(Exception::IllegalInstruction(bits)),
+            OoOOp::FetchFault(exception) => Some(exception),
+            _ => None,
+        };
+        self.rob.push(ReorderBufferEntry {
+            id,
+            instruction_id: front.instruction_id,
+            address: front.address,
+            instruction_bits: front.bits,
+            instruction: front.instruction,
+            predicted_next: front.predicted_next,
+            mispredicted: false,
+            op: front.op,
+            destinations: destinations.clone(),
+            completed: unit.is_none(),
+            exception,
+            csr_write: None,
+            last: front.last,
+        });
+
+        let entry = ReservationStationEntry {
+            id,
+            op: front.op,
+            operands,
+        };
+        if let Some(unit) = unit {
+            self.get_reservation_station_mut(unit).push(entry);
+        }
+        self.emit(OoOEvent::Rename {
+            id,
+            op: front.op,
+            destinations,
+        });
+        Some(front.last)
+    }
+}
+
+/// Helpers
+impl OoOCore {
+    fn accesses_csrs(&self, id: u64) -> bool {
+        let entry = self.rob.entries().iter().find(|entry| entry.id == id);
+        entry.is_some_and(|entry| {
+            matches!(
+                entry.op,
+                OoOOp::Compute(MicroOp::CsrRead(..) | MicroOp::CsrAccess(..) | MicroOp::Mret)
+            )
+        })
+    }
+
+    fn is_oldest_instruction(&self, id: u64) -> bool {
+        let head = self.rob.entries().front();
+        let entry = self.rob.entries().iter().find(|entry| entry.id == id);
+        head.zip(entry)
+            .is_some_and(|(head, entry)| head.instruction_id == entry.instruction_id)
+    }
+
+    /// Completes the micro operation with an exception, raised once its instruction commits
+    fn fault(&mut self, id: u64, exception: Exception) {
+        if let Some(entry) = self.rob.get_mut(id) {
+            entry.completed = true;
+            entry.exception = Some(exception);
+        }
+    }
+
+    fn read_operand(&self, register: CPUReg, address: u64, registers: &CPURegisters) -> Operand {
+        // PC is known at fetch, micro operations expect it to point to the following instruction
+        if register == PC {
+            return Operand::Ready(address.wrapping_add(4));
+        }
+
+        match self.rename_table.get(register) {
+            None => Operand::Ready(registers.get_register(register)),
+            Some(physical) => {
+                let physical_register = self.physical_registers.get(physical);
+                if physical_register.ready {
+                    Operand::Ready(physical_register.value)
+                } else {
+                    Operand::Waiting(physical)
+                }
+            }
+        }
+    }
+
+    fn writeback(&mut self, id: u64, results: Vec<u64>, bus: &mut Bus, caches: &mut Caches) {
+        let Some(entry) = self.rob.get_mut(id) else {
+            return;
+        };
+        entry.completed = true;
+        let (address, predicted_next) = (entry.address, entry.predicted_next);
+        let destinations = entry.destinations.clone();
+
+        let mut next_address = None;
+        for (destination, value) in destinations.iter().zip(results) {
+            self.physical_registers.write(destination.physical, value);
+            self.alu_station.wake_up(destination.physical, value);
+            self.agu_station.wake_up(destination.physical, value);
+            self.lsu_station.wake_up(destination.physical, value);
+            if destination.register == PC {
+                next_address = Some(value);
+            }
+        }
+        self.emit(OoOEvent::Writeback { id });
+
+        // Control instructions write PC with their last micro operation
+        let Some(actual) = next_address else {
+            return;
+        };
+        match predicted_next {
+            Some(predicted) if predicted == actual => {}
+            Some(predicted) => {
+                self.rob.get_mut(id).unwrap().mispredicted = true;
+                self.emit(OoOEvent::Mispredict {
+                    address,
+                    predicted,
+                    actual,
+                });
+                self.flush_from(id.wrapping_add(1), bus, caches);
+                self.fetch_pc = actual;
+            }
+            None => {
+                self.fetch_pc = actual;
+                self.fetch_stalled = false;
+            }
+        }
+    }
+
+    /// Squashes all micro operations starting with the given id and everything in the frontend
+    fn flush_from(&mut self, from_id: u64, bus: &mut Bus, caches: &mut Caches) {
+        let squashed = self.rob.squash(from_id);
+        for entry in squashed.iter() {
+            for destination in entry.destinations.iter().rev() {
+                self.rename_table
+                    .set(destination.register, destination.previous);
+                self.physical_registers.free(destination.physical);
+            }
+        }
+
+        self.alu_station.squash(from_id);
+        self.agu_station.squash(from_id);
+        self.lsu_station.squash(from_id);
+        self.executing.retain(|op| op.id < from_id);
+        if let Some(transaction) = self.bus_transaction {
+            let squash = match transaction.requester {
+                BusRequester::Fetch => true,
+                BusRequester::Load(id) | BusRequester::Store(id) => id >= from_id,
+            };
+            if squash {
+                transaction.cancel(bus, caches);
+                self.bus_transaction = None;
+            }
+        }
+
+        self.frontend.clear();
+        self.fetch_stalled = false;
+        self.emit(OoOEvent::Flush {
+            squashed: squashed.len(),
+        });
+    }
+
+    /// Once drained, all mapped physical registers hold committed values.
+    /// Unmapping them allows the committed state to be modified externally, e.g. by the fast execution mode.
+    fn resync(&mut self, registers: &CPURegisters) {
+        for (register, physical) in self.rename_table.mapped() {
+            self.rename_table.set(register, None);
+            self.physical_registers.free(physical);
+        }
+        self.fetch_pc = registers.get_register(PC);
+        self.fetch_stalled = false;
+    }
+}
+
+fn evaluate(micro_op: MicroOp, values: &[u64]) -> Vec<u64> {
+    match micro_op {
+        MicroOp::ALUAdd(..) => alu_results(ALUOp::Add.compute(values[0], values[1])),
+        MicroOp::ALUAddi(..) => alu_results(ALUOp::Addi.compute(values[0], values[1])),
+        MicroOp::ALUAnd(..) => alu_results(ALUOp::And.compute(values[0], values[1])),
+        MicroOp::ALUOr(..) => alu_results(ALUOp::Or.compute(values[0], values[1])),
+        MicroOp::ALUSub(..) => alu_results(ALUOp::Sub.compute(values[0], values[1])),
+        MicroOp::ALUXor(..) => alu_results(ALUOp::Xor.compute(values[0], values[1])),
+        MicroOp::ALUSll(..) => alu_results(ALUOp::Sll.compute(values[0], values[1])),
+        MicroOp::ALUSrl(..) => alu_results(ALUOp::Srl.compute(values[0], values[1])),
+        MicroOp::ALUSra(..) => alu_results(ALUOp::Sra.compute(values[0], values[1])),
+        MicroOp::AGUAdd(..) => vec![values[0].wrapping_add(values[1])],
+        MicroOp::AGUAddAligned(..) => vec![values[0].wrapping_add(values[1]) & !1],
+        MicroOp::Branch(condition, ..) => {
+            if condition.evaluate(values[0], values[1]) {
+                vec![values[3].wrapping_add(values[2])]
+            } else {
+                vec![values[3]]
+            }
+        }
+        MicroOp::RegisterLoadImm(_, imm) => vec![imm],
+        MicroOp::RegisterMove(..) => vec![values[0]],
+        MicroOp::ALUAddImm(_, _, imm) => alu_results(ALUOp::Addi.compute(values[0], imm)),
+        MicroOp::AGUAddImm(_, _, imm) => vec![values[0].wrapping_add(imm)],
+        MicroOp::FusedLuiAddi(_, upper, imm) => alu_results(ALUOp::Addi.compute(upper, imm)),
+        _ => unreachable!("{micro_op:?} is not executed by the ALU"),
+    }
+}
+
+/// Result values in the order of the micro operation's destinations
+fn alu_results(result: ALUResult) -> Vec<u64> {
+    match result.flags {
+        Some(alu_flags) => {
+            let mut flags = CPUFlags::new();
+            flags.set_zero(alu_flags.zero);
+            flags.set_carry(alu_flags.carry);
+            flags.set_subtract(alu_flags.subtract);
+            vec![result.value, flags.get_flags()]
+        }
+        None => vec![result.value],
+    }
+}
+
+fn write_status(set_write: MicroOp) -> BusStatus {
+    match set_write {
+        MicroOp::BusSetWriteByte => BusStatus::WriteByte,
+        MicroOp::BusSetWriteHalfWord => BusStatus::WriteHalfWord,
+        MicroOp::BusSetWriteWord => BusStatus::WriteWord,
+        _ => BusStatus::WriteDoubleWord,
+    }
+}
+
+/// Sign extension as done by the bus read micro operations
+fn extend_bus_data(read: MicroOp, data: u64) -> u64 {
+    match read {
+        MicroOp::BusReadByte(_) => (data & 0xFF) as i8 as i64 as u64,
+        MicroOp::BusReadHalfWord(_) => (data & 0xFFFF) as i16 as i64 as u64,
+        MicroOp::BusReadWord(_) => (data & 0xFFFF_FFFF) as i32 as i64 as u64,
+        _ => data,
+    }
+}
+
+impl Display for OoOOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OoOOp::Compute(micro_op) => write!(f, "{micro_op:?}"),
+            OoOOp::Load(address, read) => write!(f, "{read:?} ← M[{address}]"),
+            OoOOp::Store(address, data, status) => write!(f, "{status:?} M[{address}] ← {data}"),
+            OoOOp::LoadReserved(address, read) => write!(f, "{read:?} ← M[{address}] (reserved)"),
+            OoOOp::StoreConditional(address, data, status, rd) => {
+                write!(f, "{rd} ← {status:?} M[{address}] ← {data} (conditional)")
+            }
+            OoOOp::Halt => write!(f, "Halt"),
+            OoOOp::Illegal(bits) => write!(f, "Illegal({bits:032b})"),
+            OoOOp::FetchFault(exception) => write!(f, "FetchFault({exception})"),
+        }
+    }
+}
+
+impl Display for OoOEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OoOEvent::Fetch { address, bits } => {
+                write!(f, "fetch     [{address:016x}] {bits:032b}")
+            }
+            OoOEvent::Rename {
+                id,
+                op,
+                destinations,
+            } => {
+                write!(f, "rename    #{id} {op}")?;
+                for destination in destinations {
+                    write!(f, " {}→P{}", destination.register, destination.physical)?;
+                }
+                Ok(())
+            }
+            OoOEvent::Issue { id, unit } => write!(f, "issue     #{id} on {unit}"),
+            OoOEvent::Writeback { id } => write!(f, "writeback #{id}"),
+            OoOEvent::Commit {
+                address,
+                instruction,
+            } => write!(f, "commit    [{address:016x}] {instruction}"),
+            OoOEvent::Mispredict {
+                address,
+                predicted,
+                actual,
+            } => write!(
+                f,
+                "mispredict [{address:016x}] predicted {predicted:016x}, actual {actual:016x}"
+            ),
+            OoOEvent::Flush { squashed } => write!(f, "flush     {squashed} micro operations"),
+            OoOEvent::Exception { address, exception } => {
+                write!(f, "exception [{address:016x}] {exception}")
+            }
+            OoOEvent::Halt => write!(f, "halt"),
+        }
+    }
+}
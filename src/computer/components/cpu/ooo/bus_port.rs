@@ -0,0 +1,270 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::{BusError, BusResponse};
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::cache::{Cache, CacheRequest, Caches};
+use crate::computer::components::cpu::misaligned::{merge_part, part_size};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BusRequester {
+    Fetch,
+    /// Reorder buffer id of the load
+    Load(u64),
+    /// Reorder buffer id of the store
+    Store(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BusStage {
+    Take,
+    WriteAddress,
+    WriteData,
+    SetStatus,
+    Response,
+    Release,
+    Done,
+}
+
+/// Progress of a misaligned access split into naturally aligned parts
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SplitAccess {
+    size: u64,
+    /// Address of the part on the bus
+    part: u64,
+    /// Merged data of the parts read so far
+    value: u64,
+}
+
+impl SplitAccess {
+    fn offset(&self, address: Address) -> u64 {
+        self.part.wrapping_sub(address.value())
+    }
+
+    fn part_size(&self, address: Address) -> u64 {
+        part_size(self.part, self.size - self.offset(address))
+    }
+}
+
+/// Runs the same bus protocol as the micro operations of the in-order core, one step per tick.
+/// Shared by instruction fetch and the load/store unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BusTransaction {
+    pub requester: BusRequester,
+    pub address: Address,
+    data: u64,
+    status: BusStatus,
+    stage: BusStage,
+    split: Option<SplitAccess>,
+    owner: BusOwner,
+    /// Reserves the address for a following store-conditional, LR
+    reserve: bool,
+    /// The write only happens if the reservation still holds, SC
+    conditional: bool,
+    /// Served by a cache instead of the bus protocol, fetches by the instruction cache.
+    /// A flush of the data cache precedes the bus protocol.
+    cached: Option<CacheRequest>,
+}
+
+impl BusTransaction {
+    pub fn new(requester: BusRequester, address: Address) -> Self {
+        Self {
+            requester,
+            address,
+            data: 0,
+            status: BusStatus::Read,
+            stage: BusStage::Take,
+            split: None,
+            owner: BusOwner::CPU(0),
+            reserve: false,
+            conditional: false,
+            cached: None,
+        }
+    }
+
+    pub fn write(requester: BusRequester, address: Address, data: u64, status: BusStatus) -> Self {
+        Self {
+            requester,
+            address,
+            data,
+            status,
+            stage: BusStage::Take,
+            split: None,
+            owner: BusOwner::CPU(0),
+            reserve: false,
+            conditional: false,
+            cached: None,
+        }
+    }
+
+    /// Accesses `size` bytes as naturally aligned parts, one transaction each within a single bus tenure
+    pub fn split(mut self, size: u64) -> Self {
+        self.split = Some(SplitAccess {
+            size,
+            part: self.address.value(),
+            value: 0,
+        });
+        self
+    }
+
+    /// Hart which masters the transaction
+    pub fn owned_by(mut self, owner: BusOwner) -> Self {
+        self.owner = owner;
+        self
+    }
+
+    pub fn reserved(mut self) -> Self {
+        self.reserve = true;
+        self
+    }
+
+    pub fn conditional(mut self) -> Self {
+        self.conditional = true;
+        self
+    }
+
+    pub fn cached(mut self, request: CacheRequest) -> Self {
+        self.cached = Some(request);
+        self
+    }
+
+    /// Cache access serving the transaction, if any
+    pub fn cached_request(&self) -> Option<CacheRequest> {
+        self.cached
+    }
+
+    pub fn is_split(&self) -> bool {
+        self.split.is_some()
+    }
+
+    /// Address of the part on the bus, the access address unless split
+    pub fn fault_address(&self) -> u64 {
+        self.split.map_or(self.address.value(), |split| split.part)
+    }
+
+    /// Status of the current part, split writes use the part's size
+    fn part_status(&self) -> BusStatus {
+        match self.split {
+            Some(split) if self.status != BusStatus::Read => {
+                BusStatus::write_of_size(split.part_size(self.address))
+            }
+            _ => self.status,
+        }
+    }
+    /// Advances the transaction by one step.
+    /// Returns the device's answer once available: the read data, zero for writes, or the bus error.
+    /// A store-conditional which lost its reservation answers one without accessing the device.
+    /// The transaction is finished after the following release step.
+    pub fn step(&mut self, bus: &mut Bus, caches: &mut Caches) -> Option<Result<u64, BusError>> {
+        if let Some(request) = self.cached {
+            let result = self.cache(caches).access(request, bus, self.owner)?;
+            if matches!(request, CacheRequest::Flush { .. }) && result.is_ok() {
+                self.cached = None;
+                return None;
+            }
+            self.stage = BusStage::Done;
+            return Some(result);
+        }
+        match self.stage {
+            BusStage::Take => {
+                if bus.take_ownership(self.owner) {
+                    self.stage = BusStage::WriteAddress;
+                }
+            }
+            BusStage::WriteAddress => {
+                bus.put_address(Address::new(self.fault_address()), self.owner);
+                if self.reserve {
+                    bus.reserve(self.address, self.owner);
+                }
+                if self.conditional && !bus.take_reservation(self.address, self.owner) {
+                    self.stage = BusStage::Release;
+                    return Some(Ok(1));
+                }
+                self.stage = if self.status == BusStatus::Read {
+                    BusStage::SetStatus
+                } else {
+                    BusStage::WriteData
+                };
+            }
+            BusStage::WriteData => {
+                let offset = self.split.map_or(0, |split| split.offset(self.address));
+                bus.put_data(self.data >> (8 * offset), self.owner);
+                self.stage = BusStage::SetStatus;
+            }
+            BusStage::SetStatus => {
+                bus.put_status(self.part_status(), self.owner);
+                if self.requester == BusRequester::Fetch {
+                    bus.request_fetch(self.owner);
+                }
+                self.stage = BusStage::Response;
+            }
+            BusStage::Response => match bus.get_response() {
+                BusResponse::Retry => {}
+                BusResponse::Error(error) => {
+                    self.stage = BusStage::Release;
+                    return Some(Err(error));
+                }
+                BusResponse::Ok => {
+                    let data = if self.status == BusStatus::Read {
+                        bus.get_data()
+                    } else {
+                        0
+                    };
+                    let data = match self.split.as_mut() {
+                        None => data,
+                        Some(split) => {
+                            let offset = split.offset(self.address);
+                            let size = split.part_size(self.address);
+                            split.value = merge_part(split.value, data, offset, size);
+                            split.part = split.part.wrapping_add(size);
+                            // The bus is kept for the remaining parts
+                            if offset + size < split.size {
+                                // Ends the part's transaction, the next address must not be served with its status
+                                bus.put_status(BusStatus::Idle, self.owner);
+                                self.stage = BusStage::WriteAddress;
+                                return None;
+                            }
+                            split.value
+                        }
+                    };
+                    self.stage = BusStage::Release;
+                    return Some(Ok(data));
+                }
+            },
+            BusStage::Release => {
+                bus.release_ownership(self.owner);
+                self.stage = BusStage::Done;
+            }
+            BusStage::Done => {}
+        }
+        None
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.stage == BusStage::Done
+    }
+
+    /// Aborts the transaction, releasing the bus if it was already taken
+    pub fn cancel(&self, bus: &mut Bus, caches: &mut Caches) {
+        if self.cached.is_some() {
+            // Does nothing once the cache answered
+            self.cache(caches).cancel(bus, self.owner);
+            return;
+        }
+        match self.stage {
+            BusStage::Take => bus.withdraw_request(self.owner),
+            BusStage::Done => {}
+            _ => {
+                bus.release_ownership(self.owner);
+            }
+        }
+    }
+
+    fn cache<'a>(&self, caches: &'a mut Caches) -> &'a mut Cache {
+        let cache = match self.requester {
+            BusRequester::Fetch => caches.instruction.as_mut(),
+            BusRequester::Load(_) | BusRequester::Store(_) => caches.data.as_mut(),
+        };
+        cache.expect("Cached transaction without a cache")
+    }
+}
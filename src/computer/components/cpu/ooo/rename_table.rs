@@ -0,0 +1,81 @@
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::components::cpu::registers::CPU_REGISTER_COUNT;
+
+/// Maps architectural registers to physical registers holding their newest speculative value.
+/// Unmapped registers are read from the committed register file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenameTable {
+    mapping: [Option<usize>; CPU_REGISTER_COUNT],
+}
+
+impl Default for RenameTable {
+    fn default() -> Self {
+        Self {
+            mapping: [None; CPU_REGISTER_COUNT],
+        }
+    }
+}
+
+impl RenameTable {
+    pub fn get(&self, reg: CPUReg) -> Option<usize> {
+        self.mapping[reg as usize]
+    }
+
+    /// Returns the previous mapping
+    pub fn set(&mut self, reg: CPUReg, physical: Option<usize>) -> Option<usize> {
+        std::mem::replace(&mut self.mapping[reg as usize], physical)
+    }
+
+    /// All currently mapped (architectural register, physical register) pairs
+    pub fn mapped(&self) -> Vec<(CPUReg, usize)> {
+        self.mapping
+            .iter()
+            .enumerate()
+            .filter_map(|(index, physical)| physical.map(|p| (CPUReg::from(index), p)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PhysicalRegister {
+    pub value: u64,
+    pub ready: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PhysicalRegisterFile {
+    registers: Vec<PhysicalRegister>,
+    free_list: Vec<usize>,
+}
+
+impl PhysicalRegisterFile {
+    pub fn new(size: usize) -> Self {
+        Self {
+            registers: vec![PhysicalRegister::default(); size],
+            free_list: (0..size).rev().collect(),
+        }
+    }
+
+    pub fn get(&self, physical: usize) -> PhysicalRegister {
+        self.registers[physical]
+    }
+
+    pub fn free_count(&self) -> usize {
+        self.free_list.len()
+    }
+
+    pub fn allocate(&mut self) -> Option<usize> {
+        let physical = self.free_list.pop()?;
+        self.registers[physical] = PhysicalRegister::default();
+        Some(physical)
+    }
+
+    pub fn free(&mut self, physical: usize) {
+        debug_assert!(!self.free_list.contains(&physical));
+        self.free_list.push(physical);
+    }
+
+    pub fn write(&mut self, physical: usize, value: u64) {
+        self.registers[physical] = PhysicalRegister { value, ready: true };
+    }
+}
@@ -0,0 +1,98 @@
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::ooo::OoOOp;
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::instructions::Instruction;
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenamedDestination {
+    pub register: CPUReg,
+    pub physical: usize,
+    /// Mapping replaced by this destination, restored when squashed
+    pub previous: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReorderBufferEntry {
+    pub id: u64,
+    pub instruction_id: u64,
+    /// Address of the instruction this micro operation belongs to
+    pub address: u64,
+    pub instruction_bits: u32,
+    pub instruction: Option<Instruction>,
+    /// Fetch address predicted to follow a control instruction
+    pub predicted_next: Option<u64>,
+    pub mispredicted: bool,
+    pub op: OoOOp,
+    pub destinations: Vec<RenamedDestination>,
+    pub completed: bool,
+    pub exception: Option<Exception>,
+    /// csr, value; CSR write applied when the instruction commits
+    pub csr_write: Option<(u16, u64)>,
+    /// Last micro operation of its instruction
+    pub last: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReorderBuffer {
+    capacity: usize,
+    entries: VecDeque<ReorderBufferEntry>,
+}
+
+impl ReorderBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn entries(&self) -> &VecDeque<ReorderBufferEntry> {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= self.capacity
+    }
+
+    pub fn push(&mut self, entry: ReorderBufferEntry) {
+        self.entries.push_back(entry);
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut ReorderBufferEntry> {
+        self.entries.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// Number of entries belonging to the oldest instruction, if all of them are completed.
+    /// Instructions are committed as a whole.
+    pub fn head_instruction_completed(&self) -> Option<usize> {
+        let mut count = 0;
+        for entry in self.entries.iter() {
+            if !entry.completed {
+                return None;
+            }
+            count += 1;
+            if entry.last {
+                return Some(count);
+            }
+        }
+        None
+    }
+
+    pub fn pop_front(&mut self, count: usize) -> Vec<ReorderBufferEntry> {
+        self.entries.drain(..count).collect()
+    }
+
+    /// Removes all entries starting with the given id, youngest first
+    pub fn squash(&mut self, from_id: u64) -> Vec<ReorderBufferEntry> {
+        let mut squashed = Vec::new();
+        while self.entries.back().is_some_and(|entry| entry.id >= from_id) {
+            squashed.push(self.entries.pop_back().unwrap());
+        }
+        squashed
+    }
+}
@@ -0,0 +1,126 @@
+use crate::computer::components::cpu::ooo::OoOOp;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionalUnit {
+    ALU,
+    /// Address generation unit
+    AGU,
+    /// Load/store unit, drives the bus
+    LSU,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operand {
+    Ready(u64),
+    /// Waiting for the physical register to be written back
+    Waiting(usize),
+}
+
+impl Operand {
+    pub fn value(&self) -> Option<u64> {
+        match self {
+            Operand::Ready(value) => Some(*value),
+            Operand::Waiting(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReservationStationEntry {
+    /// Reorder buffer id of the micro operation
+    pub id: u64,
+    pub op: OoOOp,
+    pub operands: Vec<Operand>,
+}
+
+impl ReservationStationEntry {
+    pub fn is_ready(&self) -> bool {
+        self.operands
+            .iter()
+            .all(|operand| operand.value().is_some())
+    }
+
+    pub fn operand_values(&self) -> Vec<u64> {
+        self.operands.iter().filter_map(Operand::value).collect()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReservationStation {
+    capacity: usize,
+    entries: Vec<ReservationStationEntry>,
+}
+
+impl ReservationStation {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn entries(&self) -> &[ReservationStationEntry] {
+        &self.entries
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.entries.len() >= self.capacity
+    }
+
+    pub fn push(&mut self, entry: ReservationStationEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Ids of all entries whose operands are available, oldest first
+    pub fn ready_ids(&self) -> Vec<u64> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.is_ready())
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    pub fn take(&mut self, id: u64) -> Option<ReservationStationEntry> {
+        let index = self.entries.iter().position(|entry| entry.id == id)?;
+        Some(self.entries.remove(index))
+    }
+
+    pub fn head_is_ready(&self) -> bool {
+        self.entries.first().is_some_and(|entry| entry.is_ready())
+    }
+
+    /// Removes the oldest entry if its operands are all available, keeping program order
+    pub fn take_ready_in_order(&mut self) -> Option<ReservationStationEntry> {
+        if self.head_is_ready() {
+            Some(self.entries.remove(0))
+        } else {
+            None
+        }
+    }
+
+    /// Forwards a written back physical register to all waiting operands
+    pub fn wake_up(&mut self, physical: usize, value: u64) {
+        for entry in self.entries.iter_mut() {
+            for operand in entry.operands.iter_mut() {
+                if *operand == Operand::Waiting(physical) {
+                    *operand = Operand::Ready(value);
+                }
+            }
+        }
+    }
+
+    pub fn squash(&mut self, from_id: u64) {
+        self.entries.retain(|entry| entry.id < from_id);
+    }
+}
+
+impl Display for FunctionalUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FunctionalUnit::ALU => write!(f, "ALU"),
+            FunctionalUnit::AGU => write!(f, "AGU"),
+            FunctionalUnit::LSU => write!(f, "LSU"),
+        }
+    }
+}
@@ -7,9 +7,11 @@ pub mod builder;
 pub mod flags;
 pub mod reg;
 
+pub const CPU_REGISTER_COUNT: usize = 43;
+
 #[derive(Debug, PartialEq)]
 pub struct CPURegisters {
-    registers: [u64; 43],
+    registers: [u64; CPU_REGISTER_COUNT],
 }
 
 impl CPURegisters {
@@ -44,7 +46,9 @@ impl CPUFlagsAccessTrait for CPURegisters {
 
 impl Default for CPURegisters {
     fn default() -> Self {
-        Self { registers: [0; 43] }
+        Self {
+            registers: [0; CPU_REGISTER_COUNT],
+        }
     }
 }
 
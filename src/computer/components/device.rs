@@ -0,0 +1,122 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::BusResponse;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::fdt::DeviceTreeNode;
+use std::any::Any;
+use std::fmt::Debug;
+use std::ops::RangeInclusive;
+
+pub mod registry;
+
+/// Request of a device to change the state of the whole machine
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MachineRequest {
+    /// Stops the computer with the guest's exit code
+    Exit(u64),
+    /// Reinitialises the harts and the devices, ROM contents are kept
+    Reset,
+    /// Non-maskable interrupt of every hart, see `CPU::take_nmi`
+    Nmi { vector: u64, cause: u64 },
+}
+
+/// Memory mapped component attached to the bus.
+/// Implement this trait to add peripherals to a computer without touching the crate.
+pub trait BusDevice: Any + Debug {
+    fn name(&self) -> &str;
+
+    /// Inclusive range of absolute addresses the device responds to
+    fn address_range(&self) -> RangeInclusive<u64>;
+
+    /// Called once per transaction on the bus with an address inside the device's range.
+    /// Devices answer with a bus response, the bus defaults to Ok unless they put a different one.
+    /// Devices answering Retry are called again on the following ticks.
+    fn process_bus(&mut self, bus: &mut Bus);
+
+    /// Wait states before the device answers a transaction, the bus signals busy meanwhile
+    fn latency(&self, _status: BusStatus) -> u64 {
+        0
+    }
+
+    /// Wait states of each burst beat after the first
+    fn beat_latency(&self, status: BusStatus) -> u64 {
+        self.latency(status.single_beat())
+    }
+
+    /// Devices without native burst support are handed each beat as a single double word transfer
+    fn supports_burst(&self) -> bool {
+        false
+    }
+
+    /// Called once per computer tick, after the bus has been processed
+    fn tick(&mut self) {}
+
+    /// Called once per computer tick after the CPU, before the bus is processed.
+    /// Devices mastering the bus take it and drive their transactions here.
+    fn master_tick(&mut self, _bus: &mut Bus) {}
+
+    /// The device has an interrupt line, which gets a source of the platform interrupt controller
+    fn has_interrupt_line(&self) -> bool {
+        false
+    }
+
+    /// Level of the device's interrupt line
+    fn interrupt_pending(&self) -> bool {
+        false
+    }
+
+    /// Called once per computer tick with the levels of the devices' interrupt lines,
+    /// the line of source n at index n - 1, for platform interrupt controllers
+    fn sample_interrupt_lines(&mut self, _lines: &[bool]) {}
+
+    /// Bits of the hart's `mip` CSR the device raises, for core-local interrupt controllers
+    fn hart_interrupts(&self, _hart: usize) -> u64 {
+        0
+    }
+
+    /// Returns the device to its power-on state when the machine resets
+    fn reset(&mut self) {}
+
+    /// Node describing the device in the generated device tree, none for devices guests can not discover
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        None
+    }
+
+    /// Polled once per computer tick, a request is handed out only once
+    fn take_machine_request(&mut self) -> Option<MachineRequest> {
+        None
+    }
+
+    /// Reads without going through the bus, to inspect memory and for instruction fetches of the fast mode.
+    /// Devices with read side effects should return the value the bus would see, without applying them.
+    fn read_dw(&self, address: u64) -> u64;
+
+    /// Reads without a bus owner, used by the fast execution mode.
+    /// Runs a single transaction through `process_bus` on a private bus, so read side effects apply.
+    fn read(&mut self, address: u64) -> (BusResponse, u64) {
+        let bus = single_transaction(self, address, 0, BusStatus::Read);
+        (bus.get_response(), bus.get_data())
+    }
+
+    /// Writes without a bus owner, used by the fast execution mode.
+    /// Runs a single transaction through `process_bus` on a private bus.
+    fn write(&mut self, address: u64, data: u64, status: BusStatus) -> BusResponse {
+        single_transaction(self, address, data, status).get_response()
+    }
+}
+
+fn single_transaction<D: BusDevice + ?Sized>(
+    device: &mut D,
+    address: u64,
+    data: u64,
+    status: BusStatus,
+) -> Bus {
+    let mut bus = Bus::new();
+    bus.take_ownership(BusOwner::CPU(0));
+    bus.put_address(Address::new(address), BusOwner::CPU(0));
+    bus.put_data(data, BusOwner::CPU(0));
+    bus.put_status(status, BusOwner::CPU(0));
+    device.process_bus(&mut bus);
+    bus
+}
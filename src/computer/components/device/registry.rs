@@ -0,0 +1,385 @@
+use crate::computer::components::bus::response::{BusError, BusResponse};
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::{BusDevice, MachineRequest};
+use crate::computer::components::ram::RAM;
+use crate::computer::components::rom::ROM;
+use crate::computer::memory_map::{MemoryMap, MemoryRegion, Permissions, RegionKind};
+use log::debug;
+use std::any::Any;
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceError {
+    /// The new device's address range intersects an attached device or a region it does not fit into
+    Overlap { device: String, existing: String },
+}
+
+/// Transaction on the bus waiting for the addressed device
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PendingAccess {
+    address: u64,
+    status: BusStatus,
+    remaining: u64,
+    /// The device has answered, repeated polls of the same transaction are not forwarded
+    served: bool,
+}
+
+/// Devices attached to the bus, routed according to the memory map.
+/// Each device owns a distinct address range.
+#[derive(Debug)]
+pub struct DeviceRegistry {
+    memory_map: MemoryMap,
+    devices: Vec<Box<dyn BusDevice>>,
+    pending: Option<PendingAccess>,
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new(MemoryMap::default())
+    }
+}
+
+impl DeviceRegistry {
+    /// Creates the backing RAM and ROM devices of the memory map,
+    /// device regions stay unanswered until a device is attached to them
+    pub fn new(memory_map: MemoryMap) -> Self {
+        let mut devices: Vec<Box<dyn BusDevice>> = Vec::new();
+        for region in memory_map.regions() {
+            match region.kind {
+                RegionKind::ROM => devices.push(Box::new(ROM::new(region))),
+                RegionKind::RAM => devices.push(Box::new(RAM::new(region))),
+                RegionKind::Device => {}
+            }
+        }
+        Self {
+            memory_map,
+            devices,
+            pending: None,
+        }
+    }
+
+    pub fn get_memory_map(&self) -> &MemoryMap {
+        &self.memory_map
+    }
+
+    /// Attaches the device inside a reserved device region of the memory map,
+    /// or maps a new read/write device region for it if its range is still unmapped
+    pub fn attach(&mut self, device: impl BusDevice) -> Result<(), DeviceError> {
+        let range = device.address_range();
+        let overlap = |existing: &str| DeviceError::Overlap {
+            device: device.name().to_string(),
+            existing: existing.to_string(),
+        };
+        if let Some(existing) = self.devices.iter().find(|existing| {
+            let existing = existing.address_range();
+            existing.start() <= range.end() && range.start() <= existing.end()
+        }) {
+            return Err(overlap(existing.name()));
+        }
+
+        match self.memory_map.overlapping(&range) {
+            Some(region)
+                if region.kind == RegionKind::Device
+                    && region.contains(*range.start())
+                    && region.contains(*range.end()) => {}
+            Some(region) => return Err(overlap(&region.name)),
+            None => {
+                let region = MemoryRegion::new(
+                    device.name(),
+                    *range.start(),
+                    range.end() - range.start() + 1,
+                    RegionKind::Device,
+                    Permissions::READ_WRITE,
+                );
+                self.memory_map
+                    .insert(region)
+                    .map_err(|_| overlap("memory map"))?;
+            }
+        }
+
+        debug!(
+            target: "devices",
+            "Attached {} at 0x{:016x}..=0x{:016x}",
+            device.name(),
+            range.start(),
+            range.end()
+        );
+        self.devices.push(Box::new(device));
+        Ok(())
+    }
+
+    pub fn devices(&self) -> &[Box<dyn BusDevice>] {
+        &self.devices
+    }
+
+    pub fn device_for_address(&self, address: u64) -> Option<&dyn BusDevice> {
+        self.devices
+            .iter()
+            .find(|device| device.address_range().contains(&address))
+            .map(|device| device.as_ref())
+    }
+
+    fn device_for_address_mut(&mut self, address: u64) -> Option<&mut Box<dyn BusDevice>> {
+        self.devices
+            .iter_mut()
+            .find(|device| device.address_range().contains(&address))
+    }
+
+    /// Looks up an attached device by name and concrete type
+    pub fn get<T: BusDevice>(&self, name: &str) -> Option<&T> {
+        self.devices
+            .iter()
+            .find(|device| device.name() == name)
+            .and_then(|device| (device.as_ref() as &dyn Any).downcast_ref())
+    }
+
+    #[cfg(test)]
+    pub fn get_mut<T: BusDevice>(&mut self, name: &str) -> Option<&mut T> {
+        self.devices
+            .iter_mut()
+            .find(|device| device.name() == name)
+            .and_then(|device| (device.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    /// Looks up the device mapped at the address by its concrete type
+    pub fn get_at_mut<T: BusDevice>(&mut self, address: u64) -> Option<&mut T> {
+        self.device_for_address_mut(address)
+            .and_then(|device| (device.as_mut() as &mut dyn Any).downcast_mut())
+    }
+
+    /// Checks the access against the memory map, unmapped addresses allow nothing.
+    /// Instruction fetches need the execute permission instead of the read one.
+    fn check_access(&self, address: u64, status: BusStatus, fetch: bool) -> Result<(), BusError> {
+        let Some(region) = self.memory_map.region(address) else {
+            return Err(BusError::Unmapped);
+        };
+        let permitted = match status {
+            BusStatus::Idle => true,
+            status if status.is_read() && fetch => region.permissions.execute,
+            status if status.is_read() => region.permissions.read,
+            _ => region.permissions.write,
+        };
+        if !permitted {
+            return Err(BusError::AccessDenied);
+        }
+        if status
+            .alignment()
+            .is_some_and(|size| !address.is_multiple_of(size))
+        {
+            return Err(BusError::Misaligned);
+        }
+        Ok(())
+    }
+
+    /// Hands an active bus to the device mapped at its address once its wait states have passed.
+    /// Unmapped addresses, misaligned bursts and accesses violating the region's permissions
+    /// are answered with a bus error. Each beat of a burst is routed and checked on its own.
+    pub fn process_bus(&mut self, bus: &mut Bus) {
+        if !bus.is_active() {
+            self.pending = None;
+            return;
+        }
+
+        let address = bus.get_beat_address().value();
+        let status = bus.get_status();
+        if let Err(error) = self.check_access(address, status, bus.is_fetch()) {
+            debug!(target: "devices", "[{address:016x}] {status:?} rejected: {error:?}");
+            bus.put_response(BusResponse::Error(error));
+            return;
+        }
+        let Some(index) = self
+            .devices
+            .iter()
+            .position(|device| device.address_range().contains(&address))
+        else {
+            debug!(target: "devices", "[{address:016x}] No device attached");
+            bus.put_response(BusResponse::Error(BusError::Unmapped));
+            return;
+        };
+
+        // A new transaction or beat starts counting down the device's wait states
+        let device = &self.devices[index];
+        let mut pending = self
+            .pending
+            .filter(|pending| pending.address == address && pending.status == status)
+            .unwrap_or(PendingAccess {
+                address,
+                status,
+                remaining: match bus.get_beat() {
+                    0 => device.latency(status),
+                    _ => device.beat_latency(status),
+                },
+                served: false,
+            });
+        if pending.served {
+            return;
+        }
+        let busy = pending.remaining > 0;
+        pending.remaining = pending.remaining.saturating_sub(1);
+        self.pending = Some(pending);
+        if busy {
+            bus.put_busy();
+            return;
+        }
+
+        if !bus.is_ready() {
+            bus.put_response(BusResponse::Ok);
+        }
+        let device = &mut self.devices[index];
+        if status.is_burst() && !device.supports_burst() {
+            Self::process_single_beat(device.as_mut(), bus);
+        } else {
+            device.process_bus(bus);
+        }
+        if bus.is_ready() {
+            self.pending = Some(PendingAccess {
+                served: true,
+                ..pending
+            });
+        }
+    }
+
+    /// Presents the current burst beat to the device as a single double word transfer
+    fn process_single_beat(device: &mut dyn BusDevice, bus: &mut Bus) {
+        let owner = bus.get_owner();
+        let mut beat = Bus::new();
+        beat.take_ownership(owner);
+        beat.put_address(bus.get_beat_address(), owner);
+        beat.put_data(bus.get_data(), owner);
+        beat.put_status(bus.get_status().single_beat(), owner);
+        device.process_bus(&mut beat);
+        if bus.get_status().is_read() {
+            bus.force_put_data(beat.get_data());
+        }
+        bus.put_response(beat.get_response());
+    }
+
+    /// Lets bus mastering devices drive their transactions, before the bus is processed
+    pub fn master_tick(&mut self, bus: &mut Bus) {
+        self.devices
+            .iter_mut()
+            .for_each(|device| device.master_tick(bus));
+    }
+
+    pub fn tick(&mut self) {
+        self.devices.iter_mut().for_each(|device| device.tick());
+    }
+
+    /// Names of the devices currently raising their interrupt line
+    #[cfg(test)]
+    pub fn pending_interrupts(&self) -> Vec<&str> {
+        self.devices
+            .iter()
+            .filter(|device| device.interrupt_pending())
+            .map(|device| device.name())
+            .collect()
+    }
+
+    /// Devices with an interrupt line get the interrupt sources from 1 on in the order they were attached
+    fn interrupt_sources(&self) -> impl Iterator<Item = &dyn BusDevice> {
+        self.devices
+            .iter()
+            .filter(|device| device.has_interrupt_line())
+            .map(|device| device.as_ref())
+    }
+
+    /// Interrupt source of the device with the given name at the platform interrupt controller
+    pub fn interrupt_source(&self, name: &str) -> Option<u32> {
+        self.interrupt_sources()
+            .position(|device| device.name() == name)
+            .map(|index| index as u32 + 1)
+    }
+
+    /// Hands the levels of the interrupt lines to the platform interrupt controllers
+    pub fn route_interrupts(&mut self) {
+        let lines: Vec<bool> = self
+            .interrupt_sources()
+            .map(|device| device.interrupt_pending())
+            .collect();
+        self.devices
+            .iter_mut()
+            .for_each(|device| device.sample_interrupt_lines(&lines));
+    }
+
+    /// `mip` bits raised for the hart by all devices
+    pub fn hart_interrupts(&self, hart: usize) -> u64 {
+        self.devices
+            .iter()
+            .fold(0, |mip, device| mip | device.hart_interrupts(hart))
+    }
+
+    /// Returns all devices to their power-on state
+    pub fn reset(&mut self) {
+        self.pending = None;
+        self.devices.iter_mut().for_each(|device| device.reset());
+    }
+
+    /// Next pending request of a device to change the machine's state
+    pub fn take_machine_request(&mut self) -> Option<MachineRequest> {
+        self.devices
+            .iter_mut()
+            .find_map(|device| device.take_machine_request())
+    }
+
+    /// Reads bypassing the bus without side effects, failing where a bus read would fail.
+    /// Tests inspect memory and device registers with it.
+    #[cfg(test)]
+    pub fn read_dw(&self, address: u64) -> Result<u64, BusError> {
+        self.check_access(address, BusStatus::Read, false)?;
+        self.device_for_address(address)
+            .map(|device| device.read_dw(address))
+            .ok_or(BusError::Unmapped)
+    }
+
+    /// Instruction fetch bypassing the bus, failing where a bus fetch would fail
+    pub fn fetch_dw(&self, address: u64) -> Result<u64, BusError> {
+        self.check_access(address, BusStatus::Read, true)?;
+        self.device_for_address(address)
+            .map(|device| device.read_dw(address))
+            .ok_or(BusError::Unmapped)
+    }
+
+    /// Reads bypassing the bus, failing where a bus read would fail.
+    /// Unlike `read_dw` the device serves a read transaction, applying its side effects.
+    /// Busy devices are ticked and asked again until they answer, like on the bus.
+    pub fn read(&mut self, address: u64) -> Result<u64, BusError> {
+        self.check_access(address, BusStatus::Read, false)?;
+        let device = self
+            .device_for_address_mut(address)
+            .ok_or(BusError::Unmapped)?;
+        loop {
+            match device.read(address) {
+                (BusResponse::Ok, data) => return Ok(data),
+                (BusResponse::Error(error), _) => return Err(error),
+                (BusResponse::Retry, _) => device.tick(),
+            }
+        }
+    }
+
+    /// Writes bypassing the bus, failing where a bus write would fail.
+    /// Busy devices are ticked and asked again until they answer, like on the bus.
+    pub fn write(&mut self, address: u64, data: u64, status: BusStatus) -> Result<(), BusError> {
+        self.check_access(address, status, false)?;
+        let device = self
+            .device_for_address_mut(address)
+            .ok_or(BusError::Unmapped)?;
+        loop {
+            match device.write(address, data, status) {
+                BusResponse::Ok => return Ok(()),
+                BusResponse::Error(error) => return Err(error),
+                BusResponse::Retry => device.tick(),
+            }
+        }
+    }
+}
+
+impl Display for DeviceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeviceError::Overlap { device, existing } => {
+                write!(f, "Device {device} overlaps with {existing}")
+            }
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::BusResponse;
+use crate::computer::components::bus::status::{BusStatus, BURST_BEAT_SIZE};
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use log::debug;
+use std::ops::RangeInclusive;
+
+// Register offsets, each register is a double word
+pub const DMA_SOURCE: u64 = 0x00;
+pub const DMA_DESTINATION: u64 = 0x08;
+/// Number of bytes to copy
+pub const DMA_LENGTH: u64 = 0x10;
+pub const DMA_CONTROL: u64 = 0x18;
+/// Writing a set DONE or ERROR bit clears it, acknowledging the interrupt
+pub const DMA_STATUS: u64 = 0x20;
+pub const DMA_SIZE: u64 = 0x100;
+
+// Control bits
+pub const DMA_CONTROL_START: u64 = 1 << 0;
+pub const DMA_CONTROL_INTERRUPT_ENABLE: u64 = 1 << 1;
+/// Keeps writing to the same address, for copies into a device's data register
+pub const DMA_CONTROL_FIXED_DESTINATION: u64 = 1 << 2;
+
+// Status bits
+pub const DMA_STATUS_BUSY: u64 = 1 << 0;
+pub const DMA_STATUS_DONE: u64 = 1 << 1;
+pub const DMA_STATUS_ERROR: u64 = 1 << 2;
+
+/// Longest burst the controller issues
+pub const DMA_MAX_BURST: u8 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DmaStage {
+    Idle,
+    /// Waiting for the arbiter to grant the bus
+    Take,
+    Read,
+    Write,
+    ReadBurst(u8),
+    WriteBurst(u8),
+}
+
+/// Memory mapped DMA controller, copies bytes as a second bus master.
+/// Double word aligned copies move up to `DMA_MAX_BURST` beats per burst, other copies a single byte.
+/// Each read and write pair is its own bus tenure, so the arbiter can hand the bus to the CPU in between.
+#[derive(Debug)]
+pub struct DmaController {
+    name: String,
+    base: u64,
+    source: u64,
+    destination: u64,
+    length: u64,
+    control: u64,
+    status: u64,
+    stage: DmaStage,
+    copied: u64,
+    /// Beats read by the current burst
+    buffer: Vec<u64>,
+}
+
+impl DmaController {
+    pub fn new(name: &str, base: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            source: 0,
+            destination: 0,
+            length: 0,
+            control: 0,
+            status: 0,
+            stage: DmaStage::Idle,
+            copied: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.status & DMA_STATUS_BUSY != 0
+    }
+
+    #[cfg(test)]
+    pub fn get_status(&self) -> u64 {
+        self.status
+    }
+
+    /// Bytes copied by the current or last transfer
+    #[cfg(test)]
+    pub fn get_copied(&self) -> u64 {
+        self.copied
+    }
+
+    fn read_register(&self, offset: u64) -> u64 {
+        match offset {
+            DMA_SOURCE => self.source,
+            DMA_DESTINATION => self.destination,
+            DMA_LENGTH => self.length,
+            DMA_CONTROL => self.control,
+            DMA_STATUS => self.status,
+            _ => 0,
+        }
+    }
+
+    /// Transfer registers are locked while the controller is busy
+    fn write_register(&mut self, offset: u64, value: u64) {
+        match offset {
+            DMA_STATUS => self.status &= !(value & (DMA_STATUS_DONE | DMA_STATUS_ERROR)),
+            _ if self.is_busy() => {
+                debug!(target: "dma", "Register {offset:#x} written while busy, ignored")
+            }
+            DMA_SOURCE => self.source = value,
+            DMA_DESTINATION => self.destination = value,
+            DMA_LENGTH => self.length = value,
+            DMA_CONTROL => {
+                self.control = value & !DMA_CONTROL_START;
+                if value & DMA_CONTROL_START != 0 {
+                    self.start();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn start(&mut self) {
+        debug!(target: "dma", "Copying {} bytes from {:#x} to {:#x}", self.length, self.source, self.destination);
+        self.status = DMA_STATUS_BUSY;
+        self.copied = 0;
+        self.stage = DmaStage::Take;
+    }
+
+    fn finish(&mut self, status: u64) {
+        debug!(target: "dma", "Transfer finished after {} bytes, status {status:#x}", self.copied);
+        self.status = status;
+        self.stage = DmaStage::Idle;
+    }
+
+    fn destination_address(&self) -> u64 {
+        if self.control & DMA_CONTROL_FIXED_DESTINATION != 0 {
+            self.destination
+        } else {
+            self.destination.wrapping_add(self.copied)
+        }
+    }
+
+    /// Number of beats the next burst can move, 0 if the next chunk has to be copied bytewise
+    fn burst_beats(&self) -> u8 {
+        let source = self.source.wrapping_add(self.copied);
+        let aligned = source.is_multiple_of(BURST_BEAT_SIZE)
+            && self.destination_address().is_multiple_of(BURST_BEAT_SIZE);
+        if self.control & DMA_CONTROL_FIXED_DESTINATION != 0 || !aligned {
+            return 0;
+        }
+        let beats = (self.length - self.copied) / BURST_BEAT_SIZE;
+        beats.min(DMA_MAX_BURST as u64) as u8
+    }
+
+    /// Starts reading the next chunk once the bus is granted
+    fn take_bus(&mut self, bus: &mut Bus) {
+        if self.copied == self.length {
+            self.finish(DMA_STATUS_DONE);
+            return;
+        }
+        if !bus.take_ownership(BusOwner::DMA) {
+            self.stage = DmaStage::Take;
+            return;
+        }
+        let address = self.source.wrapping_add(self.copied);
+        bus.put_address(Address::new(address), BusOwner::DMA);
+        match self.burst_beats() {
+            0 => {
+                bus.put_status(BusStatus::Read, BusOwner::DMA);
+                self.stage = DmaStage::Read;
+            }
+            beats => {
+                bus.put_status(BusStatus::ReadBurst(beats), BusOwner::DMA);
+                self.buffer.clear();
+                self.stage = DmaStage::ReadBurst(beats);
+            }
+        }
+    }
+
+    /// Ends the bus tenure, a failed access aborts the transfer
+    fn release_bus(&mut self, bus: &mut Bus, response: BusResponse) -> bool {
+        bus.release_ownership(BusOwner::DMA);
+        if let BusResponse::Error(error) = response {
+            debug!(target: "dma", "Bus error {error:?}, aborting");
+            self.finish(DMA_STATUS_ERROR);
+            return false;
+        }
+        true
+    }
+}
+
+impl BusDevice for DmaController {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (DMA_SIZE - 1)
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = (bus.get_address().value() - self.base) & !0b111;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_register(offset)),
+            BusStatus::Idle => {}
+            _ => self.write_register(offset, bus.get_data()),
+        }
+    }
+
+    fn master_tick(&mut self, bus: &mut Bus) {
+        match self.stage {
+            DmaStage::Idle => {}
+            DmaStage::Take => self.take_bus(bus),
+            DmaStage::Read if bus.is_ready() => {
+                let response = bus.get_response();
+                if response != BusResponse::Ok {
+                    self.release_bus(bus, response);
+                    return;
+                }
+                bus.put_address(Address::new(self.destination_address()), BusOwner::DMA);
+                bus.put_data(bus.get_data() & 0xFF, BusOwner::DMA);
+                bus.put_status(BusStatus::WriteByte, BusOwner::DMA);
+                self.stage = DmaStage::Write;
+            }
+            DmaStage::Write if bus.is_ready() => {
+                let response = bus.get_response();
+                if self.release_bus(bus, response) {
+                    self.copied += 1;
+                    self.take_bus(bus);
+                }
+            }
+            DmaStage::ReadBurst(beats) if bus.is_ready() => {
+                let response = bus.get_response();
+                if response != BusResponse::Ok {
+                    self.release_bus(bus, response);
+                    return;
+                }
+                self.buffer.push(bus.get_data());
+                if !bus.next_beat(BusOwner::DMA) {
+                    bus.put_address(Address::new(self.destination_address()), BusOwner::DMA);
+                    bus.put_data(self.buffer[0], BusOwner::DMA);
+                    bus.put_status(BusStatus::WriteBurst(beats), BusOwner::DMA);
+                    self.stage = DmaStage::WriteBurst(beats);
+                }
+            }
+            DmaStage::WriteBurst(beats) if bus.is_ready() => {
+                let response = bus.get_response();
+                let next = bus.get_beat() as usize + 1;
+                if response == BusResponse::Ok && next < beats as usize {
+                    bus.put_data(self.buffer[next], BusOwner::DMA);
+                    bus.next_beat(BusOwner::DMA);
+                } else if self.release_bus(bus, response) {
+                    self.copied += beats as u64 * BURST_BEAT_SIZE;
+                    self.take_bus(bus);
+                }
+            }
+            DmaStage::Read | DmaStage::Write | DmaStage::ReadBurst(_) | DmaStage::WriteBurst(_) => {
+            }
+        }
+    }
+
+    /// Aborts a transfer in flight, the computer replaces the bus it was mastering
+    fn reset(&mut self) {
+        self.source = 0;
+        self.destination = 0;
+        self.length = 0;
+        self.control = 0;
+        self.status = 0;
+        self.stage = DmaStage::Idle;
+        self.copied = 0;
+        self.buffer.clear();
+    }
+
+    fn has_interrupt_line(&self) -> bool {
+        true
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.control & DMA_CONTROL_INTERRUPT_ENABLE != 0
+            && self.status & (DMA_STATUS_DONE | DMA_STATUS_ERROR) != 0
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        self.read_register((address - self.base) & !0b111)
+    }
+}
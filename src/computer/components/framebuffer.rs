@@ -0,0 +1,256 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::components::framebuffer::snapshot::Snapshot;
+use crate::computer::fdt::{DeviceTreeNode, PropertyValue};
+use log::debug;
+use std::ops::RangeInclusive;
+
+pub mod snapshot;
+
+// Control register offsets, each a word
+pub const FRAMEBUFFER_WIDTH: u64 = 0x00;
+pub const FRAMEBUFFER_HEIGHT: u64 = 0x04;
+/// Code of the pixel format
+pub const FRAMEBUFFER_FORMAT: u64 = 0x08;
+/// Bytes per row
+pub const FRAMEBUFFER_STRIDE: u64 = 0x0C;
+pub const FRAMEBUFFER_PAGES: u64 = 0x10;
+/// Page being displayed, writing it flips to another page
+pub const FRAMEBUFFER_FRONT: u64 = 0x14;
+/// Number of flips so far
+pub const FRAMEBUFFER_FLIPS: u64 = 0x18;
+/// Offset of the pixels of page 0, the pages follow each other
+pub const FRAMEBUFFER_PIXELS: u64 = 0x1000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PixelFormat {
+    /// One byte of luminance
+    Gray8,
+    /// Half word with red in the upper 5 bits, green in the middle 6 and blue in the lower 5
+    RGB565,
+    /// Word with blue in the lowest byte, then green and red, the upper byte is ignored
+    #[default]
+    XRGB8888,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(&self) -> u64 {
+        match self {
+            PixelFormat::Gray8 => 1,
+            PixelFormat::RGB565 => 2,
+            PixelFormat::XRGB8888 => 4,
+        }
+    }
+
+    /// Name of the format in the simple-framebuffer binding, which has none for grayscale
+    pub fn simple_framebuffer_format(&self) -> Option<&'static str> {
+        match self {
+            PixelFormat::Gray8 => None,
+            PixelFormat::RGB565 => Some("r5g6b5"),
+            PixelFormat::XRGB8888 => Some("x8r8g8b8"),
+        }
+    }
+
+    /// Value of the format register
+    pub fn code(&self) -> u32 {
+        match self {
+            PixelFormat::Gray8 => 0,
+            PixelFormat::RGB565 => 1,
+            PixelFormat::XRGB8888 => 2,
+        }
+    }
+
+    /// 8 bit RGB of the little endian pixel
+    pub fn to_rgb(self, pixel: &[u8]) -> [u8; 3] {
+        match self {
+            PixelFormat::Gray8 => [pixel[0]; 3],
+            PixelFormat::RGB565 => {
+                let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+                // Replicates the upper bits into the lower ones, so full intensity stays 255
+                let widen = |value: u16, bits: u32| {
+                    let value = (value & ((1 << bits) - 1)) as u8;
+                    value << (8 - bits) | value >> (2 * bits - 8)
+                };
+                [widen(value >> 11, 5), widen(value >> 5, 6), widen(value, 5)]
+            }
+            PixelFormat::XRGB8888 => [pixel[2], pixel[1], pixel[0]],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FramebufferConfig {
+    pub width: u32,
+    pub height: u32,
+    pub format: PixelFormat,
+    /// Pages the guest can draw to, 2 for double buffering
+    pub pages: u32,
+}
+
+impl Default for FramebufferConfig {
+    /// 320x240, 32 bits per pixel, double buffered
+    fn default() -> Self {
+        Self {
+            width: 320,
+            height: 240,
+            format: PixelFormat::default(),
+            pages: 2,
+        }
+    }
+}
+
+impl FramebufferConfig {
+    fn stride(&self) -> u64 {
+        self.width as u64 * self.format.bytes_per_pixel()
+    }
+
+    fn page_size(&self) -> u64 {
+        self.stride() * self.height as u64
+    }
+}
+
+/// Linear framebuffer without a display, the host takes snapshots of the page being displayed.
+/// The guest draws into any page and flips by writing the page's number to the front register.
+#[derive(Debug)]
+pub struct Framebuffer {
+    name: String,
+    base: u64,
+    config: FramebufferConfig,
+    pixels: Vec<u8>,
+    front: u32,
+    flips: u32,
+}
+
+impl Framebuffer {
+    pub fn new(name: &str, base: u64, config: FramebufferConfig) -> Self {
+        let size = config.page_size() * config.pages as u64;
+        Self {
+            name: name.to_string(),
+            base,
+            config,
+            pixels: vec![0; size as usize],
+            front: 0,
+            flips: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_config(&self) -> &FramebufferConfig {
+        &self.config
+    }
+
+    #[cfg(test)]
+    pub fn get_front(&self) -> u32 {
+        self.front
+    }
+
+    /// Raw pixels of the page
+    pub fn get_page(&self, page: u32) -> &[u8] {
+        let size = self.config.page_size() as usize;
+        &self.pixels[page as usize * size..(page as usize + 1) * size]
+    }
+
+    /// The page being displayed
+    pub fn snapshot(&self) -> Snapshot {
+        let pixel_size = self.config.format.bytes_per_pixel() as usize;
+        let rgb = self
+            .get_page(self.front)
+            .chunks(pixel_size)
+            .flat_map(|pixel| self.config.format.to_rgb(pixel))
+            .collect();
+        Snapshot::new(self.config.width, self.config.height, rgb)
+    }
+
+    fn read_register(&self, offset: u64) -> u32 {
+        match offset {
+            FRAMEBUFFER_WIDTH => self.config.width,
+            FRAMEBUFFER_HEIGHT => self.config.height,
+            FRAMEBUFFER_FORMAT => self.config.format.code(),
+            FRAMEBUFFER_STRIDE => self.config.stride() as u32,
+            FRAMEBUFFER_PAGES => self.config.pages,
+            FRAMEBUFFER_FRONT => self.front,
+            FRAMEBUFFER_FLIPS => self.flips,
+            _ => 0,
+        }
+    }
+
+    fn read_byte(&self, offset: u64) -> u8 {
+        match offset.checked_sub(FRAMEBUFFER_PIXELS) {
+            Some(pixel) => self.pixels.get(pixel as usize).copied().unwrap_or(0),
+            None => (self.read_register(offset & !0b11) >> (8 * (offset % 4))) as u8,
+        }
+    }
+
+    fn flip(&mut self, page: u32) {
+        if page >= self.config.pages {
+            debug!(target: "framebuffer", "{}: flip to missing page {page} ignored", self.name);
+            return;
+        }
+        debug!(target: "framebuffer", "{}: flip to page {page}", self.name);
+        self.front = page;
+        self.flips = self.flips.wrapping_add(1);
+    }
+}
+
+impl BusDevice for Framebuffer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        let size = FRAMEBUFFER_PIXELS + self.pixels.len() as u64;
+        self.base..=self.base + (size - 1)
+    }
+
+    /// Of the control registers only the front register is writable, a write of any size to it flips
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_dw(bus.get_address().value())),
+            status => {
+                let data = bus.get_data();
+                let size = status.write_size().unwrap_or(0);
+                match offset.checked_sub(FRAMEBUFFER_PIXELS) {
+                    Some(pixel) => {
+                        let bytes = &data.to_le_bytes()[..size as usize];
+                        let end = (pixel as usize + bytes.len()).min(self.pixels.len());
+                        let length = end - pixel as usize;
+                        self.pixels[pixel as usize..end].copy_from_slice(&bytes[..length]);
+                    }
+                    None if offset == FRAMEBUFFER_FRONT => self.flip(data as u32),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pixels.fill(0);
+        self.front = 0;
+        self.flips = 0;
+    }
+
+    /// A simple framebuffer showing page 0, drivers of the binding do not flip
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let format = self.config.format.simple_framebuffer_format()?;
+        let pixels = self.base + FRAMEBUFFER_PIXELS;
+        let page = pixels..=pixels + (self.config.page_size() - 1);
+        let node = DeviceTreeNode::new("framebuffer", &["simple-framebuffer"], page)
+            .with_property("width", PropertyValue::Cells(vec![self.config.width]))
+            .with_property("height", PropertyValue::Cells(vec![self.config.height]))
+            .with_property(
+                "stride",
+                PropertyValue::Cells(vec![self.config.stride() as u32]),
+            )
+            .with_property("format", PropertyValue::string(format));
+        Some(node)
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = address - self.base;
+        (0..8).fold(0, |value, byte| {
+            value | (self.read_byte(offset + byte) as u64) << (8 * byte)
+        })
+    }
+}
@@ -0,0 +1,147 @@
+use std::io::Result;
+#[cfg(test)]
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+/// Largest block of uncompressed deflate data
+const DEFLATE_STORED_BLOCK: usize = 0xFFFF;
+
+/// Frame captured from a framebuffer, 8 bit RGB triples row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub rgb: Vec<u8>,
+}
+
+impl Snapshot {
+    pub fn new(width: u32, height: u32, rgb: Vec<u8>) -> Self {
+        assert_eq!(rgb.len(), 3 * width as usize * height as usize);
+        Self { width, height, rgb }
+    }
+
+    #[cfg(test)]
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        let index = 3 * (y as usize * self.width as usize + x as usize);
+        [self.rgb[index], self.rgb[index + 1], self.rgb[index + 2]]
+    }
+
+    /// Binary PPM (P6)
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(&self.rgb);
+        ppm
+    }
+
+    /// Reads a binary PPM with 8 bit samples, e.g. a golden image of a test
+    #[cfg(test)]
+    pub fn from_ppm(ppm: &[u8]) -> Result<Self> {
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                "Not a binary PPM with 8 bit samples",
+            )
+        };
+        // Magic, width, height and maximum value, separated by whitespace and comments
+        let mut fields = Vec::new();
+        let mut position = 0;
+        while fields.len() < 4 {
+            match ppm.get(position).ok_or_else(invalid)? {
+                b'#' => {
+                    while ppm.get(position).is_some_and(|byte| *byte != b'\n') {
+                        position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => position += 1,
+                _ => {
+                    let start = position;
+                    while ppm
+                        .get(position)
+                        .is_some_and(|byte| !byte.is_ascii_whitespace())
+                    {
+                        position += 1;
+                    }
+                    fields.push(std::str::from_utf8(&ppm[start..position]).map_err(|_| invalid())?);
+                }
+            }
+        }
+        let number = |field: &str| field.parse::<u32>().map_err(|_| invalid());
+        let (width, height) = (number(fields[1])?, number(fields[2])?);
+        if fields[0] != "P6" || number(fields[3])? != 255 {
+            return Err(invalid());
+        }
+        // A single whitespace byte ends the header
+        let rgb = ppm
+            .get(position + 1..position + 1 + 3 * width as usize * height as usize)
+            .ok_or_else(invalid)?;
+        Ok(Self::new(width, height, rgb.to_vec()))
+    }
+
+    /// Truecolor PNG without compression, the data is stored in deflate blocks as is
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(self.width.to_be_bytes());
+        header.extend(self.height.to_be_bytes());
+        // Bit depth 8, truecolor, deflate, adaptive filtering, no interlace
+        header.extend([8, 2, 0, 0, 0]);
+
+        // Each row starts with filter type 0, none
+        let row = 3 * self.width as usize;
+        let mut raw = Vec::with_capacity((row + 1) * self.height as usize);
+        for line in self.rgb.chunks(row.max(1)).take(self.height as usize) {
+            raw.push(0);
+            raw.extend(line);
+        }
+
+        // zlib stream: deflate with a 32 KiB window, no preset dictionary, fastest level
+        let mut zlib = vec![0x78, 0x01];
+        let blocks = raw.chunks(DEFLATE_STORED_BLOCK).collect::<Vec<_>>();
+        for (index, block) in blocks.iter().enumerate() {
+            zlib.push((index + 1 == blocks.len()) as u8);
+            zlib.extend((block.len() as u16).to_le_bytes());
+            zlib.extend((!(block.len() as u16)).to_le_bytes());
+            zlib.extend(*block);
+        }
+        if blocks.is_empty() {
+            zlib.extend([1, 0, 0, 0xFF, 0xFF]);
+        }
+        zlib.extend(adler32(&raw).to_be_bytes());
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        for (kind, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", Vec::new())] {
+            png.extend((data.len() as u32).to_be_bytes());
+            let start = png.len();
+            png.extend(kind);
+            png.extend(data);
+            let crc = crc32(&png[start..]);
+            png.extend(crc.to_be_bytes());
+        }
+        png
+    }
+
+    /// Writes a PNG for paths ending in `.png`, a PPM otherwise
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let png = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        let data = if png { self.to_png() } else { self.to_ppm() };
+        std::fs::write(path, data)
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| {
+            (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
@@ -0,0 +1,201 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::fdt::{DeviceTreeNode, PropertyValue};
+use log::debug;
+use std::ops::RangeInclusive;
+
+// Register offsets, each a word with a bit per pin, a subset of the SiFive GPIO
+/// Level of the pins whose input is enabled
+pub const GPIO_INPUT_VAL: u64 = 0x00;
+pub const GPIO_INPUT_EN: u64 = 0x04;
+pub const GPIO_OUTPUT_EN: u64 = 0x08;
+pub const GPIO_OUTPUT_VAL: u64 = 0x0C;
+pub const GPIO_RISE_IE: u64 = 0x18;
+/// Pending bits are cleared by writing 1 to them
+pub const GPIO_RISE_IP: u64 = 0x1C;
+pub const GPIO_FALL_IE: u64 = 0x20;
+pub const GPIO_FALL_IP: u64 = 0x24;
+pub const GPIO_HIGH_IE: u64 = 0x28;
+pub const GPIO_HIGH_IP: u64 = 0x2C;
+pub const GPIO_LOW_IE: u64 = 0x30;
+pub const GPIO_LOW_IP: u64 = 0x34;
+pub const GPIO_SIZE: u64 = 0x1000;
+pub const GPIO_PINS: u32 = 32;
+
+/// Bank of 32 pins, each driven by the guest when its output is enabled and by the host otherwise.
+/// Edges and levels of the input values set pending bits, the interrupt line is raised while an enabled one is set.
+#[derive(Debug)]
+pub struct Gpio {
+    name: String,
+    base: u64,
+    /// Levels the host drives the pins to
+    external: u32,
+    input_en: u32,
+    output_en: u32,
+    output_val: u32,
+    /// Input values when the pending bits were last updated
+    input_val: u32,
+    rise_ie: u32,
+    rise_ip: u32,
+    fall_ie: u32,
+    fall_ip: u32,
+    high_ie: u32,
+    high_ip: u32,
+    low_ie: u32,
+    low_ip: u32,
+}
+
+impl Gpio {
+    pub fn new(name: &str, base: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            external: 0,
+            input_en: 0,
+            output_en: 0,
+            output_val: 0,
+            input_val: 0,
+            rise_ie: 0,
+            rise_ip: 0,
+            fall_ie: 0,
+            fall_ip: 0,
+            high_ie: 0,
+            high_ip: 0,
+            low_ie: 0,
+            low_ip: 0,
+        }
+    }
+
+    /// Drives an input pin from the host, no effect while the guest drives the pin
+    #[cfg(test)]
+    pub fn set_input(&mut self, pin: u32, high: bool) {
+        assert!(pin < GPIO_PINS, "GPIO has no pin {pin}");
+        self.external = self.external & !(1 << pin) | (high as u32) << pin;
+        self.update();
+    }
+
+    /// Level the guest drives the pin to, none if its output is disabled
+    #[cfg(test)]
+    pub fn get_output(&self, pin: u32) -> Option<bool> {
+        assert!(pin < GPIO_PINS, "GPIO has no pin {pin}");
+        (self.output_en & (1 << pin) != 0).then_some(self.output_val & (1 << pin) != 0)
+    }
+
+    /// Level of every pin, whoever drives it
+    pub fn get_levels(&self) -> u32 {
+        self.output_en & self.output_val | !self.output_en & self.external
+    }
+
+    fn current_input(&self) -> u32 {
+        self.get_levels() & self.input_en
+    }
+
+    /// Records edges since the last update and the current levels
+    fn update(&mut self) {
+        let input = self.current_input();
+        let rising = input & !self.input_val;
+        let falling = !input & self.input_val;
+        if rising | falling != 0 {
+            debug!(target: "gpio", "{}: inputs {:#010x} -> {input:#010x}", self.name, self.input_val);
+        }
+        self.rise_ip |= rising;
+        self.fall_ip |= falling;
+        self.high_ip |= input;
+        self.low_ip |= !input & self.input_en;
+        self.input_val = input;
+    }
+
+    fn read_register(&self, offset: u64) -> u32 {
+        match offset {
+            GPIO_INPUT_VAL => self.current_input(),
+            GPIO_INPUT_EN => self.input_en,
+            GPIO_OUTPUT_EN => self.output_en,
+            GPIO_OUTPUT_VAL => self.output_val,
+            GPIO_RISE_IE => self.rise_ie,
+            GPIO_RISE_IP => self.rise_ip,
+            GPIO_FALL_IE => self.fall_ie,
+            GPIO_FALL_IP => self.fall_ip,
+            GPIO_HIGH_IE => self.high_ie,
+            GPIO_HIGH_IP => self.high_ip,
+            GPIO_LOW_IE => self.low_ie,
+            GPIO_LOW_IP => self.low_ip,
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u32) {
+        match offset {
+            GPIO_INPUT_EN => self.input_en = value,
+            GPIO_OUTPUT_EN => self.output_en = value,
+            GPIO_OUTPUT_VAL => self.output_val = value,
+            GPIO_RISE_IE => self.rise_ie = value,
+            GPIO_RISE_IP => self.rise_ip &= !value,
+            GPIO_FALL_IE => self.fall_ie = value,
+            GPIO_FALL_IP => self.fall_ip &= !value,
+            GPIO_HIGH_IE => self.high_ie = value,
+            GPIO_HIGH_IP => self.high_ip &= !value,
+            GPIO_LOW_IE => self.low_ie = value,
+            GPIO_LOW_IP => self.low_ip &= !value,
+            _ => return,
+        }
+        self.update();
+    }
+}
+
+impl BusDevice for Gpio {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (GPIO_SIZE - 1)
+    }
+
+    /// Double word writes set two consecutive registers
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_dw(bus.get_address().value())),
+            status => {
+                let data = bus.get_data();
+                self.write_register(offset, data as u32);
+                if status.write_size() == Some(8) {
+                    self.write_register(offset + 4, (data >> 32) as u32);
+                }
+            }
+        }
+    }
+
+    fn has_interrupt_line(&self) -> bool {
+        true
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.rise_ip & self.rise_ie
+            | self.fall_ip & self.fall_ie
+            | self.high_ip & self.high_ie
+            | self.low_ip & self.low_ie
+            != 0
+    }
+
+    fn reset(&mut self) {
+        // The host keeps driving its pins
+        let external = self.external;
+        *self = Self::new(&std::mem::take(&mut self.name), self.base);
+        self.external = external;
+    }
+
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let node = DeviceTreeNode::new("gpio", &["sifive,gpio0"], self.address_range())
+            .with_property("gpio-controller", PropertyValue::Empty)
+            .with_property("#gpio-cells", PropertyValue::Cells(vec![2]))
+            .with_property("ngpios", PropertyValue::Cells(vec![GPIO_PINS]));
+        Some(node)
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = (address - self.base) & !0b11;
+        self.read_register(offset) as u64 | (self.read_register(offset + 4) as u64) << 32
+    }
+}
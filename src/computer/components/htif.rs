@@ -0,0 +1,171 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::{BusDevice, MachineRequest};
+use crate::computer::components::uart::backend::UartBackend;
+use crate::computer::fdt::DeviceTreeNode;
+use log::debug;
+#[cfg(test)]
+use std::any::Any;
+use std::ops::RangeInclusive;
+
+// Register offsets, each a double word
+/// Commands from the guest, cleared by the device once they are handled
+pub const HTIF_TOHOST: u64 = 0x00;
+/// Responses to the guest, which clears it after reading
+pub const HTIF_FROMHOST: u64 = 0x08;
+pub const HTIF_SIZE: u64 = 0x10;
+
+// Devices addressed by the upper byte of a command
+pub const HTIF_DEVICE_SYSCALL: u64 = 0;
+pub const HTIF_DEVICE_CONSOLE: u64 = 1;
+
+// Console commands
+pub const HTIF_CONSOLE_GETCHAR: u64 = 0;
+pub const HTIF_CONSOLE_PUTCHAR: u64 = 1;
+
+/// Encodes an HTIF command: device in bits 63..56, command in bits 55..48 and a 48 bit payload
+pub fn htif_command(device: u64, command: u64, payload: u64) -> u64 {
+    device << 56 | (command & 0xFF) << 48 | payload & 0xFFFF_FFFF_FFFF
+}
+
+/// Syscall device command stopping the machine, riscv-tests report a pass as 0 and a failure as the test number
+#[cfg(test)]
+pub fn htif_exit(code: u64) -> u64 {
+    htif_command(HTIF_DEVICE_SYSCALL, 0, code << 1 | 1)
+}
+
+/// Host-target interface mailbox of Spike, used by riscv-tests and bare-metal programs.
+/// Supports the exit syscall and the console's putchar and getchar, proxied syscalls are ignored.
+/// riscv-tests link `tohost` into RAM, place the device in a device region carved out of the memory map there.
+#[derive(Debug)]
+pub struct Htif {
+    name: String,
+    base: u64,
+    console: Box<dyn UartBackend>,
+    tohost: u64,
+    fromhost: u64,
+    exit_code: Option<u64>,
+    /// The exit was handed to the computer
+    exit_requested: bool,
+}
+
+impl Htif {
+    pub fn new(name: &str, base: u64, console: impl UartBackend) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            console: Box::new(console),
+            tohost: 0,
+            fromhost: 0,
+            exit_code: None,
+            exit_requested: false,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn get_exit_code(&self) -> Option<u64> {
+        self.exit_code
+    }
+
+    /// The console backend by its concrete type
+    #[cfg(test)]
+    pub fn get_console<T: UartBackend>(&self) -> Option<&T> {
+        (self.console.as_ref() as &dyn Any).downcast_ref()
+    }
+
+    fn handle_command(&mut self) {
+        let device = self.tohost >> 56;
+        let command = (self.tohost >> 48) & 0xFF;
+        let payload = self.tohost & 0xFFFF_FFFF_FFFF;
+        match (device, command) {
+            (HTIF_DEVICE_SYSCALL, 0) if payload & 1 != 0 => {
+                debug!(target: "htif", "{}: exit with code {}", self.name, payload >> 1);
+                self.exit_code.get_or_insert(payload >> 1);
+            }
+            (HTIF_DEVICE_CONSOLE, HTIF_CONSOLE_PUTCHAR) => {
+                self.console.transmit(payload as u8);
+                self.fromhost = htif_command(device, command, 0);
+            }
+            // Answered once the host sends a character
+            (HTIF_DEVICE_CONSOLE, HTIF_CONSOLE_GETCHAR) => return,
+            _ => debug!(target: "htif", "{}: unsupported command {:#x}", self.name, self.tohost),
+        }
+        self.tohost = 0;
+    }
+}
+
+impl BusDevice for Htif {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (HTIF_SIZE - 1)
+    }
+
+    /// Writes are merged bytewise, a command is handled as soon as `tohost` is non-zero
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_dw(bus.get_address().value())),
+            status => {
+                let data = bus.get_data();
+                let size = status.write_size().unwrap_or(0);
+                for byte in offset..(offset + size).min(HTIF_SIZE) {
+                    let shift = 8 * (byte % 8);
+                    let value = (data >> (8 * (byte - offset)) & 0xFF) << shift;
+                    let register = match byte {
+                        HTIF_TOHOST..HTIF_FROMHOST => &mut self.tohost,
+                        _ => &mut self.fromhost,
+                    };
+                    *register = *register & !(0xFF << shift) | value;
+                }
+                if offset < HTIF_FROMHOST && self.tohost != 0 {
+                    self.handle_command();
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        let getchar = htif_command(HTIF_DEVICE_CONSOLE, HTIF_CONSOLE_GETCHAR, 0);
+        if self.tohost == getchar
+            && self.fromhost == 0
+            && let Some(byte) = self.console.receive()
+        {
+            self.fromhost = getchar | byte as u64;
+            self.tohost = 0;
+        }
+    }
+
+    /// Forgets the exit and any pending command, the console keeps its contents
+    fn reset(&mut self) {
+        self.tohost = 0;
+        self.fromhost = 0;
+        self.exit_code = None;
+        self.exit_requested = false;
+    }
+
+    fn take_machine_request(&mut self) -> Option<MachineRequest> {
+        let code = self.exit_code.filter(|_| !self.exit_requested)?;
+        self.exit_requested = true;
+        Some(MachineRequest::Exit(code))
+    }
+
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        Some(DeviceTreeNode::new(
+            "htif",
+            &["ucb,htif0"],
+            self.address_range(),
+        ))
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = address - self.base;
+        let registers = [self.tohost, self.fromhost];
+        (offset..offset + 8).fold(0, |value, byte| {
+            let register = registers.get(byte as usize / 8).copied().unwrap_or(0);
+            value | (register >> (8 * (byte % 8)) & 0xFF) << (8 * (byte - offset))
+        })
+    }
+}
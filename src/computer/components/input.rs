@@ -0,0 +1,216 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use log::debug;
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+// Register offsets, each a word
+pub const INPUT_STATUS: u64 = 0x00;
+/// Reading it takes the oldest event from the FIFO
+pub const INPUT_EVENT: u64 = 0x04;
+/// Events in the FIFO
+pub const INPUT_COUNT: u64 = 0x08;
+pub const INPUT_CONTROL: u64 = 0x0C;
+pub const INPUT_SIZE: u64 = 0x1000;
+
+// Status bits
+pub const INPUT_STATUS_READY: u32 = 1 << 0;
+/// Events were dropped because the FIFO was full, writing 1 clears it
+pub const INPUT_STATUS_OVERFLOW: u32 = 1 << 1;
+
+/// Control bit enabling the interrupt while events are available
+pub const INPUT_CONTROL_IRQ_ENABLE: u32 = 1 << 0;
+
+// Bits of an event read from the event register besides the key code in the lower half word
+pub const INPUT_EVENT_PRESSED: u32 = 1 << 16;
+/// Clear when the FIFO was empty
+pub const INPUT_EVENT_VALID: u32 = 1 << 31;
+
+pub const INPUT_FIFO_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+    /// Key code, e.g. a Linux input key code
+    pub code: u16,
+    pub pressed: bool,
+}
+
+impl InputEvent {
+    pub fn press(code: u16) -> Self {
+        Self {
+            code,
+            pressed: true,
+        }
+    }
+
+    pub fn release(code: u16) -> Self {
+        Self {
+            code,
+            pressed: false,
+        }
+    }
+
+    /// Value of the event register
+    pub fn encode(&self) -> u32 {
+        INPUT_EVENT_VALID | (self.pressed as u32 * INPUT_EVENT_PRESSED) | self.code as u32
+    }
+}
+
+/// Keyboard-like input FIFO. The host pushes events directly or schedules a script of events by tick,
+/// which replays the same way on every run.
+/// Ticks are counted from the device's creation or last reset, so they match the computer's tick count
+/// for devices attached before the first tick. A reset replays the script from the start.
+#[derive(Debug)]
+pub struct InputQueue {
+    name: String,
+    base: u64,
+    fifo: VecDeque<InputEvent>,
+    overflow: bool,
+    control: u32,
+    ticks: u64,
+    /// Scheduled events sorted by tick, including the delivered ones for replays
+    script: Vec<(u64, InputEvent)>,
+    /// Index of the first scheduled event not delivered yet
+    next_scripted: usize,
+}
+
+impl InputQueue {
+    pub fn new(name: &str, base: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            fifo: VecDeque::new(),
+            overflow: false,
+            control: 0,
+            ticks: 0,
+            script: Vec::new(),
+            next_scripted: 0,
+        }
+    }
+
+    /// Queues the event now, it is dropped if the FIFO is full
+    pub fn push_event(&mut self, event: InputEvent) {
+        if self.fifo.len() >= INPUT_FIFO_SIZE {
+            debug!(target: "input", "{}: FIFO full, dropped {event:?}", self.name);
+            self.overflow = true;
+            return;
+        }
+        self.fifo.push_back(event);
+    }
+
+    /// Queues the event once the given tick is reached, right away if it has passed
+    #[cfg(test)]
+    pub fn schedule(&mut self, tick: u64, event: InputEvent) {
+        self.load_script([(tick, event)]);
+    }
+
+    /// Schedules events by tick, events of the same tick are queued in the given order
+    pub fn load_script(&mut self, events: impl IntoIterator<Item = (u64, InputEvent)>) {
+        let mut pending = self.script.split_off(self.next_scripted);
+        pending.extend(events);
+        pending.sort_by_key(|(tick, _)| *tick);
+        self.script.extend(pending);
+        self.deliver();
+    }
+
+    /// Scheduled events not delivered yet
+    #[cfg(test)]
+    pub fn get_pending_script(&self) -> &[(u64, InputEvent)] {
+        &self.script[self.next_scripted..]
+    }
+
+    #[cfg(test)]
+    pub fn get_queued(&self) -> &VecDeque<InputEvent> {
+        &self.fifo
+    }
+
+    fn deliver(&mut self) {
+        while let Some((tick, event)) = self.script.get(self.next_scripted).copied()
+            && tick <= self.ticks
+        {
+            self.next_scripted += 1;
+            self.push_event(event);
+        }
+    }
+
+    fn status(&self) -> u32 {
+        ((!self.fifo.is_empty()) as u32 * INPUT_STATUS_READY)
+            | (self.overflow as u32 * INPUT_STATUS_OVERFLOW)
+    }
+
+    fn read_register(&self, offset: u64) -> u32 {
+        match offset {
+            INPUT_STATUS => self.status(),
+            INPUT_EVENT => self.fifo.front().map_or(0, InputEvent::encode),
+            INPUT_COUNT => self.fifo.len() as u32,
+            INPUT_CONTROL => self.control,
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u32) {
+        match offset {
+            INPUT_STATUS if value & INPUT_STATUS_OVERFLOW != 0 => self.overflow = false,
+            INPUT_CONTROL => self.control = value & INPUT_CONTROL_IRQ_ENABLE,
+            _ => {}
+        }
+    }
+}
+
+impl BusDevice for InputQueue {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (INPUT_SIZE - 1)
+    }
+
+    /// Only reads addressing the event register take an event
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => {
+                bus.force_put_data(self.read_dw(bus.get_address().value()));
+                if offset == INPUT_EVENT {
+                    self.fifo.pop_front();
+                }
+            }
+            status => {
+                let data = bus.get_data();
+                self.write_register(offset, data as u32);
+                if status.write_size() == Some(8) {
+                    self.write_register(offset + 4, (data >> 32) as u32);
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+        self.deliver();
+    }
+
+    fn has_interrupt_line(&self) -> bool {
+        true
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.control & INPUT_CONTROL_IRQ_ENABLE != 0 && !self.fifo.is_empty()
+    }
+
+    fn reset(&mut self) {
+        self.fifo.clear();
+        self.overflow = false;
+        self.control = 0;
+        self.ticks = 0;
+        self.next_scripted = 0;
+        self.deliver();
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = (address - self.base) & !0b11;
+        self.read_register(offset) as u64 | (self.read_register(offset + 4) as u64) << 32
+    }
+}
@@ -0,0 +1,230 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::csr::MIP_MEIP;
+use crate::computer::components::device::BusDevice;
+use crate::computer::fdt::{
+    cpu_interrupt_controller_phandle, DeviceTreeNode, PropertyValue, PLIC_PHANDLE,
+};
+use log::debug;
+use std::ops::RangeInclusive;
+
+// Register offsets, laid out like the SiFive PLIC
+/// Priority of each source, a word per source starting with the unused source 0
+pub const PLIC_PRIORITY: u64 = 0x00_0000;
+/// Pending bit of each source
+pub const PLIC_PENDING: u64 = 0x00_1000;
+/// Enable bits of each context, 0x80 bytes per context
+pub const PLIC_ENABLE: u64 = 0x00_2000;
+pub const PLIC_ENABLE_STRIDE: u64 = 0x80;
+/// Priority threshold of each context, 0x1000 bytes per context, followed by its claim/complete register
+pub const PLIC_THRESHOLD: u64 = 0x20_0000;
+pub const PLIC_CLAIM_COMPLETE: u64 = 0x20_0004;
+pub const PLIC_CONTEXT_STRIDE: u64 = 0x1000;
+pub const PLIC_SIZE: u64 = 0x400_0000;
+
+/// Number of interrupt sources including the reserved source 0, the pending and enable bits fit one word
+pub const PLIC_SOURCES: usize = 32;
+pub const PLIC_MAX_PRIORITY: u32 = 7;
+
+/// Platform-level interrupt controller, routes the interrupt lines of the devices to the external
+/// interrupt of the harts. Each hart has a single machine mode context with the hart's index.
+/// A device's line is sampled into its pending bit while its source is not claimed, like a level triggered
+/// gateway. The highest priority enabled pending source above a context's threshold raises `MEIP` of the hart,
+/// claiming it clears the pending bit until the handler completes it.
+/// Registers are 32 bits wide and accessed by words.
+#[derive(Debug)]
+pub struct Plic {
+    name: String,
+    base: u64,
+    priority: [u32; PLIC_SOURCES],
+    pending: u32,
+    /// Sources claimed by a context and not yet completed
+    claimed: u32,
+    enable: Vec<u32>,
+    threshold: Vec<u32>,
+}
+
+impl Plic {
+    pub fn new(name: &str, base: u64, harts: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            priority: [0; PLIC_SOURCES],
+            pending: 0,
+            claimed: 0,
+            enable: vec![0; harts],
+            threshold: vec![0; harts],
+        }
+    }
+
+    /// Highest priority source the context would claim, the lowest id among equal priorities
+    fn best_source(&self, context: usize) -> Option<u32> {
+        let candidates = self.pending & self.enable[context];
+        (1..PLIC_SOURCES as u32)
+            .filter(|source| candidates & (1 << source) != 0)
+            .filter(|source| self.priority[*source as usize] > self.threshold[context])
+            .min_by_key(|source| (PLIC_MAX_PRIORITY - self.priority[*source as usize], *source))
+    }
+
+    fn claim(&mut self, context: usize) -> u32 {
+        let Some(source) = self.best_source(context) else {
+            return 0;
+        };
+        debug!(target: "plic", "Context {context} claimed source {source}");
+        self.pending &= !(1 << source);
+        self.claimed |= 1 << source;
+        source
+    }
+
+    fn complete(&mut self, context: usize, source: u32) {
+        if source as usize >= PLIC_SOURCES {
+            return;
+        }
+        debug!(target: "plic", "Context {context} completed source {source}");
+        self.claimed &= !(1 << source);
+    }
+
+    /// Context addressed by an offset into a per context block, if the hart exists
+    fn context(&self, offset: u64, block: u64, stride: u64) -> Option<usize> {
+        let context = ((offset - block) / stride) as usize;
+        (context < self.enable.len()).then_some(context)
+    }
+
+    fn read_register(&self, offset: u64) -> u32 {
+        match offset {
+            PLIC_PRIORITY..PLIC_PENDING => {
+                let source = ((offset - PLIC_PRIORITY) / 4) as usize;
+                self.priority.get(source).copied().unwrap_or(0)
+            }
+            PLIC_PENDING => self.pending,
+            PLIC_ENABLE..PLIC_THRESHOLD if offset.is_multiple_of(PLIC_ENABLE_STRIDE) => self
+                .context(offset, PLIC_ENABLE, PLIC_ENABLE_STRIDE)
+                .map_or(0, |context| self.enable[context]),
+            PLIC_THRESHOLD.. => {
+                let Some(context) = self.context(offset, PLIC_THRESHOLD, PLIC_CONTEXT_STRIDE)
+                else {
+                    return 0;
+                };
+                match PLIC_THRESHOLD + offset % PLIC_CONTEXT_STRIDE {
+                    PLIC_THRESHOLD => self.threshold[context],
+                    PLIC_CLAIM_COMPLETE => self.best_source(context).unwrap_or(0),
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u32) {
+        match offset {
+            PLIC_PRIORITY..PLIC_PENDING => {
+                let source = ((offset - PLIC_PRIORITY) / 4) as usize;
+                // Source 0 does not exist
+                if (1..PLIC_SOURCES).contains(&source) {
+                    self.priority[source] = value.min(PLIC_MAX_PRIORITY);
+                }
+            }
+            PLIC_ENABLE..PLIC_THRESHOLD if offset.is_multiple_of(PLIC_ENABLE_STRIDE) => {
+                if let Some(context) = self.context(offset, PLIC_ENABLE, PLIC_ENABLE_STRIDE) {
+                    self.enable[context] = value & !1;
+                }
+            }
+            PLIC_THRESHOLD.. => {
+                let Some(context) = self.context(offset, PLIC_THRESHOLD, PLIC_CONTEXT_STRIDE)
+                else {
+                    return;
+                };
+                match PLIC_THRESHOLD + offset % PLIC_CONTEXT_STRIDE {
+                    PLIC_THRESHOLD => self.threshold[context] = value.min(PLIC_MAX_PRIORITY),
+                    PLIC_CLAIM_COMPLETE => self.complete(context, value),
+                    _ => {}
+                }
+            }
+            // The pending bits are read-only
+            _ => {}
+        }
+    }
+}
+
+impl BusDevice for Plic {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (PLIC_SIZE - 1)
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = (bus.get_address().value() - self.base) & !0b11;
+        match bus.get_status() {
+            BusStatus::Read => {
+                let claim = (offset >= PLIC_THRESHOLD
+                    && PLIC_THRESHOLD + offset % PLIC_CONTEXT_STRIDE == PLIC_CLAIM_COMPLETE)
+                    .then(|| self.context(offset, PLIC_THRESHOLD, PLIC_CONTEXT_STRIDE))
+                    .flatten();
+                let value = match claim {
+                    Some(context) => self.claim(context),
+                    None => self.read_register(offset),
+                };
+                bus.force_put_data(value as u64);
+            }
+            _ => {
+                let data = bus.get_data() as u32;
+                debug!(target: "plic", "Write {data:#x} to {offset:#x}");
+                self.write_register(offset, data);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.priority.fill(0);
+        self.pending = 0;
+        self.claimed = 0;
+        self.enable.fill(0);
+        self.threshold.fill(0);
+    }
+
+    fn sample_interrupt_lines(&mut self, lines: &[bool]) {
+        for (source, line) in (1..PLIC_SOURCES).zip(lines) {
+            if *line && self.claimed & (1 << source) == 0 {
+                self.pending |= 1 << source;
+            }
+        }
+    }
+
+    fn hart_interrupts(&self, hart: usize) -> u64 {
+        if hart < self.enable.len() && self.best_source(hart).is_some() {
+            MIP_MEIP
+        } else {
+            0
+        }
+    }
+
+    /// Wired to the machine external interrupt of each hart
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let interrupts = (0..self.enable.len())
+            .map(cpu_interrupt_controller_phandle)
+            .flat_map(|phandle| [phandle, MIP_MEIP.trailing_zeros()])
+            .collect();
+        let node = DeviceTreeNode::new(
+            "plic",
+            &["sifive,plic-1.0.0", "riscv,plic0"],
+            self.address_range(),
+        )
+        .with_property("#address-cells", PropertyValue::Cells(vec![0]))
+        .with_property("#interrupt-cells", PropertyValue::Cells(vec![1]))
+        .with_property("interrupt-controller", PropertyValue::Empty)
+        .with_property("interrupts-extended", PropertyValue::Cells(interrupts))
+        .with_property(
+            "riscv,ndev",
+            PropertyValue::Cells(vec![PLIC_SOURCES as u32 - 1]),
+        )
+        .with_property("phandle", PropertyValue::Cells(vec![PLIC_PHANDLE]));
+        Some(node)
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        self.read_register((address - self.base) & !0b11) as u64
+    }
+}
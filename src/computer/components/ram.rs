@@ -1,60 +1,111 @@
 use crate::computer::components::bus::status::BusStatus;
 use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::memory_map::MemoryRegion;
 use crate::utils::paged_memory::PagedMemory;
 use log::debug;
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct RAM {
+    name: String,
+    base: u64,
+    size: u64,
+    latency: u64,
+    beat_latency: u64,
     memory: PagedMemory,
 }
 
-/// Bus Operations
 impl RAM {
-    pub fn process_bus(&mut self, bus: &mut Bus) {
+    pub fn new(region: &MemoryRegion) -> Self {
+        Self {
+            name: region.name.clone(),
+            base: region.base,
+            size: region.size,
+            latency: region.latency,
+            beat_latency: region.beat_latency,
+            memory: PagedMemory::new(),
+        }
+    }
+}
+
+impl BusDevice for RAM {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (self.size - 1)
+    }
+
+    fn latency(&self, _status: BusStatus) -> u64 {
+        self.latency
+    }
+
+    fn beat_latency(&self, _status: BusStatus) -> u64 {
+        self.beat_latency
+    }
+
+    fn supports_burst(&self) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.memory = PagedMemory::new();
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
         debug!(target: "ram", "RAM active");
         match bus.get_status() {
-            BusStatus::Read => self.output_data(bus),
+            BusStatus::Read | BusStatus::ReadBurst(_) => self.output_data(bus),
             BusStatus::WriteByte => self.input_data_byte(bus),
             BusStatus::WriteHalfWord => self.input_data_hw(bus),
             BusStatus::WriteWord => self.input_data_w(bus),
-            BusStatus::WriteDoubleWord => self.input_data_dw(bus),
+            BusStatus::WriteDoubleWord | BusStatus::WriteBurst(_) => self.input_data_dw(bus),
             _ => {}
         }
     }
 
+    fn read_dw(&self, address: u64) -> u64 {
+        self.memory.read_dw(address)
+    }
+}
+
+/// Bus Operations
+impl RAM {
     pub fn input_data_byte(&mut self, bus: &mut Bus) {
-        let address = bus.get_address().value();
+        let address = bus.get_beat_address().value();
         let value = bus.get_data() as u8;
         debug!(target: "ram", "[{:016x}] Input Byte {value} ", address);
         self.memory.write_byte(address, value);
     }
 
     pub fn input_data_hw(&mut self, bus: &mut Bus) {
-        let address = bus.get_address().value();
+        let address = bus.get_beat_address().value();
         let value = bus.get_data() as u16;
         debug!(target: "ram", "[{:016x}] Input HW: {value}", address);
         self.memory
-            .write_hw(bus.get_address().value(), bus.get_data() as u16);
+            .write_hw(bus.get_beat_address().value(), bus.get_data() as u16);
     }
 
     pub fn input_data_w(&mut self, bus: &mut Bus) {
-        let address = bus.get_address().value();
+        let address = bus.get_beat_address().value();
         let value = bus.get_data() as u32;
         debug!(target: "ram", "[{:016x}] Input W: {value}", address);
         self.memory
-            .write_w(bus.get_address().value(), bus.get_data() as u32);
+            .write_w(bus.get_beat_address().value(), bus.get_data() as u32);
     }
 
     pub fn input_data_dw(&mut self, bus: &Bus) {
-        let address = bus.get_address().value();
+        let address = bus.get_beat_address().value();
         let value = bus.get_data();
         debug!(target: "ram", "[{:016x}] Input DW: {value}", address);
         self.memory
-            .write_dw(bus.get_address().value(), bus.get_data());
+            .write_dw(bus.get_beat_address().value(), bus.get_data());
     }
 
     pub fn output_data(&mut self, bus: &mut Bus) {
-        let address = bus.get_address().value();
+        let address = bus.get_beat_address().value();
         let data = self.memory.read_dw(address);
         debug!(target: "ram", "[{:016x}] Output: {data}", address);
         bus.force_put_data(data);
@@ -0,0 +1,94 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use std::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Register offsets
+/// Random double word, each read of any size consumes one
+pub const RNG_DATA: u64 = 0x00;
+/// Word which is always 1, random data is always available
+pub const RNG_STATUS: u64 = 0x08;
+pub const RNG_SIZE: u64 = 0x1000;
+
+/// Random number generator with a seed, so tests get the same numbers on every run.
+/// Uses SplitMix64, which is fast but not cryptographically secure.
+#[derive(Debug)]
+pub struct Rng {
+    name: String,
+    base: u64,
+    seed: u64,
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(name: &str, base: u64, seed: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            seed,
+            state: seed,
+        }
+    }
+
+    /// Seeded from the host's clock, the numbers differ between runs
+    pub fn from_host_time(name: &str, base: u64) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self::new(name, base, seed)
+    }
+
+    #[cfg(test)]
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The number the next read returns
+    pub fn peek(&self) -> u64 {
+        let mut value = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+
+    pub fn next(&mut self) -> u64 {
+        let value = self.peek();
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        value
+    }
+}
+
+impl BusDevice for Rng {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (RNG_SIZE - 1)
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        if bus.get_status() != BusStatus::Read {
+            return;
+        }
+        bus.force_put_data(self.read_dw(bus.get_address().value()));
+        if offset < RNG_STATUS {
+            self.next();
+        }
+    }
+
+    /// Restarts the sequence of the seed
+    fn reset(&mut self) {
+        self.state = self.seed;
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        match address - self.base {
+            RNG_DATA..RNG_STATUS => self.peek() >> (8 * (address - self.base)),
+            RNG_STATUS => 1,
+            _ => 0,
+        }
+    }
+}
@@ -1,28 +1,36 @@
+use crate::computer::components::bus::response::{BusError, BusResponse};
 use crate::computer::components::bus::status::BusStatus;
 use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::memory_map::MemoryRegion;
 use crate::utils::paged_memory::PagedMemory;
 use log::debug;
+use std::ops::RangeInclusive;
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, PartialEq)]
 pub struct ROM {
+    name: String,
+    base: u64,
+    size: u64,
+    latency: u64,
+    beat_latency: u64,
     memory: PagedMemory,
 }
 
 impl ROM {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    pub fn process_bus(&mut self, bus: &mut Bus) {
-        debug!(target: "rom", "ROM active");
-        match bus.get_status() {
-            BusStatus::Read => self.output_data(bus),
-            _ => {}
+    pub fn new(region: &MemoryRegion) -> Self {
+        Self {
+            name: region.name.clone(),
+            base: region.base,
+            size: region.size,
+            latency: region.latency,
+            beat_latency: region.beat_latency,
+            memory: PagedMemory::new(),
         }
     }
 
     pub fn output_data(&mut self, bus: &mut Bus) {
-        let address = bus.get_address().value();
+        let address = bus.get_beat_address().value();
         let data = self.memory.read_dw(address);
         debug!(target: "rom", "[{:016x}] Output: {data}", address);
         bus.force_put_data(data);
@@ -35,3 +43,41 @@ impl ROM {
         })
     }
 }
+
+impl BusDevice for ROM {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (self.size - 1)
+    }
+
+    fn latency(&self, _status: BusStatus) -> u64 {
+        self.latency
+    }
+
+    fn beat_latency(&self, _status: BusStatus) -> u64 {
+        self.beat_latency
+    }
+
+    fn supports_burst(&self) -> bool {
+        true
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        debug!(target: "rom", "ROM active");
+        match bus.get_status() {
+            BusStatus::Read | BusStatus::ReadBurst(_) => self.output_data(bus),
+            BusStatus::Idle => {}
+            _ => {
+                debug!(target: "rom", "[{:016x}] Write rejected", bus.get_beat_address().value());
+                bus.put_response(BusResponse::Error(BusError::AccessDenied));
+            }
+        }
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        self.memory.read_dw(address)
+    }
+}
@@ -0,0 +1,209 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::fdt::DeviceTreeNode;
+use log::debug;
+use std::ops::RangeInclusive;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Register offsets, each a word, laid out like the Goldfish RTC of QEMU's virt machine
+/// Lower half of the time in nanoseconds since the Unix epoch, reading it latches the upper half
+pub const RTC_TIME_LOW: u64 = 0x00;
+/// Upper half of the time, writing it and then the lower half sets the time
+pub const RTC_TIME_HIGH: u64 = 0x04;
+/// Lower half of the alarm time, writing it arms the alarm
+pub const RTC_ALARM_LOW: u64 = 0x08;
+pub const RTC_ALARM_HIGH: u64 = 0x0C;
+pub const RTC_IRQ_ENABLED: u64 = 0x10;
+/// Writing disarms the alarm
+pub const RTC_CLEAR_ALARM: u64 = 0x14;
+/// Whether the alarm is armed
+pub const RTC_ALARM_STATUS: u64 = 0x18;
+/// Writing acknowledges the interrupt
+pub const RTC_CLEAR_INTERRUPT: u64 = 0x1C;
+pub const RTC_SIZE: u64 = 0x1000;
+
+/// Where the time of the clock comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RtcClock {
+    /// Wall-clock time of the host
+    Host,
+    /// Deterministic time advancing by a fixed step per computer tick
+    Virtual {
+        /// Nanoseconds since the Unix epoch at tick 0
+        start: u64,
+        tick_nanos: u64,
+    },
+}
+
+impl Default for RtcClock {
+    /// Virtual time from the epoch at 1 GHz
+    fn default() -> Self {
+        RtcClock::Virtual {
+            start: 0,
+            tick_nanos: 1,
+        }
+    }
+}
+
+/// Real-time clock with a single alarm, raising its interrupt line when the alarm goes off
+#[derive(Debug)]
+pub struct Rtc {
+    name: String,
+    base: u64,
+    clock: RtcClock,
+    ticks: u64,
+    /// Added to the clock's time, set when the guest or host sets the time
+    offset: u64,
+    time_high_latch: u32,
+    /// Upper half written by the guest, used once the lower half follows
+    time_high_write: u32,
+    alarm: u64,
+    alarm_armed: bool,
+    irq_enabled: bool,
+    interrupt: bool,
+}
+
+impl Rtc {
+    pub fn new(name: &str, base: u64, clock: RtcClock) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            clock,
+            ticks: 0,
+            offset: 0,
+            time_high_latch: 0,
+            time_high_write: 0,
+            alarm: 0,
+            alarm_armed: false,
+            irq_enabled: false,
+            interrupt: false,
+        }
+    }
+
+    /// Nanoseconds since the Unix epoch
+    pub fn get_time(&self) -> u64 {
+        let clock = match self.clock {
+            RtcClock::Host => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_nanos() as u64),
+            RtcClock::Virtual { start, tick_nanos } => {
+                start.wrapping_add(self.ticks.wrapping_mul(tick_nanos))
+            }
+        };
+        clock.wrapping_add(self.offset)
+    }
+
+    pub fn set_time(&mut self, time: u64) {
+        self.offset = 0;
+        self.offset = time.wrapping_sub(self.get_time());
+    }
+
+    fn read_register(&self, offset: u64) -> u32 {
+        match offset {
+            RTC_TIME_LOW => self.get_time() as u32,
+            RTC_TIME_HIGH => self.time_high_latch,
+            RTC_ALARM_LOW => self.alarm as u32,
+            RTC_ALARM_HIGH => (self.alarm >> 32) as u32,
+            RTC_IRQ_ENABLED => self.irq_enabled as u32,
+            RTC_ALARM_STATUS => self.alarm_armed as u32,
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u32) {
+        match offset {
+            RTC_TIME_LOW => {
+                let time = (self.time_high_write as u64) << 32 | value as u64;
+                debug!(target: "rtc", "{}: time set to {time}", self.name);
+                self.set_time(time);
+            }
+            RTC_TIME_HIGH => self.time_high_write = value,
+            RTC_ALARM_LOW => {
+                self.alarm = self.alarm & !0xFFFF_FFFF | value as u64;
+                self.alarm_armed = true;
+                self.check_alarm();
+            }
+            RTC_ALARM_HIGH => {
+                self.alarm = self.alarm & 0xFFFF_FFFF | (value as u64) << 32;
+            }
+            RTC_IRQ_ENABLED => self.irq_enabled = value & 1 != 0,
+            RTC_CLEAR_ALARM => self.alarm_armed = false,
+            RTC_CLEAR_INTERRUPT => self.interrupt = false,
+            _ => {}
+        }
+    }
+
+    fn check_alarm(&mut self) {
+        if self.alarm_armed && self.get_time() >= self.alarm {
+            debug!(target: "rtc", "{}: alarm at {}", self.name, self.alarm);
+            self.alarm_armed = false;
+            self.interrupt = true;
+        }
+    }
+}
+
+impl BusDevice for Rtc {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (RTC_SIZE - 1)
+    }
+
+    /// Double word accesses cover two registers, the upper half is written first
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => {
+                if offset == RTC_TIME_LOW {
+                    self.time_high_latch = (self.get_time() >> 32) as u32;
+                }
+                bus.force_put_data(self.read_dw(bus.get_address().value()));
+            }
+            status => {
+                let data = bus.get_data();
+                if status.write_size() == Some(8) {
+                    self.write_register(offset + 4, (data >> 32) as u32);
+                }
+                self.write_register(offset, data as u32);
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        self.ticks = self.ticks.wrapping_add(1);
+        self.check_alarm();
+    }
+
+    fn has_interrupt_line(&self) -> bool {
+        true
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.irq_enabled && self.interrupt
+    }
+
+    fn reset(&mut self) {
+        let (name, base, clock) = (std::mem::take(&mut self.name), self.base, self.clock);
+        *self = Self::new(&name, base, clock);
+    }
+
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        Some(DeviceTreeNode::new(
+            "rtc",
+            &["google,goldfish-rtc"],
+            self.address_range(),
+        ))
+    }
+
+    /// A double word at the time reads the whole time
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = (address - self.base) & !0b11;
+        if offset == RTC_TIME_LOW {
+            return self.get_time();
+        }
+        self.read_register(offset) as u64 | (self.read_register(offset + 4) as u64) << 32
+    }
+}
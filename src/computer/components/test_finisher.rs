@@ -0,0 +1,81 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::{BusDevice, MachineRequest};
+use crate::computer::fdt::DeviceTreeNode;
+use log::debug;
+use std::ops::RangeInclusive;
+
+/// Address of the finisher on QEMU's virt machine, which bare-metal programs expect
+pub const TEST_FINISHER_BASE: u64 = 0x0010_0000;
+pub const TEST_FINISHER_SIZE: u64 = 0x1000;
+
+// Status in the low half word of a write, the upper half word is the exit code of a failure
+pub const TEST_FINISHER_FAIL: u64 = 0x3333;
+pub const TEST_FINISHER_PASS: u64 = 0x5555;
+pub const TEST_FINISHER_RESET: u64 = 0x7777;
+
+/// SiFive test finisher, lets the guest power off the machine or reset it.
+/// A pass exits with code 0, a failure with the code in the upper half word, other writes are ignored.
+#[derive(Debug)]
+pub struct TestFinisher {
+    name: String,
+    request: Option<MachineRequest>,
+}
+
+impl TestFinisher {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            request: None,
+        }
+    }
+}
+
+impl BusDevice for TestFinisher {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        TEST_FINISHER_BASE..=TEST_FINISHER_BASE + (TEST_FINISHER_SIZE - 1)
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        if bus.get_address().value() != TEST_FINISHER_BASE || bus.get_status() == BusStatus::Read {
+            return;
+        }
+        let value = bus.get_data() & 0xFFFF_FFFF;
+        let request = match value & 0xFFFF {
+            TEST_FINISHER_PASS => MachineRequest::Exit(0),
+            TEST_FINISHER_FAIL => MachineRequest::Exit(value >> 16),
+            TEST_FINISHER_RESET => MachineRequest::Reset,
+            _ => {
+                debug!(target: "test_finisher", "{}: ignored write {value:#x}", self.name);
+                return;
+            }
+        };
+        debug!(target: "test_finisher", "{}: {request:?}", self.name);
+        self.request = Some(request);
+    }
+
+    fn reset(&mut self) {
+        self.request = None;
+    }
+
+    fn take_machine_request(&mut self) -> Option<MachineRequest> {
+        self.request.take()
+    }
+
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let compatible = ["sifive,test1", "sifive,test0", "syscon"];
+        Some(DeviceTreeNode::new(
+            "test",
+            &compatible,
+            self.address_range(),
+        ))
+    }
+
+    fn read_dw(&self, _address: u64) -> u64 {
+        0
+    }
+}
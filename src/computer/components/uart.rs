@@ -0,0 +1,295 @@
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::components::uart::backend::UartBackend;
+use crate::computer::fdt::{DeviceTreeNode, PropertyValue};
+use log::debug;
+#[cfg(test)]
+use std::any::Any;
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+pub mod backend;
+
+// Register offsets, one byte each like a 16550 without register shift
+/// Receiver buffer when read, transmitter holding register when written; divisor latch low while DLAB is set
+pub const UART_RBR_THR: u64 = 0;
+/// Interrupt enable; divisor latch high while DLAB is set
+pub const UART_IER: u64 = 1;
+/// Interrupt identification when read, FIFO control when written
+pub const UART_IIR_FCR: u64 = 2;
+pub const UART_LCR: u64 = 3;
+pub const UART_MCR: u64 = 4;
+pub const UART_LSR: u64 = 5;
+pub const UART_MSR: u64 = 6;
+pub const UART_SCR: u64 = 7;
+pub const UART_SIZE: u64 = 0x100;
+
+// Interrupt enable bits
+pub const UART_IER_RX_AVAILABLE: u8 = 1 << 0;
+pub const UART_IER_THR_EMPTY: u8 = 1 << 1;
+
+// Interrupt identification values, FIFO enabled bits aside
+pub const UART_IIR_NONE: u8 = 0x01;
+pub const UART_IIR_THR_EMPTY: u8 = 0x02;
+pub const UART_IIR_RX_AVAILABLE: u8 = 0x04;
+pub const UART_IIR_FIFO_ENABLED: u8 = 0xC0;
+
+// FIFO control bits
+pub const UART_FCR_ENABLE: u8 = 1 << 0;
+pub const UART_FCR_CLEAR_RX: u8 = 1 << 1;
+
+/// Divisor latch access bit of the line control register
+pub const UART_LCR_DLAB: u8 = 1 << 7;
+/// Transmitted bytes are received again instead of reaching the host
+pub const UART_MCR_LOOPBACK: u8 = 1 << 4;
+
+// Line status bits
+pub const UART_LSR_DATA_READY: u8 = 1 << 0;
+pub const UART_LSR_OVERRUN: u8 = 1 << 1;
+pub const UART_LSR_THR_EMPTY: u8 = 1 << 5;
+pub const UART_LSR_TRANSMITTER_EMPTY: u8 = 1 << 6;
+
+pub const UART_FIFO_SIZE: usize = 16;
+/// Input clock the device tree reports, divisors set by drivers have no effect
+pub const UART_CLOCK_FREQUENCY: u32 = 3_686_400;
+
+/// 16550 compatible UART connected to a host backend.
+/// Transmission completes immediately, the baud rate is kept but has no effect.
+/// The receiver polls the backend once per tick while it has room.
+/// The interrupt line is raised while an enabled interrupt is identified in IIR.
+#[derive(Debug)]
+pub struct Uart {
+    name: String,
+    base: u64,
+    backend: Box<dyn UartBackend>,
+    received: VecDeque<u8>,
+    ier: u8,
+    fcr: u8,
+    lcr: u8,
+    mcr: u8,
+    lsr_overrun: bool,
+    scr: u8,
+    divisor: u16,
+    /// The transmitter became empty since IIR last reported it
+    thr_empty_pending: bool,
+}
+
+impl Uart {
+    pub fn new(name: &str, base: u64, backend: impl UartBackend) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            backend: Box::new(backend),
+            received: VecDeque::new(),
+            ier: 0,
+            fcr: 0,
+            lcr: 0,
+            mcr: 0,
+            lsr_overrun: false,
+            scr: 0,
+            divisor: 0,
+            thr_empty_pending: false,
+        }
+    }
+
+    /// The backend by its concrete type, e.g. to inspect the output of a memory backend
+    #[cfg(test)]
+    pub fn get_backend<T: UartBackend>(&self) -> Option<&T> {
+        (self.backend.as_ref() as &dyn Any).downcast_ref()
+    }
+
+    #[cfg(test)]
+    pub fn get_backend_mut<T: UartBackend>(&mut self) -> Option<&mut T> {
+        (self.backend.as_mut() as &mut dyn Any).downcast_mut()
+    }
+
+    #[cfg(test)]
+    pub fn get_divisor(&self) -> u16 {
+        self.divisor
+    }
+
+    fn fifo_enabled(&self) -> bool {
+        self.fcr & UART_FCR_ENABLE != 0
+    }
+
+    fn capacity(&self) -> usize {
+        if self.fifo_enabled() {
+            UART_FIFO_SIZE
+        } else {
+            1
+        }
+    }
+
+    fn dlab(&self) -> bool {
+        self.lcr & UART_LCR_DLAB != 0
+    }
+
+    fn line_status(&self) -> u8 {
+        let data_ready = !self.received.is_empty() as u8 * UART_LSR_DATA_READY;
+        let overrun = self.lsr_overrun as u8 * UART_LSR_OVERRUN;
+        data_ready | overrun | UART_LSR_THR_EMPTY | UART_LSR_TRANSMITTER_EMPTY
+    }
+
+    /// Highest priority interrupt which is enabled and pending
+    fn interrupt_identification(&self) -> u8 {
+        let fifo = self.fifo_enabled() as u8 * UART_IIR_FIFO_ENABLED;
+        if self.ier & UART_IER_RX_AVAILABLE != 0 && !self.received.is_empty() {
+            fifo | UART_IIR_RX_AVAILABLE
+        } else if self.ier & UART_IER_THR_EMPTY != 0 && self.thr_empty_pending {
+            fifo | UART_IIR_THR_EMPTY
+        } else {
+            fifo | UART_IIR_NONE
+        }
+    }
+
+    fn read_register(&self, offset: u64) -> u8 {
+        match offset {
+            UART_RBR_THR if self.dlab() => self.divisor as u8,
+            UART_RBR_THR => self.received.front().copied().unwrap_or(0),
+            UART_IER if self.dlab() => (self.divisor >> 8) as u8,
+            UART_IER => self.ier,
+            UART_IIR_FCR => self.interrupt_identification(),
+            UART_LCR => self.lcr,
+            UART_MCR => self.mcr,
+            UART_LSR => self.line_status(),
+            // No modem lines are connected
+            UART_MSR => 0,
+            UART_SCR => self.scr,
+            _ => 0,
+        }
+    }
+
+    /// Side effects of reading a register over the bus
+    fn acknowledge_read(&mut self, offset: u64) {
+        match offset {
+            UART_RBR_THR if !self.dlab() => {
+                self.received.pop_front();
+            }
+            UART_IIR_FCR if self.interrupt_identification() & 0x0F == UART_IIR_THR_EMPTY => {
+                self.thr_empty_pending = false;
+            }
+            UART_LSR => self.lsr_overrun = false,
+            _ => {}
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u8) {
+        match offset {
+            UART_RBR_THR if self.dlab() => self.divisor = self.divisor & 0xFF00 | value as u16,
+            UART_RBR_THR => self.transmit(value),
+            UART_IER if self.dlab() => {
+                self.divisor = self.divisor & 0x00FF | (value as u16) << 8;
+            }
+            UART_IER => {
+                // Enabling the interrupt reports the already empty transmitter
+                if value & !self.ier & UART_IER_THR_EMPTY != 0 {
+                    self.thr_empty_pending = true;
+                }
+                self.ier = value & 0x0F;
+            }
+            UART_IIR_FCR => {
+                if value & UART_FCR_CLEAR_RX != 0 || (value ^ self.fcr) & UART_FCR_ENABLE != 0 {
+                    self.received.clear();
+                }
+                self.fcr = value & UART_FCR_ENABLE;
+            }
+            UART_LCR => self.lcr = value,
+            UART_MCR => self.mcr = value & 0x1F,
+            UART_SCR => self.scr = value,
+            _ => {}
+        }
+    }
+
+    fn transmit(&mut self, byte: u8) {
+        if self.mcr & UART_MCR_LOOPBACK != 0 {
+            self.receive(byte);
+        } else {
+            self.backend.transmit(byte);
+        }
+        self.thr_empty_pending = true;
+    }
+
+    fn receive(&mut self, byte: u8) {
+        if self.received.len() < self.capacity() {
+            self.received.push_back(byte);
+        } else {
+            debug!(target: "uart", "{}: receiver overrun, dropped {byte:#x}", self.name);
+            self.lsr_overrun = true;
+        }
+    }
+}
+
+impl BusDevice for Uart {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (UART_SIZE - 1)
+    }
+
+    /// Accesses cover the registers from the address on, read side effects only apply to the addressed one
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => {
+                bus.force_put_data(self.read_dw(bus.get_address().value()));
+                self.acknowledge_read(offset);
+            }
+            status => {
+                let data = bus.get_data();
+                let size = status.write_size().unwrap_or(0);
+                for byte in 0..size {
+                    self.write_register(offset + byte, (data >> (8 * byte)) as u8);
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.mcr & UART_MCR_LOOPBACK != 0 || self.received.len() >= self.capacity() {
+            return;
+        }
+        if let Some(byte) = self.backend.receive() {
+            self.receive(byte);
+        }
+    }
+
+    /// Clears the registers and drops the received bytes, the backend keeps its contents
+    fn reset(&mut self) {
+        self.received.clear();
+        self.ier = 0;
+        self.fcr = 0;
+        self.lcr = 0;
+        self.mcr = 0;
+        self.lsr_overrun = false;
+        self.scr = 0;
+        self.divisor = 0;
+        self.thr_empty_pending = false;
+    }
+
+    fn has_interrupt_line(&self) -> bool {
+        true
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.interrupt_identification() & UART_IIR_NONE == 0
+    }
+
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let node = DeviceTreeNode::new("serial", &["ns16550a"], self.address_range())
+            .with_property(
+                "clock-frequency",
+                PropertyValue::Cells(vec![UART_CLOCK_FREQUENCY]),
+            );
+        Some(node)
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = address - self.base;
+        (0..8).fold(0, |value, byte| {
+            value | (self.read_register(offset + byte) as u64) << (8 * byte)
+        })
+    }
+}
@@ -0,0 +1,147 @@
+use std::any::Any;
+#[cfg(test)]
+use std::collections::VecDeque;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+/// Host side of a UART, called whenever the guest transmits a byte or the receiver has room
+pub trait UartBackend: Any + Debug {
+    fn transmit(&mut self, byte: u8);
+
+    /// Next byte sent by the host, must not block
+    fn receive(&mut self) -> Option<u8>;
+}
+
+/// Keeps transmitted bytes and serves queued input, for tests
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    input: VecDeque<u8>,
+    output: Vec<u8>,
+}
+
+#[cfg(test)]
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_input(&mut self, bytes: &[u8]) {
+        self.input.extend(bytes);
+    }
+
+    pub fn get_output(&self) -> &[u8] {
+        &self.output
+    }
+
+    /// Transmitted bytes as text, invalid UTF-8 is replaced
+    pub fn output_string(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+}
+
+#[cfg(test)]
+impl UartBackend for MemoryBackend {
+    fn transmit(&mut self, byte: u8) {
+        self.output.push(byte);
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        self.input.pop_front()
+    }
+}
+
+/// Where a stream backend gets its input from, opened lazily on the first receive
+enum StreamInput {
+    Stdin,
+    Path(PathBuf),
+    Reader(Box<dyn Read + Send>),
+}
+
+/// Connects the UART to host streams: stdin and stdout, a pair of named pipes or any reader and writer.
+/// Input is read by a background thread, so a guest polling the receiver never blocks the simulation.
+pub struct StreamBackend {
+    input: Option<StreamInput>,
+    received: Option<Receiver<u8>>,
+    output: Box<dyn Write>,
+}
+
+impl StreamBackend {
+    pub fn new(input: impl Read + Send + 'static, output: impl Write + 'static) -> Self {
+        Self::with_input(StreamInput::Reader(Box::new(input)), Box::new(output))
+    }
+
+    pub fn stdio() -> Self {
+        Self::with_input(StreamInput::Stdin, Box::new(std::io::stdout()))
+    }
+
+    /// Reads from and writes to named pipes, or plain files.
+    /// The output is opened right away, the input once the guest first polls the receiver.
+    pub fn pipe(input: &Path, output: &Path) -> std::io::Result<Self> {
+        let output = OpenOptions::new().append(true).create(true).open(output)?;
+        Ok(Self::with_input(
+            StreamInput::Path(input.to_path_buf()),
+            Box::new(output),
+        ))
+    }
+
+    fn with_input(input: StreamInput, output: Box<dyn Write>) -> Self {
+        Self {
+            input: Some(input),
+            received: None,
+            output,
+        }
+    }
+
+    fn spawn_reader(input: StreamInput) -> Receiver<u8> {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let mut reader: Box<dyn Read> = match input {
+                StreamInput::Stdin => Box::new(std::io::stdin()),
+                StreamInput::Path(path) => match File::open(path) {
+                    Ok(file) => Box::new(file),
+                    Err(_) => return,
+                },
+                StreamInput::Reader(reader) => reader,
+            };
+            // Forwards whatever is available, without waiting for a full buffer
+            let mut buffer = [0; 64];
+            while let Ok(count @ 1..) = reader.read(&mut buffer) {
+                if buffer[..count]
+                    .iter()
+                    .any(|byte| sender.send(*byte).is_err())
+                {
+                    return;
+                }
+            }
+        });
+        receiver
+    }
+}
+
+impl Debug for StreamBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamBackend")
+            .field("connected", &self.received.is_some())
+            .finish()
+    }
+}
+
+impl UartBackend for StreamBackend {
+    fn transmit(&mut self, byte: u8) {
+        // A closed host side drops the output like a disconnected line
+        let _ = self.output.write_all(&[byte]);
+        let _ = self.output.flush();
+    }
+
+    fn receive(&mut self) -> Option<u8> {
+        if let Some(input) = self.input.take() {
+            self.received = Some(Self::spawn_reader(input));
+        }
+        self.received.as_ref()?.try_recv().ok()
+    }
+}
@@ -0,0 +1,675 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::response::BusResponse;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::device::BusDevice;
+use crate::computer::components::virtio::storage::BlockStorage;
+use crate::computer::fdt::DeviceTreeNode;
+use log::debug;
+#[cfg(test)]
+use std::any::Any;
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+pub mod storage;
+
+// MMIO register offsets of the virtio 1.x transport, each a word
+pub const VIRTIO_MMIO_MAGIC_VALUE: u64 = 0x000;
+pub const VIRTIO_MMIO_VERSION: u64 = 0x004;
+pub const VIRTIO_MMIO_DEVICE_ID: u64 = 0x008;
+pub const VIRTIO_MMIO_VENDOR_ID: u64 = 0x00C;
+pub const VIRTIO_MMIO_DEVICE_FEATURES: u64 = 0x010;
+pub const VIRTIO_MMIO_DEVICE_FEATURES_SEL: u64 = 0x014;
+pub const VIRTIO_MMIO_DRIVER_FEATURES: u64 = 0x020;
+pub const VIRTIO_MMIO_DRIVER_FEATURES_SEL: u64 = 0x024;
+pub const VIRTIO_MMIO_QUEUE_SEL: u64 = 0x030;
+pub const VIRTIO_MMIO_QUEUE_NUM_MAX: u64 = 0x034;
+pub const VIRTIO_MMIO_QUEUE_NUM: u64 = 0x038;
+pub const VIRTIO_MMIO_QUEUE_READY: u64 = 0x044;
+pub const VIRTIO_MMIO_QUEUE_NOTIFY: u64 = 0x050;
+pub const VIRTIO_MMIO_INTERRUPT_STATUS: u64 = 0x060;
+pub const VIRTIO_MMIO_INTERRUPT_ACK: u64 = 0x064;
+pub const VIRTIO_MMIO_STATUS: u64 = 0x070;
+pub const VIRTIO_MMIO_QUEUE_DESC_LOW: u64 = 0x080;
+pub const VIRTIO_MMIO_QUEUE_DESC_HIGH: u64 = 0x084;
+pub const VIRTIO_MMIO_QUEUE_DRIVER_LOW: u64 = 0x090;
+pub const VIRTIO_MMIO_QUEUE_DRIVER_HIGH: u64 = 0x094;
+pub const VIRTIO_MMIO_QUEUE_DEVICE_LOW: u64 = 0x0A0;
+pub const VIRTIO_MMIO_QUEUE_DEVICE_HIGH: u64 = 0x0A4;
+pub const VIRTIO_MMIO_CONFIG_GENERATION: u64 = 0x0FC;
+/// Device specific configuration, the capacity in sectors for block devices
+pub const VIRTIO_MMIO_CONFIG: u64 = 0x100;
+pub const VIRTIO_MMIO_SIZE: u64 = 0x200;
+
+/// "virt" in little endian
+pub const VIRTIO_MAGIC: u32 = 0x7472_6976;
+pub const VIRTIO_DEVICE_BLOCK: u32 = 2;
+pub const VIRTIO_QUEUE_SIZE: u32 = 16;
+
+// Device status bits
+#[cfg(test)]
+pub const VIRTIO_STATUS_ACKNOWLEDGE: u32 = 1;
+#[cfg(test)]
+pub const VIRTIO_STATUS_DRIVER: u32 = 2;
+pub const VIRTIO_STATUS_DRIVER_OK: u32 = 4;
+#[cfg(test)]
+pub const VIRTIO_STATUS_FEATURES_OK: u32 = 8;
+pub const VIRTIO_STATUS_NEEDS_RESET: u32 = 0x40;
+
+// Feature bits
+pub const VIRTIO_BLK_F_RO: u64 = 1 << 5;
+pub const VIRTIO_BLK_F_FLUSH: u64 = 1 << 9;
+pub const VIRTIO_F_VERSION_1: u64 = 1 << 32;
+
+/// The used ring was updated
+pub const VIRTIO_INTERRUPT_USED_RING: u32 = 1;
+
+// Descriptor flags
+pub const VIRTQ_DESC_F_NEXT: u16 = 1;
+/// The device writes the buffer
+pub const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+// Block request types
+pub const VIRTIO_BLK_T_IN: u32 = 0;
+pub const VIRTIO_BLK_T_OUT: u32 = 1;
+pub const VIRTIO_BLK_T_FLUSH: u32 = 4;
+pub const VIRTIO_BLK_T_GET_ID: u32 = 8;
+
+// Block request status
+pub const VIRTIO_BLK_S_OK: u8 = 0;
+pub const VIRTIO_BLK_S_IOERR: u8 = 1;
+pub const VIRTIO_BLK_S_UNSUPP: u8 = 2;
+
+pub const VIRTIO_BLK_SECTOR_SIZE: u64 = 512;
+/// Length of the request header: type, reserved and sector
+const VIRTIO_BLK_HEADER_SIZE: usize = 16;
+/// Length of the device id returned by `VIRTIO_BLK_T_GET_ID`
+const VIRTIO_BLK_ID_SIZE: usize = 20;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Descriptor {
+    address: u64,
+    length: u32,
+    flags: u16,
+    next: u16,
+}
+
+impl Descriptor {
+    fn is_writable(&self) -> bool {
+        self.flags & VIRTQ_DESC_F_WRITE != 0
+    }
+}
+
+/// Split virtqueue in guest memory as configured by the driver
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Virtqueue {
+    size: u32,
+    ready: bool,
+    descriptors: u64,
+    /// Available ring written by the driver
+    driver: u64,
+    /// Used ring written by the device
+    device: u64,
+    last_available: u16,
+    used: u16,
+}
+
+/// Memory access of the device as bus master
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Access {
+    /// Double word at the address, its bytes are appended to the read buffer
+    Read(u64),
+    /// address, data, size
+    Write(u64, u64, u64),
+}
+
+/// Step of processing the available ring, each waits for the reads issued by the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Phase {
+    Idle,
+    AvailableIndex,
+    AvailableEntry,
+    Descriptor,
+    /// Reading the device-readable buffers: the request header and the data of writes
+    ReadableBuffers,
+    /// Writing the data of reads, the status and the used ring entry
+    Complete,
+}
+
+/// Virtio block device on the MMIO transport with a single request queue.
+/// Requests are processed as bus master: descriptors, headers and data are read from and written to guest memory
+/// one access per transaction, with the bus released after each step of a request.
+/// Indirect descriptors and event index suppression are not supported.
+#[derive(Debug)]
+pub struct VirtioBlock {
+    name: String,
+    base: u64,
+    storage: Box<dyn BlockStorage>,
+    device_features_sel: u32,
+    driver_features: u64,
+    driver_features_sel: u32,
+    queue_sel: u32,
+    queue: Virtqueue,
+    status: u32,
+    interrupt_status: u32,
+    phase: Phase,
+    accesses: VecDeque<Access>,
+    /// The bus was granted for the queued accesses
+    owned: bool,
+    /// The front access is on the bus
+    active: bool,
+    /// The access on the bus was issued before a reset, its result is dropped
+    stale: bool,
+    read_buffer: Vec<u8>,
+    available_index: u16,
+    head: u16,
+    chain: Vec<Descriptor>,
+    completed: u64,
+}
+
+impl VirtioBlock {
+    pub fn new(name: &str, base: u64, storage: impl BlockStorage) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            storage: Box::new(storage),
+            device_features_sel: 0,
+            driver_features: 0,
+            driver_features_sel: 0,
+            queue_sel: 0,
+            queue: Virtqueue::default(),
+            status: 0,
+            interrupt_status: 0,
+            phase: Phase::Idle,
+            accesses: VecDeque::new(),
+            owned: false,
+            active: false,
+            stale: false,
+            read_buffer: Vec::new(),
+            available_index: 0,
+            head: 0,
+            chain: Vec::new(),
+            completed: 0,
+        }
+    }
+
+    /// The backing store by its concrete type
+    #[cfg(test)]
+    pub fn get_storage<T: BlockStorage>(&self) -> Option<&T> {
+        (self.storage.as_ref() as &dyn Any).downcast_ref()
+    }
+
+    /// Requests put into the used ring since the device was created
+    #[cfg(test)]
+    pub fn get_completed(&self) -> u64 {
+        self.completed
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.phase != Phase::Idle
+    }
+
+    fn owner(&self) -> BusOwner {
+        BusOwner::Device(self.base)
+    }
+
+    fn device_features(&self) -> u64 {
+        let read_only = self.storage.is_read_only() as u64 * VIRTIO_BLK_F_RO;
+        VIRTIO_F_VERSION_1 | VIRTIO_BLK_F_FLUSH | read_only
+    }
+
+    /// Longest chain a request may use: the header, data up to the whole disk or the id, and the status.
+    /// Bounds what a request makes the device read from the guest and hold in host memory.
+    fn max_request_length(&self) -> u64 {
+        let data = self.storage.size().max(VIRTIO_BLK_ID_SIZE as u64);
+        VIRTIO_BLK_HEADER_SIZE as u64 + data + 1
+    }
+
+    fn config(&self) -> [u8; 8] {
+        (self.storage.size() / VIRTIO_BLK_SECTOR_SIZE).to_le_bytes()
+    }
+
+    fn read_register(&self, offset: u64) -> u32 {
+        let select_half = |value: u64, select: u32| match select {
+            0 => value as u32,
+            1 => (value >> 32) as u32,
+            _ => 0,
+        };
+        match offset {
+            VIRTIO_MMIO_MAGIC_VALUE => VIRTIO_MAGIC,
+            VIRTIO_MMIO_VERSION => 2,
+            VIRTIO_MMIO_DEVICE_ID => VIRTIO_DEVICE_BLOCK,
+            // "QEMU", which drivers accept
+            VIRTIO_MMIO_VENDOR_ID => 0x554D_4551,
+            VIRTIO_MMIO_DEVICE_FEATURES => {
+                select_half(self.device_features(), self.device_features_sel)
+            }
+            VIRTIO_MMIO_QUEUE_NUM_MAX if self.queue_sel == 0 => VIRTIO_QUEUE_SIZE,
+            VIRTIO_MMIO_QUEUE_NUM if self.queue_sel == 0 => self.queue.size,
+            VIRTIO_MMIO_QUEUE_READY if self.queue_sel == 0 => self.queue.ready as u32,
+            VIRTIO_MMIO_INTERRUPT_STATUS => self.interrupt_status,
+            VIRTIO_MMIO_STATUS => self.status,
+            VIRTIO_MMIO_QUEUE_DESC_LOW => self.queue.descriptors as u32,
+            VIRTIO_MMIO_QUEUE_DESC_HIGH => (self.queue.descriptors >> 32) as u32,
+            VIRTIO_MMIO_QUEUE_DRIVER_LOW => self.queue.driver as u32,
+            VIRTIO_MMIO_QUEUE_DRIVER_HIGH => (self.queue.driver >> 32) as u32,
+            VIRTIO_MMIO_QUEUE_DEVICE_LOW => self.queue.device as u32,
+            VIRTIO_MMIO_QUEUE_DEVICE_HIGH => (self.queue.device >> 32) as u32,
+            // The configuration never changes
+            VIRTIO_MMIO_CONFIG_GENERATION => 0,
+            VIRTIO_MMIO_CONFIG.. => {
+                let config = self.config();
+                (0..4).fold(0, |value, byte| {
+                    let index = (offset - VIRTIO_MMIO_CONFIG + byte) as usize;
+                    value | (config.get(index).copied().unwrap_or(0) as u32) << (8 * byte)
+                })
+            }
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u32) {
+        let set_half = |register: &mut u64, high: bool| {
+            let shift = if high { 32 } else { 0 };
+            *register = *register & !(0xFFFF_FFFF << shift) | (value as u64) << shift;
+        };
+        match offset {
+            VIRTIO_MMIO_DEVICE_FEATURES_SEL => self.device_features_sel = value,
+            VIRTIO_MMIO_DRIVER_FEATURES => match self.driver_features_sel {
+                0 => set_half(&mut self.driver_features, false),
+                1 => set_half(&mut self.driver_features, true),
+                _ => {}
+            },
+            VIRTIO_MMIO_DRIVER_FEATURES_SEL => self.driver_features_sel = value,
+            VIRTIO_MMIO_QUEUE_SEL => self.queue_sel = value,
+            // Only queue 0 exists
+            VIRTIO_MMIO_QUEUE_NUM
+            | VIRTIO_MMIO_QUEUE_READY
+            | VIRTIO_MMIO_QUEUE_DESC_LOW..=VIRTIO_MMIO_QUEUE_DEVICE_HIGH
+                if self.queue_sel != 0 => {}
+            // The queue can't change under a request
+            VIRTIO_MMIO_QUEUE_NUM
+            | VIRTIO_MMIO_QUEUE_READY
+            | VIRTIO_MMIO_QUEUE_DESC_LOW..=VIRTIO_MMIO_QUEUE_DEVICE_HIGH
+                if self.is_busy() =>
+            {
+                debug!(target: "virtio", "{}: queue write at {offset:#x} while busy ignored", self.name);
+            }
+            VIRTIO_MMIO_QUEUE_NUM => self.queue.size = value.min(VIRTIO_QUEUE_SIZE),
+            VIRTIO_MMIO_QUEUE_READY => self.queue.ready = value & 1 != 0,
+            VIRTIO_MMIO_QUEUE_NOTIFY => self.notify(value),
+            VIRTIO_MMIO_INTERRUPT_ACK => self.interrupt_status &= !value,
+            VIRTIO_MMIO_STATUS if value == 0 => self.reset_device(),
+            VIRTIO_MMIO_STATUS => self.status = value,
+            VIRTIO_MMIO_QUEUE_DESC_LOW => set_half(&mut self.queue.descriptors, false),
+            VIRTIO_MMIO_QUEUE_DESC_HIGH => set_half(&mut self.queue.descriptors, true),
+            VIRTIO_MMIO_QUEUE_DRIVER_LOW => set_half(&mut self.queue.driver, false),
+            VIRTIO_MMIO_QUEUE_DRIVER_HIGH => set_half(&mut self.queue.driver, true),
+            VIRTIO_MMIO_QUEUE_DEVICE_LOW => set_half(&mut self.queue.device, false),
+            VIRTIO_MMIO_QUEUE_DEVICE_HIGH => set_half(&mut self.queue.device, true),
+            _ => {}
+        }
+    }
+
+    /// Writing 0 to the status register, the queue is forgotten and no request is processed further
+    fn reset_device(&mut self) {
+        debug!(target: "virtio", "{}: reset", self.name);
+        self.driver_features = 0;
+        self.queue = Virtqueue::default();
+        self.status = 0;
+        self.interrupt_status = 0;
+        self.phase = Phase::Idle;
+        // An access on the bus is finished before the bus is released
+        self.accesses.truncate(self.active as usize);
+        self.stale = self.active;
+        self.read_buffer.clear();
+    }
+
+    fn notify(&mut self, queue: u32) {
+        let driver_ok = self.status & VIRTIO_STATUS_DRIVER_OK != 0;
+        if queue != 0 || !driver_ok || !self.queue.ready || self.queue.size == 0 {
+            debug!(target: "virtio", "{}: notify of queue {queue} ignored", self.name);
+            return;
+        }
+        // A running pass reads the available index again before it goes idle
+        if self.phase == Phase::Idle {
+            self.read_available_index();
+        }
+    }
+
+    fn read_available_index(&mut self) {
+        self.phase = Phase::AvailableIndex;
+        self.accesses.push_back(Access::Read(self.queue.driver + 2));
+    }
+
+    fn read_descriptor(&mut self, index: u16) {
+        let address = self.queue.descriptors + 16 * index as u64;
+        self.phase = Phase::Descriptor;
+        self.accesses
+            .extend([Access::Read(address), Access::Read(address + 8)]);
+    }
+
+    /// Little endian value of the given size from the next double word of the read buffer
+    fn take_read(&mut self, size: usize) -> u64 {
+        let bytes = self.read_buffer.drain(..8).take(size);
+        bytes
+            .rev()
+            .fold(0, |value, byte| (value << 8) | byte as u64)
+    }
+
+    /// Moves on once the accesses of the current phase are done
+    fn advance(&mut self) {
+        match self.phase {
+            Phase::Idle => {}
+            Phase::AvailableIndex => {
+                self.available_index = self.take_read(2) as u16;
+                if self.available_index == self.queue.last_available {
+                    self.phase = Phase::Idle;
+                    return;
+                }
+                let slot = self.queue.last_available as u64 % self.queue.size as u64;
+                self.phase = Phase::AvailableEntry;
+                self.accesses
+                    .push_back(Access::Read(self.queue.driver + 4 + 2 * slot));
+            }
+            Phase::AvailableEntry => {
+                self.head = self.take_read(2) as u16;
+                self.chain.clear();
+                self.read_descriptor(self.head);
+            }
+            Phase::Descriptor => {
+                let address = self.take_read(8);
+                let rest = self.take_read(8);
+                let descriptor = Descriptor {
+                    address,
+                    length: rest as u32,
+                    flags: (rest >> 32) as u16,
+                    next: (rest >> 48) as u16,
+                };
+                self.chain.push(descriptor);
+                let next = descriptor.flags & VIRTQ_DESC_F_NEXT != 0;
+                if next && self.chain.len() < self.queue.size as usize {
+                    self.read_descriptor(descriptor.next);
+                    return;
+                }
+                // A chain longer than the queue loops
+                if next || !self.is_valid_chain() {
+                    debug!(target: "virtio", "{}: malformed chain {:?}", self.name, self.chain);
+                    self.fail_request();
+                    return;
+                }
+                self.phase = Phase::ReadableBuffers;
+                for descriptor in self.chain.iter().filter(|d| !d.is_writable()) {
+                    let reads = (descriptor.length as u64).div_ceil(8);
+                    self.accesses
+                        .extend((0..reads).map(|read| Access::Read(descriptor.address + 8 * read)));
+                }
+                if self.accesses.is_empty() {
+                    self.advance();
+                }
+            }
+            Phase::ReadableBuffers => {
+                let mut readable = Vec::new();
+                let mut buffer = std::mem::take(&mut self.read_buffer);
+                for descriptor in self.chain.iter().filter(|d| !d.is_writable()) {
+                    let reads = (descriptor.length as usize).div_ceil(8);
+                    let mut bytes: Vec<u8> = buffer.drain(..8 * reads).collect();
+                    bytes.truncate(descriptor.length as usize);
+                    readable.extend(bytes);
+                }
+                self.process_request(&readable);
+            }
+            Phase::Complete => {
+                self.queue.last_available = self.queue.last_available.wrapping_add(1);
+                self.queue.used = self.queue.used.wrapping_add(1);
+                self.completed += 1;
+                self.interrupt_status |= VIRTIO_INTERRUPT_USED_RING;
+                self.read_available_index();
+            }
+        }
+    }
+
+    /// The buffers fit a request and the status buffer, if any, has room for the status
+    fn is_valid_chain(&self) -> bool {
+        let length: u64 = self.chain.iter().map(|d| d.length as u64).sum();
+        let status = self.chain.iter().rev().find(|d| d.is_writable());
+        length <= self.max_request_length() && status.is_none_or(|d| d.length > 0)
+    }
+
+    /// Answers a malformed request with an I/O error, without reading or writing its data
+    fn fail_request(&mut self) {
+        let status = self.chain.iter().rev().find(|d| d.is_writable()).copied();
+        match status {
+            Some(descriptor) if descriptor.length > 0 => {
+                let address = descriptor.address + descriptor.length as u64 - 1;
+                self.queue_write(address, &[VIRTIO_BLK_S_IOERR]);
+                self.complete(1);
+            }
+            _ => self.complete(0),
+        }
+    }
+
+    /// Executes the request against the storage and queues the writes completing it
+    fn process_request(&mut self, readable: &[u8]) {
+        let writable: Vec<Descriptor> = self
+            .chain
+            .iter()
+            .copied()
+            .filter(Descriptor::is_writable)
+            .collect();
+        // The last writable byte is the status
+        let Some((status_descriptor, data_descriptors)) = writable.split_last() else {
+            debug!(target: "virtio", "{}: request without status buffer", self.name);
+            self.complete(0);
+            return;
+        };
+        let capacity: usize = data_descriptors.iter().map(|d| d.length as usize).sum();
+        let (status, data) = match readable.get(..VIRTIO_BLK_HEADER_SIZE) {
+            Some(header) => {
+                let kind = u32::from_le_bytes(header[..4].try_into().unwrap());
+                let sector = u64::from_le_bytes(header[8..].try_into().unwrap());
+                self.execute(kind, sector, &readable[VIRTIO_BLK_HEADER_SIZE..], capacity)
+            }
+            None => (VIRTIO_BLK_S_IOERR, Vec::new()),
+        };
+
+        let mut remaining = data.as_slice();
+        let mut written = 0;
+        for descriptor in data_descriptors {
+            let length = remaining.len().min(descriptor.length as usize);
+            let (chunk, rest) = remaining.split_at(length);
+            self.queue_write(descriptor.address, chunk);
+            remaining = rest;
+            written += length;
+        }
+        let status_address = status_descriptor.address + status_descriptor.length as u64 - 1;
+        self.queue_write(status_address, &[status]);
+        self.complete(written as u32 + 1);
+    }
+
+    /// Status and data of a request of the given type, the data is written to the device-writable buffers
+    fn execute(&mut self, kind: u32, sector: u64, data: &[u8], capacity: usize) -> (u8, Vec<u8>) {
+        let offset = sector.wrapping_mul(VIRTIO_BLK_SECTOR_SIZE);
+        debug!(target: "virtio", "{}: request {kind} at sector {sector}", self.name);
+        let result = match kind {
+            VIRTIO_BLK_T_IN => {
+                let mut buffer = vec![0; capacity];
+                self.storage.read_at(offset, &mut buffer).map(|_| buffer)
+            }
+            VIRTIO_BLK_T_OUT if self.storage.is_read_only() => {
+                return (VIRTIO_BLK_S_IOERR, Vec::new());
+            }
+            VIRTIO_BLK_T_OUT => self.storage.write_at(offset, data).map(|_| Vec::new()),
+            VIRTIO_BLK_T_FLUSH => self.storage.flush().map(|_| Vec::new()),
+            VIRTIO_BLK_T_GET_ID => {
+                let mut id = self.name.as_bytes().to_vec();
+                id.resize(VIRTIO_BLK_ID_SIZE, 0);
+                Ok(id)
+            }
+            _ => return (VIRTIO_BLK_S_UNSUPP, Vec::new()),
+        };
+        match result {
+            Ok(data) => (VIRTIO_BLK_S_OK, data),
+            Err(error) => {
+                debug!(target: "virtio", "{}: request {kind} failed: {error}", self.name);
+                (VIRTIO_BLK_S_IOERR, Vec::new())
+            }
+        }
+    }
+
+    /// Queues the used ring entry of the current request after its writes
+    fn complete(&mut self, length: u32) {
+        let slot = self.queue.used as u64 % self.queue.size as u64;
+        let entry = self.queue.device + 4 + 8 * slot;
+        self.phase = Phase::Complete;
+        self.accesses.extend([
+            Access::Write(entry, self.head as u64, 4),
+            Access::Write(entry + 4, length as u64, 4),
+            Access::Write(
+                self.queue.device + 2,
+                self.queue.used.wrapping_add(1) as u64,
+                2,
+            ),
+        ]);
+    }
+
+    /// Splits the bytes into naturally aligned writes
+    fn queue_write(&mut self, mut address: u64, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let size = [8, 4, 2, 1]
+                .into_iter()
+                .find(|size| address.is_multiple_of(*size) && *size <= bytes.len() as u64)
+                .unwrap();
+            let (chunk, rest) = bytes.split_at(size as usize);
+            let data = chunk
+                .iter()
+                .rev()
+                .fold(0, |value, byte| (value << 8) | *byte as u64);
+            self.accesses.push_back(Access::Write(address, data, size));
+            address += size;
+            bytes = rest;
+        }
+    }
+
+    fn start_access(&mut self, access: Access, bus: &mut Bus) {
+        let owner = self.owner();
+        match access {
+            Access::Read(address) => {
+                bus.put_address(Address::new(address), owner);
+                bus.put_status(BusStatus::Read, owner);
+            }
+            Access::Write(address, data, size) => {
+                bus.put_address(Address::new(address), owner);
+                bus.put_data(data, owner);
+                bus.put_status(BusStatus::write_of_size(size), owner);
+            }
+        }
+        self.active = true;
+    }
+}
+
+impl BusDevice for VirtioBlock {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (VIRTIO_MMIO_SIZE - 1)
+    }
+
+    /// Registers are words, double word writes set two consecutive registers
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_dw(bus.get_address().value())),
+            status => {
+                let data = bus.get_data();
+                match status.write_size() {
+                    Some(8) => {
+                        self.write_register(offset, data as u32);
+                        self.write_register(offset + 4, (data >> 32) as u32);
+                    }
+                    Some(_) => self.write_register(offset, data as u32),
+                    None => {}
+                }
+            }
+        }
+    }
+
+    fn master_tick(&mut self, bus: &mut Bus) {
+        let Some(access) = self.accesses.front().copied() else {
+            return;
+        };
+        let owner = self.owner();
+        if !self.owned {
+            if !bus.take_ownership(owner) {
+                return;
+            }
+            self.owned = true;
+        }
+        if !self.active {
+            self.start_access(access, bus);
+            return;
+        }
+        if !bus.is_ready() {
+            return;
+        }
+
+        let response = bus.get_response();
+        self.active = false;
+        self.accesses.pop_front();
+        // The result of an access left over from before a reset is dropped
+        let stale = std::mem::take(&mut self.stale);
+        if let BusResponse::Error(error) = response {
+            debug!(target: "virtio", "{}: bus error {error:?} at {access:?}", self.name);
+            bus.release_ownership(owner);
+            self.owned = false;
+            self.accesses.clear();
+            self.read_buffer.clear();
+            self.phase = Phase::Idle;
+            self.status |= VIRTIO_STATUS_NEEDS_RESET;
+            return;
+        }
+        if let Access::Read(_) = access
+            && !stale
+        {
+            self.read_buffer.extend(bus.get_data().to_le_bytes());
+        }
+        if self.accesses.is_empty() {
+            bus.release_ownership(owner);
+            self.owned = false;
+            self.advance();
+        } else {
+            // The next access starts on the following tick, so it is not mistaken for this one
+            bus.put_status(BusStatus::Idle, owner);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.reset_device();
+        self.accesses.clear();
+        self.owned = false;
+        self.active = false;
+        self.stale = false;
+        self.completed = 0;
+    }
+
+    fn has_interrupt_line(&self) -> bool {
+        true
+    }
+
+    fn interrupt_pending(&self) -> bool {
+        self.interrupt_status != 0
+    }
+
+    fn device_tree_node(&self) -> Option<DeviceTreeNode> {
+        let node = DeviceTreeNode::new("virtio_mmio", &["virtio,mmio"], self.address_range());
+        Some(node)
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = (address - self.base) & !0b11;
+        self.read_register(offset) as u64 | (self.read_register(offset + 4) as u64) << 32
+    }
+}
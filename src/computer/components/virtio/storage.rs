@@ -0,0 +1,141 @@
+use std::any::Any;
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::Path;
+
+/// Backing store of a block device, addressed in bytes
+pub trait BlockStorage: Any + Debug {
+    /// Size in bytes
+    fn size(&self) -> u64;
+
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<()>;
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()>;
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        false
+    }
+}
+
+fn check_range(storage: &impl BlockStorage, offset: u64, length: usize) -> Result<()> {
+    match offset.checked_add(length as u64) {
+        Some(end) if end <= storage.size() => Ok(()),
+        _ => Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "Access beyond the end of the image",
+        )),
+    }
+}
+
+/// Image held in host memory, changes are lost with it
+#[cfg(test)]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MemoryImage {
+    data: Vec<u8>,
+    read_only: bool,
+}
+
+#[cfg(test)]
+impl MemoryImage {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            read_only: false,
+        }
+    }
+
+    pub fn read_only(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            read_only: true,
+        }
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+impl BlockStorage for MemoryImage {
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<()> {
+        check_range(self, offset, buffer.len())?;
+        let offset = offset as usize;
+        buffer.copy_from_slice(&self.data[offset..offset + buffer.len()]);
+        Ok(())
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        check_range(self, offset, data.len())?;
+        let offset = offset as usize;
+        self.data[offset..offset + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
+
+/// Image file on the host, written in place
+#[derive(Debug)]
+pub struct FileImage {
+    file: File,
+    size: u64,
+    read_only: bool,
+}
+
+impl FileImage {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Self::with_file(file, false)
+    }
+
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        Self::with_file(File::open(path)?, true)
+    }
+
+    fn with_file(file: File, read_only: bool) -> Result<Self> {
+        let size = file.metadata()?.len();
+        Ok(Self {
+            file,
+            size,
+            read_only,
+        })
+    }
+}
+
+impl BlockStorage for FileImage {
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read_at(&mut self, offset: u64, buffer: &mut [u8]) -> Result<()> {
+        check_range(self, offset, buffer.len())?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(buffer)
+    }
+
+    fn write_at(&mut self, offset: u64, data: &[u8]) -> Result<()> {
+        check_range(self, offset, data.len())?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.file.sync_data()
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+}
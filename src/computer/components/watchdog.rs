@@ -0,0 +1,276 @@
+use crate::computer::address::BOOT_ROM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::csr::MCAUSE_INTERRUPT;
+use crate::computer::components::device::{BusDevice, MachineRequest};
+use log::debug;
+use std::ops::RangeInclusive;
+
+// Register offsets, a double word each
+/// Control word, see the control bits
+pub const WATCHDOG_CONTROL: u64 = 0x00;
+/// Writing the kick key restarts the countdown, writing anything else while enabled expires the watchdog.
+/// Reads as 0.
+pub const WATCHDOG_KICK: u64 = 0x08;
+/// Ticks without a kick until the watchdog expires
+pub const WATCHDOG_TIMEOUT: u64 = 0x10;
+/// Ticks since the last kick
+pub const WATCHDOG_COUNT: u64 = 0x18;
+/// Sticky reasons of past expiries, kept across the resets the watchdog causes, writing 1 clears them
+pub const WATCHDOG_STATUS: u64 = 0x20;
+pub const WATCHDOG_SIZE: u64 = 0x1000;
+
+// Control bits
+pub const WATCHDOG_CONTROL_ENABLE: u32 = 1 << 0;
+/// Resets the machine on expiry instead of raising a non-maskable interrupt
+pub const WATCHDOG_CONTROL_RESET: u32 = 1 << 1;
+/// Ignores writes to the control and timeout registers until the next reset
+pub const WATCHDOG_CONTROL_LOCK: u32 = 1 << 2;
+
+// Status bits
+pub const WATCHDOG_STATUS_TIMEOUT: u32 = 1 << 0;
+pub const WATCHDOG_STATUS_INVALID_KICK: u32 = 1 << 1;
+
+/// Key of the SiFive watchdog, chosen so stray writes do not kick by accident
+pub const WATCHDOG_KICK_KEY: u32 = 0x0051_F15E;
+/// Value of mcause for the watchdog's interrupt, a platform interrupt code above the standard ones
+pub const WATCHDOG_NMI_CAUSE: u64 = MCAUSE_INTERRUPT | 16;
+
+/// What happens when the watchdog expires
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchdogAction {
+    /// Non-maskable interrupt of every hart
+    Nmi,
+    /// Reset of the whole machine
+    Reset,
+}
+
+/// Why the watchdog expired
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchdogReason {
+    /// The guest did not kick in time
+    Timeout,
+    /// The guest wrote a wrong key to the kick register
+    InvalidKick,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchdogExpiry {
+    pub reason: WatchdogReason,
+    pub action: WatchdogAction,
+    /// Ticks of the device since the last reset of the machine
+    pub tick: u64,
+}
+
+/// Reset state of the watchdog, the host arms it for guests which do not do it themselves
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    /// Ticks without a kick until the watchdog expires
+    pub timeout: u64,
+    pub action: WatchdogAction,
+    /// Keeps the guest from disabling or reconfiguring the watchdog
+    pub locked: bool,
+    /// Address the harts continue at on a non-maskable interrupt
+    pub nmi_vector: u64,
+}
+
+impl Default for WatchdogConfig {
+    /// Disabled until the guest enables it, a million ticks, interrupting into the boot rom
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout: 1_000_000,
+            action: WatchdogAction::Nmi,
+            locked: false,
+            nmi_vector: BOOT_ROM_START,
+        }
+    }
+}
+
+/// Watchdog timer the guest has to kick periodically, e.g. to catch programs looping forever.
+/// On expiry it interrupts the harts or resets the machine and restarts its countdown.
+/// Expiries are recorded for the host and in the status register, both survive resets.
+#[derive(Debug)]
+pub struct Watchdog {
+    name: String,
+    base: u64,
+    config: WatchdogConfig,
+    control: u32,
+    timeout: u64,
+    count: u64,
+    ticks: u64,
+    status: u32,
+    expiries: Vec<WatchdogExpiry>,
+    request: Option<MachineRequest>,
+}
+
+impl Watchdog {
+    pub fn new(name: &str, base: u64, config: WatchdogConfig) -> Self {
+        let mut watchdog = Self {
+            name: name.to_string(),
+            base,
+            config,
+            control: 0,
+            timeout: 0,
+            count: 0,
+            ticks: 0,
+            status: 0,
+            expiries: Vec::new(),
+            request: None,
+        };
+        watchdog.reset();
+        watchdog
+    }
+
+    #[cfg(test)]
+    pub fn get_config(&self) -> WatchdogConfig {
+        self.config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.control & WATCHDOG_CONTROL_ENABLE != 0
+    }
+
+    /// Ticks since the last kick
+    #[cfg(test)]
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    /// Every expiry since the device was created, oldest first
+    pub fn get_expiries(&self) -> &[WatchdogExpiry] {
+        &self.expiries
+    }
+
+    #[cfg(test)]
+    pub fn get_last_expiry(&self) -> Option<WatchdogExpiry> {
+        self.expiries.last().copied()
+    }
+
+    fn action(&self) -> WatchdogAction {
+        match self.control & WATCHDOG_CONTROL_RESET {
+            0 => WatchdogAction::Nmi,
+            _ => WatchdogAction::Reset,
+        }
+    }
+
+    fn expire(&mut self, reason: WatchdogReason) {
+        let expiry = WatchdogExpiry {
+            reason,
+            action: self.action(),
+            tick: self.ticks,
+        };
+        debug!(target: "watchdog", "{}: {expiry:?}", self.name);
+        self.status |= match reason {
+            WatchdogReason::Timeout => WATCHDOG_STATUS_TIMEOUT,
+            WatchdogReason::InvalidKick => WATCHDOG_STATUS_INVALID_KICK,
+        };
+        self.expiries.push(expiry);
+        self.count = 0;
+        self.request = Some(match expiry.action {
+            WatchdogAction::Nmi => MachineRequest::Nmi {
+                vector: self.config.nmi_vector,
+                cause: WATCHDOG_NMI_CAUSE,
+            },
+            WatchdogAction::Reset => MachineRequest::Reset,
+        });
+    }
+
+    fn kick(&mut self, key: u32) {
+        if key == WATCHDOG_KICK_KEY {
+            self.count = 0;
+        } else if self.is_enabled() {
+            self.expire(WatchdogReason::InvalidKick);
+        }
+    }
+
+    fn is_locked(&self) -> bool {
+        self.control & WATCHDOG_CONTROL_LOCK != 0
+    }
+
+    fn read_register(&self, offset: u64) -> u64 {
+        match offset {
+            WATCHDOG_CONTROL => self.control as u64,
+            WATCHDOG_TIMEOUT => self.timeout,
+            WATCHDOG_COUNT => self.count,
+            WATCHDOG_STATUS => self.status as u64,
+            _ => 0,
+        }
+    }
+
+    fn write_register(&mut self, offset: u64, value: u64, size: u64) {
+        match offset {
+            WATCHDOG_CONTROL if !self.is_locked() => {
+                self.control = value as u32;
+                self.count = 0;
+            }
+            WATCHDOG_KICK => self.kick(value as u32),
+            WATCHDOG_TIMEOUT if !self.is_locked() && size == 8 => self.timeout = value,
+            WATCHDOG_TIMEOUT if !self.is_locked() => {
+                self.timeout = self.timeout & !0xFFFF_FFFF | value & 0xFFFF_FFFF
+            }
+            offset if offset == WATCHDOG_TIMEOUT + 4 && !self.is_locked() => {
+                self.timeout = self.timeout & 0xFFFF_FFFF | value << 32
+            }
+            WATCHDOG_STATUS => self.status &= !(value as u32),
+            _ => {}
+        }
+    }
+}
+
+impl BusDevice for Watchdog {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        self.base..=self.base + (WATCHDOG_SIZE - 1)
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let offset = bus.get_address().value() - self.base;
+        match bus.get_status() {
+            BusStatus::Read => bus.force_put_data(self.read_dw(bus.get_address().value())),
+            status => {
+                let data = bus.get_data();
+                let size = status.write_size().unwrap_or(1);
+                self.write_register(offset, data, size);
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+        if !self.is_enabled() {
+            return;
+        }
+        self.count += 1;
+        if self.count >= self.timeout {
+            self.expire(WatchdogReason::Timeout);
+        }
+    }
+
+    /// Returns to the configured state, the recorded expiries are kept
+    fn reset(&mut self) {
+        let config = self.config;
+        self.control = (config.enabled as u32 * WATCHDOG_CONTROL_ENABLE)
+            | ((config.action == WatchdogAction::Reset) as u32 * WATCHDOG_CONTROL_RESET)
+            | (config.locked as u32 * WATCHDOG_CONTROL_LOCK);
+        self.timeout = config.timeout;
+        self.count = 0;
+        self.ticks = 0;
+        self.request = None;
+    }
+
+    fn take_machine_request(&mut self) -> Option<MachineRequest> {
+        self.request.take()
+    }
+
+    /// Registers are laid out in double words, smaller reads return the addressed part
+    fn read_dw(&self, address: u64) -> u64 {
+        let offset = address - self.base;
+        let register = offset & !0b111;
+        self.read_register(register) >> (8 * (offset - register))
+    }
+}
@@ -0,0 +1,305 @@
+use crate::computer::components::plic::Plic;
+use crate::computer::memory_map::RegionKind;
+use crate::computer::Computer;
+use std::any::Any;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+pub const FDT_MAGIC: u32 = 0xD00D_FEED;
+pub const FDT_VERSION: u32 = 17;
+/// Oldest version the blobs are compatible with
+const FDT_LAST_COMPATIBLE_VERSION: u32 = 16;
+const FDT_HEADER_SIZE: usize = 40;
+/// A single empty entry terminating the memory reservation block
+const FDT_RESERVATION_BLOCK_SIZE: usize = 16;
+
+// Tokens of the structure block
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_END: u32 = 9;
+
+/// ISA the harts implement: Zicsr and LR/SC without the other atomics.
+/// The integer instructions are only a subset of RV64I with saturating ADD and SUB, so no base ISA is claimed
+/// and firmware built for RV64I can not rely on the harts.
+pub const ISA: &str = "rv64_zicsr_zalrsc";
+const ISA_EXTENSIONS: [&str; 2] = ["zicsr", "zalrsc"];
+/// Frequency of the CLINT's timer the device tree reports, the timer counts ticks which have no real duration
+pub const TIMEBASE_FREQUENCY: u32 = 10_000_000;
+
+/// Phandle of the interrupt controller of the hart, which devices wired to the hart refer to
+pub fn cpu_interrupt_controller_phandle(hart: usize) -> u32 {
+    hart as u32 + 1
+}
+
+/// Phandle of the PLIC, above the ones of the harts' interrupt controllers
+pub const PLIC_PHANDLE: u32 = 0x1_0000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyValue {
+    Empty,
+    /// Big endian 32 bit cells
+    Cells(Vec<u32>),
+    /// Null terminated strings
+    Strings(Vec<String>),
+}
+
+impl PropertyValue {
+    pub fn string(value: &str) -> Self {
+        PropertyValue::Strings(vec![value.to_string()])
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            PropertyValue::Empty => Vec::new(),
+            PropertyValue::Cells(cells) => {
+                cells.iter().flat_map(|cell| cell.to_be_bytes()).collect()
+            }
+            PropertyValue::Strings(strings) => strings
+                .iter()
+                .flat_map(|string| string.bytes().chain([0]))
+                .collect(),
+        }
+    }
+}
+
+/// Node a device contributes below `/soc`, named after its first register range like `serial@10000000`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceTreeNode {
+    pub name: String,
+    pub compatible: Vec<String>,
+    /// Address ranges of the registers, a device's whole address range unless only a part is meant
+    pub reg: Vec<RangeInclusive<u64>>,
+    pub properties: Vec<(String, PropertyValue)>,
+}
+
+impl DeviceTreeNode {
+    pub fn new(name: &str, compatible: &[&str], reg: RangeInclusive<u64>) -> Self {
+        Self {
+            name: name.to_string(),
+            compatible: compatible.iter().map(|value| value.to_string()).collect(),
+            reg: vec![reg],
+            properties: Vec::new(),
+        }
+    }
+
+    pub fn with_property(mut self, name: &str, value: PropertyValue) -> Self {
+        self.properties.push((name.to_string(), value));
+        self
+    }
+
+    fn unit_name(&self) -> String {
+        match self.reg.first() {
+            Some(reg) => format!("{}@{:x}", self.name, reg.start()),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Writes a flattened device tree blob, version 17.
+/// Nodes are opened and closed in order, their properties have to come before their children.
+#[derive(Debug, Default)]
+pub struct FdtWriter {
+    structure: Vec<u8>,
+    strings: Vec<u8>,
+    string_offsets: HashMap<String, u32>,
+    depth: usize,
+}
+
+impl FdtWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The root node has the empty name
+    pub fn begin_node(&mut self, name: &str) {
+        self.token(FDT_BEGIN_NODE);
+        self.structure.extend(name.as_bytes());
+        self.structure.push(0);
+        self.align();
+        self.depth += 1;
+    }
+
+    pub fn end_node(&mut self) {
+        assert!(self.depth > 0, "No open node to end");
+        self.token(FDT_END_NODE);
+        self.depth -= 1;
+    }
+
+    pub fn property(&mut self, name: &str, value: &PropertyValue) {
+        let value = value.encode();
+        let name_offset = self.string_offset(name);
+        self.token(FDT_PROP);
+        self.token(value.len() as u32);
+        self.token(name_offset);
+        self.structure.extend(value);
+        self.align();
+    }
+
+    pub fn property_u32(&mut self, name: &str, value: u32) {
+        self.property(name, &PropertyValue::Cells(vec![value]));
+    }
+
+    pub fn property_string(&mut self, name: &str, value: &str) {
+        self.property(name, &PropertyValue::string(value));
+    }
+
+    /// Address and size pairs of two cells each
+    pub fn property_reg(&mut self, ranges: &[RangeInclusive<u64>]) {
+        let cells = ranges
+            .iter()
+            .flat_map(|range| {
+                let size = range.end() - range.start() + 1;
+                [*range.start(), size]
+            })
+            .flat_map(|value| [(value >> 32) as u32, value as u32])
+            .collect();
+        self.property("reg", &PropertyValue::Cells(cells));
+    }
+
+    /// Header, an empty memory reservation block, the structure and the strings
+    pub fn finish(mut self, boot_hart: u32) -> Vec<u8> {
+        assert_eq!(self.depth, 0, "Nodes left open");
+        self.token(FDT_END);
+        let structure_offset = FDT_HEADER_SIZE + FDT_RESERVATION_BLOCK_SIZE;
+        let strings_offset = structure_offset + self.structure.len();
+        let total_size = strings_offset + self.strings.len();
+        let header = [
+            FDT_MAGIC,
+            total_size as u32,
+            structure_offset as u32,
+            strings_offset as u32,
+            FDT_HEADER_SIZE as u32,
+            FDT_VERSION,
+            FDT_LAST_COMPATIBLE_VERSION,
+            boot_hart,
+            self.strings.len() as u32,
+            self.structure.len() as u32,
+        ];
+        let mut blob: Vec<u8> = header
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        blob.extend([0; FDT_RESERVATION_BLOCK_SIZE]);
+        blob.extend(self.structure);
+        blob.extend(self.strings);
+        blob
+    }
+
+    fn token(&mut self, value: u32) {
+        self.structure.extend(value.to_be_bytes());
+    }
+
+    fn align(&mut self) {
+        let padding = self.structure.len().next_multiple_of(4) - self.structure.len();
+        self.structure.extend(std::iter::repeat_n(0, padding));
+    }
+
+    /// Property names are stored once in the strings block
+    fn string_offset(&mut self, name: &str) -> u32 {
+        if let Some(offset) = self.string_offsets.get(name) {
+            return *offset;
+        }
+        let offset = self.strings.len() as u32;
+        self.strings.extend(name.as_bytes());
+        self.strings.push(0);
+        self.string_offsets.insert(name.to_string(), offset);
+        offset
+    }
+}
+
+/// Describes the harts, the RAM of the memory map and the devices which provide a node.
+/// If a PLIC is attached, the nodes of devices with an interrupt line refer to their source at it.
+pub fn device_tree(computer: &Computer) -> Vec<u8> {
+    let mut fdt = FdtWriter::new();
+    fdt.begin_node("");
+    fdt.property_u32("#address-cells", 2);
+    fdt.property_u32("#size-cells", 2);
+    fdt.property_string("compatible", "microop-computer");
+    fdt.property_string("model", "microop-computer");
+
+    let devices = computer.devices.devices();
+    let plic = devices
+        .iter()
+        .any(|device| (device.as_ref() as &dyn Any).is::<Plic>());
+    let mut nodes: Vec<DeviceTreeNode> = devices
+        .iter()
+        .filter_map(|device| {
+            let node = device.device_tree_node()?;
+            let source = computer.devices.interrupt_source(device.name());
+            Some(match source {
+                Some(source) if plic => node
+                    .with_property("interrupt-parent", PropertyValue::Cells(vec![PLIC_PHANDLE]))
+                    .with_property("interrupts", PropertyValue::Cells(vec![source])),
+                _ => node,
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.reg.first().map(|reg| *reg.start()));
+
+    fdt.begin_node("chosen");
+    let console = nodes
+        .iter()
+        .find(|node| node.compatible.iter().any(|value| value == "ns16550a"));
+    if let Some(console) = console {
+        fdt.property_string("stdout-path", &format!("/soc/{}", console.unit_name()));
+    }
+    fdt.end_node();
+
+    fdt.begin_node("cpus");
+    fdt.property_u32("#address-cells", 1);
+    fdt.property_u32("#size-cells", 0);
+    fdt.property_u32("timebase-frequency", TIMEBASE_FREQUENCY);
+    for hart in computer.harts.iter().map(|hart| hart.get_hart_id()) {
+        fdt.begin_node(&format!("cpu@{hart:x}"));
+        fdt.property_string("device_type", "cpu");
+        fdt.property_u32("reg", hart as u32);
+        fdt.property_string("status", "okay");
+        fdt.property_string("compatible", "riscv");
+        fdt.property_string("riscv,isa", ISA);
+        let extensions = ISA_EXTENSIONS
+            .iter()
+            .map(|value| value.to_string())
+            .collect();
+        fdt.property("riscv,isa-extensions", &PropertyValue::Strings(extensions));
+        fdt.begin_node("interrupt-controller");
+        fdt.property_u32("#interrupt-cells", 1);
+        fdt.property("interrupt-controller", &PropertyValue::Empty);
+        fdt.property_string("compatible", "riscv,cpu-intc");
+        fdt.property_u32("phandle", cpu_interrupt_controller_phandle(hart));
+        fdt.end_node();
+        fdt.end_node();
+    }
+    fdt.end_node();
+
+    let memory_map = computer.devices.get_memory_map();
+    for region in memory_map
+        .regions()
+        .iter()
+        .filter(|region| region.kind == RegionKind::RAM)
+    {
+        fdt.begin_node(&format!("memory@{:x}", region.base));
+        fdt.property_string("device_type", "memory");
+        fdt.property_reg(&[region.range()]);
+        fdt.end_node();
+    }
+
+    fdt.begin_node("soc");
+    fdt.property_u32("#address-cells", 2);
+    fdt.property_u32("#size-cells", 2);
+    fdt.property_string("compatible", "simple-bus");
+    fdt.property("ranges", &PropertyValue::Empty);
+    for node in nodes {
+        fdt.begin_node(&node.unit_name());
+        fdt.property("compatible", &PropertyValue::Strings(node.compatible));
+        fdt.property_reg(&node.reg);
+        for (name, value) in node.properties {
+            fdt.property(&name, &value);
+        }
+        fdt.end_node();
+    }
+    fdt.end_node();
+
+    fdt.end_node();
+    fdt.finish(0)
+}
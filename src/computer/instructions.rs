@@ -1,5 +1,5 @@
 use crate::computer::components::cpu::registers::reg::CPUReg;
-use crate::computer::instructions::decode::decode_instruction;
+use crate::computer::instructions::decode::try_decode_instruction;
 use crate::computer::instructions::encode::encode_instruction;
 use std::fmt::Display;
 
@@ -18,7 +18,51 @@ pub enum Instruction {
     Srl(CPUReg, CPUReg, CPUReg),
     Sra(CPUReg, CPUReg, CPUReg),
     /// rd, rs1, imm
+    Addi(CPUReg, CPUReg, u64),
+    Jalr(CPUReg, CPUReg, u64),
+    /// rd, rs1, shift amount of 6 bits
+    Slli(CPUReg, CPUReg, u64),
+    Srli(CPUReg, CPUReg, u64),
+    Srai(CPUReg, CPUReg, u64),
     Lb(CPUReg, CPUReg, u64),
+    Lh(CPUReg, CPUReg, u64),
+    Lw(CPUReg, CPUReg, u64),
+    Ld(CPUReg, CPUReg, u64),
+    /// rs1 base address, rs2 value, imm
+    Sb(CPUReg, CPUReg, u64),
+    Sh(CPUReg, CPUReg, u64),
+    Sw(CPUReg, CPUReg, u64),
+    Sd(CPUReg, CPUReg, u64),
+    /// rs1, rs2, sign-extended byte offset relative to the branch
+    Beq(CPUReg, CPUReg, u64),
+    Bne(CPUReg, CPUReg, u64),
+    Blt(CPUReg, CPUReg, u64),
+    Bge(CPUReg, CPUReg, u64),
+    Bltu(CPUReg, CPUReg, u64),
+    Bgeu(CPUReg, CPUReg, u64),
+    /// rd, sign-extended byte offset relative to the jump
+    Jal(CPUReg, u64),
+    /// rd, upper 20-bit immediate
+    Lui(CPUReg, u64),
+    Auipc(CPUReg, u64),
+    /// rd, rs1 address; load-reserved
+    LrW(CPUReg, CPUReg),
+    LrD(CPUReg, CPUReg),
+    /// rd result (0 on success), rs1 address, rs2 value; store-conditional
+    ScW(CPUReg, CPUReg, CPUReg),
+    ScD(CPUReg, CPUReg, CPUReg),
+    /// rd, csr; CSRRS with rs1 = x0, reads a CSR without writing it
+    Csrr(CPUReg, u16),
+    /// rd, rs1, csr; atomically writes, sets or clears CSR bits and reads the old value to rd
+    Csrrw(CPUReg, CPUReg, u16),
+    Csrrs(CPUReg, CPUReg, u16),
+    Csrrc(CPUReg, CPUReg, u16),
+    /// rd, 5-bit unsigned immediate, csr
+    Csrrwi(CPUReg, u64, u16),
+    Csrrsi(CPUReg, u64, u16),
+    Csrrci(CPUReg, u64, u16),
+    /// Returns from a machine mode trap handler to mepc
+    Mret,
     ECall,
     EBreak,
 }
@@ -28,8 +72,53 @@ impl Instruction {
         encode_instruction(&self)
     }
 
-    pub fn decode(instruction: u32) -> Instruction {
-        decode_instruction(instruction)
+    pub fn try_decode(instruction: u32) -> Option<Instruction> {
+        try_decode_instruction(instruction)
+    }
+
+    /// Instructions after which the next PC is unknown until execution
+    pub fn is_control(&self) -> bool {
+        self.is_branch()
+            || matches!(
+                self,
+                Instruction::Jal(..)
+                    | Instruction::Jalr(..)
+                    | Instruction::Mret
+                    | Instruction::ECall
+                    | Instruction::EBreak
+            )
+    }
+
+    /// Conditional branches
+    pub fn is_branch(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Beq(..)
+                | Instruction::Bne(..)
+                | Instruction::Blt(..)
+                | Instruction::Bge(..)
+                | Instruction::Bltu(..)
+                | Instruction::Bgeu(..)
+        )
+    }
+
+    /// Target of a PC-relative branch or jump located at the given address
+    pub fn direct_target(&self, address: u64) -> Option<u64> {
+        match *self {
+            Instruction::Beq(_, _, offset)
+            | Instruction::Bne(_, _, offset)
+            | Instruction::Blt(_, _, offset)
+            | Instruction::Bge(_, _, offset)
+            | Instruction::Bltu(_, _, offset)
+            | Instruction::Bgeu(_, _, offset)
+            | Instruction::Jal(_, offset) => Some(address.wrapping_add(offset)),
+            _ => None,
+        }
+    }
+
+    /// Sign-extended value of a 20-bit upper immediate (LUI, AUIPC)
+    pub fn upper_immediate_value(imm: u64) -> u64 {
+        ((imm as u32) << 12) as i32 as i64 as u64
     }
 
     pub fn to_byte_vector(&self) -> Vec<u8> {
@@ -54,7 +143,87 @@ impl Display for Instruction {
             Instruction::Sll(rd, rs1, rs2) => write!(f, "SLL {rd} = {rs1} << {rs2}"),
             Instruction::Srl(rd, rs1, rs2) => write!(f, "SRL {rd} = {rs1} >> {rs2}"),
             Instruction::Sra(rd, rs1, rs2) => write!(f, "SRA {rd} = {rs1} >>* {rs2}"),
+            Instruction::Addi(rd, rs1, imm) => write!(f, "ADDI {rd} = {rs1} + {}", *imm as i64),
+            Instruction::Slli(rd, rs1, shamt) => write!(f, "SLLI {rd} = {rs1} << {shamt}"),
+            Instruction::Srli(rd, rs1, shamt) => write!(f, "SRLI {rd} = {rs1} >> {shamt}"),
+            Instruction::Srai(rd, rs1, shamt) => write!(f, "SRAI {rd} = {rs1} >>* {shamt}"),
+            Instruction::Jalr(rd, rs1, imm) => {
+                write!(f, "JALR {rd} = PC + 4; PC = {rs1} + {}", *imm as i64)
+            }
             Instruction::Lb(rd, rs1, imm) => write!(f, "LB {rd} = M[{rs1} + {imm}]"),
+            Instruction::Lh(rd, rs1, imm) => write!(f, "LH {rd} = M[{rs1} + {}]", *imm as i64),
+            Instruction::Lw(rd, rs1, imm) => write!(f, "LW {rd} = M[{rs1} + {}]", *imm as i64),
+            Instruction::Ld(rd, rs1, imm) => write!(f, "LD {rd} = M[{rs1} + {}]", *imm as i64),
+            Instruction::Sb(rs1, rs2, imm) => write!(f, "SB M[{rs1} + {}] = {rs2}", *imm as i64),
+            Instruction::Sh(rs1, rs2, imm) => write!(f, "SH M[{rs1} + {}] = {rs2}", *imm as i64),
+            Instruction::Sw(rs1, rs2, imm) => write!(f, "SW M[{rs1} + {}] = {rs2}", *imm as i64),
+            Instruction::Sd(rs1, rs2, imm) => write!(f, "SD M[{rs1} + {}] = {rs2}", *imm as i64),
+            Instruction::Beq(rs1, rs2, offset) => {
+                write!(f, "BEQ {rs1} == {rs2} → PC + {}", *offset as i64)
+            }
+            Instruction::Bne(rs1, rs2, offset) => {
+                write!(f, "BNE {rs1} != {rs2} → PC + {}", *offset as i64)
+            }
+            Instruction::Blt(rs1, rs2, offset) => {
+                write!(f, "BLT {rs1} < {rs2} → PC + {}", *offset as i64)
+            }
+            Instruction::Bge(rs1, rs2, offset) => {
+                write!(f, "BGE {rs1} >= {rs2} → PC + {}", *offset as i64)
+            }
+            Instruction::Bltu(rs1, rs2, offset) => {
+                write!(f, "BLTU {rs1} < {rs2} → PC + {}", *offset as i64)
+            }
+            Instruction::Bgeu(rs1, rs2, offset) => {
+                write!(f, "BGEU {rs1} >= {rs2} → PC + {}", *offset as i64)
+            }
+            Instruction::Jal(rd, offset) => {
+                write!(f, "JAL {rd} = PC + 4; PC = PC + {}", *offset as i64)
+            }
+            Instruction::Lui(rd, imm) => write!(f, "LUI {rd} = 0x{imm:05x} << 12"),
+            Instruction::Auipc(rd, imm) => write!(f, "AUIPC {rd} = PC + 0x{imm:05x} << 12"),
+            Instruction::LrW(rd, rs1) => write!(f, "LR.W {rd} = M[{rs1}]; reserve"),
+            Instruction::LrD(rd, rs1) => write!(f, "LR.D {rd} = M[{rs1}]; reserve"),
+            Instruction::ScW(rd, rs1, rs2) => {
+                write!(f, "SC.W M[{rs1}] = {rs2} if reserved; {rd} = failed")
+            }
+            Instruction::ScD(rd, rs1, rs2) => {
+                write!(f, "SC.D M[{rs1}] = {rs2} if reserved; {rd} = failed")
+            }
+            Instruction::Csrr(rd, csr) => write!(f, "CSRR {rd} = CSR[0x{csr:03x}]"),
+            Instruction::Csrrw(rd, rs1, csr) => {
+                write!(f, "CSRRW {rd} = CSR[0x{csr:03x}]; CSR[0x{csr:03x}] = {rs1}")
+            }
+            Instruction::Csrrs(rd, rs1, csr) => {
+                write!(
+                    f,
+                    "CSRRS {rd} = CSR[0x{csr:03x}]; CSR[0x{csr:03x}] |= {rs1}"
+                )
+            }
+            Instruction::Csrrc(rd, rs1, csr) => {
+                write!(
+                    f,
+                    "CSRRC {rd} = CSR[0x{csr:03x}]; CSR[0x{csr:03x}] &= !{rs1}"
+                )
+            }
+            Instruction::Csrrwi(rd, uimm, csr) => {
+                write!(
+                    f,
+                    "CSRRWI {rd} = CSR[0x{csr:03x}]; CSR[0x{csr:03x}] = {uimm}"
+                )
+            }
+            Instruction::Csrrsi(rd, uimm, csr) => {
+                write!(
+                    f,
+                    "CSRRSI {rd} = CSR[0x{csr:03x}]; CSR[0x{csr:03x}] |= {uimm}"
+                )
+            }
+            Instruction::Csrrci(rd, uimm, csr) => {
+                write!(
+                    f,
+                    "CSRRCI {rd} = CSR[0x{csr:03x}]; CSR[0x{csr:03x}] &= !{uimm}"
+                )
+            }
+            Instruction::Mret => write!(f, "MRET PC = mepc"),
             Instruction::ECall => write!(f, "ECALL"),
             Instruction::EBreak => write!(f, "EBREAK"),
         }
@@ -1,17 +1,23 @@
+use crate::computer::components::cpu::csr::Csrs;
 use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::components::cpu::registers::reg::CPUReg::X0;
 use crate::computer::instructions::Instruction;
 
-pub fn decode_instruction(instruction: u32) -> Instruction {
+pub fn try_decode_instruction(instruction: u32) -> Option<Instruction> {
     let opcode = instruction as u8 & 0b0111_1111;
 
     match opcode {
-        0b000_0011 | 0b111_0011 => decode_i(instruction, opcode),
-        0b011_0011 => decode_r(instruction, opcode),
-        _ => unimplemented!(),
+        0b000_0011 | 0b001_0011 | 0b110_0111 | 0b111_0011 => decode_i(instruction, opcode),
+        0b011_0011 | 0b010_1111 => decode_r(instruction, opcode),
+        0b001_0111 | 0b011_0111 => decode_u(instruction, opcode),
+        0b010_0011 => decode_s(instruction),
+        0b110_0011 => decode_b(instruction),
+        0b110_1111 => decode_j(instruction),
+        _ => None,
     }
 }
 
-fn decode_r(instruction: u32, opcode: u8) -> Instruction {
+fn decode_r(instruction: u32, opcode: u8) -> Option<Instruction> {
     let funct3 = get_funct3(instruction);
     let funct7 = get_funct7(instruction);
 
@@ -20,19 +26,28 @@ fn decode_r(instruction: u32, opcode: u8) -> Instruction {
     let rs2 = get_rs2(instruction);
 
     match (funct3, funct7, opcode) {
-        (0x0, 0x00, 0b011_0011) => Instruction::Add(rd, rs1, rs2),
-        (0x7, 0x00, 0b011_0011) => Instruction::And(rd, rs1, rs2),
-        (0x6, 0x00, 0b011_0011) => Instruction::Or(rd, rs1, rs2),
-        (0x0, 0x20, 0b011_0011) => Instruction::Sub(rd, rs1, rs2),
-        (0x4, 0x00, 0b011_0011) => Instruction::Xor(rd, rs1, rs2),
-        (0x1, 0x00, 0b011_0011) => Instruction::Sll(rd, rs1, rs2),
-        (0x5, 0x00, 0b011_0011) => Instruction::Srl(rd, rs1, rs2),
-        (0x5, 0x20, 0b011_0011) => Instruction::Sra(rd, rs1, rs2),
-        _ => unimplemented!(),
+        (0x0, 0x00, 0b011_0011) => Some(Instruction::Add(rd, rs1, rs2)),
+        (0x7, 0x00, 0b011_0011) => Some(Instruction::And(rd, rs1, rs2)),
+        (0x6, 0x00, 0b011_0011) => Some(Instruction::Or(rd, rs1, rs2)),
+        (0x0, 0x20, 0b011_0011) => Some(Instruction::Sub(rd, rs1, rs2)),
+        (0x4, 0x00, 0b011_0011) => Some(Instruction::Xor(rd, rs1, rs2)),
+        (0x1, 0x00, 0b011_0011) => Some(Instruction::Sll(rd, rs1, rs2)),
+        (0x5, 0x00, 0b011_0011) => Some(Instruction::Srl(rd, rs1, rs2)),
+        (0x5, 0x20, 0b011_0011) => Some(Instruction::Sra(rd, rs1, rs2)),
+        // The acquire and release bits are ignored, every access is sequentially consistent
+        (0x2, funct7, 0b010_1111) if funct7 >> 2 == 0b00010 && rs2 == X0 => {
+            Some(Instruction::LrW(rd, rs1))
+        }
+        (0x3, funct7, 0b010_1111) if funct7 >> 2 == 0b00010 && rs2 == X0 => {
+            Some(Instruction::LrD(rd, rs1))
+        }
+        (0x2, funct7, 0b010_1111) if funct7 >> 2 == 0b00011 => Some(Instruction::ScW(rd, rs1, rs2)),
+        (0x3, funct7, 0b010_1111) if funct7 >> 2 == 0b00011 => Some(Instruction::ScD(rd, rs1, rs2)),
+        _ => None,
     }
 }
 
-fn decode_i(instruction: u32, opcode: u8) -> Instruction {
+fn decode_i(instruction: u32, opcode: u8) -> Option<Instruction> {
     let funct3 = get_funct3(instruction);
     let imm = ((instruction as i32) >> 20) as u64;
 
@@ -40,13 +55,108 @@ fn decode_i(instruction: u32, opcode: u8) -> Instruction {
     let rs1 = get_rs1(instruction);
 
     match (opcode, funct3, imm) {
-        (0b000_0011, 0x0, _) => Instruction::Lb(rd, rs1, imm),
-        (0b111_0011, 0x0, 0x0) => Instruction::ECall,
-        (0b111_0011, 0x0, 0x1) => Instruction::EBreak,
-        _ => unimplemented!(),
+        (0b000_0011, 0x0, _) => Some(Instruction::Lb(rd, rs1, imm)),
+        (0b000_0011, 0x1, _) => Some(Instruction::Lh(rd, rs1, imm)),
+        (0b000_0011, 0x2, _) => Some(Instruction::Lw(rd, rs1, imm)),
+        (0b000_0011, 0x3, _) => Some(Instruction::Ld(rd, rs1, imm)),
+        (0b001_0011, 0x0, _) => Some(Instruction::Addi(rd, rs1, imm)),
+        // The upper 6 bits of the immediate select the shift, the lower 6 bits are the amount
+        (0b001_0011, 0x1, imm) if imm >> 6 == 0 => Some(Instruction::Slli(rd, rs1, imm)),
+        (0b001_0011, 0x5, imm) if imm >> 6 == 0 => Some(Instruction::Srli(rd, rs1, imm)),
+        (0b001_0011, 0x5, imm) if imm >> 6 == 0x10 => Some(Instruction::Srai(rd, rs1, imm & 0x3F)),
+        (0b110_0111, 0x0, _) => Some(Instruction::Jalr(rd, rs1, imm)),
+        (0b111_0011, 0x0, 0x0) => Some(Instruction::ECall),
+        (0b111_0011, 0x0, 0x1) => Some(Instruction::EBreak),
+        (0b111_0011, 0x0, 0x302) if rd == X0 && rs1 == X0 => Some(Instruction::Mret),
+        (0b111_0011, funct3, csr) if funct3 & 0b11 != 0 => {
+            decode_csr(instruction, funct3, csr as u16 & 0xFFF, rd)
+        }
+        _ => None,
     }
 }
 
+/// Zicsr instructions, the forms which only read the CSR decode to CSRR
+fn decode_csr(instruction: u32, funct3: u8, csr: u16, rd: CPUReg) -> Option<Instruction> {
+    // The immediate forms put an unsigned immediate in place of rs1
+    let uimm = (instruction >> 15) as u64 & 0b1_1111;
+    let rs1 = get_rs1(instruction);
+    let read_only = funct3 & 0b11 != 0b01 && uimm == 0;
+
+    if read_only {
+        return Csrs::is_supported(csr).then_some(Instruction::Csrr(rd, csr));
+    }
+    if !Csrs::is_writable(csr) {
+        return None;
+    }
+    match funct3 {
+        0x1 => Some(Instruction::Csrrw(rd, rs1, csr)),
+        0x2 => Some(Instruction::Csrrs(rd, rs1, csr)),
+        0x3 => Some(Instruction::Csrrc(rd, rs1, csr)),
+        0x5 => Some(Instruction::Csrrwi(rd, uimm, csr)),
+        0x6 => Some(Instruction::Csrrsi(rd, uimm, csr)),
+        0x7 => Some(Instruction::Csrrci(rd, uimm, csr)),
+        _ => None,
+    }
+}
+
+fn decode_u(instruction: u32, opcode: u8) -> Option<Instruction> {
+    let imm = (instruction >> 12) as u64;
+    let rd = get_rd(instruction);
+
+    match opcode {
+        0b011_0111 => Some(Instruction::Lui(rd, imm)),
+        0b001_0111 => Some(Instruction::Auipc(rd, imm)),
+        _ => None,
+    }
+}
+
+fn decode_s(instruction: u32) -> Option<Instruction> {
+    let imm = (((instruction as i32) >> 20) as u32 & !0b1_1111) | ((instruction >> 7) & 0b1_1111);
+    let imm = imm as i32 as i64 as u64;
+
+    let rs1 = get_rs1(instruction);
+    let rs2 = get_rs2(instruction);
+
+    match get_funct3(instruction) {
+        0x0 => Some(Instruction::Sb(rs1, rs2, imm)),
+        0x1 => Some(Instruction::Sh(rs1, rs2, imm)),
+        0x2 => Some(Instruction::Sw(rs1, rs2, imm)),
+        0x3 => Some(Instruction::Sd(rs1, rs2, imm)),
+        _ => None,
+    }
+}
+
+fn decode_b(instruction: u32) -> Option<Instruction> {
+    let offset = (((instruction as i32) >> 31) as u32 & !0xFFF)
+        | ((instruction >> 7) & 0b1) << 11
+        | ((instruction >> 25) & 0b11_1111) << 5
+        | ((instruction >> 8) & 0b1111) << 1;
+    let offset = offset as i32 as i64 as u64;
+
+    let rs1 = get_rs1(instruction);
+    let rs2 = get_rs2(instruction);
+
+    match get_funct3(instruction) {
+        0x0 => Some(Instruction::Beq(rs1, rs2, offset)),
+        0x1 => Some(Instruction::Bne(rs1, rs2, offset)),
+        0x4 => Some(Instruction::Blt(rs1, rs2, offset)),
+        0x5 => Some(Instruction::Bge(rs1, rs2, offset)),
+        0x6 => Some(Instruction::Bltu(rs1, rs2, offset)),
+        0x7 => Some(Instruction::Bgeu(rs1, rs2, offset)),
+        _ => None,
+    }
+}
+
+fn decode_j(instruction: u32) -> Option<Instruction> {
+    let offset = (((instruction as i32) >> 31) as u32 & !0xF_FFFF)
+        | (instruction & 0xF_F000)
+        | ((instruction >> 20) & 0b1) << 11
+        | ((instruction >> 21) & 0b11_1111_1111) << 1;
+    let offset = offset as i32 as i64 as u64;
+
+    Some(Instruction::Jal(get_rd(instruction), offset))
+}
+
 // INSTRUCTION FORMAT DECODING
 fn get_funct3(instruction: u32) -> u8 {
     (instruction >> 12) as u8 & 0b0000_0111
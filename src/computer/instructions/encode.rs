@@ -11,7 +11,52 @@ pub fn encode_instruction(instruction: &Instruction) -> u32 {
         Instruction::Sll(rd, rs1, rs2) => encode_r_type(0x00, *rs2, *rs1, 0x1, *rd, 0b011_0011),
         Instruction::Srl(rd, rs1, rs2) => encode_r_type(0x00, *rs2, *rs1, 0x5, *rd, 0b011_0011),
         Instruction::Sra(rd, rs1, rs2) => encode_r_type(0x20, *rs2, *rs1, 0x5, *rd, 0b011_0011),
+        Instruction::Addi(rd, rs1, imm) => encode_i_type(*imm, *rs1, 0x0, *rd, 0b001_0011),
+        Instruction::Jalr(rd, rs1, imm) => encode_i_type(*imm, *rs1, 0x0, *rd, 0b110_0111),
+        Instruction::Slli(rd, rs1, shamt) => {
+            encode_i_type(*shamt & 0x3F, *rs1, 0x1, *rd, 0b001_0011)
+        }
+        Instruction::Srli(rd, rs1, shamt) => {
+            encode_i_type(*shamt & 0x3F, *rs1, 0x5, *rd, 0b001_0011)
+        }
+        Instruction::Srai(rd, rs1, shamt) => {
+            encode_i_type(0x400 | *shamt & 0x3F, *rs1, 0x5, *rd, 0b001_0011)
+        }
         Instruction::Lb(rd, rs1, imm) => encode_i_type(*imm, *rs1, 0x0, *rd, 0b000_0011),
+        Instruction::Lh(rd, rs1, imm) => encode_i_type(*imm, *rs1, 0x1, *rd, 0b000_0011),
+        Instruction::Lw(rd, rs1, imm) => encode_i_type(*imm, *rs1, 0x2, *rd, 0b000_0011),
+        Instruction::Ld(rd, rs1, imm) => encode_i_type(*imm, *rs1, 0x3, *rd, 0b000_0011),
+        Instruction::Sb(rs1, rs2, imm) => encode_s_type(*imm, *rs2, *rs1, 0x0, 0b010_0011),
+        Instruction::Sh(rs1, rs2, imm) => encode_s_type(*imm, *rs2, *rs1, 0x1, 0b010_0011),
+        Instruction::Sw(rs1, rs2, imm) => encode_s_type(*imm, *rs2, *rs1, 0x2, 0b010_0011),
+        Instruction::Sd(rs1, rs2, imm) => encode_s_type(*imm, *rs2, *rs1, 0x3, 0b010_0011),
+        Instruction::Beq(rs1, rs2, offset) => encode_b_type(*offset, *rs2, *rs1, 0x0, 0b110_0011),
+        Instruction::Bne(rs1, rs2, offset) => encode_b_type(*offset, *rs2, *rs1, 0x1, 0b110_0011),
+        Instruction::Blt(rs1, rs2, offset) => encode_b_type(*offset, *rs2, *rs1, 0x4, 0b110_0011),
+        Instruction::Bge(rs1, rs2, offset) => encode_b_type(*offset, *rs2, *rs1, 0x5, 0b110_0011),
+        Instruction::Bltu(rs1, rs2, offset) => encode_b_type(*offset, *rs2, *rs1, 0x6, 0b110_0011),
+        Instruction::Bgeu(rs1, rs2, offset) => encode_b_type(*offset, *rs2, *rs1, 0x7, 0b110_0011),
+        Instruction::Jal(rd, offset) => encode_j_type(*offset, *rd, 0b110_1111),
+        Instruction::Lui(rd, imm) => encode_u_type(*imm, *rd, 0b011_0111),
+        Instruction::Auipc(rd, imm) => encode_u_type(*imm, *rd, 0b001_0111),
+        Instruction::LrW(rd, rs1) => encode_r_type(0x08, 0u8.into(), *rs1, 0x2, *rd, 0b010_1111),
+        Instruction::LrD(rd, rs1) => encode_r_type(0x08, 0u8.into(), *rs1, 0x3, *rd, 0b010_1111),
+        Instruction::ScW(rd, rs1, rs2) => encode_r_type(0x0C, *rs2, *rs1, 0x2, *rd, 0b010_1111),
+        Instruction::ScD(rd, rs1, rs2) => encode_r_type(0x0C, *rs2, *rs1, 0x3, *rd, 0b010_1111),
+        Instruction::Csrr(rd, csr) => encode_i_type(*csr as u64, 0u8.into(), 0x2, *rd, 0b111_0011),
+        Instruction::Csrrw(rd, rs1, csr) => encode_i_type(*csr as u64, *rs1, 0x1, *rd, 0b111_0011),
+        Instruction::Csrrs(rd, rs1, csr) => encode_i_type(*csr as u64, *rs1, 0x2, *rd, 0b111_0011),
+        Instruction::Csrrc(rd, rs1, csr) => encode_i_type(*csr as u64, *rs1, 0x3, *rd, 0b111_0011),
+        Instruction::Csrrwi(rd, uimm, csr) => {
+            encode_i_type(*csr as u64, (*uimm as u8).into(), 0x5, *rd, 0b111_0011)
+        }
+        Instruction::Csrrsi(rd, uimm, csr) => {
+            encode_i_type(*csr as u64, (*uimm as u8).into(), 0x6, *rd, 0b111_0011)
+        }
+        Instruction::Csrrci(rd, uimm, csr) => {
+            encode_i_type(*csr as u64, (*uimm as u8).into(), 0x7, *rd, 0b111_0011)
+        }
+        Instruction::Mret => encode_i_type(0x302, 0u8.into(), 0x0, 0u8.into(), 0b111_0011),
         Instruction::ECall => encode_i_type(0x0, 0u8.into(), 0x0, 0u8.into(), 0b111_0011),
         Instruction::EBreak => encode_i_type(0x1, 0u8.into(), 0x0, 0u8.into(), 0b111_0011),
     }
@@ -33,3 +78,41 @@ fn encode_i_type(imm: u64, rs1: CPUReg, fn3: u8, rd: CPUReg, opcode: u8) -> u32
         | ((rd.to_riscv() as u32 & 0b0001_1111) << 7)
         | ((opcode & 0b0111_1111) as u32)
 }
+
+fn encode_s_type(imm: u64, rs2: CPUReg, rs1: CPUReg, fn3: u8, opcode: u8) -> u32 {
+    let imm = imm as u32;
+    (((imm >> 5) & 0b111_1111) << 25)
+        | ((rs2.to_riscv() as u32 & 0b0001_1111) << 20)
+        | ((rs1.to_riscv() as u32 & 0b0001_1111) << 15)
+        | (((fn3 & 0b0000_0111) as u32) << 12)
+        | ((imm & 0b1_1111) << 7)
+        | ((opcode & 0b0111_1111) as u32)
+}
+
+fn encode_b_type(offset: u64, rs2: CPUReg, rs1: CPUReg, fn3: u8, opcode: u8) -> u32 {
+    let offset = offset as u32;
+    (((offset >> 12) & 0b1) << 31)
+        | (((offset >> 5) & 0b11_1111) << 25)
+        | ((rs2.to_riscv() as u32 & 0b0001_1111) << 20)
+        | ((rs1.to_riscv() as u32 & 0b0001_1111) << 15)
+        | (((fn3 & 0b0000_0111) as u32) << 12)
+        | (((offset >> 1) & 0b1111) << 8)
+        | (((offset >> 11) & 0b1) << 7)
+        | ((opcode & 0b0111_1111) as u32)
+}
+
+fn encode_j_type(offset: u64, rd: CPUReg, opcode: u8) -> u32 {
+    let offset = offset as u32;
+    (((offset >> 20) & 0b1) << 31)
+        | (((offset >> 1) & 0b11_1111_1111) << 21)
+        | (((offset >> 11) & 0b1) << 20)
+        | (((offset >> 12) & 0b1111_1111) << 12)
+        | ((rd.to_riscv() as u32 & 0b0001_1111) << 7)
+        | ((opcode & 0b0111_1111) as u32)
+}
+
+fn encode_u_type(imm: u64, rd: CPUReg, opcode: u8) -> u32 {
+    ((imm as u32 & 0xF_FFFF) << 12)
+        | ((rd.to_riscv() as u32 & 0b0001_1111) << 7)
+        | ((opcode & 0b0111_1111) as u32)
+}
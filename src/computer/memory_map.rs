@@ -0,0 +1,204 @@
+use crate::computer::address::{BOOT_ROM_END, BOOT_ROM_START, RAM_SIZE, RAM_START};
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionKind {
+    ROM,
+    RAM,
+    /// Reserved for a peripheral attached to the computer
+    Device,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Permissions {
+    pub const READ_EXECUTE: Self = Self::new(true, false, true);
+    pub const READ_WRITE: Self = Self::new(true, true, false);
+    pub const READ_WRITE_EXECUTE: Self = Self::new(true, true, true);
+
+    pub const fn new(read: bool, write: bool, execute: bool) -> Self {
+        Self {
+            read,
+            write,
+            execute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub base: u64,
+    pub size: u64,
+    pub kind: RegionKind,
+    pub permissions: Permissions,
+    /// Wait states of every access to the backing RAM or ROM
+    pub latency: u64,
+    /// Wait states of each burst beat after the first
+    pub beat_latency: u64,
+}
+
+impl MemoryRegion {
+    pub fn new(
+        name: &str,
+        base: u64,
+        size: u64,
+        kind: RegionKind,
+        permissions: Permissions,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            size,
+            kind,
+            permissions,
+            latency: 0,
+            beat_latency: 0,
+        }
+    }
+
+    pub fn with_latency(mut self, ticks: u64) -> Self {
+        self.latency = ticks;
+        self
+    }
+
+    pub fn with_beat_latency(mut self, ticks: u64) -> Self {
+        self.beat_latency = ticks;
+        self
+    }
+
+    /// Inclusive
+    pub fn end(&self) -> u64 {
+        self.base.wrapping_add(self.size.wrapping_sub(1))
+    }
+
+    pub fn range(&self) -> RangeInclusive<u64> {
+        self.base..=self.end()
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        self.range().contains(&address)
+    }
+
+    pub fn overlaps(&self, range: &RangeInclusive<u64>) -> bool {
+        self.base <= *range.end() && *range.start() <= self.end()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemoryMapError {
+    /// Regions of size zero can not be mapped, regions may not wrap around the address space
+    InvalidSize(String),
+    Overlap {
+        region: String,
+        existing: String,
+    },
+}
+
+/// Describes which address ranges exist, what backs them and how they may be accessed.
+/// Addresses outside every region are unmapped and answered with a bus error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryMap {
+    /// Sorted by base address
+    regions: Vec<MemoryRegion>,
+}
+
+impl Default for MemoryMap {
+    /// Boot rom at the start of the address space and 128 MiB of RAM
+    fn default() -> Self {
+        Self::new(vec![
+            MemoryRegion::new(
+                "boot_rom",
+                BOOT_ROM_START,
+                BOOT_ROM_END - BOOT_ROM_START + 1,
+                RegionKind::ROM,
+                Permissions::READ_EXECUTE,
+            ),
+            MemoryRegion::new(
+                "ram",
+                RAM_START,
+                RAM_SIZE,
+                RegionKind::RAM,
+                Permissions::READ_WRITE_EXECUTE,
+            ),
+        ])
+        .unwrap()
+    }
+}
+
+impl MemoryMap {
+    pub fn new(regions: Vec<MemoryRegion>) -> Result<Self, MemoryMapError> {
+        let mut map = Self {
+            regions: Vec::with_capacity(regions.len()),
+        };
+        for region in regions {
+            map.insert(region)?;
+        }
+        Ok(map)
+    }
+
+    pub fn insert(&mut self, region: MemoryRegion) -> Result<(), MemoryMapError> {
+        if region.size == 0 || region.base.checked_add(region.size - 1).is_none() {
+            return Err(MemoryMapError::InvalidSize(region.name));
+        }
+        if let Some(existing) = self.overlapping(&region.range()) {
+            return Err(MemoryMapError::Overlap {
+                region: region.name,
+                existing: existing.name.clone(),
+            });
+        }
+
+        let index = self.regions.partition_point(|r| r.base < region.base);
+        self.regions.insert(index, region);
+        Ok(())
+    }
+
+    pub fn regions(&self) -> &[MemoryRegion] {
+        &self.regions
+    }
+
+    pub fn region(&self, address: u64) -> Option<&MemoryRegion> {
+        self.regions.iter().find(|region| region.contains(address))
+    }
+
+    pub fn overlapping(&self, range: &RangeInclusive<u64>) -> Option<&MemoryRegion> {
+        self.regions.iter().find(|region| region.overlaps(range))
+    }
+}
+
+impl Display for MemoryMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryMapError::InvalidSize(region) => write!(f, "Region {region} has an invalid size"),
+            MemoryMapError::Overlap { region, existing } => {
+                write!(f, "Region {region} overlaps with {existing}")
+            }
+        }
+    }
+}
+
+impl Display for MemoryMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for region in self.regions.iter() {
+            let permissions = region.permissions;
+            writeln!(
+                f,
+                "0x{:016x}..=0x{:016x} {}{}{} {:?} {}",
+                region.base,
+                region.end(),
+                if permissions.read { 'r' } else { '-' },
+                if permissions.write { 'w' } else { '-' },
+                if permissions.execute { 'x' } else { '-' },
+                region.kind,
+                region.name
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,294 @@
+use crate::computer::address::Address;
+use crate::computer::components::bus::response::BusError;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::execute::FunctionalMemory;
+use crate::computer::components::cpu::registers::reg::CPUReg::X0;
+use crate::computer::components::uart::backend::UartBackend;
+use crate::computer::instructions::Instruction;
+use log::debug;
+#[cfg(test)]
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+// Operations, passed in a0 with the parameter in a1
+pub const SYS_OPEN: u64 = 0x01;
+pub const SYS_CLOSE: u64 = 0x02;
+pub const SYS_WRITEC: u64 = 0x03;
+pub const SYS_WRITE0: u64 = 0x04;
+pub const SYS_WRITE: u64 = 0x05;
+pub const SYS_READ: u64 = 0x06;
+pub const SYS_EXIT: u64 = 0x18;
+
+/// Reason of an exit by the program itself, the exit code is the subcode
+pub const ADP_STOPPED_APPLICATION_EXIT: u64 = 0x2_0026;
+/// Name opening the console instead of a file
+pub const SEMIHOSTING_CONSOLE: &str = ":tt";
+
+/// Instructions before and after the `ebreak` of a semihosting call
+pub const SEMIHOSTING_ENTRY: Instruction = Instruction::Slli(X0, X0, 0x1F);
+pub const SEMIHOSTING_EXIT: Instruction = Instruction::Srai(X0, X0, 7);
+
+/// Returned for failed operations
+const FAILURE: u64 = -1i64 as u64;
+
+/// Outcome of a call for the calling hart
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SemihostingReturn {
+    /// Continues after the call with the value in a0
+    Value(u64),
+    /// Stops the machine with the exit code
+    Exit(u64),
+}
+
+#[derive(Debug)]
+enum Handle {
+    Console,
+    File(File),
+}
+
+/// Host services for bare-metal programs, requested with the RISC-V semihosting convention:
+/// an `ebreak` between `slli x0, x0, 0x1f` and `srai x0, x0, 7`, a plain `ebreak` still halts the hart.
+/// Parameter blocks hold a double word per field. Text goes to the console backend,
+/// files are only opened below the root directory, without one every open fails.
+#[derive(Debug)]
+pub struct Semihosting {
+    console: Box<dyn UartBackend>,
+    root: Option<PathBuf>,
+    /// Open handles, numbered from 1 in the order they were opened
+    handles: HashMap<u64, Handle>,
+    next_handle: u64,
+}
+
+impl Semihosting {
+    pub fn new(console: impl UartBackend) -> Self {
+        Self {
+            console: Box::new(console),
+            root: None,
+            handles: HashMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    /// Directory the guest's file names are relative to, names leaving it are rejected
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    #[cfg(test)]
+    pub fn get_console<T: UartBackend>(&self) -> Option<&T> {
+        (self.console.as_ref() as &dyn Any).downcast_ref()
+    }
+
+    #[cfg(test)]
+    pub fn get_console_mut<T: UartBackend>(&mut self) -> Option<&mut T> {
+        (self.console.as_mut() as &mut dyn Any).downcast_mut()
+    }
+
+    /// Closes all handles
+    pub fn reset(&mut self) {
+        self.handles.clear();
+        self.next_handle = 1;
+    }
+
+    /// Whether the instruction words around the `ebreak` at the address mark a semihosting call
+    pub fn is_call(memory: &mut impl FunctionalMemory, ebreak: u64) -> bool {
+        let mut word = |address: u64| read_word(memory, address).ok();
+        ebreak >= 4
+            && word(ebreak - 4) == Some(SEMIHOSTING_ENTRY.encode())
+            && word(ebreak) == Some(Instruction::EBreak.encode())
+            && word(ebreak + 4) == Some(SEMIHOSTING_EXIT.encode())
+    }
+
+    /// Performs the operation, unknown operations fail
+    pub fn call(
+        &mut self,
+        operation: u64,
+        parameter: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> SemihostingReturn {
+        debug!(target: "semihosting", "Operation {operation:#x} with {parameter:#x}");
+        let result = match operation {
+            SYS_OPEN => self.open(parameter, memory),
+            SYS_CLOSE => self.close(parameter, memory),
+            SYS_WRITEC => read_byte(memory, parameter).map(|byte| {
+                self.console.transmit(byte);
+                0
+            }),
+            SYS_WRITE0 => read_string(memory, parameter).map(|text| {
+                text.into_iter()
+                    .for_each(|byte| self.console.transmit(byte));
+                0
+            }),
+            SYS_WRITE => self.write(parameter, memory),
+            SYS_READ => self.read(parameter, memory),
+            SYS_EXIT => {
+                let code = match read_fields::<2>(memory, parameter) {
+                    Ok([ADP_STOPPED_APPLICATION_EXIT, code]) => code,
+                    _ => 1,
+                };
+                return SemihostingReturn::Exit(code);
+            }
+            _ => {
+                debug!(target: "semihosting", "Unsupported operation {operation:#x}");
+                Ok(FAILURE)
+            }
+        };
+        SemihostingReturn::Value(result.unwrap_or(FAILURE))
+    }
+
+    /// Block of name address, mode and name length, the mode numbers the modes of C's `fopen`
+    fn open(
+        &mut self,
+        parameter: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> Result<u64, BusError> {
+        let [name, mode, length] = read_fields(memory, parameter)?;
+        let name = read_bytes(memory, name, length)?;
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let handle = if name == SEMIHOSTING_CONSOLE {
+            Some(Handle::Console)
+        } else {
+            self.open_file(&name, mode).map(Handle::File)
+        };
+        let Some(handle) = handle else {
+            debug!(target: "semihosting", "Failed to open {name:?}");
+            return Ok(FAILURE);
+        };
+        let number = self.next_handle;
+        self.next_handle += 1;
+        self.handles.insert(number, handle);
+        Ok(number)
+    }
+
+    fn open_file(&self, name: &str, mode: u64) -> Option<File> {
+        let path = self.resolve(name)?;
+        let mut options = OpenOptions::new();
+        match mode {
+            0 | 1 => options.read(true),
+            2 | 3 => options.read(true).write(true),
+            4 | 5 => options.write(true).create(true).truncate(true),
+            6 | 7 => options.read(true).write(true).create(true).truncate(true),
+            8 | 9 => options.append(true).create(true),
+            10 | 11 => options.read(true).append(true).create(true),
+            _ => return None,
+        };
+        options.open(path).ok()
+    }
+
+    /// Path below the root, none for absolute names and names with parent components
+    fn resolve(&self, name: &str) -> Option<PathBuf> {
+        let root = self.root.as_ref()?;
+        let path = Path::new(name);
+        let contained = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        contained.then(|| root.join(path))
+    }
+
+    fn close(
+        &mut self,
+        parameter: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> Result<u64, BusError> {
+        let [handle] = read_fields(memory, parameter)?;
+        Ok(match self.handles.remove(&handle) {
+            Some(_) => 0,
+            None => FAILURE,
+        })
+    }
+
+    /// Block of handle, buffer address and length, returns the number of bytes not written
+    fn write(
+        &mut self,
+        parameter: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> Result<u64, BusError> {
+        let [handle, buffer, length] = read_fields(memory, parameter)?;
+        let data = read_bytes(memory, buffer, length)?;
+        let written = match self.handles.get_mut(&handle) {
+            Some(Handle::Console) => {
+                data.iter().for_each(|byte| self.console.transmit(*byte));
+                data.len()
+            }
+            Some(Handle::File(file)) => file.write_all(&data).map_or(0, |_| data.len()),
+            None => return Ok(FAILURE),
+        };
+        Ok(length - written as u64)
+    }
+
+    /// Block of handle, buffer address and length, returns the number of bytes not read.
+    /// The console only serves the input already available.
+    fn read(
+        &mut self,
+        parameter: u64,
+        memory: &mut impl FunctionalMemory,
+    ) -> Result<u64, BusError> {
+        let [handle, buffer, length] = read_fields(memory, parameter)?;
+        let data = match self.handles.get_mut(&handle) {
+            Some(Handle::Console) => std::iter::from_fn(|| self.console.receive())
+                .take(length as usize)
+                .collect(),
+            Some(Handle::File(file)) => {
+                let mut data = Vec::new();
+                if file.take(length).read_to_end(&mut data).is_err() {
+                    return Ok(FAILURE);
+                }
+                data
+            }
+            None => return Ok(FAILURE),
+        };
+        for (address, byte) in (buffer..).zip(data.iter()) {
+            memory.write(Address::new(address), *byte as u64, BusStatus::WriteByte)?;
+        }
+        Ok(length - data.len() as u64)
+    }
+}
+
+/// Reads through aligned double words, which never cross the end of a region
+fn read_byte(memory: &mut impl FunctionalMemory, address: u64) -> Result<u8, BusError> {
+    let value = memory.read_dw(Address::new(address & !0b111))?;
+    Ok((value >> (8 * (address & 0b111))) as u8)
+}
+
+fn read_word(memory: &mut impl FunctionalMemory, address: u64) -> Result<u32, BusError> {
+    let value = memory.read_dw(Address::new(address & !0b111))?;
+    Ok((value >> (8 * (address & 0b100))) as u32)
+}
+
+fn read_bytes(
+    memory: &mut impl FunctionalMemory,
+    address: u64,
+    length: u64,
+) -> Result<Vec<u8>, BusError> {
+    (address..address.wrapping_add(length))
+        .map(|address| read_byte(memory, address))
+        .collect()
+}
+
+/// Null terminated string
+fn read_string(memory: &mut impl FunctionalMemory, address: u64) -> Result<Vec<u8>, BusError> {
+    let mut text = Vec::new();
+    for address in address.. {
+        match read_byte(memory, address)? {
+            0 => break,
+            byte => text.push(byte),
+        }
+    }
+    Ok(text)
+}
+
+fn read_fields<const N: usize>(
+    memory: &mut impl FunctionalMemory,
+    address: u64,
+) -> Result<[u64; N], BusError> {
+    let mut fields = [0; N];
+    for (index, field) in fields.iter_mut().enumerate() {
+        let bytes = read_bytes(memory, address + index as u64 * 8, 8)?;
+        *field = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    Ok(fields)
+}
@@ -0,0 +1,278 @@
+use crate::computer::address::{BOOT_ROM_END, BOOT_ROM_START, RAM_SIZE, RAM_START};
+use crate::computer::components::bus::arbitration::Arbitration;
+use crate::computer::components::clint::Clint;
+use crate::computer::components::cpu::branch_prediction::predictors::{
+    Bimodal, GShare, Tournament,
+};
+use crate::computer::components::cpu::builder::CPUBuilder;
+use crate::computer::components::cpu::cache::CacheConfig;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::BusDevice;
+use crate::computer::components::dma::DmaController;
+use crate::computer::components::framebuffer::{Framebuffer, FramebufferConfig};
+use crate::computer::components::gpio::Gpio;
+use crate::computer::components::htif::{Htif, HTIF_SIZE};
+use crate::computer::components::input::{InputEvent, InputQueue};
+use crate::computer::components::plic::Plic;
+use crate::computer::components::rng::Rng;
+use crate::computer::components::rtc::{Rtc, RtcClock};
+use crate::computer::components::test_finisher::TestFinisher;
+use crate::computer::components::uart::backend::StreamBackend;
+use crate::computer::components::uart::Uart;
+use crate::computer::components::virtio::storage::FileImage;
+use crate::computer::components::virtio::VirtioBlock;
+use crate::computer::components::watchdog::{Watchdog, WatchdogAction, WatchdogConfig};
+use crate::computer::memory_map::{MemoryMap, MemoryRegion, Permissions, RegionKind};
+use crate::computer::semihosting::Semihosting;
+use crate::computer::Computer;
+use crate::options::{ExecutionMode, Options, Predictor};
+use std::path::Path;
+
+// Devices of the platform, at the addresses of QEMU's virt machine where it has the device
+pub const RTC_BASE: u64 = 0x0010_1000;
+pub const CLINT_BASE: u64 = 0x0200_0000;
+pub const PLIC_BASE: u64 = 0x0C00_0000;
+pub const UART_BASE: u64 = 0x1000_0000;
+pub const VIRTIO_BASE: u64 = 0x1000_1000;
+pub const DMA_BASE: u64 = 0x1001_1000;
+pub const GPIO_BASE: u64 = 0x1001_2000;
+pub const RNG_BASE: u64 = 0x1001_3000;
+pub const INPUT_BASE: u64 = 0x1001_4000;
+pub const WATCHDOG_BASE: u64 = 0x1001_5000;
+/// Default address of the HTIF mailbox, riscv-tests move it to their `tohost` symbol
+pub const HTIF_BASE: u64 = 0x1002_0000;
+pub const FRAMEBUFFER_BASE: u64 = 0x2000_0000;
+
+/// Builds the computer the options describe, without loading a program
+pub fn build_computer(options: &Options) -> Result<Computer, String> {
+    let harts = (0..options.harts)
+        .map(|_| hart(options).build().map_err(|error| error.to_string()))
+        .collect::<Result<Vec<CPU>, String>>()?;
+    let mut computer = Computer::with_harts(memory_map(options), harts);
+    if options.round_robin {
+        computer.bus.set_arbitration(Arbitration::RoundRobin);
+    }
+    attach_devices(&mut computer, options)?;
+    if let Some(root) = &options.semihosting {
+        let semihosting = Semihosting::new(StreamBackend::stdio()).with_root(root);
+        computer.set_semihosting(Some(semihosting));
+    }
+    Ok(computer)
+}
+
+fn hart(options: &Options) -> CPUBuilder {
+    let mut builder = CPU::builder()
+        .micro_op_fusion(options.fusion)
+        .macro_op_fusion(options.fusion)
+        .misaligned_policy(options.misaligned);
+    if options.mode == ExecutionMode::OutOfOrder || options.predictor != Predictor::Static {
+        builder = builder.out_of_order(Default::default());
+    }
+    if let Some(width) = options.issue_width {
+        builder = builder.issue_width(width);
+    }
+    builder = match options.predictor {
+        Predictor::Static => builder,
+        Predictor::Bimodal => builder.branch_predictor(Bimodal::new(512)),
+        Predictor::GShare => builder.branch_predictor(GShare::new(512, 8)),
+        Predictor::Tournament => builder.branch_predictor(Tournament::new(512, 8)),
+    };
+    let cache = CacheConfig {
+        replacement: options.replacement,
+        write_policy: options.write_policy,
+        coherence: options.coherence,
+        ..CacheConfig::default()
+    };
+    if options.icache {
+        builder = builder.icache(cache.clone());
+    }
+    if options.dcache {
+        builder = builder.dcache(cache);
+    }
+    builder
+}
+
+/// Boot ROM and RAM like the default memory map, with the wait states of the options.
+/// An HTIF mailbox within the RAM, like the `tohost` of riscv-tests, gets a device region carved out of it.
+fn memory_map(options: &Options) -> MemoryMap {
+    let ram = |name, base, size| {
+        MemoryRegion::new(
+            name,
+            base,
+            size,
+            RegionKind::RAM,
+            Permissions::READ_WRITE_EXECUTE,
+        )
+        .with_latency(options.latency)
+        .with_beat_latency(options.beat_latency)
+    };
+    let mut regions = vec![MemoryRegion::new(
+        "boot_rom",
+        BOOT_ROM_START,
+        BOOT_ROM_END - BOOT_ROM_START + 1,
+        RegionKind::ROM,
+        Permissions::READ_EXECUTE,
+    )];
+    let ram_end = RAM_START + RAM_SIZE;
+    match options.htif {
+        Some(tohost) if (RAM_START..ram_end).contains(&tohost) => {
+            let tohost_end = (tohost + HTIF_SIZE).min(ram_end);
+            if tohost > RAM_START {
+                regions.push(ram("ram", RAM_START, tohost - RAM_START));
+            }
+            regions.push(MemoryRegion::new(
+                "tohost",
+                tohost,
+                tohost_end - tohost,
+                RegionKind::Device,
+                Permissions::READ_WRITE,
+            ));
+            if tohost_end < ram_end {
+                regions.push(ram("ram_high", tohost_end, ram_end - tohost_end));
+            }
+        }
+        _ => regions.push(ram("ram", RAM_START, RAM_SIZE)),
+    }
+    MemoryMap::new(regions).unwrap()
+}
+
+fn attach_devices(computer: &mut Computer, options: &Options) -> Result<(), String> {
+    let harts = options.harts;
+    let uart_backend = match &options.uart_pipe {
+        Some((input, output)) => StreamBackend::pipe(input, output)
+            .map_err(|error| format!("Can't open the UART pipes: {error}"))?,
+        None => StreamBackend::stdio(),
+    };
+    let rng = match options.seed {
+        Some(seed) => Rng::new("rng", RNG_BASE, seed),
+        None => Rng::from_host_time("rng", RNG_BASE),
+    };
+    let watchdog = WatchdogConfig {
+        enabled: options.watchdog.is_some(),
+        timeout: options
+            .watchdog
+            .unwrap_or(WatchdogConfig::default().timeout),
+        action: match options.watchdog_reset {
+            true => WatchdogAction::Reset,
+            false => WatchdogAction::Nmi,
+        },
+        ..WatchdogConfig::default()
+    };
+    let mut input = InputQueue::new("input", INPUT_BASE);
+    if let Some(path) = &options.input {
+        input.load_script(read_input_script(path)?);
+    }
+    // The HTIF console only prints, the UART reads the input
+    let htif_console = StreamBackend::new(std::io::empty(), std::io::stdout());
+    let htif = Htif::new("htif", options.htif.unwrap_or(HTIF_BASE), htif_console);
+
+    attach(computer, TestFinisher::new("test_finisher"))?;
+    attach(computer, Rtc::new("rtc", RTC_BASE, RtcClock::Host))?;
+    attach(computer, Clint::new("clint", CLINT_BASE, harts))?;
+    attach(computer, Plic::new("plic", PLIC_BASE, harts))?;
+    attach(computer, Uart::new("uart", UART_BASE, uart_backend))?;
+    if let Some(path) = &options.disk {
+        let image = match options.read_only {
+            true => FileImage::open_read_only(path),
+            false => FileImage::open(path),
+        }
+        .map_err(|error| format!("Can't open the disk image: {error}"))?;
+        attach(computer, VirtioBlock::new("virtio", VIRTIO_BASE, image))?;
+    }
+    attach(computer, DmaController::new("dma", DMA_BASE))?;
+    attach(computer, Gpio::new("gpio", GPIO_BASE))?;
+    attach(computer, rng)?;
+    attach(computer, input)?;
+    attach(computer, Watchdog::new("watchdog", WATCHDOG_BASE, watchdog))?;
+    attach(computer, htif)?;
+    let framebuffer = Framebuffer::new(
+        "framebuffer",
+        FRAMEBUFFER_BASE,
+        FramebufferConfig {
+            format: options.pixel_format,
+            ..FramebufferConfig::default()
+        },
+    );
+    attach(computer, framebuffer)
+}
+
+fn attach(computer: &mut Computer, device: impl BusDevice) -> Result<(), String> {
+    let name = device.name().to_string();
+    computer
+        .attach_device(device)
+        .map_err(|error| format!("Can't attach {name}: {error}"))
+}
+
+/// Key events by tick, a line per event like `100 press 28`, empty lines and lines starting with # are skipped
+pub fn read_input_script(path: &Path) -> Result<Vec<(u64, InputEvent)>, String> {
+    let script = std::fs::read_to_string(path)
+        .map_err(|error| format!("Can't read the input script: {error}"))?;
+    script
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_input_event)
+        .collect()
+}
+
+fn parse_input_event(line: &str) -> Result<(u64, InputEvent), String> {
+    let invalid = || format!("Invalid input event {line}");
+    let [tick, action, code] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(invalid());
+    };
+    let tick = tick.parse().map_err(|_| invalid())?;
+    let code = code.parse().map_err(|_| invalid())?;
+    let event = match action {
+        "press" => InputEvent::press(code),
+        "release" => InputEvent::release(code),
+        _ => return Err(invalid()),
+    };
+    Ok((tick, event))
+}
+
+/// Per hart statistics of the run, ticks and whatever the configured backend and caches count
+pub fn print_stats(computer: &Computer) {
+    println!("Ticks: {}", computer.get_ticks());
+    for (index, hart) in computer.harts.iter().enumerate() {
+        println!("Hart {index}: {} ticks", hart.get_ticks());
+        if let Some(ooo) = hart.get_ooo() {
+            let stats = ooo.get_stats();
+            let branches = ooo.get_branch_stats().total();
+            println!(
+                "  {} instructions committed, IPC {:.2} of {:.2}",
+                stats.committed_instructions,
+                stats.ipc(),
+                stats.max_ipc()
+            );
+            println!(
+                "  {} predictor {:.1}% accurate",
+                ooo.get_branch_predictor().name(),
+                branches.accuracy() * 100.0
+            );
+        }
+        for (name, stats) in [
+            ("icache", hart.get_icache_stats()),
+            ("dcache", hart.get_dcache_stats()),
+        ] {
+            if let Some(stats) = stats {
+                println!(
+                    "  {name}: {} hits, {} misses, {:.1}% hit rate",
+                    stats.hits,
+                    stats.misses,
+                    stats.hit_rate() * 100.0
+                );
+            }
+        }
+        let ticks_saved = hart.get_fusion_stats().total_ticks_saved();
+        if ticks_saved > 0 {
+            println!("  Fusion saved {ticks_saved} ticks");
+        }
+    }
+    let expiries = computer
+        .devices
+        .get::<Watchdog>("watchdog")
+        .map_or(0, |watchdog| watchdog.get_expiries().len());
+    if expiries > 0 {
+        println!("Watchdog expired {expiries} times");
+    }
+}
@@ -3,13 +3,17 @@ use crate::compiler::layers::instructions::InstructionLayer;
 use crate::compiler::layers::program_builder::ProgramBuilderLayer;
 use crate::compiler::Compiler;
 use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::framebuffer::Framebuffer;
 use crate::computer::Computer;
 use crate::logging::initialize_logging;
+use crate::options::{ExecutionMode, Options, USAGE};
 use computer::components::cpu::registers::reg::CPUReg::*;
 
 mod compiler;
 mod computer;
 mod logging;
+mod machine;
+mod options;
 #[cfg(test)]
 mod tests;
 mod utils;
@@ -17,16 +21,78 @@ mod utils;
 fn main() {
     initialize_logging();
 
-    let program = Compiler::new()
-        .data("lb_data", vec![17])
-        .lb_label(X1, X0, "lb_data")
-        .add(X1, X1, X1)
-        .compile();
-    println!("{}", program.display_binary());
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) if options.help => {
+            print!("{USAGE}");
+            return;
+        }
+        Ok(options) => options,
+        Err(error) => fail(&error),
+    };
 
-    let mut computer = Computer::new();
-    computer.set_boot_rom(program.binary);
+    let mut computer = machine::build_computer(&options).unwrap_or_else(|error| fail(&error));
+    let program = match &options.program {
+        Some(path) => std::fs::read(path)
+            .unwrap_or_else(|error| fail(&format!("Can't read {}: {error}", path.display()))),
+        None => {
+            let program = Compiler::new()
+                .data("lb_data", vec![17])
+                .lb_label(X1, X0, "lb_data")
+                .add(X1, X1, X1)
+                .compile();
+            println!("{}", program.display_binary());
+            program.binary
+        }
+    };
 
-    while computer.tick() {}
-    println!("{}", computer.cpu.get_registers());
+    computer.set_boot_rom(program);
+    if options.device_tree && computer.boot_with_device_tree().is_none() {
+        fail("The device tree doesn't fit into the RAM");
+    }
+
+    run(&mut computer, &options);
+    computer.write_back_caches();
+
+    if let Some(path) = &options.framebuffer {
+        let framebuffer = computer.devices.get::<Framebuffer>("framebuffer").unwrap();
+        if let Err(error) = framebuffer.snapshot().save(path) {
+            eprintln!("Can't save the framebuffer to {}: {error}", path.display());
+        }
+    }
+    for hart in computer.harts.iter() {
+        println!("{}", hart.get_registers());
+    }
+    if options.stats {
+        machine::print_stats(&computer);
+    }
+    if let Some(code) = computer.get_exit_code() {
+        println!("Exited with code {code}");
+        std::process::exit(code as i32);
+    }
+}
+
+/// Runs until the harts stop or the tick limit, in instructions when fast-forwarding
+fn run(computer: &mut Computer, options: &Options) {
+    match (options.mode, options.ticks) {
+        (ExecutionMode::FastForward, Some(limit)) => {
+            computer.fast_forward(limit);
+        }
+        (ExecutionMode::FastForward, None) => while computer.step_instruction() {},
+        (_, Some(limit)) => {
+            for _ in 0..limit {
+                if !computer.tick() {
+                    break;
+                }
+            }
+        }
+        (_, None) => while computer.tick() {},
+    }
+    if options.mode != ExecutionMode::FastForward {
+        computer.finish_instruction();
+    }
+}
+
+fn fail(error: &str) -> ! {
+    eprintln!("{error}\n\n{USAGE}");
+    std::process::exit(2);
 }
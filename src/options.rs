@@ -0,0 +1,282 @@
+use crate::computer::components::cpu::cache::{Coherence, Replacement, WritePolicy};
+use crate::computer::components::cpu::misaligned::MisalignedPolicy;
+use crate::computer::components::framebuffer::PixelFormat;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: microop-computer [options] [program]
+
+Runs the raw binary program from the boot ROM, or a built-in demo program without one.
+
+Harts:
+  --harts <n>               Number of harts, 1 by default
+  --mode <mode>             in-order, out-of-order or fast-forward
+  --issue-width <n>         Issue width of the out-of-order backend
+  --predictor <predictor>   static, bimodal, gshare or tournament, out-of-order only
+  --fusion                  Fuses micro-ops and instructions
+  --misaligned <policy>     trap, split or allow
+  --icache, --dcache        Adds instruction or data caches
+  --replacement <policy>    lru, fifo or random
+  --write-policy <policy>   write-back or write-through
+  --coherence <protocol>    none, msi or mesi
+
+Memory:
+  --round-robin             Arbitrates the bus round robin instead of by fixed priority
+  --latency <ticks>         Wait states of RAM accesses
+  --beat-latency <ticks>    Wait states of each further RAM burst beat
+
+Devices:
+  --disk <image>            Attaches a virtio block device backed by the image
+  --read-only               Keeps the guest from writing the disk image
+  --uart-pipe <in> <out>    Connects the UART to named pipes instead of stdin and stdout
+  --htif <address>          Moves the HTIF mailbox, e.g. to the tohost symbol of riscv-tests
+  --framebuffer <png>       Saves the displayed framebuffer page when the machine stops
+  --pixel-format <format>   xrgb8888, rgb565 or gray8
+  --input <script>          Replays key events, lines of `<tick> press|release <key code>`
+  --watchdog <ticks>        Arms the watchdog to interrupt the harts after the ticks without a kick
+  --watchdog-reset          Resets the machine on expiry instead
+  --seed <seed>             Seeds the random number generator, host time by default
+  --semihosting <root>      Serves semihosting calls with files relative to the directory
+
+Run:
+  --device-tree             Passes a device tree to the harts
+  --ticks <n>               Stops after the ticks, instructions in fast-forward mode
+  --stats                   Prints statistics of the harts when the machine stops
+  --help                    Prints this help
+";
+
+/// How the harts execute the program
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionMode {
+    #[default]
+    InOrder,
+    OutOfOrder,
+    /// Whole instructions at a time, without micro-ops
+    FastForward,
+}
+
+/// Direction predictor of the out-of-order backend
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Predictor {
+    #[default]
+    Static,
+    Bimodal,
+    GShare,
+    Tournament,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub program: Option<PathBuf>,
+    pub harts: usize,
+    pub mode: ExecutionMode,
+    pub issue_width: Option<usize>,
+    pub predictor: Predictor,
+    pub fusion: bool,
+    pub misaligned: MisalignedPolicy,
+    pub icache: bool,
+    pub dcache: bool,
+    pub replacement: Replacement,
+    pub write_policy: WritePolicy,
+    pub coherence: Coherence,
+    pub round_robin: bool,
+    pub latency: u64,
+    pub beat_latency: u64,
+    pub disk: Option<PathBuf>,
+    pub read_only: bool,
+    pub uart_pipe: Option<(PathBuf, PathBuf)>,
+    pub htif: Option<u64>,
+    pub framebuffer: Option<PathBuf>,
+    pub pixel_format: PixelFormat,
+    pub input: Option<PathBuf>,
+    pub watchdog: Option<u64>,
+    pub watchdog_reset: bool,
+    pub seed: Option<u64>,
+    pub semihosting: Option<PathBuf>,
+    pub device_tree: bool,
+    pub ticks: Option<u64>,
+    pub stats: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            program: None,
+            harts: 1,
+            mode: ExecutionMode::default(),
+            issue_width: None,
+            predictor: Predictor::default(),
+            fusion: false,
+            misaligned: MisalignedPolicy::default(),
+            icache: false,
+            dcache: false,
+            replacement: Replacement::default(),
+            write_policy: WritePolicy::default(),
+            coherence: Coherence::default(),
+            round_robin: false,
+            latency: 0,
+            beat_latency: 0,
+            disk: None,
+            read_only: false,
+            uart_pipe: None,
+            htif: None,
+            framebuffer: None,
+            pixel_format: PixelFormat::default(),
+            input: None,
+            watchdog: None,
+            watchdog_reset: false,
+            seed: None,
+            semihosting: None,
+            device_tree: false,
+            ticks: None,
+            stats: false,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    /// Parses the arguments following the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("{arg} needs a value"));
+            match arg.as_str() {
+                "--harts" => {
+                    options.harts = number(&value()?)?;
+                    if options.harts == 0 {
+                        return Err("The machine needs at least one hart".to_string());
+                    }
+                }
+                "--mode" => {
+                    options.mode = choice(
+                        &value()?,
+                        &[
+                            ("in-order", ExecutionMode::InOrder),
+                            ("out-of-order", ExecutionMode::OutOfOrder),
+                            ("fast-forward", ExecutionMode::FastForward),
+                        ],
+                    )?
+                }
+                "--issue-width" => options.issue_width = Some(number(&value()?)?),
+                "--predictor" => {
+                    options.predictor = choice(
+                        &value()?,
+                        &[
+                            ("static", Predictor::Static),
+                            ("bimodal", Predictor::Bimodal),
+                            ("gshare", Predictor::GShare),
+                            ("tournament", Predictor::Tournament),
+                        ],
+                    )?
+                }
+                "--fusion" => options.fusion = true,
+                "--misaligned" => {
+                    options.misaligned = choice(
+                        &value()?,
+                        &[
+                            ("trap", MisalignedPolicy::Trap),
+                            ("split", MisalignedPolicy::Split),
+                            ("allow", MisalignedPolicy::Allow),
+                        ],
+                    )?
+                }
+                "--icache" => options.icache = true,
+                "--dcache" => options.dcache = true,
+                "--replacement" => {
+                    options.replacement = choice(
+                        &value()?,
+                        &[
+                            ("lru", Replacement::LRU),
+                            ("fifo", Replacement::FIFO),
+                            ("random", Replacement::Random),
+                        ],
+                    )?
+                }
+                "--write-policy" => {
+                    options.write_policy = choice(
+                        &value()?,
+                        &[
+                            ("write-back", WritePolicy::WriteBack),
+                            ("write-through", WritePolicy::WriteThrough),
+                        ],
+                    )?
+                }
+                "--coherence" => {
+                    options.coherence = choice(
+                        &value()?,
+                        &[
+                            ("none", Coherence::None),
+                            ("msi", Coherence::MSI),
+                            ("mesi", Coherence::MESI),
+                        ],
+                    )?
+                }
+                "--round-robin" => options.round_robin = true,
+                "--latency" => options.latency = number(&value()?)?,
+                "--beat-latency" => options.beat_latency = number(&value()?)?,
+                "--disk" => options.disk = Some(value()?.into()),
+                "--read-only" => options.read_only = true,
+                "--uart-pipe" => {
+                    let input = value()?;
+                    options.uart_pipe = Some((input.into(), value()?.into()));
+                }
+                "--htif" => options.htif = Some(number(&value()?)?),
+                "--framebuffer" => options.framebuffer = Some(value()?.into()),
+                "--pixel-format" => {
+                    options.pixel_format = choice(
+                        &value()?,
+                        &[
+                            ("xrgb8888", PixelFormat::XRGB8888),
+                            ("rgb565", PixelFormat::RGB565),
+                            ("gray8", PixelFormat::Gray8),
+                        ],
+                    )?
+                }
+                "--input" => options.input = Some(value()?.into()),
+                "--watchdog" => options.watchdog = Some(number(&value()?)?),
+                "--watchdog-reset" => options.watchdog_reset = true,
+                "--seed" => options.seed = Some(number(&value()?)?),
+                "--semihosting" => options.semihosting = Some(value()?.into()),
+                "--device-tree" => options.device_tree = true,
+                "--ticks" => options.ticks = Some(number(&value()?)?),
+                "--stats" => options.stats = true,
+                "--help" | "-h" => options.help = true,
+                flag if flag.starts_with('-') => return Err(format!("Unknown option {flag}")),
+                _ if options.program.is_none() => options.program = Some(arg.into()),
+                _ => return Err(format!("Unexpected argument {arg}")),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Decimal or, with a 0x prefix, hexadecimal number
+fn number<T: FromStr>(value: &str) -> Result<T, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(&hex.replace('_', ""), 16)
+            .map(|number| number.to_string())
+            .ok(),
+        None => Some(value.replace('_', "")),
+    };
+    parsed
+        .and_then(|number| number.parse().ok())
+        .ok_or(format!("Invalid number {value}"))
+}
+
+fn choice<T: Copy>(value: &str, choices: &[(&str, T)]) -> Result<T, String> {
+    choices
+        .iter()
+        .find(|(name, _)| *name == value)
+        .map(|(_, choice)| *choice)
+        .ok_or_else(|| {
+            let names: Vec<&str> = choices.iter().map(|(name, _)| *name).collect();
+            format!(
+                "Invalid choice {value}, expected one of {}",
+                names.join(", ")
+            )
+        })
+}
@@ -1,28 +1,143 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
 use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::ooo::OoOConfig;
+use crate::computer::components::cpu::registers::reg::CPUReg::X0;
 use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::BusDevice;
 use crate::computer::Computer;
+use std::marker::PhantomData;
 
+mod test_branch_prediction;
+mod test_burst;
+mod test_bus_response;
+mod test_cache;
+mod test_coherence;
+mod test_device_tree;
+mod test_devices;
+mod test_dma;
+mod test_execution_modes;
+mod test_finisher;
+mod test_framebuffer;
+mod test_fusion;
+mod test_gpio;
+mod test_htif;
+mod test_input;
 mod test_instructions;
+mod test_interrupts;
+mod test_memory_map;
+mod test_misaligned;
+mod test_multi_hart;
+mod test_options;
+mod test_out_of_order;
+mod test_rng;
+mod test_rtc;
+mod test_semihosting;
+mod test_uart;
+mod test_virtio;
+mod test_wait_states;
+mod test_watchdog;
 
-pub fn setup_and_run(program: Program, ticks: u64) -> Computer {
-    let mut computer = Computer::new();
-    computer.set_boot_rom(program.binary);
-    for _ in 0..ticks {
-        if !computer.tick() {
-            break;
+/// Implementation the harts run a test program with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    InOrder,
+    OutOfOrder,
+    /// Whole instructions at a time, without micro-ops
+    FastForward,
+}
+
+impl Mode {
+    /// Switches harts without an out-of-order backend to the default one in that mode
+    pub fn configure(self, computer: &mut Computer) {
+        if self != Mode::OutOfOrder {
+            return;
+        }
+        for hart in computer.harts.iter_mut() {
+            if hart.get_ooo().is_none() {
+                hart.set_out_of_order(Some(OoOConfig::default()));
+            }
         }
     }
-    computer
 }
 
-pub fn setup_and_run_custom_cpu(cpu: CPU, program: Program, ticks: u64) -> Computer {
+/// Ticks until the harts halt, the limit counts instructions in fast-forward mode
+pub fn run_computer(mode: Mode, computer: &mut Computer, ticks: u64) {
+    match mode {
+        Mode::FastForward => {
+            computer.fast_forward(ticks);
+        }
+        Mode::InOrder | Mode::OutOfOrder => {
+            for _ in 0..ticks {
+                if !computer.tick() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub fn setup_and_run(mode: Mode, program: Program, ticks: u64) -> Computer {
+    setup_and_run_custom_cpu(mode, CPU::new(), program, ticks)
+}
+
+pub fn setup_and_run_custom_cpu(mode: Mode, cpu: CPU, program: Program, ticks: u64) -> Computer {
     let mut computer = Computer::new();
-    computer.cpu = cpu;
+    computer.harts[0] = cpu;
+    mode.configure(&mut computer);
     computer.set_boot_rom(program.binary);
-    for _ in 0..ticks {
-        if !computer.tick() {
-            break;
+    run_computer(mode, &mut computer, ticks);
+    computer
+}
+
+/// Device of type `D` a test attaches at a base address and drives through its registers of type `R`
+pub struct DeviceFixture<D, R> {
+    name: &'static str,
+    base: u64,
+    types: PhantomData<(D, R)>,
+}
+
+impl<D: BusDevice, R: Into<u64> + TryFrom<u64>> DeviceFixture<D, R> {
+    pub const fn new(name: &'static str, base: u64) -> Self {
+        Self {
+            name,
+            base,
+            types: PhantomData,
         }
     }
-    computer
+
+    /// Computer with the device attached, the hart spins until the test loads its program
+    pub fn setup(&self, mode: Mode, cpu: CPU, device: D) -> Computer {
+        let mut computer = Computer::new();
+        computer.harts[0] = cpu;
+        mode.configure(&mut computer);
+        computer.attach_device(device).unwrap();
+        computer.set_boot_rom(Compiler::new().beq(X0, X0, 0).compile().binary);
+        computer
+    }
+
+    pub fn get<'a>(&self, computer: &'a Computer) -> &'a D {
+        computer.devices.get::<D>(self.name).unwrap()
+    }
+
+    pub fn get_mut<'a>(&self, computer: &'a mut Computer) -> &'a mut D {
+        computer.devices.get_mut::<D>(self.name).unwrap()
+    }
+
+    /// Reads without the side effects of a read by the harts
+    pub fn read_register(&self, computer: &Computer, offset: u64) -> R {
+        let value = computer.devices.read_dw(self.base + offset).unwrap();
+        let mask = u64::MAX >> (64 - 8 * size_of::<R>());
+        R::try_from(value & mask).unwrap_or_else(|_| unreachable!())
+    }
+
+    pub fn write_register(&self, computer: &mut Computer, offset: u64, value: R) {
+        let status = BusStatus::write_of_size(size_of::<R>() as u64);
+        computer
+            .devices
+            .write(self.base + offset, value.into(), status)
+            .unwrap();
+    }
 }
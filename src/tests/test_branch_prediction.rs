@@ -0,0 +1,203 @@
+use crate::compiler::layers::instruction_label::InstructionLabelLayer;
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::branch_prediction::predictors::{
+    Bimodal, GShare, StaticNotTaken, Tournament,
+};
+use crate::computer::components::cpu::branch_prediction::BranchPredictor;
+use crate::computer::components::cpu::builder::CPUBuildError;
+use crate::computer::components::cpu::ooo::{OoOConfig, OoOEvent};
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::Computer;
+use crate::tests::{setup_and_run, setup_and_run_custom_cpu, Mode};
+
+fn run_predicted(predictor: Box<dyn BranchPredictor>, program: Program) -> Computer {
+    let mut cpu = CPU::builder()
+        .out_of_order(OoOConfig::default())
+        .build()
+        .unwrap();
+    cpu.set_branch_predictor(predictor).unwrap();
+    setup_and_run_custom_cpu(Mode::OutOfOrder, cpu, program, 10000)
+}
+
+fn alternating_program() -> Program {
+    Compiler::new()
+        .addi(X2, X0, 20)
+        .addi(X4, X0, 1)
+        .sub(X3, X4, X3)
+        .beq(X3, X0, 8)
+        .addi(X5, X5, 1)
+        .addi(X1, X1, 1)
+        .bne(X1, X2, -16i64 as u64)
+        .compile()
+}
+
+#[test]
+fn test_predictors_match_in_order() {
+    let in_order = setup_and_run(Mode::InOrder, alternating_program(), 10000);
+    let predictors: Vec<Box<dyn BranchPredictor>> = vec![
+        Box::new(StaticNotTaken),
+        Box::new(Bimodal::new(16)),
+        Box::new(GShare::new(16, 4)),
+        Box::new(Tournament::new(16, 4)),
+    ];
+
+    for predictor in predictors {
+        let computer = run_predicted(predictor, alternating_program());
+        let name = computer.harts[0]
+            .get_ooo()
+            .unwrap()
+            .get_branch_predictor()
+            .name();
+        for reg in [X1, X3, X5, PC] {
+            assert_eq!(
+                computer.harts[0].get_register(reg),
+                in_order.harts[0].get_register(reg),
+                "{name}: {reg}"
+            );
+        }
+        let stats = computer.harts[0].get_ooo().unwrap().get_branch_stats();
+        assert_eq!(stats.get(12).executed, 20, "{name}");
+        assert_eq!(stats.get(12).taken, 10, "{name}");
+        assert_eq!(stats.get(24).executed, 20, "{name}");
+        assert_eq!(stats.get(24).taken, 19, "{name}");
+    }
+}
+
+#[test]
+fn test_predictor_needs_out_of_order() {
+    let mut cpu = CPU::builder().build().unwrap();
+    assert!(cpu
+        .set_branch_predictor(Box::new(Bimodal::new(16)))
+        .is_err());
+    assert!(cpu.get_ooo().is_none());
+
+    let cpu = CPU::builder().branch_predictor(GShare::new(16, 4)).build();
+    assert_eq!(cpu.err(), Some(CPUBuildError::PredictorWithoutOutOfOrder));
+
+    let cpu = CPU::builder()
+        .branch_predictor(GShare::new(16, 4))
+        .out_of_order(OoOConfig::default())
+        .build()
+        .unwrap();
+    let name = cpu.get_ooo().unwrap().get_branch_predictor().name();
+    assert_eq!(name, GShare::new(16, 4).name());
+}
+
+#[test]
+fn test_predictor_accuracy() {
+    let run = |predictor: Box<dyn BranchPredictor>| {
+        let computer = run_predicted(predictor, alternating_program());
+        computer.harts[0]
+            .get_ooo()
+            .unwrap()
+            .get_branch_stats()
+            .clone()
+    };
+    let static_not_taken = run(Box::new(StaticNotTaken));
+    let bimodal = run(Box::new(Bimodal::new(16)));
+    let gshare = run(Box::new(GShare::new(16, 4)));
+    let tournament = run(Box::new(Tournament::new(16, 4)));
+
+    // Static not-taken mispredicts exactly the taken branches
+    assert_eq!(static_not_taken.get(12).mispredicted, 10);
+    assert_eq!(static_not_taken.get(24).mispredicted, 19);
+    // The loop branch is biased, the inner branch alternates
+    assert!(bimodal.get(24).accuracy() > static_not_taken.get(24).accuracy());
+    assert!(gshare.get(12).accuracy() > bimodal.get(12).accuracy());
+    assert!(tournament.total().accuracy() > static_not_taken.total().accuracy());
+    assert!(tournament.total().accuracy() > 0.8);
+}
+
+#[test]
+fn test_mispredict_flushes_wrong_path() {
+    // The branch waits for the load, both are fetched with the wrong path instruction
+    let program = Compiler::new()
+        .data("value", vec![0])
+        .lb_label(X5, X0, "value")
+        .beq(X5, X0, 8)
+        .addi(X3, X0, 99)
+        .addi(X4, X0, 1)
+        .compile();
+    let cpu = CPU::builder().issue_width(2).build().unwrap();
+
+    let mut computer = Computer::new();
+    computer.harts[0] = cpu;
+    computer.set_boot_rom(program.binary);
+    let mut events = Vec::new();
+    for _ in 0..1000 {
+        let running = computer.tick();
+        events.extend_from_slice(computer.harts[0].get_ooo().unwrap().get_events());
+        if !running {
+            break;
+        }
+    }
+
+    assert!(events.contains(&OoOEvent::Mispredict {
+        address: 4,
+        predicted: 8,
+        actual: 12,
+    }));
+    assert!(events
+        .iter()
+        .any(|event| matches!(event, OoOEvent::Flush { squashed } if *squashed > 0)));
+    assert!(!events
+        .iter()
+        .any(|event| matches!(event, OoOEvent::Commit { address: 8, .. })));
+    assert_eq!(computer.harts[0].get_register(X3), 0);
+    assert_eq!(computer.harts[0].get_register(X4), 1);
+    let stats = computer.harts[0].get_ooo().unwrap().get_branch_stats();
+    assert_eq!(stats.get(4).mispredicted, 1);
+}
+
+#[test]
+fn test_return_address_stack() {
+    let program = Compiler::new()
+        .addi(X6, X0, 2)
+        .addi(X10, X0, 1)
+        .jal(X1, 16)
+        .sub(X6, X6, X10)
+        // X0 is not hardwired, the jumps below overwrite it
+        .bne(X6, X11, -8i64 as u64)
+        .jal(X0, 12)
+        .addi(X7, X7, 1)
+        .jalr(X0, X1, 0)
+        .compile();
+
+    let computer = run_predicted(Box::new(Bimodal::new(16)), program);
+
+    assert_eq!(computer.harts[0].get_register(X6), 0);
+    assert_eq!(computer.harts[0].get_register(X7), 2);
+    let stats = computer.harts[0].get_ooo().unwrap().get_branch_stats();
+    assert_eq!(stats.ras_predictions, 2);
+    assert_eq!(stats.get(28).executed, 2);
+    assert_eq!(stats.get(28).mispredicted, 0);
+    assert_eq!(stats.get(8).mispredicted, 0);
+}
+
+#[test]
+fn test_branch_target_buffer() {
+    let program = Compiler::new()
+        .addi(X6, X0, 3)
+        .addi(X8, X0, 20)
+        .addi(X10, X0, 1)
+        .jalr(X0, X8, 0)
+        .addi(X9, X0, 1)
+        .sub(X6, X6, X10)
+        .bne(X6, X11, -12i64 as u64)
+        .compile();
+
+    let computer = run_predicted(Box::new(Bimodal::new(16)), program);
+
+    assert_eq!(computer.harts[0].get_register(X6), 0);
+    assert_eq!(computer.harts[0].get_register(X9), 0);
+    let stats = computer.harts[0].get_ooo().unwrap().get_branch_stats();
+    assert_eq!(stats.btb_misses, 1);
+    assert_eq!(stats.btb_hits, 2);
+    assert_eq!(stats.get(12).executed, 3);
+    assert_eq!(stats.get(12).mispredicted, 0);
+}
@@ -0,0 +1,192 @@
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::address::{BOOT_ROM_END, BOOT_ROM_START, RAM_SIZE, RAM_START};
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::micro_op::MicroOp;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::BusDevice;
+use crate::computer::memory_map::{MemoryMap, MemoryRegion, Permissions, RegionKind};
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+use std::ops::RangeInclusive;
+
+const RECORDER_BASE: u64 = 0x2000_0000;
+
+/// Records single transfers, reads return the address; has no native burst support
+#[derive(Debug, Default)]
+struct Recorder {
+    transfers: Vec<(u64, BusStatus, u64)>,
+}
+
+impl BusDevice for Recorder {
+    fn name(&self) -> &str {
+        "recorder"
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        RECORDER_BASE..=RECORDER_BASE + 0xFF
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        let address = bus.get_address().value();
+        if bus.get_status() == BusStatus::Read {
+            bus.force_put_data(address);
+        }
+        self.transfers
+            .push((address, bus.get_status(), bus.get_data()));
+    }
+
+    fn read_dw(&self, address: u64) -> u64 {
+        address
+    }
+}
+
+fn memory_map(ram_latency: u64, ram_beat_latency: u64) -> MemoryMap {
+    MemoryMap::new(vec![
+        MemoryRegion::new(
+            "boot_rom",
+            BOOT_ROM_START,
+            BOOT_ROM_END - BOOT_ROM_START + 1,
+            RegionKind::ROM,
+            Permissions::READ_EXECUTE,
+        ),
+        MemoryRegion::new(
+            "ram",
+            RAM_START,
+            RAM_SIZE,
+            RegionKind::RAM,
+            Permissions::READ_WRITE_EXECUTE,
+        )
+        .with_latency(ram_latency)
+        .with_beat_latency(ram_beat_latency),
+    ])
+    .unwrap()
+}
+
+/// Runs the micro operations ahead of the boot rom, which only holds the EBREAK of an empty program
+fn run(cpu: CPU, map: MemoryMap, micro_ops: Vec<MicroOp>) -> Computer {
+    let mut computer = Computer::with_memory_map(map);
+    computer.harts[0] = cpu;
+    computer.harts[0].queue_micro_ops(micro_ops);
+    computer.set_boot_rom(Compiler::new().compile().binary);
+    computer.attach_device(Recorder::default()).unwrap();
+    run_computer(Mode::InOrder, &mut computer, 1000);
+    computer
+}
+
+fn read_burst(beats: Vec<CPUReg>) -> Vec<MicroOp> {
+    let mut micro_ops = vec![
+        MicroOp::BusTake,
+        MicroOp::BusWriteAddress(X1),
+        MicroOp::BusSetReadBurst(beats.len() as u8),
+    ];
+    micro_ops.extend(beats.into_iter().map(MicroOp::BusReadBeat));
+    micro_ops.push(MicroOp::BusRelease);
+    micro_ops
+}
+
+fn write_burst() -> Vec<MicroOp> {
+    vec![
+        MicroOp::BusTake,
+        MicroOp::BusWriteAddress(X1),
+        MicroOp::BusWriteData(X2),
+        MicroOp::BusSetWriteBurst(3),
+        MicroOp::BusWriteBeat(X3),
+        MicroOp::BusWriteBeat(X4),
+        MicroOp::BusAwaitWrite,
+        MicroOp::BusRelease,
+    ]
+}
+
+#[test]
+fn test_burst_round_trip() {
+    let cpu = CPU::builder()
+        .x1(RAM_START + 0x40)
+        .x2(11)
+        .x3(22)
+        .x4(33)
+        .build()
+        .unwrap();
+    let mut micro_ops = write_burst();
+    micro_ops.extend(read_burst(vec![X5, X6, X7]));
+
+    let computer = run(cpu, memory_map(0, 0), micro_ops);
+
+    assert_eq!(computer.harts[0].get_exception(), None);
+    assert_eq!(computer.devices.read_dw(RAM_START + 0x50), Ok(33));
+    assert_eq!(computer.harts[0].get_register(X5), 11);
+    assert_eq!(computer.harts[0].get_register(X6), 22);
+    assert_eq!(computer.harts[0].get_register(X7), 33);
+}
+
+#[test]
+fn test_burst_beat_latency() {
+    let cpu = || CPU::builder().x1(RAM_START).build().unwrap();
+    let burst = || read_burst(vec![X5, X6, X7, X8]);
+    let singles = || {
+        (0..4)
+            .flat_map(|_| {
+                [
+                    MicroOp::BusTake,
+                    MicroOp::BusWriteAddress(X1),
+                    MicroOp::BusSetRead,
+                    MicroOp::BusReadDoubleWord(X5),
+                    MicroOp::BusRelease,
+                ]
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let burst_fast = run(cpu(), memory_map(0, 0), burst()).harts[0].get_ticks();
+    let burst_slow = run(cpu(), memory_map(3, 1), burst()).harts[0].get_ticks();
+    let singles_slow = run(cpu(), memory_map(3, 1), singles()).harts[0].get_ticks();
+
+    // The first beat pays the full latency, the three following beats the cheaper beat latency
+    assert_eq!(burst_slow, burst_fast + 3 + 3);
+    assert!(burst_slow < singles_slow);
+}
+
+#[test]
+fn test_burst_split_for_device() {
+    let cpu = CPU::builder()
+        .x1(RECORDER_BASE)
+        .x2(11)
+        .x3(22)
+        .x4(33)
+        .build()
+        .unwrap();
+    let mut micro_ops = write_burst();
+    micro_ops.extend(read_burst(vec![X5, X6]));
+
+    let computer = run(cpu, memory_map(0, 0), micro_ops);
+
+    let recorder = computer.devices.get::<Recorder>("recorder").unwrap();
+    assert_eq!(
+        recorder.transfers[..3],
+        [
+            (RECORDER_BASE, BusStatus::WriteDoubleWord, 11),
+            (RECORDER_BASE + 8, BusStatus::WriteDoubleWord, 22),
+            (RECORDER_BASE + 16, BusStatus::WriteDoubleWord, 33),
+        ]
+    );
+    assert_eq!(computer.harts[0].get_register(X5), RECORDER_BASE);
+    assert_eq!(computer.harts[0].get_register(X6), RECORDER_BASE + 8);
+}
+
+#[rstest]
+#[case::crosses_region_end(RAM_START + RAM_SIZE - 8, Exception::LoadAccessFault(RAM_START + RAM_SIZE))]
+#[case::misaligned(RAM_START + 4, Exception::LoadAddressMisaligned(RAM_START + 4))]
+fn test_burst_faults(#[case] address: u64, #[case] exception: Exception) {
+    let cpu = CPU::builder().x1(address).build().unwrap();
+
+    let computer = run(cpu, memory_map(0, 0), read_burst(vec![X5, X6]));
+
+    assert_eq!(computer.harts[0].get_exception(), Some(exception));
+}
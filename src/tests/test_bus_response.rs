@@ -0,0 +1,135 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::response::BusResponse;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::BusDevice;
+use crate::computer::Computer;
+use crate::tests::{run_computer, setup_and_run_custom_cpu, Mode};
+use rstest::rstest;
+use std::ops::RangeInclusive;
+
+const SLOW_BASE: u64 = 0x2000_0000;
+
+/// Answers reads with Retry until the configured number of ticks has passed
+#[derive(Debug)]
+struct SlowDevice {
+    latency: u64,
+    remaining: Option<u64>,
+}
+
+impl BusDevice for SlowDevice {
+    fn name(&self) -> &str {
+        "slow"
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        SLOW_BASE..=SLOW_BASE + 0xFF
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        if bus.get_status() != BusStatus::Read {
+            return;
+        }
+        let remaining = self.remaining.get_or_insert(self.latency);
+        if *remaining > 0 {
+            *remaining -= 1;
+            bus.put_response(BusResponse::Retry);
+        } else {
+            self.remaining = None;
+            bus.force_put_data(42);
+        }
+    }
+
+    fn read_dw(&self, _address: u64) -> u64 {
+        42
+    }
+}
+
+fn run_all_modes(cpu: fn() -> CPU, program: Program) -> Vec<Computer> {
+    [Mode::InOrder, Mode::OutOfOrder, Mode::FastForward]
+        .into_iter()
+        .map(|mode| setup_and_run_custom_cpu(mode, cpu(), program.clone(), 1000))
+        .collect()
+}
+
+fn store_cpu() -> CPU {
+    CPU::builder().x1(RAM_START).x2(0xAB).build().unwrap()
+}
+
+#[rstest]
+#[case::rom(Compiler::new().sb(X0, X2, 0x100), Exception::StoreAccessFault(0x100))]
+#[case::misaligned(
+    Compiler::new().sw(X1, X2, 2),
+    Exception::StoreAddressMisaligned(RAM_START + 2)
+)]
+fn test_rejected_store(#[case] compiler: Compiler, #[case] exception: Exception) {
+    let program = compiler.addi(X3, X0, 1).compile();
+
+    for computer in run_all_modes(store_cpu, program) {
+        assert_eq!(computer.harts[0].get_exception(), Some(exception));
+        assert_eq!(computer.harts[0].get_register(X3), 0);
+        assert!(computer.bus.is_available());
+    }
+}
+
+#[test]
+fn test_store_then_load() {
+    let program = Compiler::new()
+        .sb(X1, X2, 16)
+        .lb(X3, X1, 16)
+        .addi(X4, X3, 1)
+        .compile();
+
+    for computer in run_all_modes(store_cpu, program) {
+        assert_eq!(computer.harts[0].get_exception(), None);
+        assert_eq!(computer.harts[0].get_register(X3), 0xFFFF_FFFF_FFFF_FFAB);
+        assert_eq!(computer.devices.read_dw(RAM_START + 16), Ok(0xAB));
+    }
+}
+
+fn run_slow_device(mode: Mode, latency: u64) -> Computer {
+    let program = Compiler::new()
+        .lui(X1, SLOW_BASE >> 12)
+        .lb(X2, X1, 0)
+        .compile();
+    let mut computer = Computer::new();
+    computer
+        .attach_device(SlowDevice {
+            latency,
+            remaining: None,
+        })
+        .unwrap();
+    mode.configure(&mut computer);
+    computer.set_boot_rom(program.binary);
+    run_computer(mode, &mut computer, 1000);
+    computer
+}
+
+#[test]
+fn test_retry_response_stalls() {
+    let fast = run_slow_device(Mode::InOrder, 0);
+    let slow = run_slow_device(Mode::InOrder, 5);
+
+    assert_eq!(slow.harts[0].get_register(X2), 42);
+    assert_eq!(slow.harts[0].get_exception(), None);
+    assert_eq!(slow.harts[0].get_ticks(), fast.harts[0].get_ticks() + 5);
+}
+
+#[test]
+fn test_retry_response_fast_forward() {
+    let computer = run_slow_device(Mode::FastForward, 5);
+
+    assert_eq!(computer.harts[0].get_register(X2), 42);
+    assert_eq!(computer.harts[0].get_exception(), None);
+    let slow = computer.devices.get::<SlowDevice>("slow").unwrap();
+    assert_eq!(slow.remaining, None);
+}
@@ -0,0 +1,352 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::{BOOT_ROM_END, BOOT_ROM_START, RAM_START};
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::cache::{CacheConfig, CacheStats, Replacement, WritePolicy};
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+/// A single set of two 16 byte lines, addresses 16 bytes apart compete for it
+fn tiny_cache() -> CacheConfig {
+    CacheConfig {
+        size: 32,
+        associativity: 2,
+        line_size: 16,
+        ..CacheConfig::default()
+    }
+}
+
+fn setup(mode: Mode, cpu: CPU, program: Program) -> Computer {
+    let mut computer = Computer::new();
+    computer.harts[0] = cpu;
+    mode.configure(&mut computer);
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+/// Returns the computer and the ticks until it halted
+fn run(mode: Mode, cpu: CPU, program: Program) -> (Computer, u64) {
+    let mut computer = setup(mode, cpu, program);
+    for tick in 1..=5000 {
+        if !computer.tick() {
+            return (computer, tick);
+        }
+    }
+    panic!("Program did not halt");
+}
+
+fn dcache_stats(computer: &Computer) -> CacheStats {
+    computer.harts[0].get_dcache_stats().unwrap()
+}
+
+#[rstest]
+fn test_dcache_hit_within_line(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new()
+        .ld(X4, X3, 0)
+        .ld(X5, X3, 8)
+        .lw(X6, X3, 12)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(RAM_START)
+        .dcache(tiny_cache())
+        .build()
+        .unwrap();
+    let mut computer = setup(mode, cpu, program);
+    computer
+        .devices
+        .write(RAM_START, 0x1111, BusStatus::WriteDoubleWord)
+        .unwrap();
+    computer
+        .devices
+        .write(
+            RAM_START + 8,
+            0xFFFF_FFFF_0000_2222,
+            BusStatus::WriteDoubleWord,
+        )
+        .unwrap();
+
+    run_computer(mode, &mut computer, 5000);
+
+    let hart = &computer.harts[0];
+    assert_eq!(hart.get_register(X4), 0x1111);
+    assert_eq!(hart.get_register(X5), 0xFFFF_FFFF_0000_2222);
+    // Sign extended like a bus read
+    assert_eq!(hart.get_register(X6), 0xFFFF_FFFF_FFFF_FFFF);
+    let stats = dcache_stats(&computer);
+    assert_eq!((stats.hits, stats.misses), (2, 1));
+    assert_eq!(stats.hit_rate(), 2.0 / 3.0);
+}
+
+#[rstest]
+#[case::lru(Replacement::LRU, 2, 3, 1)]
+#[case::fifo(Replacement::FIFO, 1, 4, 2)]
+fn test_replacement(
+    #[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode,
+    #[case] replacement: Replacement,
+    #[case] hits: u64,
+    #[case] misses: u64,
+    #[case] evictions: u64,
+) {
+    // Lines A, B, A, C, A: LRU keeps A when C is filled, FIFO evicts it
+    let program = Compiler::new()
+        .ld(X4, X3, 0)
+        .ld(X5, X3, 16)
+        .ld(X6, X3, 0)
+        .ld(X7, X3, 32)
+        .ld(X8, X3, 0)
+        .compile();
+    let config = CacheConfig {
+        replacement,
+        ..tiny_cache()
+    };
+    let cpu = CPU::builder().x3(RAM_START).dcache(config).build().unwrap();
+
+    let (computer, _) = run(mode, cpu, program);
+
+    let stats = dcache_stats(&computer);
+    assert_eq!(stats.hits, hits);
+    assert_eq!(stats.misses, misses);
+    assert_eq!(stats.evictions, evictions);
+}
+
+#[test]
+fn test_random_replacement() {
+    let program = Compiler::new()
+        .ld(X4, X3, 0)
+        .ld(X5, X3, 16)
+        .ld(X6, X3, 32)
+        .ld(X7, X3, 48)
+        .ld(X8, X3, 0)
+        .compile();
+    let config = CacheConfig {
+        replacement: Replacement::Random,
+        ..tiny_cache()
+    };
+    let cpu = || {
+        CPU::builder()
+            .x3(RAM_START)
+            .dcache(config.clone())
+            .build()
+            .unwrap()
+    };
+
+    let (first, _) = run(Mode::InOrder, cpu(), program.clone());
+    let (second, _) = run(Mode::InOrder, cpu(), program);
+
+    let stats = dcache_stats(&first);
+    assert_eq!(stats.hits + stats.misses, 5);
+    assert_eq!(stats.evictions, stats.misses - 2);
+    // Deterministic across runs
+    assert_eq!(stats, dcache_stats(&second));
+}
+
+#[rstest]
+fn test_write_back(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new().sd(X3, X4, 0).ld(X5, X3, 0).compile();
+    let cpu = CPU::builder()
+        .x3(RAM_START)
+        .x4(0xAB)
+        .dcache(tiny_cache())
+        .build()
+        .unwrap();
+
+    let (mut computer, _) = run(mode, cpu, program);
+
+    // The store allocated the line and only wrote it
+    assert_eq!(computer.harts[0].get_register(X5), 0xAB);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0));
+    computer.write_back_caches();
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0xAB));
+}
+
+#[rstest]
+fn test_write_back_on_eviction(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new()
+        .sd(X3, X4, 0)
+        .ld(X5, X3, 16)
+        .ld(X6, X3, 32)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(RAM_START)
+        .x4(0xAB)
+        .dcache(tiny_cache())
+        .build()
+        .unwrap();
+
+    let (computer, _) = run(mode, cpu, program);
+
+    let stats = dcache_stats(&computer);
+    assert_eq!(stats.evictions, 1);
+    assert_eq!(stats.write_backs, 1);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0xAB));
+}
+
+#[rstest]
+fn test_write_through(
+    #[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode,
+    #[values(true, false)] write_allocate: bool,
+) {
+    let program = Compiler::new().sw(X3, X4, 4).ld(X5, X3, 0).compile();
+    let config = CacheConfig {
+        write_policy: WritePolicy::WriteThrough,
+        write_allocate,
+        ..tiny_cache()
+    };
+    let cpu = CPU::builder()
+        .x3(RAM_START)
+        .x4(0xAB)
+        .dcache(config)
+        .build()
+        .unwrap();
+
+    let (computer, _) = run(mode, cpu, program);
+
+    // Memory is up to date without writing the cache back
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0xAB << 32));
+    assert_eq!(computer.harts[0].get_register(X5), 0xAB << 32);
+    let stats = dcache_stats(&computer);
+    assert_eq!(stats.write_backs, 0);
+    // Without allocation the load misses as well
+    let expected = if write_allocate { (1, 1) } else { (0, 2) };
+    assert_eq!((stats.hits, stats.misses), expected);
+}
+
+#[rstest]
+fn test_no_write_allocate(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new().sd(X3, X4, 0).compile();
+    let config = CacheConfig {
+        write_allocate: false,
+        ..tiny_cache()
+    };
+    let cpu = CPU::builder()
+        .x3(RAM_START)
+        .x4(0xAB)
+        .dcache(config)
+        .build()
+        .unwrap();
+
+    let (computer, _) = run(mode, cpu, program);
+
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0xAB));
+    assert!(!computer.harts[0]
+        .get_caches()
+        .data
+        .as_ref()
+        .unwrap()
+        .contains(RAM_START));
+}
+
+#[rstest]
+fn test_dcache_hits_are_faster(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // Loads the same double word ten times
+    let program = Compiler::new()
+        .ld(X4, X3, 0)
+        .sub(X5, X5, X6)
+        .bne(X5, X0, -8i64 as u64)
+        .compile();
+    let cpu = || CPU::builder().x3(RAM_START).x5(10).x6(1);
+
+    let (_, uncached) = run(mode, cpu().build().unwrap(), program.clone());
+    let (computer, cached) = run(
+        mode,
+        cpu().dcache(CacheConfig::default()).build().unwrap(),
+        program,
+    );
+
+    let stats = dcache_stats(&computer);
+    assert_eq!((stats.hits, stats.misses), (9, 1));
+    assert!(cached < uncached, "{cached} >= {uncached}");
+}
+
+#[rstest]
+fn test_icache(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new()
+        .sub(X5, X5, X6)
+        .bne(X5, X0, -4i64 as u64)
+        .compile();
+    let config = CacheConfig {
+        cacheable: vec![BOOT_ROM_START..=BOOT_ROM_END - 1],
+        ..CacheConfig::default()
+    };
+    let cpu = || CPU::builder().x5(20).x6(1);
+
+    let (_, uncached) = run(mode, cpu().build().unwrap(), program.clone());
+    let (computer, cached) = run(mode, cpu().icache(config).build().unwrap(), program);
+
+    assert_eq!(computer.harts[0].get_register(X5), 0);
+    let stats = computer.harts[0].get_icache_stats().unwrap();
+    assert!(stats.hits > stats.misses, "{stats:?}");
+    assert!(cached < uncached, "{cached} >= {uncached}");
+}
+
+#[rstest]
+fn test_uncacheable_access(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // The boot ROM is not cacheable by default
+    let program = Compiler::new().ld(X4, X0, 0).compile();
+    let cpu = CPU::builder()
+        .dcache(CacheConfig::default())
+        .build()
+        .unwrap();
+
+    let (computer, _) = run(mode, cpu, program.clone());
+
+    let expected = u64::from_le_bytes(program.binary[..8].try_into().unwrap());
+    assert_eq!(computer.harts[0].get_register(X4), expected);
+    assert_eq!(dcache_stats(&computer), CacheStats::default());
+}
+
+#[rstest]
+fn test_fill_bus_error(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // Cacheable but unmapped
+    let address = 0x4000_0000;
+    let program = Compiler::new().ld(X4, X3, 0).compile();
+    let config = CacheConfig {
+        cacheable: vec![address..=address + 0xFFF],
+        ..CacheConfig::default()
+    };
+    let cpu = CPU::builder().x3(address).dcache(config).build().unwrap();
+
+    let (computer, _) = run(mode, cpu, program);
+
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::LoadAccessFault(address))
+    );
+    assert!(computer.bus.is_available());
+}
+
+#[test]
+fn test_fast_mode_sees_cached_writes() {
+    let program = Compiler::new()
+        .sd(X3, X4, 0)
+        .ld(X5, X3, 0)
+        .sd(X3, X6, 8)
+        .ld(X7, X3, 8)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(RAM_START)
+        .x4(0xAB)
+        .x6(0xCD)
+        .dcache(CacheConfig::default())
+        .build()
+        .unwrap();
+    let mut computer = setup(Mode::InOrder, cpu, program);
+
+    // The first store ticked, the load and second store fast, the last load ticked again
+    computer.tick();
+    computer.finish_instruction();
+    computer.fast_forward(2);
+    while computer.tick() {}
+
+    assert_eq!(computer.harts[0].get_register(X5), 0xAB);
+    assert_eq!(computer.harts[0].get_register(X7), 0xCD);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0xAB));
+}
@@ -0,0 +1,149 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::cache::{CacheConfig, Coherence, LineState};
+use crate::computer::components::cpu::csr::CSR_MHARTID;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::memory_map::MemoryMap;
+use crate::computer::Computer;
+use crate::tests::Mode;
+use rstest::rstest;
+
+fn coherent_cache(coherence: Coherence) -> CacheConfig {
+    CacheConfig {
+        coherence,
+        ..CacheConfig::default()
+    }
+}
+
+/// Harts with coherent data caches running the same program
+fn setup(mode: Mode, harts: usize, coherence: Coherence, program: Program) -> Computer {
+    let harts = (0..harts)
+        .map(|_| {
+            CPU::builder()
+                .x3(RAM_START)
+                .x4(0xAB)
+                .x5(5)
+                .x6(1)
+                .dcache(coherent_cache(coherence))
+                .build()
+                .unwrap()
+        })
+        .collect();
+    let mut computer = Computer::with_harts(MemoryMap::default(), harts);
+    mode.configure(&mut computer);
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+fn run(mut computer: Computer) -> Computer {
+    for _ in 0..50_000 {
+        if !computer.tick() {
+            return computer;
+        }
+    }
+    panic!("Program did not halt");
+}
+
+fn line_state(computer: &Computer, hart: usize, address: u64) -> LineState {
+    computer.harts[hart]
+        .get_caches()
+        .data
+        .as_ref()
+        .unwrap()
+        .line_state(address)
+}
+
+#[rstest]
+fn test_store_invalidates_other_copies(
+    #[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode,
+    #[values(Coherence::MSI, Coherence::MESI)] coherence: Coherence,
+) {
+    // Hart 1 caches X and sets a flag, hart 0 waits for it, stores X and sets a second flag,
+    // then hart 1 loads X again
+    let program = Compiler::new()
+        .csrr(X1, CSR_MHARTID)
+        .bne(X1, X0, 24)
+        // Hart 0
+        .ld(X2, X3, 64)
+        .beq(X2, X0, -4i64 as u64)
+        .sd(X3, X4, 0)
+        .sd(X3, X6, 128)
+        .jal(X0, 24)
+        // Hart 1
+        .ld(X7, X3, 0)
+        .sd(X3, X6, 64)
+        .ld(X2, X3, 128)
+        .beq(X2, X0, -4i64 as u64)
+        .ld(X7, X3, 0)
+        .compile();
+    let mut computer = setup(mode, 2, coherence, program);
+    computer
+        .devices
+        .write(RAM_START, 0x11, BusStatus::WriteDoubleWord)
+        .unwrap();
+
+    let computer = run(computer);
+
+    assert_eq!(computer.harts[1].get_register(X7), 0xAB);
+    let stats = computer.harts[1].get_dcache_stats().unwrap();
+    assert!(stats.invalidations >= 1, "{stats:?}");
+    // Hart 1's read took the modified line from hart 0, which wrote it to memory
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0xAB));
+    assert_eq!(line_state(&computer, 0, RAM_START), LineState::Shared);
+    assert_eq!(line_state(&computer, 1, RAM_START), LineState::Shared);
+}
+
+#[rstest]
+#[case::msi(Coherence::MSI, 1)]
+#[case::mesi(Coherence::MESI, 0)]
+fn test_write_after_read(
+    #[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode,
+    #[case] coherence: Coherence,
+    #[case] upgrades: u64,
+) {
+    // MESI fills an unshared line exclusively and writes it without a bus transaction
+    let program = Compiler::new().ld(X2, X3, 0).sd(X3, X4, 0).compile();
+    let computer = setup(mode, 1, coherence, program);
+
+    let computer = run(computer);
+
+    let stats = computer.harts[0].get_dcache_stats().unwrap();
+    assert_eq!(stats.upgrades, upgrades);
+    assert_eq!((stats.hits, stats.misses), (1, 1));
+    assert_eq!(line_state(&computer, 0, RAM_START), LineState::Modified);
+}
+
+#[rstest]
+fn test_spinlock_counter(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // The lock is at X3, the counter in the following double word of the same line
+    let program = Compiler::new()
+        .lr_d(X1, X3)
+        .bne(X1, X0, -4i64 as u64)
+        .sc_d(X2, X3, X6)
+        .bne(X2, X0, -12i64 as u64)
+        .ld(X4, X3, 8)
+        .addi(X4, X4, 1)
+        .sd(X3, X4, 8)
+        .sd(X3, X0, 0)
+        .sub(X5, X5, X6)
+        .bne(X5, X0, -36i64 as u64)
+        .compile();
+    let computer = setup(mode, 3, Coherence::MESI, program);
+
+    let mut computer = run(computer);
+
+    assert!(computer
+        .harts
+        .iter()
+        .all(|hart| hart.get_exception().is_none()));
+    computer.write_back_caches();
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0));
+    assert_eq!(computer.devices.read_dw(RAM_START + 8), Ok(15));
+}
@@ -0,0 +1,237 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::address::{RAM_SIZE, RAM_START};
+use crate::computer::components::clint::Clint;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::plic::Plic;
+use crate::computer::components::test_finisher::TestFinisher;
+use crate::computer::components::uart::backend::MemoryBackend;
+use crate::computer::components::uart::Uart;
+use crate::computer::fdt::*;
+use crate::computer::memory_map::MemoryMap;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+use std::collections::HashMap;
+
+const UART_BASE: u64 = 0x1000_0000;
+const CLINT_BASE: u64 = 0x0200_0000;
+const PLIC_BASE: u64 = 0x0C00_0000;
+
+/// Two harts with a CLINT, a PLIC, a UART and a test finisher, spinning in the boot rom
+fn setup() -> Computer {
+    let mut computer = setup_without_plic();
+    let harts = computer.harts.len();
+    computer
+        .attach_device(Plic::new("plic", PLIC_BASE, harts))
+        .unwrap();
+    computer
+}
+
+fn setup_without_plic() -> Computer {
+    let mut computer =
+        Computer::with_harts(MemoryMap::default(), vec![CPU::default(), CPU::default()]);
+    let harts = computer.harts.len();
+    computer
+        .attach_device(Clint::new("clint", CLINT_BASE, harts))
+        .unwrap();
+    computer
+        .attach_device(Uart::new("uart", UART_BASE, MemoryBackend::new()))
+        .unwrap();
+    computer
+        .attach_device(TestFinisher::new("finisher"))
+        .unwrap();
+    computer.set_boot_rom(Compiler::new().beq(X0, X0, 0).compile().binary);
+    computer
+}
+
+fn be32(blob: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(blob[offset..offset + 4].try_into().unwrap())
+}
+
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|byte| *byte == 0).unwrap();
+    String::from_utf8(bytes[..end].to_vec()).unwrap()
+}
+
+/// Properties of every node by path, e.g. `/soc/serial@10000000`
+fn parse(blob: &[u8]) -> HashMap<String, HashMap<String, Vec<u8>>> {
+    let structure = be32(blob, 8) as usize;
+    let strings = be32(blob, 12) as usize;
+    let mut nodes = HashMap::new();
+    let mut path: Vec<String> = Vec::new();
+    let mut offset = structure;
+    loop {
+        let token = be32(blob, offset);
+        offset += 4;
+        match token {
+            1 => {
+                let name = c_string(&blob[offset..]);
+                offset = (offset + name.len() + 1).next_multiple_of(4);
+                path.push(name);
+                nodes.insert(path.join("/"), HashMap::new());
+            }
+            2 => {
+                path.pop();
+            }
+            3 => {
+                let length = be32(blob, offset) as usize;
+                let name = c_string(&blob[strings + be32(blob, offset + 4) as usize..]);
+                let value = blob[offset + 8..offset + 8 + length].to_vec();
+                offset = (offset + 8 + length).next_multiple_of(4);
+                nodes.get_mut(&path.join("/")).unwrap().insert(name, value);
+            }
+            9 => return nodes,
+            token => panic!("Unexpected token {token}"),
+        }
+    }
+}
+
+fn cells(value: &[u8]) -> Vec<u32> {
+    value
+        .chunks(4)
+        .map(|cell| u32::from_be_bytes(cell.try_into().unwrap()))
+        .collect()
+}
+
+fn strings(value: &[u8]) -> Vec<String> {
+    value
+        .split(|byte| *byte == 0)
+        .filter(|string| !string.is_empty())
+        .map(|string| String::from_utf8(string.to_vec()).unwrap())
+        .collect()
+}
+
+#[rstest]
+fn test_header() {
+    let blob = setup().device_tree();
+
+    assert_eq!(be32(&blob, 0), FDT_MAGIC);
+    assert_eq!(be32(&blob, 4) as usize, blob.len());
+    assert_eq!(be32(&blob, 20), FDT_VERSION);
+    // Memory reservation block with only the terminating entry
+    let reservations = be32(&blob, 16) as usize;
+    assert_eq!(&blob[reservations..reservations + 16], [0; 16]);
+}
+
+#[rstest]
+fn test_harts_and_memory() {
+    let nodes = parse(&setup().device_tree());
+
+    for hart in 0..2u32 {
+        let cpu = &nodes[&format!("/cpus/cpu@{hart}")];
+        assert_eq!(cells(&cpu["reg"]), [hart]);
+        assert_eq!(strings(&cpu["riscv,isa"]), [ISA]);
+        assert_eq!(strings(&cpu["riscv,isa-extensions"]), ["zicsr", "zalrsc"]);
+        assert!(!cpu.contains_key("riscv,isa-base"));
+        let intc = &nodes[&format!("/cpus/cpu@{hart}/interrupt-controller")];
+        assert_eq!(
+            cells(&intc["phandle"]),
+            [cpu_interrupt_controller_phandle(hart as usize)]
+        );
+    }
+    assert!(!nodes.contains_key("/cpus/cpu@2"));
+
+    let memory = &nodes[&format!("/memory@{RAM_START:x}")];
+    let reg = [0, RAM_START as u32, 0, RAM_SIZE as u32];
+    assert_eq!(cells(&memory["reg"]), reg);
+}
+
+#[rstest]
+fn test_devices() {
+    let nodes = parse(&setup().device_tree());
+
+    let uart = &nodes[&format!("/soc/serial@{UART_BASE:x}")];
+    assert_eq!(strings(&uart["compatible"]), ["ns16550a"]);
+    assert_eq!(cells(&uart["reg"])[..2], [0, UART_BASE as u32]);
+    let chosen = &nodes["/chosen"];
+    let stdout = format!("/soc/serial@{UART_BASE:x}");
+    assert_eq!(strings(&chosen["stdout-path"]), [stdout]);
+
+    // Software and timer interrupts of both harts
+    let clint = &nodes[&format!("/soc/clint@{CLINT_BASE:x}")];
+    assert_eq!(
+        cells(&clint["interrupts-extended"]),
+        [1, 3, 1, 7, 2, 3, 2, 7]
+    );
+    assert!(nodes.contains_key("/soc/test@100000"));
+}
+
+#[rstest]
+fn test_interrupts() {
+    let nodes = parse(&setup().device_tree());
+
+    // External interrupts of both harts
+    let plic = &nodes[&format!("/soc/plic@{PLIC_BASE:x}")];
+    assert_eq!(
+        strings(&plic["compatible"]),
+        ["sifive,plic-1.0.0", "riscv,plic0"]
+    );
+    assert_eq!(cells(&plic["interrupts-extended"]), [1, 11, 2, 11]);
+    assert_eq!(cells(&plic["phandle"]), [PLIC_PHANDLE]);
+    assert!(plic.contains_key("interrupt-controller"));
+
+    let uart = &nodes[&format!("/soc/serial@{UART_BASE:x}")];
+    assert_eq!(cells(&uart["interrupt-parent"]), [PLIC_PHANDLE]);
+    assert_eq!(cells(&uart["interrupts"]), [1]);
+    // The test finisher has no interrupt line
+    assert!(!nodes["/soc/test@100000"].contains_key("interrupts"));
+}
+
+#[rstest]
+fn test_no_interrupts_without_plic() {
+    let nodes = parse(&setup_without_plic().device_tree());
+
+    let uart = &nodes[&format!("/soc/serial@{UART_BASE:x}")];
+    assert!(!uart.contains_key("interrupts"));
+    assert!(!uart.contains_key("interrupt-parent"));
+}
+
+#[rstest]
+fn test_boot_with_device_tree() {
+    let mut computer = setup();
+
+    let address = computer.boot_with_device_tree().unwrap();
+
+    let blob = computer.device_tree();
+    assert_eq!(address % 8, 0);
+    assert!(address + blob.len() as u64 <= RAM_START + RAM_SIZE);
+    for (index, hart) in computer.harts.iter().enumerate() {
+        assert_eq!(hart.get_register(X10), index as u64);
+        assert_eq!(hart.get_register(X11), address);
+    }
+    for (offset, chunk) in blob.chunks(8).enumerate() {
+        let value = computer
+            .devices
+            .read_dw(address + offset as u64 * 8)
+            .unwrap();
+        assert_eq!(&value.to_le_bytes()[..chunk.len()], chunk);
+    }
+}
+
+#[rstest]
+fn test_guest_reads_magic() {
+    let mut computer = setup();
+    computer.set_boot_rom(Compiler::new().lw(X5, X11, 0).compile().binary);
+    computer.boot_with_device_tree().unwrap();
+
+    run_computer(Mode::InOrder, &mut computer, 1000);
+
+    let magic = u32::from_le_bytes(FDT_MAGIC.to_be_bytes());
+    assert_eq!(computer.harts[0].get_register(X5), magic as i32 as u64);
+}
+
+#[rstest]
+fn test_reset_places_device_tree_again() {
+    let mut computer = setup();
+    let address = computer.boot_with_device_tree().unwrap();
+
+    computer.reset();
+
+    assert_eq!(computer.harts[1].get_register(X11), address);
+    let header = computer.devices.read_dw(address).unwrap();
+    assert_eq!(header as u32, u32::from_le_bytes(FDT_MAGIC.to_be_bytes()));
+}
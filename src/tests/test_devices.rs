@@ -0,0 +1,80 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::device::registry::DeviceError;
+use crate::computer::components::device::BusDevice;
+use crate::computer::Computer;
+use std::ops::RangeInclusive;
+
+const COUNTER_BASE: u64 = 0x1000_0000;
+
+/// Counts computer ticks, reading the device returns the current count
+#[derive(Debug, Default)]
+struct TickCounter {
+    ticks: u64,
+    reads: u64,
+}
+
+impl BusDevice for TickCounter {
+    fn name(&self) -> &str {
+        "counter"
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        COUNTER_BASE..=COUNTER_BASE + 0xFF
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        if bus.get_status() == BusStatus::Read {
+            self.reads += 1;
+            bus.force_put_data(self.ticks);
+        }
+    }
+
+    fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    fn read_dw(&self, _address: u64) -> u64 {
+        self.ticks
+    }
+}
+
+#[test]
+fn test_custom_device() {
+    let program = Compiler::new()
+        .lui(X2, COUNTER_BASE >> 12)
+        .lb(X1, X2, 0)
+        .compile();
+
+    let mut computer = Computer::new();
+    computer.attach_device(TickCounter::default()).unwrap();
+    computer.set_boot_rom(program.binary);
+    while computer.tick() {}
+
+    let counter = computer.devices.get::<TickCounter>("counter").unwrap();
+    assert!(counter.reads > 0);
+    assert_eq!(counter.ticks, computer.harts[0].get_ticks());
+    assert!(computer.harts[0].get_register(X1) > 0);
+    assert!(computer.harts[0].get_register(X1) < counter.ticks);
+}
+
+#[test]
+fn test_overlapping_device_rejected() {
+    let mut computer = Computer::new();
+    computer.attach_device(TickCounter::default()).unwrap();
+
+    assert_eq!(
+        computer.attach_device(TickCounter::default()),
+        Err(DeviceError::Overlap {
+            device: "counter".to_string(),
+            existing: "counter".to_string(),
+        })
+    );
+    assert_eq!(computer.devices.devices().len(), 3);
+    assert!(computer.devices.get::<TickCounter>("rom").is_none());
+}
@@ -0,0 +1,246 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::arbitration::Arbitration;
+use crate::computer::components::bus::owner::BusOwner;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::bus::Bus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::BusDevice;
+use crate::computer::components::dma::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+use std::ops::RangeInclusive;
+
+const DMA_BASE: u64 = 0x1000_0000;
+const SINK_BASE: u64 = 0x2000_0000;
+const SOURCE: u64 = RAM_START + 0x100;
+const DATA: u64 = 0x1122_3344_5566_7788;
+
+/// Records every byte written to it
+#[derive(Debug, Default)]
+struct Sink {
+    bytes: Vec<u8>,
+}
+
+impl BusDevice for Sink {
+    fn name(&self) -> &str {
+        "sink"
+    }
+
+    fn address_range(&self) -> RangeInclusive<u64> {
+        SINK_BASE..=SINK_BASE + 0xFF
+    }
+
+    fn process_bus(&mut self, bus: &mut Bus) {
+        if bus.get_status() == BusStatus::WriteByte {
+            self.bytes.push(bus.get_data() as u8);
+        }
+    }
+
+    fn read_dw(&self, _address: u64) -> u64 {
+        0
+    }
+}
+
+fn setup(cpu: CPU) -> Computer {
+    let mut computer = Computer::new();
+    computer.harts[0] = cpu;
+    computer
+        .attach_device(DmaController::new("dma", DMA_BASE))
+        .unwrap();
+    computer
+        .devices
+        .write(SOURCE, DATA, BusStatus::WriteDoubleWord)
+        .unwrap();
+    computer
+}
+
+fn start_transfer(computer: &mut Computer, destination: u64, length: u64, control: u64) {
+    for (offset, value) in [
+        (DMA_SOURCE, SOURCE),
+        (DMA_DESTINATION, destination),
+        (DMA_LENGTH, length),
+        (DMA_CONTROL, control | DMA_CONTROL_START),
+    ] {
+        computer
+            .devices
+            .write(DMA_BASE + offset, value, BusStatus::WriteDoubleWord)
+            .unwrap();
+    }
+}
+
+fn dma(computer: &Computer) -> &DmaController {
+    computer.devices.get::<DmaController>("dma").unwrap()
+}
+
+/// Ticks until the transfer has finished, returning the number of ticks
+fn run_transfer(computer: &mut Computer) -> u64 {
+    let mut ticks = 0;
+    while dma(computer).is_busy() {
+        computer.tick();
+        ticks += 1;
+        assert!(ticks < 10_000, "DMA transfer does not finish");
+    }
+    ticks
+}
+
+#[rstest]
+fn test_memory_to_memory_copy(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // The CPU programs the controller and polls the status until the copy is done
+    let program = Compiler::new()
+        .lui(X1, DMA_BASE >> 12)
+        .sd(X1, X2, DMA_SOURCE)
+        .sd(X1, X3, DMA_DESTINATION)
+        .sd(X1, X4, DMA_LENGTH)
+        .sd(X1, X5, DMA_CONTROL)
+        .lb(X6, X1, DMA_STATUS)
+        .beq(X6, X7, -4i64 as u64)
+        .compile();
+    let cpu = CPU::builder()
+        .x2(SOURCE)
+        .x3(RAM_START + 0x200)
+        .x4(8)
+        .x5(DMA_CONTROL_START | DMA_CONTROL_INTERRUPT_ENABLE)
+        .x7(DMA_STATUS_BUSY)
+        .build()
+        .unwrap();
+
+    let mut computer = setup(cpu);
+    mode.configure(&mut computer);
+    computer.set_boot_rom(program.binary);
+    run_computer(mode, &mut computer, 10_000);
+
+    assert_eq!(computer.harts[0].get_register(X6), DMA_STATUS_DONE);
+    assert_eq!(computer.devices.read_dw(RAM_START + 0x200), Ok(DATA));
+    assert_eq!(dma(&computer).get_copied(), 8);
+    assert_eq!(computer.pending_interrupts(), vec!["dma"]);
+
+    // Writing the DONE bit acknowledges the interrupt
+    computer
+        .devices
+        .write(
+            DMA_BASE + DMA_STATUS,
+            DMA_STATUS_DONE,
+            BusStatus::WriteDoubleWord,
+        )
+        .unwrap();
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_memory_to_device_copy() {
+    let mut computer = setup(CPU::default());
+    computer.attach_device(Sink::default()).unwrap();
+    computer.set_boot_rom(Compiler::new().jal(X0, 0).compile().binary);
+
+    start_transfer(&mut computer, SINK_BASE, 4, DMA_CONTROL_FIXED_DESTINATION);
+    run_transfer(&mut computer);
+
+    let sink = computer.devices.get::<Sink>("sink").unwrap();
+    assert_eq!(sink.bytes, vec![0x88, 0x77, 0x66, 0x55]);
+    assert_eq!(dma(&computer).get_status(), DMA_STATUS_DONE);
+    // Interrupts were not enabled
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_transfer_bus_error() {
+    let mut computer = setup(CPU::default());
+    computer.set_boot_rom(Compiler::new().jal(X0, 0).compile().binary);
+
+    // Nothing is mapped at the destination
+    start_transfer(&mut computer, 0x4000_0000, 4, DMA_CONTROL_INTERRUPT_ENABLE);
+    run_transfer(&mut computer);
+
+    assert_eq!(dma(&computer).get_status(), DMA_STATUS_ERROR);
+    assert_eq!(dma(&computer).get_copied(), 0);
+    assert_eq!(computer.pending_interrupts(), vec!["dma"]);
+}
+
+#[rstest]
+#[case::fixed_priority(Arbitration::FixedPriority(vec![BusOwner::DMA, BusOwner::CPU(0)]), BusOwner::DMA)]
+#[case::round_robin(Arbitration::RoundRobin, BusOwner::CPU(0))]
+fn test_arbitration_grants_waiting_master(
+    #[case] arbitration: Arbitration,
+    #[case] expected: BusOwner,
+) {
+    let mut bus = Bus::with_arbitration(arbitration);
+    assert!(bus.take_ownership(BusOwner::DMA));
+    assert!(!bus.take_ownership(BusOwner::CPU(0)));
+
+    // The DMA releases and asks again right away while the CPU is still waiting
+    bus.release_ownership(BusOwner::DMA);
+    let dma_granted = bus.take_ownership(BusOwner::DMA);
+    let cpu_granted = bus.take_ownership(BusOwner::CPU(0));
+
+    assert_eq!(dma_granted, expected == BusOwner::DMA);
+    assert_eq!(cpu_granted, expected == BusOwner::CPU(0));
+    assert_eq!(bus.get_owner(), expected);
+}
+
+#[test]
+fn test_arbitration_policies() {
+    // The CPU counts in a loop while the DMA copies, both compete for the bus
+    let program = Compiler::new()
+        .addi(X1, X1, 1)
+        .jal(X0, -4i64 as u64)
+        .compile();
+    let run = |arbitration: Arbitration| {
+        let mut computer = setup(CPU::default());
+        computer.bus.set_arbitration(arbitration);
+        computer.set_boot_rom(program.binary.clone());
+        // The unaligned destination is copied bytewise, one bus tenure per byte
+        start_transfer(&mut computer, RAM_START + 0x201, 8, 0);
+        let ticks = run_transfer(&mut computer);
+        assert_eq!(computer.devices.read_dw(RAM_START + 0x201), Ok(DATA));
+        (ticks, computer.harts[0].get_register(X1))
+    };
+
+    let (dma_first, cpu_starved) = run(Arbitration::FixedPriority(vec![
+        BusOwner::DMA,
+        BusOwner::CPU(0),
+    ]));
+    let (round_robin, cpu_shared) = run(Arbitration::RoundRobin);
+    let (cpu_first, cpu_preferred) = run(Arbitration::FixedPriority(vec![
+        BusOwner::CPU(0),
+        BusOwner::DMA,
+    ]));
+
+    assert!(dma_first < round_robin);
+    assert!(round_robin <= cpu_first);
+    assert!(cpu_starved < cpu_shared);
+    assert!(cpu_shared <= cpu_preferred);
+}
+
+#[rstest]
+fn test_reset_aborts_transfer() {
+    let mut computer = setup(CPU::default());
+    computer.set_boot_rom(Compiler::new().beq(X0, X0, 0).compile().binary);
+    let destination = RAM_START + 0x200;
+    start_transfer(&mut computer, destination, 0x80, 0);
+    for _ in 0..3 {
+        computer.tick();
+    }
+    assert!(dma(&computer).is_busy());
+
+    computer.reset();
+    // Source data the aborted transfer must not copy
+    computer
+        .devices
+        .write(SOURCE, DATA, BusStatus::WriteDoubleWord)
+        .unwrap();
+    for _ in 0..500 {
+        computer.tick();
+    }
+
+    assert!(!dma(&computer).is_busy());
+    assert_eq!(dma(&computer).get_status(), 0);
+    assert_eq!(computer.devices.read_dw(DMA_BASE + DMA_LENGTH), Ok(0));
+    assert_eq!(computer.devices.read_dw(destination), Ok(0));
+}
@@ -0,0 +1,104 @@
+use crate::compiler::layers::instruction_label::InstructionLabelLayer;
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::Computer;
+use crate::tests::{setup_and_run, Mode};
+
+fn build_program() -> Program {
+    Compiler::new()
+        .data("value", vec![17])
+        .lb_label(X1, X0, "value")
+        .add(X1, X1, X1)
+        .add(X2, X1, X1)
+        .sub(X3, X2, X1)
+        .lui(X4, 0x1)
+        .addi(X4, X4, 5)
+        .auipc(X5, 0)
+        .compile()
+}
+
+#[test]
+fn test_fast_forward_matches_micro_op_mode() {
+    let micro_op = setup_and_run(Mode::InOrder, build_program(), 1000);
+
+    let mut fast = Computer::new();
+    fast.set_boot_rom(build_program().binary);
+    assert!(!fast.fast_forward(100));
+
+    for reg in [X1, X2, X3, X4, X5, PC] {
+        assert_eq!(
+            fast.harts[0].get_register(reg),
+            micro_op.harts[0].get_register(reg)
+        );
+    }
+    assert_eq!(fast.harts[0].get_register(X3), 34);
+    assert_eq!(fast.harts[0].get_register(X4), 0x1005);
+    assert_eq!(fast.harts[0].get_register(X5), 24);
+}
+
+#[test]
+fn test_fast_forward_then_micro_step() {
+    let mut computer = Computer::new();
+    computer.set_boot_rom(build_program().binary);
+
+    assert!(computer.fast_forward(2));
+    assert_eq!(computer.harts[0].get_register(X1), 34);
+    assert_eq!(computer.harts[0].get_register(PC), 8);
+
+    // Fetch/Decode cycle (6 ticks) + ADD (1 tick)
+    for _ in 0..7 {
+        assert!(computer.tick());
+    }
+    assert!(computer.harts[0].is_at_instruction_boundary());
+    assert_eq!(computer.harts[0].get_register(X2), 68);
+    assert_eq!(computer.harts[0].get_register(PC), 12);
+}
+
+#[test]
+fn test_fast_forward_finishes_partial_instruction() {
+    let mut computer = Computer::new();
+    computer.set_boot_rom(build_program().binary);
+
+    // Stop in the middle of the first fetch/decode cycle
+    for _ in 0..3 {
+        computer.tick();
+    }
+    assert!(!computer.harts[0].is_at_instruction_boundary());
+
+    assert!(computer.fast_forward(1));
+    assert!(computer.harts[0].is_at_instruction_boundary());
+    assert_eq!(computer.harts[0].get_register(X1), 34);
+}
+
+#[test]
+fn test_jalr_clears_bit_zero() {
+    // Jumps to 12, the odd target has its lowest bit cleared
+    let program = || {
+        Compiler::new()
+            .addi(X1, X0, 13)
+            .jalr(X2, X1, 0)
+            .addi(X3, X0, 1)
+            .addi(X4, X0, 2)
+            .compile()
+    };
+    let micro_op = setup_and_run(Mode::InOrder, program(), 1000);
+
+    let mut fast = Computer::new();
+    fast.set_boot_rom(program().binary);
+    assert!(!fast.fast_forward(100));
+
+    for computer in [&micro_op, &fast] {
+        assert_eq!(computer.harts[0].get_exception(), None);
+        assert_eq!(computer.harts[0].get_register(X2), 8);
+        assert_eq!(computer.harts[0].get_register(X3), 0);
+        assert_eq!(computer.harts[0].get_register(X4), 2);
+    }
+    assert_eq!(
+        fast.harts[0].get_register(PC),
+        micro_op.harts[0].get_register(PC)
+    );
+}
@@ -0,0 +1,102 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::test_finisher::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+fn setup(mode: Mode, cpu: CPU, program: Program) -> Computer {
+    let mut computer = Computer::new();
+    computer.harts[0] = cpu;
+    mode.configure(&mut computer);
+    computer
+        .attach_device(TestFinisher::new("finisher"))
+        .unwrap();
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+#[rstest]
+#[case::pass(TEST_FINISHER_PASS, 0)]
+#[case::fail(7 << 16 | TEST_FINISHER_FAIL, 7)]
+fn test_power_off(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+    #[case] value: u64,
+    #[case] code: u64,
+) {
+    let program = Compiler::new()
+        .sw(X3, X4, 0)
+        .beq(X0, X0, -4i64 as u64)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(TEST_FINISHER_BASE)
+        .x4(value)
+        .build()
+        .unwrap();
+
+    let mut computer = setup(mode, cpu, program);
+    run_computer(mode, &mut computer, 5000);
+
+    assert_eq!(computer.get_exit_code(), Some(code));
+}
+
+#[rstest]
+fn test_ignored_write(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new().sw(X3, X4, 0).compile();
+    let cpu = CPU::builder()
+        .x3(TEST_FINISHER_BASE)
+        .x4(0x1234)
+        .build()
+        .unwrap();
+
+    let mut computer = setup(mode, cpu, program);
+    run_computer(mode, &mut computer, 5000);
+
+    assert_eq!(computer.get_exit_code(), None);
+    assert!(computer.harts[0].get_exception().is_none());
+}
+
+#[rstest]
+fn test_reset(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    // Resets while RAM holds the marker, passes once the reset cleared it.
+    // Addresses are built by the program as the reset clears the registers.
+    let program = Compiler::new()
+        .addi(X6, X0, 31)
+        .addi(X3, X0, 1)
+        .sll(X3, X3, X6)
+        .addi(X6, X0, 20)
+        .addi(X7, X0, 1)
+        .sll(X7, X7, X6)
+        .ld(X5, X3, 0)
+        .beq(X5, X0, 16)
+        .lui(X4, 0x7)
+        .addi(X4, X4, 0x777)
+        .beq(X0, X0, 12)
+        .lui(X4, 0x5)
+        .addi(X4, X4, 0x555)
+        .sw(X7, X4, 0)
+        .beq(X0, X0, 0)
+        .compile();
+    let cpu = CPU::builder().x9(42).build().unwrap();
+    let mut computer = setup(mode, cpu, program);
+    computer
+        .devices
+        .write(RAM_START, 1, BusStatus::WriteDoubleWord)
+        .unwrap();
+
+    run_computer(mode, &mut computer, 5000);
+
+    assert_eq!(computer.get_exit_code(), Some(0));
+    let hart = &computer.harts[0];
+    assert_eq!(hart.get_register(X5), 0);
+    assert_eq!(hart.get_register(X9), 0);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0));
+}
@@ -0,0 +1,198 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::framebuffer::snapshot::Snapshot;
+use crate::computer::components::framebuffer::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+const FRAMEBUFFER_BASE: u64 = 0x2000_0000;
+const RED: [u8; 3] = [0xFF, 0, 0];
+const GREEN: [u8; 3] = [0, 0xFF, 0];
+const BLACK: [u8; 3] = [0; 3];
+
+/// 4x2 pixels, double buffered
+fn config(format: PixelFormat) -> FramebufferConfig {
+    FramebufferConfig {
+        width: 4,
+        height: 2,
+        format,
+        pages: 2,
+    }
+}
+
+fn setup(config: FramebufferConfig) -> Computer {
+    let mut computer = Computer::new();
+    computer
+        .attach_device(Framebuffer::new("fb", FRAMEBUFFER_BASE, config))
+        .unwrap();
+    computer
+}
+
+fn framebuffer(computer: &Computer) -> &Framebuffer {
+    computer.devices.get::<Framebuffer>("fb").unwrap()
+}
+
+fn golden(pixels: &[[u8; 3]]) -> Snapshot {
+    Snapshot::new(4, 2, pixels.concat())
+}
+
+#[rstest]
+fn test_registers() {
+    let computer = setup(config(PixelFormat::RGB565));
+    let read = |offset: u64| computer.devices.read_dw(FRAMEBUFFER_BASE + offset).unwrap() as u32;
+
+    assert_eq!(read(FRAMEBUFFER_WIDTH), 4);
+    assert_eq!(read(FRAMEBUFFER_HEIGHT), 2);
+    assert_eq!(read(FRAMEBUFFER_FORMAT), PixelFormat::RGB565.code());
+    assert_eq!(read(FRAMEBUFFER_STRIDE), 8);
+    assert_eq!(read(FRAMEBUFFER_PAGES), 2);
+    assert_eq!(read(FRAMEBUFFER_FRONT), 0);
+    assert_eq!(
+        framebuffer(&computer).get_config(),
+        &config(PixelFormat::RGB565)
+    );
+}
+
+#[rstest]
+fn test_draw_and_flip(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // Draws into the back page, then shows it
+    let program = Compiler::new()
+        .sw(X3, X5, 32)
+        .sw(X3, X6, 32 + 16 + 12)
+        .sw(X4, X7, FRAMEBUFFER_FRONT)
+        .compile();
+    let mut computer = setup(config(PixelFormat::XRGB8888));
+    computer.harts[0] = CPU::builder()
+        .x3(FRAMEBUFFER_BASE + FRAMEBUFFER_PIXELS)
+        .x4(FRAMEBUFFER_BASE)
+        .x5(0x00FF_0000)
+        .x6(0x0000_FF00)
+        .x7(1)
+        .build()
+        .unwrap();
+    mode.configure(&mut computer);
+    computer.set_boot_rom(program.binary);
+
+    run_computer(mode, &mut computer, 2000);
+
+    let expected = golden(&[RED, BLACK, BLACK, BLACK, BLACK, BLACK, BLACK, GREEN]);
+    assert_eq!(framebuffer(&computer).snapshot(), expected);
+    assert_eq!(framebuffer(&computer).get_front(), 1);
+    let flips = computer
+        .devices
+        .read_dw(FRAMEBUFFER_BASE + FRAMEBUFFER_FLIPS);
+    assert_eq!(flips.unwrap() as u32, 1);
+}
+
+#[rstest]
+fn test_flip_to_missing_page() {
+    let mut computer = setup(config(PixelFormat::XRGB8888));
+
+    computer
+        .devices
+        .write(
+            FRAMEBUFFER_BASE + FRAMEBUFFER_FRONT,
+            2,
+            BusStatus::WriteWord,
+        )
+        .unwrap();
+
+    assert_eq!(framebuffer(&computer).get_front(), 0);
+}
+
+#[rstest]
+#[case::gray(PixelFormat::Gray8, &[0x80], [0x80, 0x80, 0x80])]
+#[case::rgb565_red(PixelFormat::RGB565, &[0x00, 0xF8], RED)]
+#[case::rgb565_green(PixelFormat::RGB565, &[0xE0, 0x07], GREEN)]
+#[case::rgb565_mixed(PixelFormat::RGB565, &[0x10, 0x84], [0x84, 0x82, 0x84])]
+#[case::xrgb(PixelFormat::XRGB8888, &[0x30, 0x20, 0x10, 0xFF], [0x10, 0x20, 0x30])]
+fn test_pixel_format(#[case] format: PixelFormat, #[case] pixel: &[u8], #[case] rgb: [u8; 3]) {
+    let mut computer = setup(config(format));
+    let address = FRAMEBUFFER_BASE + FRAMEBUFFER_PIXELS + 5 * format.bytes_per_pixel();
+    for (offset, byte) in pixel.iter().enumerate() {
+        computer
+            .devices
+            .write(address + offset as u64, *byte as u64, BusStatus::WriteByte)
+            .unwrap();
+    }
+
+    let snapshot = framebuffer(&computer).snapshot();
+
+    assert_eq!(snapshot.pixel(1, 1), rgb);
+    assert_eq!(snapshot.pixel(0, 1), BLACK);
+}
+
+#[rstest]
+fn test_ppm_golden_image() {
+    let snapshot = golden(&[RED, GREEN, BLACK, RED, GREEN, BLACK, RED, GREEN]);
+    let path = std::env::temp_dir().join(format!("framebuffer_test_{}.ppm", std::process::id()));
+
+    snapshot.save(&path).unwrap();
+    let ppm = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(ppm.starts_with(b"P6\n4 2\n255\n"));
+    assert_eq!(Snapshot::from_ppm(&ppm).unwrap(), snapshot);
+    let commented = [b"P6\n# golden\n4 2\n255\n".as_slice(), &snapshot.rgb].concat();
+    assert_eq!(Snapshot::from_ppm(&commented).unwrap(), snapshot);
+    assert!(Snapshot::from_ppm(b"P3\n4 2\n255\n").is_err());
+}
+
+#[rstest]
+fn test_png() {
+    let snapshot = golden(&[RED, GREEN, BLACK, RED, GREEN, BLACK, RED, GREEN]);
+
+    let png = snapshot.to_png();
+
+    assert_eq!(
+        png[..8],
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']
+    );
+    assert_eq!(png[12..16], *b"IHDR");
+    assert_eq!(png[16..25], [0, 0, 0, 4, 0, 0, 0, 2, 8]);
+    // An empty IEND chunk always has the same checksum
+    assert_eq!(
+        png[png.len() - 12..],
+        [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]
+    );
+    // The rows are stored uncompressed, each after its filter type
+    let idat = 8 + 25;
+    assert_eq!(png[idat + 4..idat + 8], *b"IDAT");
+    let stored = &png[idat + 8 + 2 + 5..];
+    assert_eq!(stored[0], 0);
+    assert_eq!(stored[1..13], snapshot.rgb[..12]);
+    assert_eq!(stored[13], 0);
+    assert_eq!(stored[14..26], snapshot.rgb[12..]);
+}
+
+#[rstest]
+fn test_reset() {
+    let mut computer = setup(config(PixelFormat::Gray8));
+    computer
+        .devices
+        .write(
+            FRAMEBUFFER_BASE + FRAMEBUFFER_PIXELS,
+            0xFF,
+            BusStatus::WriteByte,
+        )
+        .unwrap();
+    computer
+        .devices
+        .write(
+            FRAMEBUFFER_BASE + FRAMEBUFFER_FRONT,
+            1,
+            BusStatus::WriteWord,
+        )
+        .unwrap();
+
+    computer.reset();
+
+    assert_eq!(framebuffer(&computer).get_front(), 0);
+    assert_eq!(framebuffer(&computer).get_page(0), [0; 8]);
+}
@@ -0,0 +1,151 @@
+use crate::compiler::layers::instruction_label::InstructionLabelLayer;
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::instructions::Instruction;
+use crate::computer::Computer;
+use crate::tests::{setup_and_run, setup_and_run_custom_cpu, Mode};
+use rstest::rstest;
+
+fn run_fused(program: Program, micro_op: bool, macro_op: bool) -> Computer {
+    let cpu = CPU::builder()
+        .micro_op_fusion(micro_op)
+        .macro_op_fusion(macro_op)
+        .build()
+        .unwrap();
+    setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 1000)
+}
+
+#[test]
+fn test_micro_op_fusion_add_immediate() {
+    let program = Compiler::new()
+        .data("value", vec![42])
+        .lb_label(X1, X0, "value")
+        .addi(X2, X1, 8)
+        .compile();
+
+    let unfused = setup_and_run(Mode::InOrder, program.clone(), 1000);
+    let fused = run_fused(program, true, false);
+
+    assert_eq!(fused.harts[0].get_register(X1), 42);
+    assert_eq!(fused.harts[0].get_register(X2), 50);
+    let stats = fused.harts[0].get_fusion_stats();
+    assert_eq!(stats.add_immediate.count, 2);
+    assert_eq!(stats.add_immediate.ticks_saved, 2);
+    assert_eq!(
+        unfused.harts[0].get_ticks() - fused.harts[0].get_ticks(),
+        stats.total_ticks_saved()
+    );
+}
+
+#[test]
+fn test_macro_op_fusion_lui_addi() {
+    let program = Compiler::new().lui(X1, 0x12).addi(X1, X1, 0x34).compile();
+
+    let unfused = setup_and_run(Mode::InOrder, program.clone(), 1000);
+    let fused = run_fused(program, false, true);
+
+    assert_eq!(fused.harts[0].get_register(X1), 0x12034);
+    assert_eq!(
+        fused.harts[0].get_register(X1),
+        unfused.harts[0].get_register(X1)
+    );
+    let stats = fused.harts[0].get_fusion_stats();
+    assert_eq!(stats.lui_addi.count, 1);
+    assert_eq!(stats.lui_addi.ticks_saved, 2);
+    assert_eq!(
+        unfused.harts[0].get_ticks() - fused.harts[0].get_ticks(),
+        stats.total_ticks_saved()
+    );
+}
+
+#[test]
+fn test_macro_op_fusion_illegal_partner() {
+    let mut code = Instruction::Lui(X1, 0x12).to_byte_vector();
+    code.extend(0xFFFF_FFFFu32.to_le_bytes());
+    // Jumps over the EBREAK appended at 4 into the data section at 8
+    let program = Compiler::new().data("code", code).jalr(X0, X0, 8).compile();
+
+    let fused = run_fused(program, false, true);
+
+    assert_eq!(
+        fused.harts[0].get_exception(),
+        Some(Exception::IllegalInstruction(0xFFFF_FFFF))
+    );
+    assert_eq!(fused.harts[0].get_register(X1), 0x12000);
+    assert_eq!(fused.harts[0].get_register(PC), 12);
+    assert_eq!(fused.harts[0].get_fusion_stats().lui_addi.count, 0);
+}
+
+#[rstest]
+#[case::micro_op(true, false)]
+#[case::macro_op(false, true)]
+#[case::both(true, true)]
+fn test_fusion_negative_immediate(#[case] micro_op: bool, #[case] macro_op: bool) {
+    let program = Compiler::new()
+        .lui(X1, 0x12)
+        .addi(X1, X1, -1i64 as u64)
+        .addi(X2, X0, 5)
+        .addi(X2, X2, -1i64 as u64)
+        .compile();
+
+    let unfused = setup_and_run(Mode::InOrder, program.clone(), 1000);
+    let fused = run_fused(program, micro_op, macro_op);
+
+    for computer in [&unfused, &fused] {
+        assert_eq!(computer.harts[0].get_register(X1), 0x11FFF);
+        assert_eq!(computer.harts[0].get_register(X2), 4);
+    }
+}
+
+#[test]
+fn test_macro_op_fusion_auipc_jalr() {
+    let program = Compiler::new()
+        .auipc(X1, 0)
+        .jalr(X1, X1, 12)
+        .addi(X2, X0, 1)
+        .addi(X3, X0, 1)
+        .compile();
+
+    let unfused = setup_and_run(Mode::InOrder, program.clone(), 1000);
+    let fused = run_fused(program, true, true);
+
+    for reg in [X1, X2, X3, PC] {
+        assert_eq!(
+            fused.harts[0].get_register(reg),
+            unfused.harts[0].get_register(reg)
+        );
+    }
+    assert_eq!(fused.harts[0].get_register(X1), 8);
+    assert_eq!(fused.harts[0].get_register(X2), 0);
+    assert_eq!(fused.harts[0].get_register(X3), 1);
+    let stats = fused.harts[0].get_fusion_stats();
+    assert_eq!(stats.auipc_jalr.count, 1);
+    assert_eq!(stats.auipc_jalr.ticks_saved, 5);
+    assert_eq!(
+        unfused.harts[0].get_ticks() - fused.harts[0].get_ticks(),
+        stats.total_ticks_saved()
+    );
+}
+
+#[test]
+fn test_macro_op_fusion_without_partner() {
+    let program = Compiler::new()
+        .lui(X1, 1)
+        .add(X2, X1, X1)
+        .auipc(X3, 0)
+        .compile();
+
+    let unfused = setup_and_run(Mode::InOrder, program.clone(), 1000);
+    let fused = run_fused(program, false, true);
+
+    assert_eq!(fused.harts[0].get_register(X2), 0x2000);
+    assert_eq!(fused.harts[0].get_register(X3), 8);
+    assert_eq!(fused.harts[0].get_ticks(), unfused.harts[0].get_ticks());
+    assert_eq!(fused.harts[0].get_fusion_stats().total_ticks_saved(), 0);
+}
@@ -0,0 +1,119 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::gpio::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, DeviceFixture, Mode};
+use rstest::rstest;
+
+const GPIO_BASE: u64 = 0x1001_2000;
+
+const GPIO: DeviceFixture<Gpio, u32> = DeviceFixture::new("gpio", GPIO_BASE);
+
+fn setup(mode: Mode, cpu: CPU) -> Computer {
+    GPIO.setup(mode, cpu, Gpio::new("gpio", GPIO_BASE))
+}
+
+#[rstest]
+fn test_guest_drives_outputs(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new()
+        .sw(X3, X4, GPIO_OUTPUT_EN)
+        .sw(X3, X5, GPIO_OUTPUT_VAL)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(GPIO_BASE)
+        .x4(0b0110)
+        .x5(0b1100)
+        .build()
+        .unwrap();
+    let mut computer = setup(mode, cpu);
+    computer.set_boot_rom(program.binary);
+
+    run_computer(mode, &mut computer, 1000);
+
+    let gpio = GPIO.get_mut(&mut computer);
+    assert_eq!(gpio.get_output(1), Some(false));
+    assert_eq!(gpio.get_output(2), Some(true));
+    assert_eq!(gpio.get_output(3), None);
+    assert_eq!(gpio.get_levels(), 0b0100);
+}
+
+#[test]
+fn test_guest_reads_inputs() {
+    let program = Compiler::new()
+        .sw(X3, X4, GPIO_INPUT_EN)
+        .lw(X5, X3, GPIO_INPUT_VAL)
+        .compile();
+    let cpu = CPU::builder().x3(GPIO_BASE).x4(0b11).build().unwrap();
+    let mut computer = setup(Mode::InOrder, cpu);
+    computer.set_boot_rom(program.binary);
+    GPIO.get_mut(&mut computer).set_input(0, true);
+    GPIO.get_mut(&mut computer).set_input(5, true);
+
+    run_computer(Mode::InOrder, &mut computer, 1000);
+
+    // Pin 5 is high but its input is not enabled
+    assert_eq!(computer.harts[0].get_register(X5), 0b01);
+}
+
+#[test]
+fn test_host_cannot_drive_outputs() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    GPIO.write_register(&mut computer, GPIO_INPUT_EN, 1);
+    GPIO.write_register(&mut computer, GPIO_OUTPUT_EN, 1);
+
+    GPIO.get_mut(&mut computer).set_input(0, true);
+
+    assert_eq!(GPIO.read_register(&computer, GPIO_INPUT_VAL), 0);
+}
+
+#[rstest]
+#[case::rise(GPIO_RISE_IE, GPIO_RISE_IP, &[true])]
+#[case::fall(GPIO_FALL_IE, GPIO_FALL_IP, &[true, false])]
+#[case::high(GPIO_HIGH_IE, GPIO_HIGH_IP, &[true])]
+fn test_pin_change_interrupt(#[case] enable: u64, #[case] pending: u64, #[case] levels: &[bool]) {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    GPIO.write_register(&mut computer, GPIO_INPUT_EN, 1 << 4);
+    GPIO.write_register(&mut computer, enable, 1 << 4);
+    assert!(computer.pending_interrupts().is_empty());
+
+    for level in levels {
+        GPIO.get_mut(&mut computer).set_input(4, *level);
+    }
+
+    assert_eq!(GPIO.read_register(&computer, pending) & 1 << 4, 1 << 4);
+    assert_eq!(computer.pending_interrupts(), ["gpio"]);
+    GPIO.write_register(&mut computer, enable, 0);
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_acknowledge_edge() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    GPIO.write_register(&mut computer, GPIO_INPUT_EN, 1);
+    GPIO.write_register(&mut computer, GPIO_RISE_IE, 1);
+    GPIO.get_mut(&mut computer).set_input(0, true);
+
+    GPIO.write_register(&mut computer, GPIO_RISE_IP, 1);
+
+    assert_eq!(GPIO.read_register(&computer, GPIO_RISE_IP), 0);
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_low_level_pending_while_low() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    GPIO.write_register(&mut computer, GPIO_INPUT_EN, 1);
+    GPIO.write_register(&mut computer, GPIO_LOW_IE, 1);
+
+    GPIO.write_register(&mut computer, GPIO_LOW_IP, 1);
+    assert_eq!(GPIO.read_register(&computer, GPIO_LOW_IP), 1);
+
+    GPIO.get_mut(&mut computer).set_input(0, true);
+    GPIO.write_register(&mut computer, GPIO_LOW_IP, 1);
+    assert_eq!(GPIO.read_register(&computer, GPIO_LOW_IP), 0);
+}
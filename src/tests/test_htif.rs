@@ -0,0 +1,183 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::{BOOT_ROM_END, BOOT_ROM_START, RAM_SIZE, RAM_START};
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::htif::*;
+use crate::computer::components::uart::backend::MemoryBackend;
+use crate::computer::memory_map::{MemoryMap, MemoryRegion, Permissions, RegionKind};
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+/// Where riscv-tests link `tohost`, inside RAM
+const TOHOST: u64 = RAM_START + 0x1000;
+
+/// RAM with a device region for the mailbox carved out of it
+fn memory_map() -> MemoryMap {
+    MemoryMap::new(vec![
+        MemoryRegion::new(
+            "boot_rom",
+            BOOT_ROM_START,
+            BOOT_ROM_END - BOOT_ROM_START + 1,
+            RegionKind::ROM,
+            Permissions::READ_EXECUTE,
+        ),
+        MemoryRegion::new(
+            "ram",
+            RAM_START,
+            TOHOST - RAM_START,
+            RegionKind::RAM,
+            Permissions::READ_WRITE_EXECUTE,
+        ),
+        MemoryRegion::new(
+            "tohost",
+            TOHOST,
+            HTIF_SIZE,
+            RegionKind::Device,
+            Permissions::READ_WRITE,
+        ),
+        MemoryRegion::new(
+            "ram_high",
+            TOHOST + HTIF_SIZE,
+            RAM_SIZE - (TOHOST + HTIF_SIZE - RAM_START),
+            RegionKind::RAM,
+            Permissions::READ_WRITE_EXECUTE,
+        ),
+    ])
+    .unwrap()
+}
+
+fn run(mode: Mode, cpu: CPU, program: Program, input: &[u8]) -> Computer {
+    let mut computer = Computer::with_memory_map(memory_map());
+    computer.harts[0] = cpu;
+    mode.configure(&mut computer);
+    let mut console = MemoryBackend::new();
+    console.push_input(input);
+    computer
+        .attach_device(Htif::new("htif", TOHOST, console))
+        .unwrap();
+    computer.set_boot_rom(program.binary);
+
+    run_computer(mode, &mut computer, 5000);
+    computer
+}
+
+fn console(computer: &Computer) -> &MemoryBackend {
+    computer
+        .devices
+        .get::<Htif>("htif")
+        .unwrap()
+        .get_console()
+        .unwrap()
+}
+
+#[rstest]
+#[case::pass(0)]
+#[case::fail(3)]
+fn test_exit(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+    #[case] code: u64,
+) {
+    // Like riscv-tests, keeps writing tohost until the host stops the machine
+    let program = Compiler::new()
+        .sd(X3, X4, HTIF_TOHOST)
+        .beq(X0, X0, -4i64 as u64)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(TOHOST)
+        .x4(htif_exit(code))
+        .build()
+        .unwrap();
+
+    let mut computer = run(mode, cpu, program, &[]);
+
+    assert_eq!(computer.get_exit_code(), Some(code));
+    assert!(!computer.tick());
+    assert!(!computer.step_instruction());
+}
+
+#[rstest]
+fn test_console_putchar(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    // Waits for each command to be taken before sending the next one
+    let program = Compiler::new()
+        .sd(X3, X4, HTIF_TOHOST)
+        .ld(X7, X3, HTIF_TOHOST)
+        .bne(X7, X0, -4i64 as u64)
+        .sd(X3, X5, HTIF_TOHOST)
+        .ld(X7, X3, HTIF_TOHOST)
+        .bne(X7, X0, -4i64 as u64)
+        .sd(X3, X6, HTIF_TOHOST)
+        .compile();
+    let putchar = |byte: u8| htif_command(HTIF_DEVICE_CONSOLE, HTIF_CONSOLE_PUTCHAR, byte as u64);
+    let cpu = CPU::builder()
+        .x3(TOHOST)
+        .x4(putchar(b'o'))
+        .x5(putchar(b'k'))
+        .x6(htif_exit(0))
+        .build()
+        .unwrap();
+
+    let computer = run(mode, cpu, program, &[]);
+
+    assert_eq!(console(&computer).output_string(), "ok");
+    assert_eq!(computer.get_exit_code(), Some(0));
+}
+
+#[rstest]
+fn test_console_getchar(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // Polls fromhost for the answer, then clears it
+    let program = Compiler::new()
+        .sd(X3, X4, HTIF_TOHOST)
+        .ld(X5, X3, HTIF_FROMHOST)
+        .beq(X5, X0, -4i64 as u64)
+        .sd(X3, X0, HTIF_FROMHOST)
+        .compile();
+    let getchar = htif_command(HTIF_DEVICE_CONSOLE, HTIF_CONSOLE_GETCHAR, 0);
+    let cpu = CPU::builder().x3(TOHOST).x4(getchar).build().unwrap();
+
+    let computer = run(mode, cpu, program, b"z");
+
+    assert_eq!(computer.harts[0].get_register(X5), getchar | b'z' as u64);
+    assert_eq!(computer.devices.read_dw(TOHOST + HTIF_FROMHOST), Ok(0));
+    assert_eq!(computer.get_exit_code(), None);
+}
+
+#[rstest]
+fn test_exit_after_reset() {
+    let program = Compiler::new()
+        .sd(X3, X4, HTIF_TOHOST)
+        .beq(X0, X0, 0)
+        .compile();
+    let cpu = CPU::builder().x3(TOHOST).x4(htif_exit(3)).build().unwrap();
+    let mut computer = run(Mode::InOrder, cpu, program, &[]);
+    assert_eq!(computer.get_exit_code(), Some(3));
+
+    computer.reset();
+    assert_eq!(computer.get_exit_code(), None);
+    assert_eq!(
+        computer
+            .devices
+            .get::<Htif>("htif")
+            .unwrap()
+            .get_exit_code(),
+        None
+    );
+    assert!(computer.tick());
+    computer
+        .devices
+        .write(
+            TOHOST + HTIF_TOHOST,
+            htif_exit(5),
+            BusStatus::WriteDoubleWord,
+        )
+        .unwrap();
+
+    assert!(!computer.tick());
+    assert_eq!(computer.get_exit_code(), Some(5));
+}
@@ -0,0 +1,144 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::input::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, DeviceFixture, Mode};
+use rstest::rstest;
+
+const INPUT_BASE: u64 = 0x1001_4000;
+const KEY_A: u16 = 30;
+const KEY_ENTER: u16 = 28;
+
+const INPUT: DeviceFixture<InputQueue, u32> = DeviceFixture::new("input", INPUT_BASE);
+
+fn setup(mode: Mode, cpu: CPU) -> Computer {
+    INPUT.setup(mode, cpu, InputQueue::new("input", INPUT_BASE))
+}
+
+#[test]
+fn test_script_delivered_by_tick() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    INPUT.get_mut(&mut computer).load_script([
+        (20, InputEvent::release(KEY_A)),
+        (10, InputEvent::press(KEY_A)),
+    ]);
+
+    for _ in 0..9 {
+        computer.tick();
+    }
+    assert_eq!(INPUT.read_register(&computer, INPUT_STATUS), 0);
+    computer.tick();
+    assert_eq!(computer.get_ticks(), 10);
+    assert_eq!(
+        INPUT.read_register(&computer, INPUT_STATUS),
+        INPUT_STATUS_READY
+    );
+    assert_eq!(
+        INPUT.read_register(&computer, INPUT_EVENT),
+        InputEvent::press(KEY_A).encode()
+    );
+
+    for _ in 0..10 {
+        computer.tick();
+    }
+    assert_eq!(INPUT.read_register(&computer, INPUT_COUNT), 2);
+    assert!(INPUT.get_mut(&mut computer).get_pending_script().is_empty());
+}
+
+#[rstest]
+fn test_guest_reads_events(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    // Polls the status for each event, then takes it
+    let program = Compiler::new()
+        .lw(X4, X3, INPUT_STATUS)
+        .beq(X4, X0, -4i64 as u64)
+        .lw(X5, X3, INPUT_EVENT)
+        .lw(X4, X3, INPUT_STATUS)
+        .beq(X4, X0, -4i64 as u64)
+        .lw(X6, X3, INPUT_EVENT)
+        .lw(X7, X3, INPUT_EVENT)
+        .compile();
+    let cpu = CPU::builder().x3(INPUT_BASE).build().unwrap();
+    let mut computer = setup(mode, cpu);
+    computer.set_boot_rom(program.binary);
+    INPUT.get_mut(&mut computer).load_script([
+        (50, InputEvent::press(KEY_ENTER)),
+        (300, InputEvent::release(KEY_ENTER)),
+    ]);
+
+    run_computer(mode, &mut computer, 2000);
+
+    let cpu = &computer.harts[0];
+    let press = InputEvent::press(KEY_ENTER).encode() as i32 as u64;
+    let release = InputEvent::release(KEY_ENTER).encode() as i32 as u64;
+    assert_eq!(cpu.get_register(X5), press);
+    assert_eq!(cpu.get_register(X6), release);
+    // Nothing left, the valid bit is clear
+    assert_eq!(cpu.get_register(X7), 0);
+    assert!(computer.get_ticks() > 300);
+}
+
+#[test]
+fn test_overflow() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    for code in 0..=INPUT_FIFO_SIZE as u16 {
+        INPUT
+            .get_mut(&mut computer)
+            .push_event(InputEvent::press(code));
+    }
+
+    assert_eq!(
+        INPUT.read_register(&computer, INPUT_COUNT),
+        INPUT_FIFO_SIZE as u32
+    );
+    let status = INPUT.read_register(&computer, INPUT_STATUS);
+    assert_eq!(status, INPUT_STATUS_READY | INPUT_STATUS_OVERFLOW);
+
+    INPUT.write_register(&mut computer, INPUT_STATUS, INPUT_STATUS_OVERFLOW);
+    assert_eq!(
+        INPUT.read_register(&computer, INPUT_STATUS),
+        INPUT_STATUS_READY
+    );
+}
+
+#[test]
+fn test_interrupt() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    INPUT
+        .get_mut(&mut computer)
+        .schedule(5, InputEvent::press(KEY_A));
+
+    for _ in 0..5 {
+        computer.tick();
+    }
+    assert!(computer.pending_interrupts().is_empty());
+
+    INPUT.write_register(&mut computer, INPUT_CONTROL, INPUT_CONTROL_IRQ_ENABLE);
+    assert_eq!(computer.pending_interrupts(), ["input"]);
+}
+
+#[test]
+fn test_reset_replays_script() {
+    let mut computer = setup(Mode::InOrder, CPU::new());
+    INPUT.get_mut(&mut computer).load_script([
+        (0, InputEvent::press(KEY_A)),
+        (3, InputEvent::release(KEY_A)),
+    ]);
+    for _ in 0..3 {
+        computer.tick();
+    }
+    assert_eq!(INPUT.get_mut(&mut computer).get_queued().len(), 2);
+
+    computer.reset();
+
+    assert_eq!(computer.get_ticks(), 0);
+    let queued = INPUT.get_mut(&mut computer).get_queued().clone();
+    assert_eq!(queued, [InputEvent::press(KEY_A)]);
+    assert_eq!(INPUT.get_mut(&mut computer).get_pending_script().len(), 1);
+}
@@ -2,12 +2,14 @@ use crate::compiler::layers::instruction_label::InstructionLabelLayer;
 use crate::compiler::layers::instructions::InstructionLayer;
 use crate::compiler::layers::program_builder::ProgramBuilderLayer;
 use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
 use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
 use crate::computer::components::cpu::registers::flags::CPUFlagsAccessTrait;
 use crate::computer::components::cpu::registers::reg::CPUReg::*;
 use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
 use crate::computer::components::cpu::CPU;
-use crate::tests::{setup_and_run, setup_and_run_custom_cpu};
+use crate::computer::instructions::Instruction;
+use crate::tests::{setup_and_run, setup_and_run_custom_cpu, Mode};
 use rstest::rstest;
 
 #[rstest]
@@ -21,13 +23,13 @@ fn test_add(
     #[case] zero: bool,
     #[case] carry: bool,
 ) {
-    let cpu = CPU::builder().x1(a).x2(b).build();
+    let cpu = CPU::builder().x1(a).x2(b).build().unwrap();
     let program = Compiler::new().add(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
-    assert_eq!(computer.cpu.get_zero(), zero);
-    assert_eq!(computer.cpu.get_carry(), carry);
-    assert!(!computer.cpu.get_subtract());
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
+    assert_eq!(computer.harts[0].get_zero(), zero);
+    assert_eq!(computer.harts[0].get_carry(), carry);
+    assert!(!computer.harts[0].get_subtract());
 }
 
 #[rstest]
@@ -39,13 +41,14 @@ fn test_and(#[case] a: u64, #[case] b: u64, #[case] result: u64, #[case] zero: b
         .x2(b)
         .carry(true)
         .subtract(true)
-        .build();
+        .build()
+        .unwrap();
     let program = Compiler::new().and(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
-    assert_eq!(computer.cpu.get_zero(), zero);
-    assert!(!computer.cpu.get_carry());
-    assert!(!computer.cpu.get_subtract());
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
+    assert_eq!(computer.harts[0].get_zero(), zero);
+    assert!(!computer.harts[0].get_carry());
+    assert!(!computer.harts[0].get_subtract());
 }
 
 #[rstest]
@@ -57,13 +60,14 @@ fn test_or(#[case] a: u64, #[case] b: u64, #[case] result: u64, #[case] zero: bo
         .x2(b)
         .carry(true)
         .subtract(true)
-        .build();
+        .build()
+        .unwrap();
     let program = Compiler::new().or(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
-    assert_eq!(computer.cpu.get_zero(), zero);
-    assert!(!computer.cpu.get_carry());
-    assert!(!computer.cpu.get_subtract());
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
+    assert_eq!(computer.harts[0].get_zero(), zero);
+    assert!(!computer.harts[0].get_carry());
+    assert!(!computer.harts[0].get_subtract());
 }
 
 #[rstest]
@@ -77,13 +81,13 @@ fn test_sub(
     #[case] zero: bool,
     #[case] carry: bool,
 ) {
-    let cpu = CPU::builder().x1(a).x2(b).build();
+    let cpu = CPU::builder().x1(a).x2(b).build().unwrap();
     let program = Compiler::new().sub(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
-    assert_eq!(computer.cpu.get_zero(), zero);
-    assert_eq!(computer.cpu.get_carry(), carry);
-    assert!(computer.cpu.get_subtract());
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
+    assert_eq!(computer.harts[0].get_zero(), zero);
+    assert_eq!(computer.harts[0].get_carry(), carry);
+    assert!(computer.harts[0].get_subtract());
 }
 
 #[rstest]
@@ -95,43 +99,66 @@ fn test_xor(#[case] a: u64, #[case] b: u64, #[case] result: u64, #[case] zero: b
         .x2(b)
         .carry(true)
         .subtract(true)
-        .build();
+        .build()
+        .unwrap();
     let program = Compiler::new().xor(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
-    assert_eq!(computer.cpu.get_zero(), zero);
-    assert!(!computer.cpu.get_carry());
-    assert!(!computer.cpu.get_subtract());
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
+    assert_eq!(computer.harts[0].get_zero(), zero);
+    assert!(!computer.harts[0].get_carry());
+    assert!(!computer.harts[0].get_subtract());
 }
 
 #[rstest]
 #[case(0b1010, 2, 0b101000)]
 #[case(120, 1, 240)]
 fn test_sll(#[case] value: u64, #[case] shift: u64, #[case] result: u64) {
-    let cpu = CPU::builder().x1(value).x2(shift).build();
+    let cpu = CPU::builder().x1(value).x2(shift).build().unwrap();
     let program = Compiler::new().sll(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
 }
 
 #[rstest]
 #[case(0b1010, 2, 0b10)]
 #[case(120, 1, 60)]
 fn test_srl(#[case] value: u64, #[case] shift: u64, #[case] result: u64) {
-    let cpu = CPU::builder().x1(value).x2(shift).build();
+    let cpu = CPU::builder().x1(value).x2(shift).build().unwrap();
     let program = Compiler::new().srl(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result);
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result);
 }
 
 #[rstest]
 #[case(-2, 1, -1)]
 #[case(-1280, 3, -160)]
 fn test_sra(#[case] value: i64, #[case] shift: u64, #[case] result: i64) {
-    let cpu = CPU::builder().x1(value as u64).x2(shift).build();
+    let cpu = CPU::builder().x1(value as u64).x2(shift).build().unwrap();
     let program = Compiler::new().sra(X3, X1, X2).compile();
-    let computer = setup_and_run_custom_cpu(cpu, program, 7);
-    assert_eq!(computer.cpu.get_register(X3), result as u64);
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 7);
+    assert_eq!(computer.harts[0].get_register(X3), result as u64);
+}
+
+#[rstest]
+#[case(0b1010, 2, 0b101000, 0b10, 0b10)]
+#[case(-1280i64 as u64, 3, -10240i64 as u64, 0x1FFF_FFFF_FFFF_FF60, -160i64 as u64)]
+fn test_shift_immediate(
+    #[case] value: u64,
+    #[case] shamt: u64,
+    #[case] left: u64,
+    #[case] logical: u64,
+    #[case] arithmetic: u64,
+) {
+    let cpu = CPU::builder().x1(value).build().unwrap();
+    let program = Compiler::new()
+        .slli(X2, X1, shamt)
+        .srli(X3, X1, shamt)
+        .srai(X4, X1, shamt)
+        .compile();
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 30);
+    assert_eq!(computer.harts[0].get_register(X2), left);
+    assert_eq!(computer.harts[0].get_register(X3), logical);
+    assert_eq!(computer.harts[0].get_register(X4), arithmetic);
 }
 
 #[test]
@@ -140,6 +167,172 @@ fn test_lb() {
         .data("test", vec![69])
         .lb_label(X1, X0, "test")
         .compile();
-    let computer = setup_and_run(program, 13);
-    assert_eq!(computer.cpu.get_register(X1), 69);
+    let computer = setup_and_run(Mode::InOrder, program, 13);
+    assert_eq!(computer.harts[0].get_register(X1), 69);
+}
+
+#[rstest]
+#[case(5, 12, 17)]
+#[case(0, 0, 0)]
+#[case::negative(5, -1i64 as u64, 4)]
+#[case::negative_zero(1, -1i64 as u64, 0)]
+#[case::negative_result(5, -2048i64 as u64, -2043i64 as u64)]
+fn test_addi(#[case] value: u64, #[case] imm: u64, #[case] result: u64) {
+    let cpu = CPU::builder().x1(value).build().unwrap();
+    let program = Compiler::new().addi(X2, X1, imm).compile();
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 8);
+    assert_eq!(computer.harts[0].get_register(X2), result);
+    assert_eq!(computer.harts[0].get_zero(), result == 0);
+}
+
+#[rstest]
+#[case(0x12345, 0x1234_5000)]
+#[case(0xFFFFF, 0xFFFF_FFFF_FFFF_F000)]
+fn test_lui(#[case] imm: u64, #[case] result: u64) {
+    let program = Compiler::new().lui(X1, imm).compile();
+    let computer = setup_and_run(Mode::InOrder, program, 7);
+    assert_eq!(computer.harts[0].get_register(X1), result);
+}
+
+#[test]
+fn test_auipc() {
+    let program = Compiler::new().add(X0, X0, X0).auipc(X1, 1).compile();
+    let computer = setup_and_run(Mode::InOrder, program, 15);
+    assert_eq!(computer.harts[0].get_register(X1), 4 + 0x1000);
+}
+
+#[test]
+fn test_jalr() {
+    let program = Compiler::new()
+        .addi(X1, X0, 12)
+        .jalr(X2, X1, 0)
+        .addi(X3, X0, 1)
+        .addi(X4, X0, 2)
+        .compile();
+    let computer = setup_and_run(Mode::InOrder, program, 100);
+    assert_eq!(computer.harts[0].get_register(X2), 8);
+    assert_eq!(computer.harts[0].get_register(X3), 0);
+    assert_eq!(computer.harts[0].get_register(X4), 2);
+}
+
+#[rstest]
+#[case::beq_taken(Instruction::Beq(X1, X2, 8), 5, 5, true)]
+#[case::beq_not_taken(Instruction::Beq(X1, X2, 8), 5, 6, false)]
+#[case::bne_taken(Instruction::Bne(X1, X2, 8), 5, 6, true)]
+#[case::bne_not_taken(Instruction::Bne(X1, X2, 8), 5, 5, false)]
+#[case::blt_taken(Instruction::Blt(X1, X2, 8), -1i64 as u64, 0, true)]
+#[case::blt_not_taken(Instruction::Blt(X1, X2, 8), 1, 0, false)]
+#[case::bge_taken(Instruction::Bge(X1, X2, 8), 0, -1i64 as u64, true)]
+#[case::bge_not_taken(Instruction::Bge(X1, X2, 8), -1i64 as u64, 0, false)]
+#[case::bltu_taken(Instruction::Bltu(X1, X2, 8), 0, u64::MAX, true)]
+#[case::bltu_not_taken(Instruction::Bltu(X1, X2, 8), u64::MAX, 0, false)]
+#[case::bgeu_taken(Instruction::Bgeu(X1, X2, 8), u64::MAX, 0, true)]
+#[case::bgeu_not_taken(Instruction::Bgeu(X1, X2, 8), 0, u64::MAX, false)]
+fn test_branch(#[case] branch: Instruction, #[case] a: u64, #[case] b: u64, #[case] taken: bool) {
+    let cpu = CPU::builder().x1(a).x2(b).build().unwrap();
+    let mut compiler = Compiler::new();
+    compiler.add_instruction(branch);
+    let program = compiler.addi(X3, X0, 1).addi(X4, X0, 2).compile();
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 100);
+    assert_eq!(
+        computer.harts[0].get_register(X3),
+        if taken { 0 } else { 1 }
+    );
+    assert_eq!(computer.harts[0].get_register(X4), 2);
+}
+
+#[test]
+fn test_compiler_layer_encodings() {
+    let instructions = [
+        Instruction::Sh(X1, X2, 8),
+        Instruction::Blt(X1, X2, 8),
+        Instruction::Bge(X1, X2, 8),
+        Instruction::Bltu(X1, X2, 8),
+        Instruction::Bgeu(X1, X2, 8),
+        Instruction::ScW(X3, X1, X2),
+    ];
+    let mut compiler = Compiler::new();
+    for instruction in instructions {
+        compiler.add_instruction(instruction);
+    }
+
+    let program = Compiler::new()
+        .sh(X1, X2, 8)
+        .blt(X1, X2, 8)
+        .bge(X1, X2, 8)
+        .bltu(X1, X2, 8)
+        .bgeu(X1, X2, 8)
+        .sc_w(X3, X1, X2)
+        .compile();
+
+    assert_eq!(program.binary, compiler.compile().binary);
+}
+
+#[test]
+fn test_backward_branch() {
+    let program = Compiler::new()
+        .addi(X2, X0, 3)
+        .addi(X1, X1, 1)
+        .bne(X1, X2, -4i64 as u64)
+        .compile();
+    let computer = setup_and_run(Mode::InOrder, program, 1000);
+    assert_eq!(computer.harts[0].get_register(X1), 3);
+    assert_eq!(computer.harts[0].get_register(PC), 16);
+}
+
+#[test]
+fn test_jal() {
+    let program = Compiler::new()
+        .jal(X1, 8)
+        .addi(X3, X0, 1)
+        .addi(X4, X0, 2)
+        .compile();
+    let computer = setup_and_run(Mode::InOrder, program, 100);
+    assert_eq!(computer.harts[0].get_register(X1), 4);
+    assert_eq!(computer.harts[0].get_register(X3), 0);
+    assert_eq!(computer.harts[0].get_register(X4), 2);
+}
+
+#[rstest]
+#[case::sb(Instruction::Sb(X1, X2, 8), 0x88)]
+#[case::sh(Instruction::Sh(X1, X2, 8), 0x7788)]
+#[case::sw(Instruction::Sw(X1, X2, 8), 0x5566_7788)]
+#[case::sd(Instruction::Sd(X1, X2, 8), 0x1122_3344_5566_7788)]
+fn test_store(#[case] store: Instruction, #[case] stored: u64) {
+    let cpu = CPU::builder()
+        .x1(RAM_START)
+        .x2(0x1122_3344_5566_7788)
+        .build()
+        .unwrap();
+    let mut compiler = Compiler::new();
+    compiler.add_instruction(store);
+    let program = compiler.lb(X3, X1, 8).compile();
+    let computer = setup_and_run_custom_cpu(Mode::InOrder, cpu, program, 100);
+
+    assert_eq!(computer.devices.read_dw(RAM_START + 8), Ok(stored));
+    assert_eq!(computer.harts[0].get_register(X3), 0xFFFF_FFFF_FFFF_FF88);
+    assert_eq!(Instruction::try_decode(store.encode()), Some(store));
+}
+
+#[rstest]
+fn test_negative_offset(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    let cpu = CPU::builder()
+        .x1(RAM_START + 0x20)
+        .x2(0x1122_3344_5566_7788)
+        .build()
+        .unwrap();
+    let program = Compiler::new()
+        .sd(X1, X2, -8i64 as u64)
+        .ld(X3, X1, -8i64 as u64)
+        .lw(X4, X1, -4i64 as u64)
+        .compile();
+    let computer = setup_and_run_custom_cpu(mode, cpu, program, 200);
+
+    assert_eq!(computer.harts[0].get_exception(), None);
+    assert_eq!(
+        computer.devices.read_dw(RAM_START + 0x18),
+        Ok(0x1122_3344_5566_7788)
+    );
+    assert_eq!(computer.harts[0].get_register(X3), 0x1122_3344_5566_7788);
+    assert_eq!(computer.harts[0].get_register(X4), 0x1122_3344);
 }
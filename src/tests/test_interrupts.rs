@@ -0,0 +1,187 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::csr::*;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::plic::*;
+use crate::computer::components::uart::backend::MemoryBackend;
+use crate::computer::components::uart::*;
+use crate::computer::instructions::Instruction;
+use crate::computer::Computer;
+use crate::tests::{run_computer, setup_and_run, setup_and_run_custom_cpu, DeviceFixture, Mode};
+use rstest::rstest;
+
+const PLIC_BASE: u64 = 0x0C00_0000;
+const UART_BASE: u64 = 0x1000_0000;
+/// The UART is the first device with an interrupt line
+const UART_SOURCE: u64 = 1;
+
+const UART: DeviceFixture<Uart, u8> = DeviceFixture::new("uart", UART_BASE);
+const PLIC: DeviceFixture<Plic, u32> = DeviceFixture::new("plic", PLIC_BASE);
+
+fn setup(mode: Mode, cpu: CPU, input: &[u8]) -> Computer {
+    let mut backend = MemoryBackend::new();
+    backend.push_input(input);
+    let mut computer = UART.setup(mode, cpu, Uart::new("uart", UART_BASE, backend));
+    computer
+        .attach_device(Plic::new("plic", PLIC_BASE, 1))
+        .unwrap();
+    computer
+}
+
+#[rstest]
+fn test_csr_instructions(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    let program = Compiler::new()
+        .csrrw(X0, X1, CSR_MSCRATCH)
+        .csrrs(X2, X3, CSR_MSCRATCH)
+        .csrrc(X4, X5, CSR_MSCRATCH)
+        .csrrwi(X6, 0b10101, CSR_MSCRATCH)
+        .csrrsi(X7, 0b01010, CSR_MSCRATCH)
+        .csrrci(X8, 0b00011, CSR_MSCRATCH)
+        .csrr(X9, CSR_MSCRATCH)
+        .compile();
+    let cpu = CPU::builder().x1(0xF0).x3(0x0F).x5(0x3C).build().unwrap();
+
+    let computer = setup_and_run_custom_cpu(mode, cpu, program, 200);
+
+    let hart = &computer.harts[0];
+    assert_eq!(hart.get_register(X2), 0xF0);
+    assert_eq!(hart.get_register(X4), 0xFF);
+    assert_eq!(hart.get_register(X6), 0xC3);
+    assert_eq!(hart.get_register(X7), 0b10101);
+    assert_eq!(hart.get_register(X8), 0b11111);
+    assert_eq!(hart.get_register(X9), 0b11100);
+}
+
+#[rstest]
+fn test_read_only_csr_write_is_illegal(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let write = Instruction::Csrrw(X0, X1, CSR_MHARTID).encode();
+    let mut program = Compiler::new().compile();
+    program.binary[..4].copy_from_slice(&write.to_le_bytes());
+
+    let computer = setup_and_run(mode, program, 100);
+
+    let hart = &computer.harts[0];
+    assert!(hart.is_halted());
+    assert_eq!(
+        hart.get_exception(),
+        Some(Exception::IllegalInstruction(write))
+    );
+}
+
+#[rstest]
+fn test_uart_interrupt_traps_to_handler(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    // Enables the receive interrupt and waits for the handler to store the byte in x9
+    let handler = 14 * 4;
+    let program = Compiler::new()
+        .csrrw(X0, X10, CSR_MTVEC)
+        .addi(X6, X0, 1)
+        .sw(X5, X6, PLIC_PRIORITY + 4 * UART_SOURCE)
+        .addi(X6, X0, 1 << UART_SOURCE)
+        .sw(X7, X6, 0)
+        .addi(X6, X0, UART_IER_RX_AVAILABLE as u64)
+        .sb(X3, X6, UART_IER)
+        .csrrs(X0, X8, CSR_MIE)
+        .csrrsi(X0, MSTATUS_MIE, CSR_MSTATUS)
+        .beq(X9, X0, 0)
+        .csrr(X13, CSR_MSTATUS)
+        .csrr(X14, CSR_MCAUSE)
+        .csrr(X15, CSR_MEPC)
+        .ebreak()
+        // Handler: claim, read the byte, complete
+        .lw(X11, X12, 0)
+        .lb(X9, X3, UART_RBR_THR)
+        .sw(X12, X11, 0)
+        .mret()
+        .compile();
+    let cpu = CPU::builder()
+        .x3(UART_BASE)
+        .x5(PLIC_BASE)
+        .x7(PLIC_BASE + PLIC_ENABLE)
+        .x8(MIP_MEIP)
+        .x10(handler)
+        .x12(PLIC_BASE + PLIC_CLAIM_COMPLETE)
+        .build()
+        .unwrap();
+    let mut computer = setup(mode, cpu, b"x");
+    computer.set_boot_rom(program.binary);
+
+    run_computer(mode, &mut computer, 2000);
+
+    let hart = &computer.harts[0];
+    assert!(hart.is_halted(), "Program did not halt");
+    assert_eq!(hart.get_exception(), None);
+    assert_eq!(hart.get_register(X9), b'x' as u64);
+    assert_eq!(hart.get_register(X11), UART_SOURCE);
+    assert_eq!(hart.get_register(X13) & MSTATUS_MIE, MSTATUS_MIE);
+    assert_eq!(hart.get_register(X14), MCAUSE_INTERRUPT | 11);
+    // Interrupted while waiting
+    assert_eq!(hart.get_register(X15), 9 * 4);
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_masked_interrupt_is_not_taken() {
+    let program = Compiler::new()
+        .addi(X6, X0, UART_IER_RX_AVAILABLE as u64)
+        .sb(X3, X6, UART_IER)
+        .csrrs(X0, X8, CSR_MIE)
+        .addi(X1, X0, 1)
+        .compile();
+    let cpu = CPU::builder().x3(UART_BASE).x8(MIP_MEIP).build().unwrap();
+    let mut computer = setup(Mode::InOrder, cpu, b"x");
+    PLIC.write_register(&mut computer, PLIC_PRIORITY + 4 * UART_SOURCE, 1);
+    PLIC.write_register(&mut computer, PLIC_ENABLE, 1 << UART_SOURCE);
+    computer.set_boot_rom(program.binary);
+
+    run_computer(Mode::InOrder, &mut computer, 1000);
+
+    let hart = &computer.harts[0];
+    assert_eq!(hart.get_csrs().mip & MIP_MEIP, MIP_MEIP);
+    assert_eq!(hart.get_csrs().mcause, 0);
+    assert_eq!(hart.get_register(X1), 1);
+}
+
+#[test]
+fn test_plic_claim_and_complete() {
+    let cpu = CPU::builder().build().unwrap();
+    let mut computer = setup(Mode::InOrder, cpu, b"xy");
+    UART.write_register(&mut computer, UART_IER, UART_IER_RX_AVAILABLE);
+    PLIC.write_register(&mut computer, PLIC_ENABLE, 1 << UART_SOURCE);
+    computer.tick();
+
+    // Priority 0 never interrupts, the threshold masks priorities up to its own
+    assert_eq!(computer.harts[0].get_csrs().mip, 0);
+    PLIC.write_register(&mut computer, PLIC_PRIORITY + 4 * UART_SOURCE, 2);
+    PLIC.write_register(&mut computer, PLIC_THRESHOLD, 2);
+    computer.tick();
+    assert_eq!(computer.harts[0].get_csrs().mip, 0);
+    PLIC.write_register(&mut computer, PLIC_THRESHOLD, 1);
+    computer.tick();
+    assert_eq!(computer.harts[0].get_csrs().mip, MIP_MEIP);
+    assert_eq!(
+        PLIC.read_register(&computer, PLIC_PENDING),
+        1 << UART_SOURCE
+    );
+
+    // The claimed source stays quiet until it is completed, although the UART still has data
+    let claim = computer.devices.read(PLIC_BASE + PLIC_CLAIM_COMPLETE);
+    assert_eq!(claim, Ok(UART_SOURCE));
+    computer.tick();
+    assert_eq!(computer.harts[0].get_csrs().mip, 0);
+    assert_eq!(
+        computer.devices.read(PLIC_BASE + PLIC_CLAIM_COMPLETE),
+        Ok(0)
+    );
+    PLIC.write_register(&mut computer, PLIC_CLAIM_COMPLETE, UART_SOURCE as u32);
+    computer.tick();
+    assert_eq!(computer.harts[0].get_csrs().mip, MIP_MEIP);
+}
@@ -0,0 +1,171 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::{RAM_SIZE, RAM_START};
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::cache::CacheConfig;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::registry::DeviceError;
+use crate::computer::components::ram::RAM;
+use crate::computer::instructions::Instruction;
+use crate::computer::memory_map::{
+    MemoryMap, MemoryMapError, MemoryRegion, Permissions, RegionKind,
+};
+use crate::computer::Computer;
+use crate::tests::{run_computer, setup_and_run, Mode};
+use rstest::rstest;
+
+const UNMAPPED: u64 = 0x4000_0000;
+
+fn boot_rom() -> MemoryRegion {
+    MemoryRegion::new(
+        "boot_rom",
+        0,
+        0x1000,
+        RegionKind::ROM,
+        Permissions::READ_EXECUTE,
+    )
+}
+
+#[test]
+fn test_overlapping_regions_rejected() {
+    let ram = MemoryRegion::new(
+        "ram",
+        0x800,
+        0x1000,
+        RegionKind::RAM,
+        Permissions::READ_WRITE,
+    );
+
+    assert_eq!(
+        MemoryMap::new(vec![boot_rom(), ram]),
+        Err(MemoryMapError::Overlap {
+            region: "ram".to_string(),
+            existing: "boot_rom".to_string(),
+        })
+    );
+    let empty = MemoryRegion::new("empty", 0x2000, 0, RegionKind::RAM, Permissions::READ_WRITE);
+    assert_eq!(
+        MemoryMap::new(vec![boot_rom(), empty]),
+        Err(MemoryMapError::InvalidSize("empty".to_string()))
+    );
+}
+
+fn unmapped_load_program() -> Program {
+    Compiler::new()
+        .lui(X2, UNMAPPED >> 12)
+        .lb(X1, X2, 0)
+        .addi(X3, X0, 1)
+        .compile()
+}
+
+fn unmapped_jump_program() -> Program {
+    Compiler::new()
+        .lui(X2, UNMAPPED >> 12)
+        .jalr(X4, X2, 0)
+        .addi(X3, X0, 1)
+        .compile()
+}
+
+#[rstest]
+#[case::load(unmapped_load_program(), Exception::LoadAccessFault(UNMAPPED))]
+#[case::fetch(unmapped_jump_program(), Exception::InstructionAccessFault(UNMAPPED))]
+fn test_unmapped_access_faults(#[case] program: Program, #[case] exception: Exception) {
+    for mode in [Mode::InOrder, Mode::OutOfOrder, Mode::FastForward] {
+        let computer = setup_and_run(mode, program.clone(), 1000);
+        assert_eq!(computer.harts[0].get_exception(), Some(exception));
+        assert_eq!(computer.harts[0].get_register(X3), 0);
+        assert!(computer.bus.is_available());
+    }
+}
+
+#[test]
+fn test_device_regions() {
+    let map = MemoryMap::new(vec![
+        boot_rom(),
+        MemoryRegion::new(
+            "uart",
+            UNMAPPED,
+            0x100,
+            RegionKind::Device,
+            Permissions::READ_WRITE,
+        ),
+        MemoryRegion::new(
+            "ram",
+            RAM_START,
+            RAM_SIZE,
+            RegionKind::RAM,
+            Permissions::READ_WRITE_EXECUTE,
+        ),
+    ])
+    .unwrap();
+    let mut computer = Computer::with_memory_map(map);
+    computer.set_boot_rom(unmapped_load_program().binary);
+    while computer.tick() {}
+
+    // Reserved for a device, but nothing attached to answer
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::LoadAccessFault(UNMAPPED))
+    );
+    let ram = MemoryRegion::new(
+        "shadow",
+        RAM_START,
+        0x100,
+        RegionKind::RAM,
+        Permissions::READ_WRITE,
+    );
+    assert_eq!(
+        computer.attach_device(RAM::new(&ram)),
+        Err(DeviceError::Overlap {
+            device: "shadow".to_string(),
+            existing: "ram".to_string(),
+        })
+    );
+    assert_eq!(computer.devices.get_memory_map().regions().len(), 3);
+}
+
+#[rstest]
+#[case::in_order(CPU::default(), Mode::InOrder)]
+#[case::out_of_order(CPU::default(), Mode::OutOfOrder)]
+#[case::icache(CPU::builder().icache(CacheConfig::default()).build().unwrap(), Mode::InOrder)]
+#[case::fast(CPU::default(), Mode::FastForward)]
+fn test_fetch_needs_execute_permission(#[case] cpu: CPU, #[case] mode: Mode) {
+    let map = MemoryMap::new(vec![
+        boot_rom(),
+        MemoryRegion::new(
+            "ram",
+            RAM_START,
+            RAM_SIZE,
+            RegionKind::RAM,
+            Permissions::READ_WRITE,
+        ),
+    ])
+    .unwrap();
+    let mut computer = Computer::with_memory_map(map);
+    computer.harts[0] = cpu;
+    mode.configure(&mut computer);
+    let code = Instruction::Addi(X3, X0, 1).encode();
+    computer
+        .devices
+        .write(RAM_START, code as u64, BusStatus::WriteWord)
+        .unwrap();
+    let program = Compiler::new().lw(X5, X2, 0).jalr(X4, X2, 0).compile();
+    computer.harts[0].set_register(X2, RAM_START);
+    computer.set_boot_rom(program.binary);
+
+    run_computer(mode, &mut computer, 1000);
+
+    // Readable, but not executable
+    let hart = &computer.harts[0];
+    assert_eq!(hart.get_register(X5), code as u64);
+    assert_eq!(
+        hart.get_exception(),
+        Some(Exception::InstructionAccessFault(RAM_START))
+    );
+    assert_eq!(hart.get_register(X3), 0);
+}
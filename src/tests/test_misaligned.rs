@@ -0,0 +1,160 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::misaligned::MisalignedPolicy;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+fn setup(mode: Mode, policy: MisalignedPolicy, cpu: CPU, program: Program) -> Computer {
+    let mut computer = Computer::new();
+    computer.harts[0] = cpu;
+    computer.harts[0].set_misaligned_policy(policy);
+    mode.configure(&mut computer);
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+fn run(mode: Mode, policy: MisalignedPolicy, cpu: CPU, program: Program) -> Computer {
+    let mut computer = setup(mode, policy, cpu, program);
+    run_computer(mode, &mut computer, 2000);
+    computer
+}
+
+fn load_store_program() -> Program {
+    Compiler::new()
+        .sw(X1, X2, 1)
+        .lw(X3, X1, 1)
+        .ld(X4, X1, 1)
+        .lh(X5, X1, 3)
+        .compile()
+}
+
+#[rstest]
+fn test_misaligned_load_store(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+    #[values(MisalignedPolicy::Split, MisalignedPolicy::Allow)] policy: MisalignedPolicy,
+) {
+    let cpu = CPU::builder()
+        .x1(RAM_START)
+        .x2(0x8765_4321)
+        .build()
+        .unwrap();
+
+    let computer = run(mode, policy, cpu, load_store_program());
+    assert_eq!(computer.harts[0].get_misaligned_policy(), policy);
+
+    assert_eq!(computer.harts[0].get_exception(), None);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0x87_6543_2100));
+    assert_eq!(computer.harts[0].get_register(X3), 0xFFFF_FFFF_8765_4321);
+    assert_eq!(computer.harts[0].get_register(X4), 0x8765_4321);
+    assert_eq!(computer.harts[0].get_register(X5), 0xFFFF_FFFF_FFFF_8765);
+}
+
+#[rstest]
+fn test_misaligned_store_traps(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let cpu = CPU::builder()
+        .x1(RAM_START)
+        .x2(0x8765_4321)
+        .build()
+        .unwrap();
+
+    let computer = run(mode, MisalignedPolicy::Trap, cpu, load_store_program());
+
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::StoreAddressMisaligned(RAM_START + 1))
+    );
+    // The trapped store never reaches memory
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0));
+}
+
+#[rstest]
+fn test_misaligned_load_traps(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let program = Compiler::new().lh(X3, X1, 1).addi(X4, X0, 1).compile();
+    let cpu = CPU::builder().x1(RAM_START).build().unwrap();
+
+    let computer = run(mode, MisalignedPolicy::Trap, cpu, program);
+
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::LoadAddressMisaligned(RAM_START + 1))
+    );
+    assert_eq!(computer.harts[0].get_register(X4), 0);
+}
+
+#[rstest]
+fn test_split_crosses_double_word(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let program = Compiler::new().sd(X1, X2, 6).ld(X3, X1, 6).compile();
+    let cpu = CPU::builder()
+        .x1(RAM_START)
+        .x2(0x1122_3344_5566_7788)
+        .build()
+        .unwrap();
+
+    let computer = run(mode, MisalignedPolicy::Split, cpu, program);
+
+    assert_eq!(computer.harts[0].get_exception(), None);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0x7788 << 48));
+    assert_eq!(
+        computer.devices.read_dw(RAM_START + 8),
+        Ok(0x1122_3344_5566)
+    );
+    assert_eq!(computer.harts[0].get_register(X3), 0x1122_3344_5566_7788);
+}
+
+/// Jumps to a copy of `addi x7, x0, 5` and the closing EBREAK at a half word aligned address
+fn run_misaligned_fetch(mode: Mode, policy: MisalignedPolicy) -> Computer {
+    let target = RAM_START + 0x102;
+    let program = Compiler::new().jalr(X6, X1, 0).compile();
+    let cpu = CPU::builder().x1(target).build().unwrap();
+
+    let mut computer = setup(mode, policy, cpu, program);
+    let code = Compiler::new().addi(X7, X0, 5).compile().binary;
+    for (offset, byte) in code.into_iter().enumerate() {
+        computer
+            .devices
+            .write(target + offset as u64, byte as u64, BusStatus::WriteByte)
+            .unwrap();
+    }
+    run_computer(mode, &mut computer, 2000);
+    computer
+}
+
+#[rstest]
+fn test_misaligned_fetch(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+    #[values(MisalignedPolicy::Split, MisalignedPolicy::Allow)] policy: MisalignedPolicy,
+) {
+    let computer = run_misaligned_fetch(mode, policy);
+
+    assert_eq!(computer.harts[0].get_exception(), None);
+    assert_eq!(computer.harts[0].get_register(X7), 5);
+}
+
+#[rstest]
+fn test_misaligned_fetch_traps(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let computer = run_misaligned_fetch(mode, MisalignedPolicy::Trap);
+
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::InstructionAddressMisaligned(RAM_START + 0x102))
+    );
+    assert_eq!(computer.harts[0].get_register(X7), 0);
+}
@@ -0,0 +1,247 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::clint::{Clint, CLINT_MSIP, CLINT_MTIMECMP};
+use crate::computer::components::cpu::csr::{CSR_MHARTID, CSR_MIP, MIP_MSIP, MIP_MTIP};
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::misaligned::MisalignedPolicy;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::memory_map::MemoryMap;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+const CLINT_BASE: u64 = 0x0200_0000;
+
+/// Harts built from the same registers, running the same program
+fn setup(mode: Mode, harts: usize, cpu: impl Fn() -> CPU, program: Program) -> Computer {
+    let harts = (0..harts).map(|_| cpu()).collect();
+    let mut computer = Computer::with_harts(MemoryMap::default(), harts);
+    mode.configure(&mut computer);
+    computer
+        .attach_device(Clint::new("clint", CLINT_BASE, computer.harts.len()))
+        .unwrap();
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+#[rstest]
+fn test_hart_ids(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    // Each hart stores its id + 1 to its own double word
+    let program = Compiler::new()
+        .csrr(X1, CSR_MHARTID)
+        .add(X2, X1, X1)
+        .add(X2, X2, X2)
+        .add(X2, X2, X2)
+        .add(X2, X2, X3)
+        .addi(X4, X1, 1)
+        .sd(X2, X4, 0)
+        .compile();
+    let mut computer = setup(
+        mode,
+        3,
+        || CPU::builder().x3(RAM_START).build().unwrap(),
+        program,
+    );
+
+    run_computer(mode, &mut computer, 50_000);
+
+    for hart in 0..3 {
+        assert_eq!(computer.harts[hart].get_hart_id(), hart);
+        assert_eq!(computer.harts[hart].get_register(X1), hart as u64);
+        assert_eq!(
+            computer.devices.read_dw(RAM_START + 8 * hart as u64),
+            Ok(hart as u64 + 1)
+        );
+    }
+}
+
+#[rstest]
+fn test_spinlock_counter(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    // The lock is at X3, the counter in the following double word
+    let program = Compiler::new()
+        .lr_d(X1, X3)
+        .bne(X1, X0, -4i64 as u64)
+        .sc_d(X2, X3, X6)
+        .bne(X2, X0, -12i64 as u64)
+        .ld(X4, X3, 8)
+        .addi(X4, X4, 1)
+        .sd(X3, X4, 8)
+        .sd(X3, X0, 0)
+        .sub(X5, X5, X6)
+        .bne(X5, X0, -36i64 as u64)
+        .compile();
+    let cpu = || CPU::builder().x3(RAM_START).x5(5).x6(1).build().unwrap();
+    let mut computer = setup(mode, 3, cpu, program);
+
+    run_computer(mode, &mut computer, 50_000);
+
+    assert!(computer
+        .harts
+        .iter()
+        .all(|hart| hart.get_exception().is_none()));
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0));
+    assert_eq!(computer.devices.read_dw(RAM_START + 8), Ok(15));
+}
+
+#[rstest]
+fn test_store_conditional_fails_after_foreign_write(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    // Hart 0 reserves X3 and waits for hart 1 to write it, flags are in other granules
+    let program = Compiler::new()
+        .csrr(X1, CSR_MHARTID)
+        .bne(X1, X0, 28)
+        .lr_d(X2, X3)
+        .sd(X3, X6, 16)
+        .ld(X4, X3, 32)
+        .beq(X4, X0, -4i64 as u64)
+        .sc_d(X5, X3, X7)
+        .jal(X9, 20)
+        .ld(X4, X3, 16)
+        .beq(X4, X0, -4i64 as u64)
+        .sd(X3, X6, 0)
+        .sd(X3, X6, 32)
+        .compile();
+    let cpu = || CPU::builder().x3(RAM_START).x6(1).x7(0xAA).build().unwrap();
+    let mut computer = setup(mode, 2, cpu, program);
+
+    run_computer(mode, &mut computer, 50_000);
+
+    assert_eq!(computer.harts[0].get_register(X5), 1);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(1));
+}
+
+#[rstest]
+fn test_store_conditional_without_reservation(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let program = Compiler::new()
+        .lr_d(X1, X3)
+        .sc_d(X2, X3, X6)
+        .sc_d(X4, X3, X7)
+        .compile();
+    let cpu = || {
+        CPU::builder()
+            .x3(RAM_START)
+            .x6(0x11)
+            .x7(0x22)
+            .x4(7)
+            .build()
+            .unwrap()
+    };
+    let mut computer = setup(mode, 1, cpu, program);
+
+    run_computer(mode, &mut computer, 50_000);
+
+    // The first SC consumes the reservation
+    assert_eq!(computer.harts[0].get_register(X2), 0);
+    assert_eq!(computer.harts[0].get_register(X4), 1);
+    assert_eq!(computer.devices.read_dw(RAM_START), Ok(0x11));
+}
+
+#[rstest]
+fn test_misaligned_atomic_traps(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let program = Compiler::new().lr_w(X1, X3).compile();
+    let cpu = || {
+        CPU::builder()
+            .x3(RAM_START + 2)
+            .misaligned_policy(MisalignedPolicy::Split)
+            .build()
+            .unwrap()
+    };
+    let mut computer = setup(mode, 1, cpu, program);
+
+    run_computer(mode, &mut computer, 50_000);
+
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::LoadAddressMisaligned(RAM_START + 2))
+    );
+}
+
+#[rstest]
+fn test_software_interrupt(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    // Hart 0 raises the software interrupt of hart 1, which polls mip
+    let program = Compiler::new()
+        .csrr(X1, CSR_MHARTID)
+        .bne(X1, X0, 12)
+        .sw(X7, X6, 0)
+        .jal(X9, 12)
+        .csrr(X2, CSR_MIP)
+        .beq(X2, X0, -4i64 as u64)
+        .compile();
+    let cpu = || {
+        CPU::builder()
+            .x6(1)
+            .x7(CLINT_BASE + CLINT_MSIP + 4)
+            .build()
+            .unwrap()
+    };
+    let mut computer = setup(mode, 2, cpu, program);
+
+    run_computer(mode, &mut computer, 50_000);
+
+    assert_eq!(computer.harts[0].get_register(X2), 0);
+    assert_eq!(computer.harts[1].get_register(X2), MIP_MSIP);
+    assert_eq!(computer.harts[0].get_csrs().mip, 0);
+}
+
+#[rstest]
+fn test_timer_interrupt(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let program = Compiler::new()
+        .sd(X7, X6, 0)
+        .csrr(X2, CSR_MIP)
+        .beq(X2, X0, -4i64 as u64)
+        .compile();
+    let cpu = || {
+        CPU::builder()
+            .x6(200)
+            .x7(CLINT_BASE + CLINT_MTIMECMP)
+            .build()
+            .unwrap()
+    };
+    let mut computer = setup(mode, 1, cpu, program);
+
+    run_computer(mode, &mut computer, 50_000);
+
+    let clint = computer.devices.get::<Clint>("clint").unwrap();
+    assert_eq!(computer.harts[0].get_register(X2), MIP_MTIP);
+    assert!(clint.get_mtime() >= 200);
+}
+
+#[rstest]
+fn test_clint_reset() {
+    let program = Compiler::new().beq(X0, X0, 0).compile();
+    let mut computer = setup(Mode::InOrder, 1, CPU::default, program);
+    for (offset, status) in [
+        (CLINT_MSIP, BusStatus::WriteWord),
+        (CLINT_MTIMECMP, BusStatus::WriteDoubleWord),
+    ] {
+        computer
+            .devices
+            .write(CLINT_BASE + offset, 1, status)
+            .unwrap();
+    }
+    for _ in 0..10 {
+        computer.tick();
+    }
+    assert_eq!(computer.harts[0].get_csrs().mip, MIP_MSIP | MIP_MTIP);
+
+    computer.reset();
+    computer.tick();
+
+    let clint = computer.devices.get::<Clint>("clint").unwrap();
+    assert_eq!(clint.get_mtime(), 1);
+    assert_eq!(computer.harts[0].get_csrs().mip, 0);
+}
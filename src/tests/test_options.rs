@@ -0,0 +1,75 @@
+use crate::computer::components::cpu::cache::Coherence;
+use crate::computer::components::cpu::misaligned::MisalignedPolicy;
+use crate::computer::components::htif::Htif;
+use crate::computer::components::plic::Plic;
+use crate::computer::components::uart::Uart;
+use crate::computer::components::virtio::VirtioBlock;
+use crate::computer::memory_map::RegionKind;
+use crate::machine::{build_computer, HTIF_BASE, PLIC_BASE, UART_BASE};
+use crate::options::{ExecutionMode, Options, Predictor};
+use rstest::rstest;
+
+fn parse(args: &str) -> Result<Options, String> {
+    Options::parse(args.split_whitespace().map(str::to_string))
+}
+
+#[test]
+fn test_parse() {
+    let options = parse(
+        "--harts 2 --mode out-of-order --predictor gshare --misaligned split --dcache \
+         --coherence mesi --htif 0x8000_1000 --ticks 1_000 program.bin",
+    )
+    .unwrap();
+    assert_eq!(options.harts, 2);
+    assert_eq!(options.mode, ExecutionMode::OutOfOrder);
+    assert_eq!(options.predictor, Predictor::GShare);
+    assert_eq!(options.misaligned, MisalignedPolicy::Split);
+    assert!(options.dcache && !options.icache);
+    assert_eq!(options.coherence, Coherence::MESI);
+    assert_eq!(options.htif, Some(0x8000_1000));
+    assert_eq!(options.ticks, Some(1000));
+    assert_eq!(options.program, Some("program.bin".into()));
+}
+
+#[rstest]
+#[case::unknown_option("--turbo")]
+#[case::missing_value("--ticks")]
+#[case::invalid_number("--ticks many")]
+#[case::invalid_choice("--mode sideways")]
+#[case::no_harts("--harts 0")]
+#[case::second_program("one.bin two.bin")]
+fn test_parse_error(#[case] args: &str) {
+    assert!(parse(args).is_err());
+}
+
+#[test]
+fn test_build_computer() {
+    let options = parse("--harts 2 --predictor bimodal --seed 1").unwrap();
+    let computer = build_computer(&options).unwrap();
+    assert_eq!(computer.harts.len(), 2);
+    assert!(computer.harts.iter().all(|hart| hart.get_ooo().is_some()));
+    assert!(computer.devices.get::<Uart>("uart").is_some());
+    assert!(computer.devices.device_for_address(UART_BASE).is_some());
+    assert!(computer.devices.get::<Plic>("plic").is_some());
+    assert!(computer.devices.get::<Htif>("htif").is_some());
+    assert!(computer.devices.device_for_address(HTIF_BASE).is_some());
+    assert!(computer.devices.device_for_address(PLIC_BASE).is_some());
+    // The virtio block device needs a disk image
+    assert!(computer.devices.get::<VirtioBlock>("virtio").is_none());
+}
+
+#[test]
+fn test_htif_in_ram() {
+    let options = parse("--htif 0x8000_1000 --seed 1").unwrap();
+    let computer = build_computer(&options).unwrap();
+    let memory_map = computer.devices.get_memory_map();
+    let kinds: Vec<_> = memory_map
+        .regions()
+        .iter()
+        .map(|region| (region.name.as_str(), region.kind))
+        .collect();
+    assert!(kinds.contains(&("ram", RegionKind::RAM)));
+    assert!(kinds.contains(&("tohost", RegionKind::Device)));
+    assert!(kinds.contains(&("ram_high", RegionKind::RAM)));
+    assert!(computer.devices.device_for_address(0x8000_1000).is_some());
+}
@@ -0,0 +1,247 @@
+use crate::compiler::layers::instruction_label::InstructionLabelLayer;
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::exception::Exception;
+use crate::computer::components::cpu::ooo::{OoOConfig, OoOEvent};
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::instructions::Instruction;
+use crate::computer::Computer;
+use crate::tests::{setup_and_run, setup_and_run_custom_cpu, Mode};
+use rstest::rstest;
+
+fn out_of_order_cpu() -> CPU {
+    CPU::builder()
+        .out_of_order(OoOConfig::default())
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn test_out_of_order_matches_in_order() {
+    let program = Compiler::new()
+        .data("value", vec![17])
+        .lb_label(X1, X0, "value")
+        .add(X1, X1, X1)
+        .add(X2, X1, X1)
+        .sub(X3, X2, X1)
+        .lui(X4, 0x1)
+        .addi(X4, X4, 5)
+        .auipc(X5, 0)
+        .jalr(X6, X5, 12)
+        .addi(X7, X0, 1)
+        .addi(X8, X0, 2)
+        .compile();
+
+    let in_order = setup_and_run(Mode::InOrder, program.clone(), 1000);
+    let out_of_order =
+        setup_and_run_custom_cpu(Mode::OutOfOrder, out_of_order_cpu(), program, 1000);
+
+    for reg in [X1, X2, X3, X4, X5, X6, X7, X8, PC] {
+        assert_eq!(
+            out_of_order.harts[0].get_register(reg),
+            in_order.harts[0].get_register(reg),
+            "{reg}"
+        );
+    }
+    assert_eq!(out_of_order.harts[0].get_register(X3), 34);
+    assert_eq!(out_of_order.harts[0].get_register(X7), 0);
+    assert_eq!(out_of_order.harts[0].get_register(X8), 2);
+    assert_eq!(out_of_order.harts[0].get_exception(), None);
+    assert!(out_of_order.harts[0].get_ticks() < in_order.harts[0].get_ticks());
+
+    // Everything committed, registers still renamed hold their committed values
+    let ooo = out_of_order.harts[0].get_ooo().unwrap();
+    assert_eq!(ooo.get_config(), OoOConfig::default());
+    assert!(ooo.get_reorder_buffer().is_empty());
+    for (reg, physical) in ooo.get_rename_table().mapped() {
+        let register = ooo.get_physical_registers().get(physical);
+        assert!(register.ready, "{reg}");
+        assert_eq!(
+            register.value,
+            out_of_order.harts[0].get_register(reg),
+            "{reg}"
+        );
+    }
+}
+
+#[test]
+fn test_out_of_order_writeback_in_order_commit() {
+    let program = Compiler::new()
+        .data("value", vec![42])
+        .lb_label(X1, X0, "value")
+        .add(X2, X3, X3)
+        .compile();
+
+    let mut computer = Computer::new();
+    computer.harts[0] = out_of_order_cpu();
+    computer.set_boot_rom(program.binary);
+
+    let mut events = Vec::new();
+    for _ in 0..1000 {
+        let running = computer.tick();
+        events.extend_from_slice(computer.harts[0].get_ooo().unwrap().get_events());
+        if !running {
+            break;
+        }
+    }
+
+    let renamed_id = |register| {
+        events
+            .iter()
+            .find_map(|event| match event {
+                OoOEvent::Rename {
+                    id, destinations, ..
+                } if destinations.iter().any(|d| d.register == register) => Some(*id),
+                _ => None,
+            })
+            .unwrap()
+    };
+    let position = |expected: &OoOEvent| events.iter().position(|e| e == expected).unwrap();
+    let load_writeback = position(&OoOEvent::Writeback { id: renamed_id(X1) });
+    let add_writeback = position(&OoOEvent::Writeback { id: renamed_id(X2) });
+    let load_commit = position(&OoOEvent::Commit {
+        address: 0,
+        instruction: Instruction::Lb(X1, X0, 12),
+    });
+    let add_commit = position(&OoOEvent::Commit {
+        address: 4,
+        instruction: Instruction::Add(X2, X3, X3),
+    });
+
+    assert!(add_writeback < load_writeback);
+    assert!(load_commit < add_commit);
+    assert_eq!(computer.harts[0].get_register(X1), 42);
+    assert_eq!(
+        computer.harts[0]
+            .get_ooo()
+            .unwrap()
+            .get_stats()
+            .committed_instructions,
+        3
+    );
+}
+
+#[rstest]
+fn test_out_of_order_precise_exception(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let mut data = 0xFFFF_FFFFu32.to_le_bytes().to_vec();
+    data.extend(Instruction::Addi(X5, X0, 7).to_byte_vector());
+    // Jumps over the EBREAK appended at 8 into the data section at 12
+    let program = Compiler::new()
+        .data("code", data)
+        .addi(X1, X0, 1)
+        .jalr(X6, X0, 12)
+        .compile();
+
+    let computer = setup_and_run(mode, program, 1000);
+
+    assert_eq!(
+        computer.harts[0].get_exception(),
+        Some(Exception::IllegalInstruction(0xFFFF_FFFF))
+    );
+    assert_eq!(computer.harts[0].get_register(X1), 1);
+    assert_eq!(computer.harts[0].get_register(X6), 8);
+    assert_eq!(computer.harts[0].get_register(X5), 0);
+    assert_eq!(computer.harts[0].get_register(PC), 12);
+    assert!(computer.harts[0].is_halted());
+    if mode == Mode::OutOfOrder {
+        assert!(computer.harts[0].get_ooo().unwrap().is_drained());
+    }
+}
+
+fn independent_program() -> Program {
+    Compiler::new()
+        .addi(X1, X0, 1)
+        .addi(X2, X0, 2)
+        .addi(X3, X0, 3)
+        .addi(X4, X0, 4)
+        .add(X5, X1, X2)
+        .add(X6, X3, X4)
+        .add(X7, X1, X3)
+        .add(X8, X2, X4)
+        .compile()
+}
+
+fn run_superscalar(config: OoOConfig) -> Computer {
+    let cpu = CPU::builder().out_of_order(config).build().unwrap();
+    setup_and_run_custom_cpu(Mode::OutOfOrder, cpu, independent_program(), 1000)
+}
+
+#[test]
+fn test_issue_width_overrides_configuration() {
+    let config = OoOConfig {
+        alu_units: 3,
+        ..OoOConfig::default()
+    };
+    let cpu = CPU::builder()
+        .issue_width(2)
+        .out_of_order(config)
+        .build()
+        .unwrap();
+
+    let expected = OoOConfig {
+        issue_width: 2,
+        ..config
+    };
+    assert_eq!(cpu.get_ooo().unwrap().get_config(), expected);
+}
+
+#[test]
+fn test_superscalar_issue_width() {
+    let scalar = setup_and_run_custom_cpu(
+        Mode::OutOfOrder,
+        CPU::builder().issue_width(1).build().unwrap(),
+        independent_program(),
+        1000,
+    );
+    let superscalar = run_superscalar(OoOConfig {
+        issue_width: 2,
+        alu_units: 2,
+        ..OoOConfig::default()
+    });
+
+    for reg in [X1, X2, X3, X4, X5, X6, X7, X8, PC] {
+        assert_eq!(
+            superscalar.harts[0].get_register(reg),
+            scalar.harts[0].get_register(reg),
+            "{reg}"
+        );
+    }
+    assert_eq!(superscalar.harts[0].get_register(X8), 6);
+
+    let scalar_stats = scalar.harts[0].get_ooo().unwrap().get_stats();
+    let superscalar_stats = superscalar.harts[0].get_ooo().unwrap().get_stats();
+    assert_eq!(scalar_stats.max_ipc(), 1.0);
+    assert_eq!(superscalar_stats.max_ipc(), 2.0);
+    assert_eq!(superscalar_stats.committed_instructions, 9);
+    assert!(superscalar_stats.ipc() > scalar_stats.ipc());
+    assert!(superscalar_stats.ipc() <= superscalar_stats.max_ipc());
+    assert!(superscalar.harts[0].get_ticks() < scalar.harts[0].get_ticks());
+}
+
+#[test]
+fn test_superscalar_structural_limit() {
+    let unlimited = run_superscalar(OoOConfig {
+        issue_width: 2,
+        alu_units: 2,
+        ..OoOConfig::default()
+    });
+    let limited = run_superscalar(OoOConfig {
+        issue_width: 2,
+        alu_units: 1,
+        ..OoOConfig::default()
+    });
+
+    assert_eq!(limited.harts[0].get_register(X8), 6);
+    let unlimited_stats = unlimited.harts[0].get_ooo().unwrap().get_stats();
+    let limited_stats = limited.harts[0].get_ooo().unwrap().get_stats();
+    assert_eq!(unlimited_stats.structural_stalls.alu, 0);
+    assert!(limited_stats.structural_stalls.alu > 0);
+    assert_eq!(limited_stats.issued.alu, unlimited_stats.issued.alu);
+    assert!(limited.harts[0].get_ticks() >= unlimited.harts[0].get_ticks());
+}
@@ -0,0 +1,77 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::rng::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+const RNG_BASE: u64 = 0x1001_3000;
+
+fn run(mode: Mode, seed: u64) -> Computer {
+    let program = Compiler::new()
+        .lw(X4, X3, RNG_STATUS)
+        .ld(X5, X3, RNG_DATA)
+        .ld(X6, X3, RNG_DATA)
+        .compile();
+    let mut computer = Computer::new();
+    computer.harts[0] = CPU::builder().x3(RNG_BASE).build().unwrap();
+    mode.configure(&mut computer);
+    computer
+        .attach_device(Rng::new("rng", RNG_BASE, seed))
+        .unwrap();
+    computer.set_boot_rom(program.binary);
+    run_computer(mode, &mut computer, 1000);
+    computer
+}
+
+#[rstest]
+fn test_guest_reads_seeded_sequence(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    let computer = run(mode, 42);
+
+    let mut expected = Rng::new("expected", 0, 42);
+    let cpu = &computer.harts[0];
+    assert_eq!(cpu.get_register(X4), 1);
+    assert_eq!(cpu.get_register(X5), expected.next());
+    assert_eq!(cpu.get_register(X6), expected.next());
+    assert_ne!(cpu.get_register(X5), cpu.get_register(X6));
+}
+
+#[rstest]
+fn test_seeds() {
+    let first = run(Mode::InOrder, 1);
+    let again = run(Mode::InOrder, 1);
+    let other = run(Mode::InOrder, 2);
+
+    let values = |computer: &Computer| {
+        let cpu = &computer.harts[0];
+        [cpu.get_register(X5), cpu.get_register(X6)]
+    };
+    assert_eq!(values(&first), values(&again));
+    assert_ne!(values(&first), values(&other));
+}
+
+#[rstest]
+fn test_seed_from_host_time() {
+    let rng = Rng::from_host_time("rng", RNG_BASE);
+
+    let seeded = Rng::new("seeded", RNG_BASE, rng.get_seed());
+
+    assert_eq!(rng.peek(), seeded.peek());
+}
+
+#[rstest]
+fn test_reset_restarts_sequence() {
+    let mut computer = run(Mode::InOrder, 7);
+    let first = computer.harts[0].get_register(X5);
+
+    computer.reset();
+
+    assert_eq!(computer.devices.read_dw(RNG_BASE + RNG_DATA), Ok(first));
+}
@@ -0,0 +1,125 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::rtc::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, DeviceFixture, Mode};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const RTC_BASE: u64 = 0x0010_1000;
+/// 2024-01-01T00:00:00Z
+const START: u64 = 1_704_067_200_000_000_000;
+const VIRTUAL: RtcClock = RtcClock::Virtual {
+    start: START,
+    tick_nanos: 100,
+};
+
+const RTC: DeviceFixture<Rtc, u32> = DeviceFixture::new("rtc", RTC_BASE);
+
+fn setup(cpu: CPU, clock: RtcClock) -> Computer {
+    RTC.setup(Mode::InOrder, cpu, Rtc::new("rtc", RTC_BASE, clock))
+}
+
+#[test]
+fn test_virtual_time() {
+    let mut computer = setup(CPU::new(), VIRTUAL);
+
+    for _ in 0..10 {
+        computer.tick();
+    }
+
+    assert_eq!(RTC.get(&computer).get_time(), START + 1000);
+    assert_eq!(
+        computer.devices.read_dw(RTC_BASE + RTC_TIME_LOW),
+        Ok(START + 1000)
+    );
+}
+
+#[test]
+fn test_host_time() {
+    let now = || {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64
+    };
+    let before = now();
+    let computer = setup(CPU::new(), RtcClock::Host);
+
+    let time = RTC.get(&computer).get_time();
+
+    assert!(before <= time && time <= now());
+}
+
+#[test]
+fn test_guest_reads_time() {
+    // Reads the halves like Linux does, the lower half latches the upper one
+    let program = Compiler::new()
+        .lw(X4, X3, RTC_TIME_LOW)
+        .lw(X5, X3, RTC_TIME_HIGH)
+        .ld(X6, X3, RTC_TIME_LOW)
+        .compile();
+    let cpu = CPU::builder().x3(RTC_BASE).build().unwrap();
+    let mut computer = setup(cpu, VIRTUAL);
+    computer.set_boot_rom(program.binary);
+
+    run_computer(Mode::InOrder, &mut computer, 1000);
+
+    let cpu = &computer.harts[0];
+    let first = cpu.get_register(X5) << 32 | cpu.get_register(X4) & 0xFFFF_FFFF;
+    assert!(START < first && first < cpu.get_register(X6));
+    assert!(cpu.get_register(X6) < RTC.get(&computer).get_time());
+}
+
+#[test]
+fn test_set_time() {
+    let mut computer = setup(CPU::new(), VIRTUAL);
+    let time: u64 = 0x1234_5678_9ABC_DEF0;
+
+    RTC.write_register(&mut computer, RTC_TIME_HIGH, (time >> 32) as u32);
+    RTC.write_register(&mut computer, RTC_TIME_LOW, time as u32);
+    computer.tick();
+
+    assert_eq!(RTC.get(&computer).get_time(), time + 100);
+}
+
+#[test]
+fn test_alarm() {
+    let mut computer = setup(CPU::new(), VIRTUAL);
+    let alarm = START + 1000;
+    RTC.write_register(&mut computer, RTC_IRQ_ENABLED, 1);
+    RTC.write_register(&mut computer, RTC_ALARM_HIGH, (alarm >> 32) as u32);
+    RTC.write_register(&mut computer, RTC_ALARM_LOW, alarm as u32);
+    assert_eq!(RTC.read_register(&computer, RTC_ALARM_STATUS), 1);
+
+    for _ in 0..9 {
+        computer.tick();
+    }
+    assert!(computer.pending_interrupts().is_empty());
+    computer.tick();
+    assert_eq!(computer.pending_interrupts(), ["rtc"]);
+    assert_eq!(RTC.read_register(&computer, RTC_ALARM_STATUS), 0);
+
+    RTC.write_register(&mut computer, RTC_CLEAR_INTERRUPT, 1);
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_clear_alarm() {
+    let mut computer = setup(CPU::new(), VIRTUAL);
+    let alarm = START + 1000;
+    RTC.write_register(&mut computer, RTC_IRQ_ENABLED, 1);
+    RTC.write_register(&mut computer, RTC_ALARM_HIGH, (alarm >> 32) as u32);
+    RTC.write_register(&mut computer, RTC_ALARM_LOW, alarm as u32);
+
+    RTC.write_register(&mut computer, RTC_CLEAR_ALARM, 1);
+    for _ in 0..20 {
+        computer.tick();
+    }
+
+    assert!(computer.pending_interrupts().is_empty());
+}
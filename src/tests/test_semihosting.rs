@@ -0,0 +1,254 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::uart::backend::MemoryBackend;
+use crate::computer::semihosting::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+/// Table of operation, parameter and result triples the guest works through, ended by operation 0
+const TABLE: u64 = RAM_START;
+/// Strings and parameter blocks
+const DATA: u64 = RAM_START + 0x1000;
+const FAILURE: u64 = -1i64 as u64;
+
+/// Computer running the calls of the table with semihosting enabled
+fn setup(mode: Mode, semihosting: Semihosting) -> Computer {
+    let program = Compiler::new()
+        .ld(X10, X12, 0)
+        .beq(X10, X0, 32)
+        .ld(X11, X12, 8)
+        .semihosting_call()
+        .sd(X12, X10, 16)
+        .addi(X12, X12, 24)
+        .beq(X0, X0, -32i64 as u64)
+        .compile();
+    let mut computer = Computer::new();
+    computer.harts[0] = CPU::builder().x12(TABLE).build().unwrap();
+    mode.configure(&mut computer);
+    computer.set_semihosting(Some(semihosting));
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+fn write_bytes(computer: &mut Computer, address: u64, bytes: &[u8]) {
+    for (address, byte) in (address..).zip(bytes) {
+        computer
+            .devices
+            .write(address, *byte as u64, BusStatus::WriteByte)
+            .unwrap();
+    }
+}
+
+fn write_fields(computer: &mut Computer, address: u64, fields: &[u64]) {
+    let bytes: Vec<u8> = fields
+        .iter()
+        .flat_map(|field| field.to_le_bytes())
+        .collect();
+    write_bytes(computer, address, &bytes);
+}
+
+fn read_bytes(computer: &Computer, address: u64, length: u64) -> Vec<u8> {
+    (address..address + length)
+        .map(|address| computer.devices.read_dw(address).unwrap() as u8)
+        .collect()
+}
+
+/// Runs the calls, returns their results
+fn run(mode: Mode, computer: &mut Computer, calls: &[(u64, u64)]) -> Vec<u64> {
+    let table: Vec<u64> = calls
+        .iter()
+        .flat_map(|(operation, parameter)| [*operation, *parameter, 0])
+        .chain([0])
+        .collect();
+    write_fields(computer, TABLE, &table);
+    run_computer(mode, computer, 50_000);
+    (0..calls.len() as u64)
+        .map(|index| computer.devices.read_dw(TABLE + index * 24 + 16).unwrap())
+        .collect()
+}
+
+fn console_output(computer: &Computer) -> String {
+    let semihosting = computer.get_semihosting().unwrap();
+    semihosting
+        .get_console::<MemoryBackend>()
+        .unwrap()
+        .output_string()
+}
+
+fn sandbox(name: &str) -> std::path::PathBuf {
+    let directory = std::env::temp_dir().join(format!("semihosting-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+#[rstest]
+fn test_console_output(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    let mut computer = setup(mode, Semihosting::new(MemoryBackend::new()));
+    write_bytes(&mut computer, DATA, b"Hello\0");
+    write_bytes(&mut computer, DATA + 0x10, b"!");
+
+    let results = run(
+        mode,
+        &mut computer,
+        &[(SYS_WRITE0, DATA), (SYS_WRITEC, DATA + 0x10)],
+    );
+
+    assert_eq!(results, [0, 0]);
+    assert_eq!(console_output(&computer), "Hello!");
+    assert!(computer.harts[0].is_halted());
+    assert_eq!(computer.get_exit_code(), None);
+}
+
+#[rstest]
+#[case::success(ADP_STOPPED_APPLICATION_EXIT, 3, 3)]
+#[case::other_reason(0x2_0023, 3, 1)]
+fn test_exit(#[case] reason: u64, #[case] subcode: u64, #[case] code: u64) {
+    let mut computer = setup(Mode::InOrder, Semihosting::new(MemoryBackend::new()));
+    write_fields(&mut computer, DATA, &[reason, subcode]);
+
+    run(
+        Mode::InOrder,
+        &mut computer,
+        &[(SYS_EXIT, DATA), (SYS_WRITE0, 0)],
+    );
+
+    assert_eq!(computer.get_exit_code(), Some(code));
+    assert!(!computer.tick());
+}
+
+#[rstest]
+fn test_plain_ebreak_halts() {
+    let program = Compiler::new()
+        .addi(X5, X0, 1)
+        .ebreak()
+        .addi(X5, X0, 2)
+        .compile();
+    let mut computer = Computer::new();
+    computer.set_semihosting(Some(Semihosting::new(MemoryBackend::new())));
+    computer.set_boot_rom(program.binary);
+
+    while computer.tick() {}
+
+    assert_eq!(computer.harts[0].get_register(X5), 1);
+    assert_eq!(computer.get_exit_code(), None);
+}
+
+#[rstest]
+fn test_calls_halt_without_semihosting() {
+    let program = Compiler::new().semihosting_call().addi(X5, X0, 2).compile();
+    let mut computer = Computer::new();
+    computer.set_boot_rom(program.binary);
+
+    while computer.tick() {}
+
+    assert_eq!(computer.harts[0].get_register(X5), 0);
+}
+
+#[rstest]
+fn test_file_write(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let root = sandbox("write");
+    let semihosting = Semihosting::new(MemoryBackend::new()).with_root(&root);
+    let mut computer = setup(mode, semihosting);
+    let name = b"out.txt";
+    write_bytes(&mut computer, DATA, name);
+    write_bytes(&mut computer, DATA + 0x10, b"written");
+    // Open for writing, write to handle 1 and close it
+    write_fields(&mut computer, DATA + 0x20, &[DATA, 4, name.len() as u64]);
+    write_fields(&mut computer, DATA + 0x40, &[1, DATA + 0x10, 7]);
+    write_fields(&mut computer, DATA + 0x60, &[1]);
+
+    let results = run(
+        mode,
+        &mut computer,
+        &[
+            (SYS_OPEN, DATA + 0x20),
+            (SYS_WRITE, DATA + 0x40),
+            (SYS_CLOSE, DATA + 0x60),
+            (SYS_CLOSE, DATA + 0x60),
+        ],
+    );
+
+    let written = std::fs::read(root.join("out.txt")).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(results, [1, 0, 0, FAILURE]);
+    assert_eq!(written, b"written");
+}
+
+#[rstest]
+fn test_file_read() {
+    let root = sandbox("read");
+    std::fs::write(root.join("in.txt"), b"input").unwrap();
+    let semihosting = Semihosting::new(MemoryBackend::new()).with_root(&root);
+    let mut computer = setup(Mode::InOrder, semihosting);
+    write_bytes(&mut computer, DATA, b"in.txt");
+    write_fields(&mut computer, DATA + 0x20, &[DATA, 0, 6]);
+    // Asks for more than the file holds
+    write_fields(&mut computer, DATA + 0x40, &[1, DATA + 0x100, 8]);
+
+    let results = run(
+        Mode::InOrder,
+        &mut computer,
+        &[(SYS_OPEN, DATA + 0x20), (SYS_READ, DATA + 0x40)],
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+    assert_eq!(results, [1, 3]);
+    assert_eq!(read_bytes(&computer, DATA + 0x100, 5), b"input");
+}
+
+#[rstest]
+#[case::parent("../escape.txt")]
+#[case::absolute("/tmp/escape.txt")]
+#[case::nested("logs/../../escape.txt")]
+fn test_sandbox(#[case] name: &str) {
+    // Never created, nothing below it is opened
+    let root = std::env::temp_dir().join("semihosting-sandbox");
+    let semihosting = Semihosting::new(MemoryBackend::new()).with_root(&root);
+    let mut computer = setup(Mode::InOrder, semihosting);
+    write_bytes(&mut computer, DATA, name.as_bytes());
+    write_fields(&mut computer, DATA + 0x40, &[DATA, 4, name.len() as u64]);
+
+    let results = run(Mode::InOrder, &mut computer, &[(SYS_OPEN, DATA + 0x40)]);
+
+    assert_eq!(results, [FAILURE]);
+    assert!(!root.exists());
+}
+
+#[rstest]
+fn test_console_handle() {
+    let mut computer = setup(Mode::InOrder, Semihosting::new(MemoryBackend::new()));
+    computer
+        .get_semihosting_mut()
+        .unwrap()
+        .get_console_mut::<MemoryBackend>()
+        .unwrap()
+        .push_input(b"ok");
+    write_bytes(&mut computer, DATA, SEMIHOSTING_CONSOLE.as_bytes());
+    write_bytes(&mut computer, DATA + 0x10, b"out");
+    write_fields(&mut computer, DATA + 0x20, &[DATA, 0, 3]);
+    write_fields(&mut computer, DATA + 0x40, &[1, DATA + 0x100, 4]);
+    write_fields(&mut computer, DATA + 0x60, &[1, DATA + 0x10, 3]);
+
+    let results = run(
+        Mode::InOrder,
+        &mut computer,
+        &[
+            (SYS_OPEN, DATA + 0x20),
+            (SYS_READ, DATA + 0x40),
+            (SYS_WRITE, DATA + 0x60),
+            (0x100, 0),
+        ],
+    );
+
+    assert_eq!(results, [1, 2, 0, FAILURE]);
+    assert_eq!(read_bytes(&computer, DATA + 0x100, 2), b"ok");
+    assert_eq!(console_output(&computer), "out");
+}
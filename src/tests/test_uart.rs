@@ -0,0 +1,213 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::device::BusDevice;
+use crate::computer::components::uart::backend::{MemoryBackend, StreamBackend};
+use crate::computer::components::uart::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, DeviceFixture, Mode};
+use rstest::rstest;
+use std::io::Cursor;
+
+const UART_BASE: u64 = 0x1000_0000;
+
+const UART: DeviceFixture<Uart, u8> = DeviceFixture::new("uart", UART_BASE);
+
+fn setup(mode: Mode, cpu: CPU, input: &[u8]) -> Computer {
+    let mut backend = MemoryBackend::new();
+    backend.push_input(input);
+    UART.setup(mode, cpu, Uart::new("uart", UART_BASE, backend))
+}
+
+fn run(mode: Mode, computer: &mut Computer, program: Program) {
+    computer.set_boot_rom(program.binary);
+    run_computer(mode, computer, 5000);
+    assert!(computer.harts[0].is_halted(), "Program did not halt");
+}
+
+fn backend(computer: &Computer) -> &MemoryBackend {
+    UART.get(computer).get_backend().unwrap()
+}
+
+#[rstest]
+fn test_transmit(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    let program = Compiler::new()
+        .addi(X4, X0, b'H' as u64)
+        .sb(X3, X4, UART_RBR_THR)
+        .addi(X4, X0, b'i' as u64)
+        .sb(X3, X4, UART_RBR_THR)
+        .compile();
+    let cpu = CPU::builder().x3(UART_BASE).build().unwrap();
+    let mut computer = setup(mode, cpu, &[]);
+
+    run(mode, &mut computer, program);
+
+    assert_eq!(backend(&computer).output_string(), "Hi");
+}
+
+#[rstest]
+fn test_echo(#[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode) {
+    // Waits for data ready, then sends the received byte back, three times
+    let program = Compiler::new()
+        .lb(X4, X3, UART_LSR)
+        .and(X4, X4, X6)
+        .beq(X4, X0, -8i64 as u64)
+        .lb(X5, X3, UART_RBR_THR)
+        .sb(X3, X5, UART_RBR_THR)
+        .sub(X7, X7, X6)
+        .bne(X7, X0, -24i64 as u64)
+        .compile();
+    let cpu = CPU::builder().x3(UART_BASE).x6(1).x7(3).build().unwrap();
+    let mut computer = setup(mode, cpu, b"abc");
+
+    run(mode, &mut computer, program);
+
+    assert_eq!(backend(&computer).get_output(), b"abc");
+    assert_eq!(
+        UART.read_register(&computer, UART_LSR) & UART_LSR_DATA_READY,
+        0
+    );
+}
+
+#[test]
+fn test_receive_interrupt() {
+    let cpu = CPU::builder().x3(UART_BASE).build().unwrap();
+    let mut computer = setup(Mode::InOrder, cpu, b"x");
+    UART.write_register(&mut computer, UART_IIR_FCR, UART_FCR_ENABLE);
+    UART.write_register(&mut computer, UART_IER, UART_IER_RX_AVAILABLE);
+    assert!(computer.pending_interrupts().is_empty());
+
+    // Reading the received byte clears the interrupt
+    run(
+        Mode::InOrder,
+        &mut computer,
+        Compiler::new().lb(X4, X3, 0).compile(),
+    );
+
+    assert_eq!(computer.harts[0].get_register(X4), b'x' as u64);
+    assert!(computer.pending_interrupts().is_empty());
+    UART.get_mut(&mut computer)
+        .get_backend_mut::<MemoryBackend>()
+        .unwrap()
+        .push_input(b"y");
+    computer.tick();
+    assert_eq!(computer.pending_interrupts(), vec!["uart"]);
+    assert_eq!(
+        UART.read_register(&computer, UART_IIR_FCR),
+        UART_IIR_FIFO_ENABLED | UART_IIR_RX_AVAILABLE
+    );
+}
+
+#[test]
+fn test_transmitter_empty_interrupt() {
+    let cpu = CPU::builder().x3(UART_BASE).build().unwrap();
+    let mut computer = setup(Mode::InOrder, cpu, &[]);
+    UART.write_register(&mut computer, UART_IER, UART_IER_THR_EMPTY);
+    assert_eq!(computer.pending_interrupts(), vec!["uart"]);
+
+    // Reading IIR acknowledges it
+    run(
+        Mode::InOrder,
+        &mut computer,
+        Compiler::new().lb(X4, X3, UART_IIR_FCR).compile(),
+    );
+
+    assert_eq!(
+        computer.harts[0].get_register(X4),
+        UART_IIR_THR_EMPTY as u64
+    );
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[rstest]
+#[case::without_fifo(0, 1)]
+#[case::with_fifo(UART_FCR_ENABLE, UART_FIFO_SIZE)]
+fn test_loopback_overrun(#[case] fcr: u8, #[case] capacity: usize) {
+    let mut computer = setup(Mode::InOrder, CPU::new(), &[]);
+    UART.write_register(&mut computer, UART_IIR_FCR, fcr);
+    UART.write_register(&mut computer, UART_MCR, UART_MCR_LOOPBACK);
+
+    for byte in 0..=capacity as u8 {
+        UART.write_register(&mut computer, UART_RBR_THR, byte);
+    }
+
+    assert!(backend(&computer).get_output().is_empty());
+    let lsr = UART.read_register(&computer, UART_LSR);
+    assert_eq!(lsr & UART_LSR_DATA_READY, UART_LSR_DATA_READY);
+    assert_eq!(lsr & UART_LSR_OVERRUN, UART_LSR_OVERRUN);
+    assert_eq!(UART.read_register(&computer, UART_RBR_THR), 0);
+}
+
+#[test]
+fn test_divisor_latch() {
+    let mut computer = setup(Mode::InOrder, CPU::new(), &[]);
+    UART.write_register(&mut computer, UART_LCR, UART_LCR_DLAB | 0x03);
+    UART.write_register(&mut computer, UART_RBR_THR, 0x34);
+    UART.write_register(&mut computer, UART_IER, 0x12);
+    UART.write_register(&mut computer, UART_LCR, 0x03);
+    UART.write_register(&mut computer, UART_RBR_THR, b'!');
+
+    assert_eq!(UART.get(&computer).get_divisor(), 0x1234);
+    assert_eq!(UART.read_register(&computer, UART_IER), 0);
+    assert_eq!(backend(&computer).output_string(), "!");
+}
+
+#[test]
+fn test_stream_backend() {
+    let directory = std::env::temp_dir().join(format!("uart-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let (input, output) = (directory.join("input"), directory.join("output"));
+    std::fs::write(&input, b"ok").unwrap();
+    let backends = [
+        StreamBackend::new(Cursor::new(b"ok".to_vec()), std::io::sink()),
+        StreamBackend::pipe(&input, &output).unwrap(),
+    ];
+
+    for backend in backends {
+        let mut uart = Uart::new("uart", UART_BASE, backend);
+        uart.write(
+            UART_BASE + UART_IIR_FCR,
+            UART_FCR_ENABLE as u64,
+            BusStatus::WriteByte,
+        );
+        uart.write(UART_BASE, b'!' as u64, BusStatus::WriteByte);
+        // The input arrives from a background thread
+        for _ in 0..1000 {
+            uart.tick();
+            if uart.read_dw(UART_BASE + UART_LSR) & UART_LSR_DATA_READY as u64 != 0 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(uart.read_dw(UART_BASE) as u8, b'o');
+    }
+    assert_eq!(std::fs::read(&output).unwrap(), b"!");
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_reset() {
+    let mut computer = setup(Mode::InOrder, CPU::default(), b"ab");
+    computer.tick();
+    UART.write_register(&mut computer, UART_IER, UART_IER_RX_AVAILABLE);
+    UART.write_register(&mut computer, UART_LCR, 0x03);
+    UART.write_register(&mut computer, UART_SCR, 0x5A);
+    assert!(UART.read_register(&computer, UART_LSR) & UART_LSR_DATA_READY != 0);
+
+    computer.reset();
+
+    for offset in [UART_IER, UART_LCR, UART_MCR, UART_SCR] {
+        assert_eq!(UART.read_register(&computer, offset), 0);
+    }
+    assert_eq!(
+        UART.read_register(&computer, UART_LSR) & UART_LSR_DATA_READY,
+        0
+    );
+    assert_eq!(UART.read_register(&computer, UART_IIR_FCR), UART_IIR_NONE);
+}
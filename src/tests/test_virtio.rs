@@ -0,0 +1,402 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::Compiler;
+use crate::computer::address::RAM_START;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::virtio::storage::{BlockStorage, FileImage, MemoryImage};
+use crate::computer::components::virtio::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, DeviceFixture, Mode};
+use rstest::rstest;
+
+const VIRTIO_BASE: u64 = 0x1000_1000;
+const DESCRIPTORS: u64 = RAM_START + 0x2000;
+const AVAILABLE: u64 = RAM_START + 0x2100;
+const USED: u64 = RAM_START + 0x2200;
+/// Request headers, data buffers and status bytes of request `i` start at `REQUESTS + i * 0x1000`
+const REQUESTS: u64 = RAM_START + 0x4000;
+const SECTORS: usize = 8;
+
+/// Image whose bytes count up, so every sector has different contents
+fn image() -> Vec<u8> {
+    (0..SECTORS * VIRTIO_BLK_SECTOR_SIZE as usize)
+        .map(|byte| (byte % 251) as u8)
+        .collect()
+}
+
+const VIRTIO: DeviceFixture<VirtioBlock, u32> = DeviceFixture::new("vda", VIRTIO_BASE);
+
+fn setup(mode: Mode, cpu: CPU, storage: impl BlockStorage) -> Computer {
+    VIRTIO.setup(mode, cpu, VirtioBlock::new("vda", VIRTIO_BASE, storage))
+}
+
+fn write_memory(computer: &mut Computer, address: u64, bytes: &[u8]) {
+    for (offset, byte) in bytes.iter().enumerate() {
+        computer
+            .devices
+            .write(address + offset as u64, *byte as u64, BusStatus::WriteByte)
+            .unwrap();
+    }
+}
+
+fn read_memory(computer: &Computer, address: u64, length: usize) -> Vec<u8> {
+    (0..length as u64)
+        .map(|offset| computer.devices.read_dw(address + offset).unwrap() as u8)
+        .collect()
+}
+
+/// Goes through the driver initialisation and sets up the queue
+fn initialise(computer: &mut Computer) {
+    VIRTIO.write_register(computer, VIRTIO_MMIO_STATUS, 0);
+    VIRTIO.write_register(computer, VIRTIO_MMIO_STATUS, VIRTIO_STATUS_ACKNOWLEDGE);
+    let driver = VIRTIO_STATUS_ACKNOWLEDGE | VIRTIO_STATUS_DRIVER;
+    VIRTIO.write_register(computer, VIRTIO_MMIO_STATUS, driver);
+    VIRTIO.write_register(computer, VIRTIO_MMIO_DRIVER_FEATURES_SEL, 1);
+    VIRTIO.write_register(computer, VIRTIO_MMIO_DRIVER_FEATURES, 1);
+    let features_ok = driver | VIRTIO_STATUS_FEATURES_OK;
+    VIRTIO.write_register(computer, VIRTIO_MMIO_STATUS, features_ok);
+    VIRTIO.write_register(computer, VIRTIO_MMIO_QUEUE_SEL, 0);
+    VIRTIO.write_register(computer, VIRTIO_MMIO_QUEUE_NUM, VIRTIO_QUEUE_SIZE);
+    let addresses = [
+        (VIRTIO_MMIO_QUEUE_DESC_LOW, DESCRIPTORS),
+        (VIRTIO_MMIO_QUEUE_DRIVER_LOW, AVAILABLE),
+        (VIRTIO_MMIO_QUEUE_DEVICE_LOW, USED),
+    ];
+    for (register, address) in addresses {
+        VIRTIO.write_register(computer, register, address as u32);
+        VIRTIO.write_register(computer, register + 4, (address >> 32) as u32);
+    }
+    VIRTIO.write_register(computer, VIRTIO_MMIO_QUEUE_READY, 1);
+    let driver_ok = features_ok | VIRTIO_STATUS_DRIVER_OK;
+    VIRTIO.write_register(computer, VIRTIO_MMIO_STATUS, driver_ok);
+}
+
+fn write_descriptor(computer: &mut Computer, index: u16, address: u64, length: u32, flags: u16) {
+    let mut bytes = address.to_le_bytes().to_vec();
+    bytes.extend(length.to_le_bytes());
+    bytes.extend(flags.to_le_bytes());
+    bytes.extend((index + 1).to_le_bytes());
+    write_memory(computer, DESCRIPTORS + 16 * index as u64, &bytes);
+}
+
+/// Queues request `slot` as a chain of header, data and status descriptors and returns its status address
+fn push_request(computer: &mut Computer, slot: u16, kind: u32, sector: u64, data: &[u8]) -> u64 {
+    let header = REQUESTS + 0x1000 * slot as u64;
+    let buffer = header + 0x100;
+    let status = header + 0x800;
+    let mut bytes = kind.to_le_bytes().to_vec();
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(sector.to_le_bytes());
+    write_memory(computer, header, &bytes);
+    write_memory(computer, buffer, data);
+    write_memory(computer, status, &[0xFF]);
+
+    let writable = if kind == VIRTIO_BLK_T_OUT {
+        0
+    } else {
+        VIRTQ_DESC_F_WRITE
+    };
+    let first = 3 * slot;
+    write_descriptor(computer, first, header, 16, VIRTQ_DESC_F_NEXT);
+    write_descriptor(
+        computer,
+        first + 1,
+        buffer,
+        data.len() as u32,
+        VIRTQ_DESC_F_NEXT | writable,
+    );
+    write_descriptor(computer, first + 2, status, 1, VIRTQ_DESC_F_WRITE);
+
+    write_memory(
+        computer,
+        AVAILABLE + 4 + 2 * slot as u64,
+        &first.to_le_bytes(),
+    );
+    write_memory(computer, AVAILABLE + 2, &(slot + 1).to_le_bytes());
+    status
+}
+
+fn notify_and_wait(computer: &mut Computer, requests: u64) {
+    VIRTIO.write_register(computer, VIRTIO_MMIO_QUEUE_NOTIFY, 0);
+    for _ in 0..20000 {
+        computer.tick();
+        if VIRTIO.get(computer).get_completed() == requests && !VIRTIO.get(computer).is_busy() {
+            return;
+        }
+    }
+    panic!("Requests were not completed");
+}
+
+fn used_entry(computer: &Computer, slot: u64) -> (u32, u32) {
+    let entry = read_memory(computer, USED + 4 + 8 * slot, 8);
+    (
+        u32::from_le_bytes(entry[..4].try_into().unwrap()),
+        u32::from_le_bytes(entry[4..].try_into().unwrap()),
+    )
+}
+
+#[test]
+fn test_identification() {
+    let computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_MAGIC_VALUE),
+        VIRTIO_MAGIC
+    );
+    assert_eq!(VIRTIO.read_register(&computer, VIRTIO_MMIO_VERSION), 2);
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_DEVICE_ID),
+        VIRTIO_DEVICE_BLOCK
+    );
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_QUEUE_NUM_MAX),
+        VIRTIO_QUEUE_SIZE
+    );
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_CONFIG),
+        SECTORS as u32
+    );
+    assert_eq!(VIRTIO.read_register(&computer, VIRTIO_MMIO_CONFIG + 4), 0);
+}
+
+#[rstest]
+#[case::writable(MemoryImage::new(image()), 0)]
+#[case::read_only(MemoryImage::read_only(image()), VIRTIO_BLK_F_RO)]
+fn test_features(#[case] storage: MemoryImage, #[case] read_only: u64) {
+    let mut computer = setup(Mode::InOrder, CPU::new(), storage);
+
+    let low = VIRTIO.read_register(&computer, VIRTIO_MMIO_DEVICE_FEATURES);
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_DEVICE_FEATURES_SEL, 1);
+    let high = VIRTIO.read_register(&computer, VIRTIO_MMIO_DEVICE_FEATURES);
+
+    let features = (high as u64) << 32 | low as u64;
+    assert_eq!(
+        features,
+        VIRTIO_F_VERSION_1 | VIRTIO_BLK_F_FLUSH | read_only
+    );
+}
+
+#[rstest]
+fn test_read_sector(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let mut computer = setup(mode, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    let status = push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 3, &[0; 512]);
+
+    notify_and_wait(&mut computer, 1);
+
+    let sector = &image()[3 * 512..4 * 512];
+    assert_eq!(read_memory(&computer, REQUESTS + 0x100, 512), sector);
+    assert_eq!(read_memory(&computer, status, 1), [VIRTIO_BLK_S_OK]);
+    assert_eq!(used_entry(&computer, 0), (0, 513));
+    assert_eq!(read_memory(&computer, USED + 2, 2), [1, 0]);
+    assert!(computer.pending_interrupts().contains(&"vda"));
+}
+
+#[rstest]
+fn test_write_sector(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    let mut computer = setup(mode, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    let data: Vec<u8> = (0..512).map(|byte| (byte % 7) as u8 + 0x80).collect();
+    let status = push_request(&mut computer, 0, VIRTIO_BLK_T_OUT, 5, &data);
+
+    notify_and_wait(&mut computer, 1);
+
+    let storage = VIRTIO.get(&computer).get_storage::<MemoryImage>().unwrap();
+    assert_eq!(&storage.get_data()[5 * 512..6 * 512], data);
+    assert_eq!(&storage.get_data()[..5 * 512], &image()[..5 * 512]);
+    assert_eq!(read_memory(&computer, status, 1), [VIRTIO_BLK_S_OK]);
+    assert_eq!(used_entry(&computer, 0), (0, 1));
+}
+
+#[test]
+fn test_multiple_requests() {
+    let mut computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 0, &[0; 512]);
+    push_request(&mut computer, 1, VIRTIO_BLK_T_OUT, 1, &[0x5A; 512]);
+    push_request(&mut computer, 2, VIRTIO_BLK_T_IN, 1, &[0; 512]);
+
+    notify_and_wait(&mut computer, 3);
+
+    assert_eq!(
+        read_memory(&computer, REQUESTS + 0x100, 512),
+        &image()[..512]
+    );
+    assert_eq!(read_memory(&computer, REQUESTS + 0x2100, 512), [0x5A; 512]);
+    assert_eq!(used_entry(&computer, 1), (3, 1));
+    assert_eq!(used_entry(&computer, 2), (6, 513));
+    assert_eq!(read_memory(&computer, USED + 2, 2), [3, 0]);
+}
+
+#[rstest]
+#[case::beyond_capacity(MemoryImage::new(image()), VIRTIO_BLK_T_IN, SECTORS as u64, VIRTIO_BLK_S_IOERR)]
+#[case::read_only(
+    MemoryImage::read_only(image()),
+    VIRTIO_BLK_T_OUT,
+    0,
+    VIRTIO_BLK_S_IOERR
+)]
+#[case::unsupported(MemoryImage::new(image()), 11, 0, VIRTIO_BLK_S_UNSUPP)]
+#[case::flush(MemoryImage::new(image()), VIRTIO_BLK_T_FLUSH, 0, VIRTIO_BLK_S_OK)]
+fn test_request_status(
+    #[case] storage: MemoryImage,
+    #[case] kind: u32,
+    #[case] sector: u64,
+    #[case] expected: u8,
+) {
+    let mut computer = setup(Mode::InOrder, CPU::new(), storage);
+    initialise(&mut computer);
+    let status = push_request(&mut computer, 0, kind, sector, &[0x11; 512]);
+
+    notify_and_wait(&mut computer, 1);
+
+    assert_eq!(read_memory(&computer, status, 1), [expected]);
+    let storage = VIRTIO.get(&computer).get_storage::<MemoryImage>().unwrap();
+    assert_eq!(storage.get_data(), image());
+}
+
+/// Status request 0 is answered with, after the descriptor at index is patched at the offset
+#[rstest]
+#[case::oversized_data(1, 8, &u32::MAX.to_le_bytes(), VIRTIO_BLK_S_IOERR, 1)]
+#[case::looping_chain(2, 12, &[3, 0, 0, 0], VIRTIO_BLK_S_IOERR, 1)]
+#[case::empty_status(2, 8, &[0; 4], 0xFF, 0)]
+fn test_malformed_request(
+    #[case] index: u64,
+    #[case] offset: u64,
+    #[case] patch: &[u8],
+    #[case] expected: u8,
+    #[case] used_length: u32,
+) {
+    let mut computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    let status = push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 0, &[0x11; 512]);
+    write_memory(&mut computer, DESCRIPTORS + 16 * index + offset, patch);
+
+    notify_and_wait(&mut computer, 1);
+
+    assert_eq!(read_memory(&computer, status, 1), [expected]);
+    assert_eq!(used_entry(&computer, 0), (0, used_length));
+    assert_eq!(read_memory(&computer, REQUESTS + 0x100, 512), [0x11; 512]);
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_STATUS) & VIRTIO_STATUS_NEEDS_RESET,
+        0
+    );
+}
+
+#[test]
+fn test_queue_fixed_while_busy() {
+    let mut computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 0, &[0; 512]);
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_QUEUE_NOTIFY, 0);
+    assert!(VIRTIO.get(&computer).is_busy());
+
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_QUEUE_NUM, 0);
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_QUEUE_READY, 0);
+
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_QUEUE_NUM),
+        VIRTIO_QUEUE_SIZE
+    );
+    assert_eq!(VIRTIO.read_register(&computer, VIRTIO_MMIO_QUEUE_READY), 1);
+    notify_and_wait(&mut computer, 1);
+    assert_eq!(
+        read_memory(&computer, REQUESTS + 0x100, 512),
+        &image()[..512]
+    );
+}
+
+#[test]
+fn test_interrupt_acknowledge() {
+    let mut computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 0, &[0; 512]);
+    notify_and_wait(&mut computer, 1);
+    let status = VIRTIO.read_register(&computer, VIRTIO_MMIO_INTERRUPT_STATUS);
+    assert_eq!(status, VIRTIO_INTERRUPT_USED_RING);
+
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_INTERRUPT_ACK, status);
+
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_INTERRUPT_STATUS),
+        0
+    );
+    assert!(computer.pending_interrupts().is_empty());
+}
+
+#[test]
+fn test_notify_before_driver_ok() {
+    let mut computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+    push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 0, &[0; 512]);
+
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_QUEUE_NOTIFY, 0);
+    for _ in 0..1000 {
+        computer.tick();
+    }
+
+    assert_eq!(VIRTIO.get(&computer).get_completed(), 0);
+    assert!(!VIRTIO.get(&computer).is_busy());
+}
+
+#[rstest]
+fn test_guest_notify(#[values(Mode::InOrder, Mode::OutOfOrder)] mode: Mode) {
+    // Notifies the device and polls the used index until the request is done
+    let program = Compiler::new()
+        .sw(X3, X0, VIRTIO_MMIO_QUEUE_NOTIFY)
+        .lh(X5, X4, 2)
+        .beq(X5, X0, -4i64 as u64)
+        .compile();
+    let cpu = CPU::builder().x3(VIRTIO_BASE).x4(USED).build().unwrap();
+    let mut computer = setup(mode, cpu, MemoryImage::new(image()));
+    computer.set_boot_rom(program.binary);
+    initialise(&mut computer);
+    push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 2, &[0; 512]);
+
+    run_computer(mode, &mut computer, 20000);
+
+    assert_eq!(VIRTIO.get(&computer).get_completed(), 1);
+    let sector = &image()[2 * 512..3 * 512];
+    assert_eq!(read_memory(&computer, REQUESTS + 0x100, 512), sector);
+}
+
+#[test]
+fn test_file_image() {
+    let path = std::env::temp_dir().join(format!("virtio_test_{}.img", std::process::id()));
+    std::fs::write(&path, image()).unwrap();
+    let mut computer = setup(Mode::InOrder, CPU::new(), FileImage::open(&path).unwrap());
+    initialise(&mut computer);
+    push_request(&mut computer, 0, VIRTIO_BLK_T_OUT, 7, &[0xEE; 512]);
+    push_request(&mut computer, 1, VIRTIO_BLK_T_IN, 6, &[0; 512]);
+
+    notify_and_wait(&mut computer, 2);
+
+    let contents = std::fs::read(&path).unwrap();
+    assert!(FileImage::open_read_only(&path).unwrap().is_read_only());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(&contents[7 * 512..], [0xEE; 512]);
+    assert_eq!(&contents[..7 * 512], &image()[..7 * 512]);
+    assert_eq!(
+        read_memory(&computer, REQUESTS + 0x1100, 512),
+        &image()[6 * 512..7 * 512]
+    );
+}
+
+#[test]
+fn test_reset() {
+    let mut computer = setup(Mode::InOrder, CPU::new(), MemoryImage::new(image()));
+    initialise(&mut computer);
+    push_request(&mut computer, 0, VIRTIO_BLK_T_IN, 0, &[0; 512]);
+    notify_and_wait(&mut computer, 1);
+
+    VIRTIO.write_register(&mut computer, VIRTIO_MMIO_STATUS, 0);
+
+    assert_eq!(VIRTIO.read_register(&computer, VIRTIO_MMIO_STATUS), 0);
+    assert_eq!(VIRTIO.read_register(&computer, VIRTIO_MMIO_QUEUE_READY), 0);
+    assert_eq!(
+        VIRTIO.read_register(&computer, VIRTIO_MMIO_INTERRUPT_STATUS),
+        0
+    );
+}
@@ -0,0 +1,84 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::address::{BOOT_ROM_END, BOOT_ROM_START, RAM_SIZE, RAM_START};
+use crate::computer::components::cpu::ooo::OoOConfig;
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::memory_map::{MemoryMap, MemoryRegion, Permissions, RegionKind};
+use crate::computer::Computer;
+
+fn memory_map(rom_latency: u64, ram_latency: u64) -> MemoryMap {
+    MemoryMap::new(vec![
+        MemoryRegion::new(
+            "boot_rom",
+            BOOT_ROM_START,
+            BOOT_ROM_END - BOOT_ROM_START + 1,
+            RegionKind::ROM,
+            Permissions::READ_EXECUTE,
+        )
+        .with_latency(rom_latency),
+        MemoryRegion::new(
+            "ram",
+            RAM_START,
+            RAM_SIZE,
+            RegionKind::RAM,
+            Permissions::READ_WRITE_EXECUTE,
+        )
+        .with_latency(ram_latency),
+    ])
+    .unwrap()
+}
+
+fn run(cpu: CPU, map: MemoryMap, program: Program) -> Computer {
+    let mut computer = Computer::with_memory_map(map);
+    computer.harts[0] = cpu;
+    computer.set_boot_rom(program.binary);
+    for _ in 0..1000 {
+        if !computer.tick() {
+            break;
+        }
+    }
+    computer
+}
+
+fn alu_program() -> Program {
+    Compiler::new().addi(X1, X0, 1).addi(X2, X1, 1).compile()
+}
+
+#[test]
+fn test_slow_rom_fetch() {
+    let fast = run(CPU::new(), memory_map(0, 0), alu_program());
+    let slow = run(CPU::new(), memory_map(2, 0), alu_program());
+
+    assert_eq!(slow.harts[0].get_register(X2), 2);
+    // Two wait states for each of the three fetches, including the appended EBREAK
+    assert_eq!(slow.harts[0].get_ticks(), fast.harts[0].get_ticks() + 6);
+
+    let out_of_order = || {
+        CPU::builder()
+            .out_of_order(OoOConfig::default())
+            .build()
+            .unwrap()
+    };
+    let fast = run(out_of_order(), memory_map(0, 0), alu_program());
+    let slow = run(out_of_order(), memory_map(2, 0), alu_program());
+    assert_eq!(slow.harts[0].get_register(X2), 2);
+    assert!(slow.harts[0].get_ticks() > fast.harts[0].get_ticks());
+}
+
+#[test]
+fn test_ram_latency() {
+    let program = || Compiler::new().sb(X1, X2, 0).lb(X3, X1, 0).compile();
+    let cpu = || CPU::builder().x1(RAM_START).x2(7).build().unwrap();
+    let fast = run(cpu(), memory_map(0, 0), program());
+    let slow = run(cpu(), memory_map(0, 3), program());
+
+    assert_eq!(slow.harts[0].get_register(X3), 7);
+    assert_eq!(slow.harts[0].get_exception(), None);
+    // Fetches from ROM are unaffected, the store and the load wait three ticks each
+    assert_eq!(slow.harts[0].get_ticks(), fast.harts[0].get_ticks() + 6);
+}
@@ -0,0 +1,222 @@
+use crate::compiler::layers::instructions::InstructionLayer;
+use crate::compiler::layers::program_builder::ProgramBuilderLayer;
+use crate::compiler::program::Program;
+use crate::compiler::Compiler;
+use crate::computer::components::bus::status::BusStatus;
+use crate::computer::components::cpu::csr::{CSR_MCAUSE, CSR_MEPC};
+use crate::computer::components::cpu::registers::builder::CPURegistersBuilderTrait;
+use crate::computer::components::cpu::registers::reg::CPUReg::*;
+use crate::computer::components::cpu::registers::CPURegistersAccessTrait;
+use crate::computer::components::cpu::CPU;
+use crate::computer::components::watchdog::*;
+use crate::computer::Computer;
+use crate::tests::{run_computer, Mode};
+use rstest::rstest;
+
+const WATCHDOG_BASE: u64 = 0x1001_5000;
+
+fn setup(mode: Mode, cpu: CPU, config: WatchdogConfig, program: Program) -> Computer {
+    let mut computer = Computer::new();
+    computer.harts[0] = cpu;
+    mode.configure(&mut computer);
+    computer
+        .attach_device(Watchdog::new("watchdog", WATCHDOG_BASE, config))
+        .unwrap();
+    computer.set_boot_rom(program.binary);
+    computer
+}
+
+fn watchdog(computer: &Computer) -> &Watchdog {
+    computer.devices.get::<Watchdog>("watchdog").unwrap()
+}
+
+/// Watchdog armed by the host, the guest hangs at address 0
+fn armed(action: WatchdogAction, timeout: u64) -> WatchdogConfig {
+    WatchdogConfig {
+        enabled: true,
+        timeout,
+        action,
+        locked: true,
+        ..WatchdogConfig::default()
+    }
+}
+
+#[rstest]
+fn test_nmi_interrupts_hung_guest(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    // The guest arms the watchdog and hangs, the handler at 12 reads the cause and the interrupted address
+    let program = Compiler::new()
+        .sd(X3, X8, WATCHDOG_TIMEOUT)
+        .sw(X3, X9, WATCHDOG_CONTROL)
+        .beq(X0, X0, 0)
+        .csrr(X5, CSR_MCAUSE)
+        .csrr(X6, CSR_MEPC)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(WATCHDOG_BASE)
+        .x8(300)
+        .x9(WATCHDOG_CONTROL_ENABLE as u64)
+        .build()
+        .unwrap();
+    let config = WatchdogConfig {
+        nmi_vector: 12,
+        ..WatchdogConfig::default()
+    };
+    let mut computer = setup(mode, cpu, config, program);
+
+    run_computer(mode, &mut computer, 5000);
+
+    let cpu = &computer.harts[0];
+    assert!(cpu.is_halted());
+    assert_eq!(cpu.get_register(X5), WATCHDOG_NMI_CAUSE);
+    assert_eq!(cpu.get_register(X6), 8);
+    let expiry = watchdog(&computer).get_last_expiry().unwrap();
+    assert_eq!(expiry.reason, WatchdogReason::Timeout);
+    assert_eq!(expiry.action, WatchdogAction::Nmi);
+    assert!(expiry.tick > 300);
+}
+
+#[rstest]
+fn test_kicks_keep_guest_running(
+    #[values(Mode::InOrder, Mode::OutOfOrder, Mode::FastForward)] mode: Mode,
+) {
+    // Kicks on every iteration of a loop running much longer than the timeout
+    let program = Compiler::new()
+        .sw(X3, X4, WATCHDOG_KICK)
+        .sub(X7, X7, X8)
+        .bne(X7, X0, -8i64 as u64)
+        .compile();
+    let cpu = CPU::builder()
+        .x3(WATCHDOG_BASE)
+        .x4(WATCHDOG_KICK_KEY as u64)
+        .x7(100)
+        .x8(1)
+        .build()
+        .unwrap();
+    let mut computer = setup(mode, cpu, armed(WatchdogAction::Nmi, 200), program);
+
+    run_computer(mode, &mut computer, 20_000);
+
+    assert!(computer.harts[0].is_halted());
+    assert_eq!(computer.harts[0].get_register(X7), 0);
+    assert!(computer.get_ticks() > 200);
+    assert!(watchdog(&computer).get_expiries().is_empty());
+    assert!(watchdog(&computer).get_count() < 200);
+}
+
+#[rstest]
+fn test_reset_on_expiry() {
+    let program = Compiler::new().beq(X0, X0, 0).compile();
+    let config = armed(WatchdogAction::Reset, 100);
+    let mut computer = setup(Mode::InOrder, CPU::default(), config, program);
+
+    run_computer(Mode::InOrder, &mut computer, 250);
+
+    let expected = WatchdogExpiry {
+        reason: WatchdogReason::Timeout,
+        action: WatchdogAction::Reset,
+        tick: 100,
+    };
+    assert_eq!(watchdog(&computer).get_expiries(), [expected, expected]);
+    assert_eq!(computer.get_ticks(), 50);
+    // The status survives the resets, the armed configuration is restored
+    let status = computer
+        .devices
+        .read_dw(WATCHDOG_BASE + WATCHDOG_STATUS)
+        .unwrap();
+    assert_eq!(status as u32, WATCHDOG_STATUS_TIMEOUT);
+    assert!(watchdog(&computer).is_enabled());
+}
+
+#[rstest]
+fn test_invalid_kick() {
+    let program = Compiler::new()
+        .sw(X3, X4, WATCHDOG_KICK)
+        .beq(X0, X0, 0)
+        .compile();
+    let cpu = CPU::builder().x3(WATCHDOG_BASE).x4(0x1234).build().unwrap();
+    let mut computer = setup(
+        Mode::InOrder,
+        cpu,
+        armed(WatchdogAction::Nmi, 10_000),
+        program,
+    );
+
+    run_computer(Mode::InOrder, &mut computer, 100);
+
+    let expiry = watchdog(&computer).get_last_expiry().unwrap();
+    assert_eq!(expiry.reason, WatchdogReason::InvalidKick);
+    let status = computer
+        .devices
+        .read_dw(WATCHDOG_BASE + WATCHDOG_STATUS)
+        .unwrap();
+    assert_eq!(status as u32, WATCHDOG_STATUS_INVALID_KICK);
+}
+
+#[rstest]
+fn test_enable_with_double_word_write() {
+    let program = Compiler::new().sd(X3, X9, WATCHDOG_CONTROL).compile();
+    let cpu = CPU::builder()
+        .x3(WATCHDOG_BASE)
+        .x9(WATCHDOG_CONTROL_ENABLE as u64)
+        .build()
+        .unwrap();
+    let mut computer = setup(Mode::InOrder, cpu, WatchdogConfig::default(), program);
+
+    run_computer(Mode::InOrder, &mut computer, 100);
+
+    assert!(computer.harts[0].is_halted());
+    assert!(watchdog(&computer).is_enabled());
+    assert!(watchdog(&computer).get_expiries().is_empty());
+}
+
+#[rstest]
+fn test_lock() {
+    let program = Compiler::new().beq(X0, X0, 0).compile();
+    let mut computer = setup(
+        Mode::InOrder,
+        CPU::default(),
+        armed(WatchdogAction::Nmi, 1000),
+        program,
+    );
+
+    for (offset, status) in [
+        (WATCHDOG_CONTROL, BusStatus::WriteWord),
+        (WATCHDOG_TIMEOUT, BusStatus::WriteDoubleWord),
+    ] {
+        computer
+            .devices
+            .write(WATCHDOG_BASE + offset, 0, status)
+            .unwrap();
+    }
+
+    assert!(watchdog(&computer).is_enabled());
+    assert_eq!(
+        watchdog(&computer).get_config(),
+        armed(WatchdogAction::Nmi, 1000)
+    );
+    let timeout = computer
+        .devices
+        .read_dw(WATCHDOG_BASE + WATCHDOG_TIMEOUT)
+        .unwrap();
+    assert_eq!(timeout, 1000);
+}
+
+#[rstest]
+fn test_status_write_clears() {
+    let program = Compiler::new().beq(X0, X0, 0).compile();
+    let config = armed(WatchdogAction::Nmi, 10);
+    let mut computer = setup(Mode::InOrder, CPU::default(), config, program);
+    run_computer(Mode::InOrder, &mut computer, 10);
+    assert_eq!(watchdog(&computer).get_expiries().len(), 1);
+
+    let address = WATCHDOG_BASE + WATCHDOG_STATUS;
+    let clear = WATCHDOG_STATUS_TIMEOUT as u64;
+    computer
+        .devices
+        .write(address, clear, BusStatus::WriteWord)
+        .unwrap();
+
+    assert_eq!(computer.devices.read_dw(address).unwrap(), 0);
+}